@@ -0,0 +1,174 @@
+#!/usr/bin/env python3
+"""Build `log_data.so`, fixture program of `test_utils` emitting its instruction data as event
+
+Program logs instruction data by `sol_log_data` (`Program data: <base64>`) and fails with
+custom error 1 if any account is passed. It's assembled & linked here by hand, so the fixture
+is rebuilt without Solana toolchain:
+
+    python3 fixtures/programs/log_data.py
+
+Output is SBPFv1 shared object, as produced by `cargo build-sbf`: single `.text` section with
+`sh_addr == sh_offset` and `sol_log_data` syscall resolved by `R_BPF_64_32` dynamic
+relocation of `call -1`.
+"""
+
+import os
+import struct
+
+
+def insn(opcode, dst=0, src=0, off=0, imm=0):
+    return struct.pack("<BBhi", opcode, (src << 4) | dst, off, imm)
+
+
+TEXT = [
+    insn(0x79, dst=2, src=1, off=0),  # ldxdw r2, [r1+0]     ; count of accounts
+    insn(0x15, dst=2, off=2, imm=0),  # jeq r2, 0, +2
+    insn(0xB7, dst=0, imm=1),  # mov64 r0, 1
+    insn(0x95),  # exit
+    insn(0x79, dst=2, src=1, off=8),  # ldxdw r2, [r1+8]     ; length of instruction data
+    insn(0x07, dst=1, imm=16),  # add64 r1, 16          ; instruction data
+    insn(0x7B, dst=10, src=1, off=-16),  # stxdw [r10-16], r1  ; &[u8] of data on stack
+    insn(0x7B, dst=10, src=2, off=-8),  # stxdw [r10-8], r2
+    insn(0xBF, dst=1, src=10),  # mov64 r1, r10
+    insn(0x07, dst=1, imm=-16),  # add64 r1, -16
+    insn(0xB7, dst=2, imm=1),  # mov64 r2, 1           ; one slice
+    insn(0x85, imm=-1),  # call sol_log_data     ; relocated by `.rel.dyn`
+    insn(0xB7, dst=0, imm=0),  # mov64 r0, 0
+    insn(0x95),  # exit
+]
+CALL_INDEX = 11
+
+EHDR_SIZE, PHDR_SIZE, SHDR_SIZE, DYN_SIZE, SYM_SIZE, REL_SIZE = 64, 56, 64, 16, 24, 16
+
+EM_BPF, ET_DYN = 247, 3
+PT_LOAD, PT_DYNAMIC = 1, 2
+PF_X, PF_W, PF_R = 1, 2, 4
+SHT_NULL, SHT_PROGBITS, SHT_STRTAB, SHT_DYNAMIC, SHT_REL, SHT_DYNSYM = 0, 1, 3, 6, 9, 11
+SHF_WRITE, SHF_ALLOC, SHF_EXECINSTR = 1, 2, 4
+DT_NULL, DT_STRTAB, DT_SYMTAB, DT_STRSZ, DT_SYMENT = 0, 5, 6, 10, 11
+DT_REL, DT_RELSZ, DT_RELENT, DT_TEXTREL = 17, 18, 19, 22
+R_BPF_64_32 = 10
+STB_GLOBAL, STT_NOTYPE = 1, 0
+
+
+def align(offset, alignment=8):
+    return (offset + alignment - 1) // alignment * alignment
+
+
+def build():
+    text = b"".join(TEXT)
+    dynstr = b"\0sol_log_data\0"
+    dynsym = struct.pack("<IBBHQQ", 0, 0, 0, 0, 0, 0) + struct.pack(
+        "<IBBHQQ", 1, (STB_GLOBAL << 4) | STT_NOTYPE, 0, 0, 0, 0
+    )
+    names = [".text", ".dynamic", ".dynsym", ".dynstr", ".rel.dyn", ".shstrtab"]
+    shstrtab = b"\0" + b"".join(name.encode() + b"\0" for name in names)
+    name_offset = {name: shstrtab.index(name.encode() + b"\0") for name in names}
+    dynamic_tags = [DT_REL, DT_RELSZ, DT_RELENT, DT_SYMTAB, DT_SYMENT, DT_STRTAB, DT_STRSZ]
+    dynamic_tags += [DT_TEXTREL, DT_NULL]
+
+    # Sections follow each other in file, virtual address of allocated ones is file offset
+    phdr_count = 3
+    text_offset = align(EHDR_SIZE + phdr_count * PHDR_SIZE, 16)
+    dynamic_offset = align(text_offset + len(text))
+    dynamic_size = len(dynamic_tags) * DYN_SIZE
+    dynsym_offset = dynamic_offset + dynamic_size
+    dynstr_offset = dynsym_offset + len(dynsym)
+    rel_offset = align(dynstr_offset + len(dynstr))
+    shstrtab_offset = rel_offset + REL_SIZE
+    shdr_offset = align(shstrtab_offset + len(shstrtab))
+
+    rel = struct.pack("<QQ", text_offset + CALL_INDEX * 8, (1 << 32) | R_BPF_64_32)
+    dynamic_values = {
+        DT_REL: rel_offset,
+        DT_RELSZ: len(rel),
+        DT_RELENT: REL_SIZE,
+        DT_SYMTAB: dynsym_offset,
+        DT_SYMENT: SYM_SIZE,
+        DT_STRTAB: dynstr_offset,
+        DT_STRSZ: len(dynstr),
+    }
+    dynamic = b"".join(struct.pack("<qQ", tag, dynamic_values.get(tag, 0)) for tag in dynamic_tags)
+
+    # name, type, flags, offset, size, link, info, align, entsize
+    sections = [
+        (0, SHT_NULL, 0, 0, 0, 0, 0, 0, 0),
+        (".text", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, text_offset, len(text), 0, 0, 8, 0),
+        (".dynamic", SHT_DYNAMIC, SHF_ALLOC | SHF_WRITE, dynamic_offset, dynamic_size, 4, 0, 8,
+         DYN_SIZE),
+        (".dynsym", SHT_DYNSYM, SHF_ALLOC, dynsym_offset, len(dynsym), 4, 1, 8, SYM_SIZE),
+        (".dynstr", SHT_STRTAB, SHF_ALLOC, dynstr_offset, len(dynstr), 0, 0, 1, 0),
+        (".rel.dyn", SHT_REL, SHF_ALLOC, rel_offset, len(rel), 3, 0, 8, REL_SIZE),
+        (".shstrtab", SHT_STRTAB, 0, shstrtab_offset, len(shstrtab), 0, 0, 1, 0),
+    ]
+    section_headers = b"".join(
+        struct.pack(
+            "<IIQQQQIIQQ",
+            name_offset[name] if name else 0,
+            kind,
+            flags,
+            # Address of allocated sections
+            offset if flags & SHF_ALLOC else 0,
+            offset,
+            size,
+            link,
+            info,
+            alignment,
+            entsize,
+        )
+        for name, kind, flags, offset, size, link, info, alignment, entsize in sections
+    )
+
+    def phdr(kind, flags, offset, size):
+        return struct.pack("<IIQQQQQQ", kind, flags, offset, offset, offset, size, size, 8)
+
+    program_headers = (
+        phdr(PT_LOAD, PF_R | PF_X, text_offset, len(text))
+        + phdr(PT_LOAD, PF_R | PF_W, dynamic_offset, rel_offset + len(rel) - dynamic_offset)
+        + phdr(PT_DYNAMIC, PF_R | PF_W, dynamic_offset, dynamic_size)
+    )
+
+    header = struct.pack(
+        "<4sBBBBB7sHHIQQQIHHHHHH",
+        b"\x7fELF",
+        2,  # ELFCLASS64
+        1,  # ELFDATA2LSB
+        1,  # EV_CURRENT
+        0,  # ELFOSABI_NONE
+        0,
+        b"\0" * 7,
+        ET_DYN,
+        EM_BPF,
+        1,  # EV_CURRENT
+        text_offset,  # entrypoint
+        EHDR_SIZE,
+        shdr_offset,
+        0,  # SBPFv1
+        EHDR_SIZE,
+        PHDR_SIZE,
+        phdr_count,
+        SHDR_SIZE,
+        len(sections),
+        len(sections) - 1,
+    )
+
+    elf = bytearray(shdr_offset + len(section_headers))
+    for offset, data in [
+        (0, header),
+        (EHDR_SIZE, program_headers),
+        (text_offset, text),
+        (dynamic_offset, dynamic),
+        (dynsym_offset, dynsym),
+        (dynstr_offset, dynstr),
+        (rel_offset, rel),
+        (shstrtab_offset, shstrtab),
+        (shdr_offset, section_headers),
+    ]:
+        elf[offset : offset + len(data)] = data
+    return bytes(elf)
+
+
+if __name__ == "__main__":
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "log_data.so")
+    with open(path, "wb") as file:
+        file.write(build())
@@ -0,0 +1,48 @@
+#ifndef SOLANA_EVENTS_PARSER_H
+#define SOLANA_EVENTS_PARSER_H
+
+/* Generated by cbindgen, do not edit manually */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct sep_idl sep_idl;
+
+// Bind logs of transaction, `logs_json` is JSON array of log lines
+//
+// `ok` is object of logs (in format of [`ProgramLogDto`]) by
+// `"<program_id>:<level>:<call_index>"` key
+//
+// # Safety
+// `logs_json` must be null or valid NUL-terminated string
+char *sep_parse_events(const char *logs_json);
+
+// Parse anchor IDL json into handle for [`sep_idl_decode`], null on error.
+// Handle must be released by [`sep_idl_free`]
+//
+// # Safety
+// `idl_json` must be null or valid NUL-terminated string
+sep_idl *sep_idl_new(const char *idl_json);
+
+// # Safety
+// `idl` must be null or returned by [`sep_idl_new`] and not freed yet
+void sep_idl_free(sep_idl *idl);
+
+// Decode base64 `data` of `kind` (`instruction`, `event` or `account`) by IDL
+//
+// `ok` is `{"name": ..., "data": ...}` or `null` if discriminator is unknown for IDL
+//
+// # Safety
+// `idl` must be null or live handle from [`sep_idl_new`], `kind` & `data_base64` must be
+// null or valid NUL-terminated strings
+char *sep_idl_decode(const sep_idl *idl, const char *kind, const char *data_base64);
+
+// Release string returned by this library
+//
+// # Safety
+// `output` must be null or returned by this library and not freed yet
+void sep_free_string(char *output);
+
+#endif /* SOLANA_EVENTS_PARSER_H */
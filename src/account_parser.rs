@@ -0,0 +1,156 @@
+pub use anchor_lang::{AnchorDeserialize, Discriminator, Owner};
+use async_trait::async_trait;
+use futures::future;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_request::MAX_MULTIPLE_ACCOUNTS};
+pub use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::instruction_parser::split_discriminator;
+pub use crate::transaction_parser::{DecodeError, Error};
+
+/// [`ParseAccount`] is a trait providing the method [`ParseAccount::parse_account`] to decode
+/// data of anchor accounts, the same way as [`crate::ParseEvent`] does for events
+///
+/// The trait is defined for any type `T` that implements:
+/// - [`anchor_lang::Discriminator`] - Defines a specific account type via a binary prefix
+/// - [`anchor_lang::Owner`] - Links the type of account and its owner (solana-program)
+/// - [`anchor_lang::AnchorDeserialize`] - Enables account data to be deserialised in the
+///   structure
+///
+/// ```
+/// use solana_events_parser::account_parser::{Account, ParseAccount};
+///
+/// use anchor_lang::prelude::*;
+///
+/// const PROGRAM_ID: Pubkey = Pubkey::new_from_array([0; 32]);
+///
+/// #[derive(anchor_lang::AnchorDeserialize)]
+/// struct State {
+///     counter: u64,
+/// }
+///
+/// impl anchor_lang::Owner for State {
+///     fn owner() -> Pubkey {
+///         PROGRAM_ID
+///     }
+/// }
+/// impl anchor_lang::Discriminator for State {
+///     const DISCRIMINATOR: [u8; 8] = [1u8; 8];
+/// }
+///
+/// let account = Account {
+///     owner: PROGRAM_ID,
+///     data: [[1u8; 8].as_slice(), &7u64.to_le_bytes()].concat(),
+///     ..Default::default()
+/// };
+/// let state = account.parse_account::<State>().unwrap().unwrap();
+/// assert_eq!(state.counter, 7);
+/// ```
+///
+/// Returns `None` if account doesn't belong to `T` (owner or discriminator not match, or data
+/// is too short to contain discriminator)
+pub trait ParseAccount {
+    fn parse_account<T: Discriminator + Owner + AnchorDeserialize>(
+        &self,
+    ) -> Option<Result<T, DecodeError>>;
+}
+
+impl ParseAccount for Account {
+    fn parse_account<T: Discriminator + Owner + AnchorDeserialize>(
+        &self,
+    ) -> Option<Result<T, DecodeError>> {
+        T::owner()
+            .eq(&self.owner)
+            .then(|| parse_account_data(&self.data))
+            .flatten()
+    }
+}
+
+/// Decode raw account data into `T`, owner of account isn't checked
+///
+/// Returns `None` if discriminator doesn't match or data is too short to contain it.
+/// Trailing bytes (e.g. space reserved for future fields) are ignored
+pub fn parse_account_data<T: Discriminator + Owner + AnchorDeserialize>(
+    data: &[u8],
+) -> Option<Result<T, DecodeError>> {
+    let (discriminator, mut account) = split_discriminator(data)?;
+    T::discriminator().eq(discriminator).then(|| {
+        T::deserialize(&mut account).map_err(|source| DecodeError::Deserialize {
+            program_id: T::owner(),
+            program_ctx: None,
+            discriminator: Some(T::discriminator()),
+            type_name: std::any::type_name::<T>(),
+            source,
+        })
+    })
+}
+
+/// Fetch & decode accounts by [`RpcClient`]
+#[async_trait]
+pub trait GetParsedAccount {
+    /// `None` if account doesn't exist or doesn't belong to `T`
+    async fn get_and_parse_account<T: Discriminator + Owner + AnchorDeserialize>(
+        &self,
+        address: &Pubkey,
+    ) -> Result<Option<T>, Error>;
+
+    /// Accounts in order of `addresses`, `None` for absent ones or not belonging to `T`
+    ///
+    /// Requested by `getMultipleAccounts` in parallel chunks of [`MAX_MULTIPLE_ACCOUNTS`]
+    async fn get_and_parse_accounts<T: Discriminator + Owner + AnchorDeserialize + Send>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<T>>, Error>;
+}
+
+#[async_trait]
+impl GetParsedAccount for RpcClient {
+    async fn get_and_parse_account<T: Discriminator + Owner + AnchorDeserialize>(
+        &self,
+        address: &Pubkey,
+    ) -> Result<Option<T>, Error> {
+        let account = self
+            .get_account_with_commitment(address, self.commitment())
+            .await?
+            .value;
+        Ok(account
+            .and_then(|account| account.parse_account::<T>())
+            .transpose()?)
+    }
+
+    async fn get_and_parse_accounts<T: Discriminator + Owner + AnchorDeserialize + Send>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<T>>, Error> {
+        let chunks = future::try_join_all(
+            addresses
+                .chunks(MAX_MULTIPLE_ACCOUNTS)
+                .map(|chunk| self.get_multiple_accounts_with_commitment(chunk, self.commitment())),
+        )
+        .await?;
+
+        chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.value)
+            .map(|account| {
+                account
+                    .and_then(|account| account.parse_account::<T>())
+                    .transpose()
+                    .map_err(Error::from)
+            })
+            .collect()
+    }
+}
+
+/// Decode account by program IDL, `None` if account isn't owned by `program_id` or its
+/// discriminator is unknown for IDL
+#[cfg(feature = "idl")]
+pub fn parse_account_by_idl(
+    idl: &crate::idl::Idl,
+    program_id: &Pubkey,
+    account: &Account,
+) -> Result<Option<crate::idl::Decoded>, crate::idl::Error> {
+    match account.owner.eq(program_id) {
+        true => idl.decode_account(&account.data),
+        false => Ok(None),
+    }
+}
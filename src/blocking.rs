@@ -0,0 +1,77 @@
+//! Synchronous counterparts of [`crate::transaction_parser::BindTransactionLogs`] &
+//! [`crate::transaction_parser::BindTransactionInstructionLogs`] over blocking [`RpcClient`],
+//! for tools that don't run tokio runtime
+
+use std::collections::HashMap;
+
+pub use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::transaction_parser::{
+    default_transaction_config, log_parser, parse_transaction, transaction_logs, Error,
+    ProgramContext, ProgramLog, RpcTransactionConfig, Signature, TransactionParsedMeta,
+    UiTransactionEncoding,
+};
+
+pub trait BindTransactionLogs {
+    fn bind_transaction_logs(
+        &self,
+        signature: Signature,
+    ) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error>;
+}
+
+impl BindTransactionLogs for RpcClient {
+    fn bind_transaction_logs(
+        &self,
+        signature: Signature,
+    ) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error> {
+        let transaction = self.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base58),
+                max_supported_transaction_version: Some(0),
+                commitment: Some(CommitmentConfig::finalized()),
+            },
+        )?;
+        Ok(log_parser::parse_events(transaction_logs(
+            &transaction.transaction,
+            signature,
+        )?)?)
+    }
+}
+
+pub trait BindTransactionInstructionLogs {
+    fn bind_transaction_instructions_logs(
+        &self,
+        signature: Signature,
+        commitment_config: CommitmentConfig,
+    ) -> Result<TransactionParsedMeta, Error>;
+
+    fn bind_transaction_instructions_logs_with_config(
+        &self,
+        signature: Signature,
+        transaction_config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta, Error>;
+}
+
+impl BindTransactionInstructionLogs for RpcClient {
+    fn bind_transaction_instructions_logs(
+        &self,
+        signature: Signature,
+        commitment_config: CommitmentConfig,
+    ) -> Result<TransactionParsedMeta, Error> {
+        self.bind_transaction_instructions_logs_with_config(
+            signature,
+            default_transaction_config(commitment_config),
+        )
+    }
+
+    fn bind_transaction_instructions_logs_with_config(
+        &self,
+        signature: Signature,
+        transaction_config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta, Error> {
+        let transaction = self.get_transaction_with_config(&signature, transaction_config)?;
+        parse_transaction(signature, &transaction)
+    }
+}
@@ -0,0 +1,423 @@
+//! Transaction consumer of [`crate::event_reader_service::EventsReader`] inserting
+//! [`crate::export`] tables into ClickHouse
+//!
+//! Rows are inserted by HTTP interface of ClickHouse as `JSONEachRow`, so no native client
+//! is needed. Tables can be created by [`ClickHouseSink::create_tables`] or by DDL from
+//! [`ClickHouseSink::tables_ddl`]
+//!
+//! HTTP client is hand-rolled over `reqwest` on purpose: it's already a dependency of
+//! `webhook`, and rows of [`crate::export`] are inserted by their `serde` implementation as is.
+//! `clickhouse` crate requires its own `Row` derive on the rows (and `serde` attributes of
+//! its `RowBinary` format on `Pubkey` & `i128` fields), and native protocol of
+//! `clickhouse-rs` has no stable release.
+
+use std::{
+    sync::{Arc, Weak},
+    time::Duration,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+use reqwest::{Client, StatusCode, Url};
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::*;
+
+use crate::{
+    event_reader_service,
+    export::Tables,
+    transaction_parser::{Signature, TransactionParsedMeta},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Serialize(#[from] serde_json::Error),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("ClickHouse responded with {status} status: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("{pending} rows are waiting for flush, limit of pending rows is reached")]
+    PendingLimit { pending: usize },
+}
+
+impl From<Error> for event_reader_service::Error {
+    fn from(error: Error) -> Self {
+        Self::StorageError(format!("{error:?}"))
+    }
+}
+
+#[derive(Debug, Clone, derive_builder::Builder)]
+pub struct ClickHouseConfig {
+    #[builder(default, setter(into, strip_option))]
+    pub database: Option<String>,
+    #[builder(default, setter(into, strip_option))]
+    pub user: Option<String>,
+    #[builder(default, setter(into, strip_option))]
+    pub password: Option<String>,
+    /// Table of [`crate::export::InstructionRow`]
+    #[builder(default = "\"instructions\".to_owned()", setter(into))]
+    pub instructions_table: String,
+    /// Table of [`crate::export::EventRow`]
+    #[builder(default = "\"events\".to_owned()", setter(into))]
+    pub events_table: String,
+    /// Table of [`crate::export::TokenTransferRow`]
+    #[builder(default = "\"token_transfers\".to_owned()", setter(into))]
+    pub token_transfers_table: String,
+    /// Rows of all tables, after which batch is flushed without waiting for `flush_interval`
+    #[builder(default = "10_000")]
+    pub max_batch_rows: usize,
+    /// Rows of all tables kept while ClickHouse is unavailable, after which transactions are
+    /// rejected by [`ClickHouseSink::push`] with [`Error::PendingLimit`]
+    #[builder(default = "100_000")]
+    pub max_pending_rows: usize,
+    /// Interval of flushes by [`ClickHouseSink::spawn_flusher`]
+    #[builder(default = "Duration::from_secs(5)")]
+    pub flush_interval: Duration,
+}
+
+impl Default for ClickHouseConfig {
+    fn default() -> Self {
+        ClickHouseConfigBuilder::default()
+            .build()
+            .expect("All fields have default values")
+    }
+}
+
+/// Batches rows of consumed transactions & inserts them into ClickHouse
+///
+/// Rows of failed insert are kept and inserted with the next flush, up to
+/// [`ClickHouseConfig::max_pending_rows`]. Failed flush is returned to the reader, which
+/// consumes the transaction again later, so the same rows can be inserted twice: use
+/// `ReplacingMergeTree` (as in [`ClickHouseSink::tables_ddl`]) to deduplicate them.
+///
+/// Rows are inserted in batches, so [`ClickHouseSink::close`] must be called after the reader
+/// is stopped, otherwise the last batch is lost
+pub struct ClickHouseSink {
+    client: Client,
+    url: Url,
+    config: ClickHouseConfig,
+    pending: Mutex<Tables>,
+}
+
+impl ClickHouseSink {
+    /// `url` of HTTP interface, e.g. `http://localhost:8123`
+    pub fn new(url: Url, config: ClickHouseConfig) -> Arc<Self> {
+        Self::with_client(Client::new(), url, config)
+    }
+
+    pub fn with_client(client: Client, url: Url, config: ClickHouseConfig) -> Arc<Self> {
+        Arc::new(Self {
+            client,
+            url,
+            config,
+            pending: Mutex::default(),
+        })
+    }
+
+    /// Count of rows waiting for flush
+    pub async fn pending_rows(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    /// Add rows of transaction into batch, flush if batch is full
+    ///
+    /// If [`ClickHouseConfig::max_pending_rows`] would be exceeded and pending rows can't be
+    /// flushed, transaction is rejected with [`Error::PendingLimit`]. Error of flush of full
+    /// batch is returned too, though rows of transaction stay in batch & are inserted with
+    /// the next flush
+    pub async fn push(
+        &self,
+        signature: &Signature,
+        meta: &TransactionParsedMeta,
+    ) -> Result<(), Error> {
+        let rows = Tables::of_transaction(signature, meta);
+        let mut is_flushed = false;
+        let is_full = loop {
+            let mut pending = self.pending.lock().await;
+            if pending.len() + rows.len() <= self.config.max_pending_rows {
+                pending.instructions.extend(rows.instructions);
+                pending.events.extend(rows.events);
+                pending.token_transfers.extend(rows.token_transfers);
+                break pending.len() >= self.config.max_batch_rows;
+            }
+            if is_flushed {
+                return Err(Error::PendingLimit {
+                    pending: pending.len(),
+                });
+            }
+            drop(pending);
+
+            if let Err(err) = self.flush().await {
+                warn!("Error while flush of pending rows into ClickHouse: {err:?}");
+            }
+            is_flushed = true;
+        };
+        if is_full {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Insert all pending rows, rows of failed tables are returned into batch
+    pub async fn flush(&self) -> Result<(), Error> {
+        let batch = self.pending.lock().await.take();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        debug!("Flush {} rows into ClickHouse", batch.len());
+
+        let mut failed = Tables::new();
+        let mut result = Ok(());
+        if let Err(err) = self
+            .insert(&self.config.instructions_table, &batch.instructions)
+            .await
+        {
+            failed.instructions = batch.instructions;
+            result = Err(err);
+        }
+        if let Err(err) = self.insert(&self.config.events_table, &batch.events).await {
+            failed.events = batch.events;
+            result = Err(err);
+        }
+        if let Err(err) = self
+            .insert(&self.config.token_transfers_table, &batch.token_transfers)
+            .await
+        {
+            failed.token_transfers = batch.token_transfers;
+            result = Err(err);
+        }
+
+        if !failed.is_empty() {
+            let mut pending = self.pending.lock().await;
+            failed.instructions.append(&mut pending.instructions);
+            failed.events.append(&mut pending.events);
+            failed.token_transfers.append(&mut pending.token_transfers);
+            *pending = failed;
+        }
+        result
+    }
+
+    /// Flush pending rows at shutdown, after the reader is stopped
+    pub async fn close(&self) -> Result<(), Error> {
+        self.flush().await?;
+        info!("ClickHouse sink is closed");
+        Ok(())
+    }
+
+    /// Flush every `flush_interval` until sink is dropped
+    pub fn spawn_flusher(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let sink = Arc::downgrade(self);
+        let flush_interval = self.config.flush_interval;
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(sink) = Weak::upgrade(&sink) else {
+                    return;
+                };
+                if let Err(err) = sink.flush().await {
+                    error!("Error while flush into ClickHouse: {err:?}");
+                }
+            }
+        })
+    }
+
+    /// `transaction_consumer` of [`event_reader_service::EventsReader`], which pushes rows of
+    /// every consumed transaction
+    #[allow(clippy::type_complexity)]
+    pub fn transaction_consumer<R: Send + Sync + 'static>(
+        self: &Arc<Self>,
+    ) -> impl Send
+           + Sync
+           + Fn(
+        Signature,
+        TransactionParsedMeta,
+        Arc<solana_client::nonblocking::rpc_client::RpcClient>,
+        Arc<R>,
+    ) -> BoxFuture<'static, event_reader_service::Result<()>> {
+        let sink = Arc::clone(self);
+        move |signature, meta, _, _| {
+            let sink = Arc::clone(&sink);
+            async move { Ok(sink.push(&signature, &meta).await?) }.boxed()
+        }
+    }
+
+    /// `CREATE TABLE IF NOT EXISTS` of all tables
+    pub fn tables_ddl(&self) -> [String; 3] {
+        [
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+    signature String,
+    slot UInt64,
+    block_time Nullable(Int64),
+    program_id String,
+    call_index UInt64,
+    invoke_level UInt32,
+    instruction_index Nullable(UInt64),
+    inner_index Nullable(UInt64),
+    parent_program_id Nullable(String),
+    parent_call_index Nullable(UInt64),
+    data String,
+    accounts Array(String),
+    logs_count UInt64
+) ENGINE = ReplacingMergeTree ORDER BY (slot, signature, program_id, call_index)",
+                self.config.instructions_table
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+    signature String,
+    slot UInt64,
+    block_time Nullable(Int64),
+    program_id String,
+    call_index UInt64,
+    invoke_level UInt32,
+    log_index UInt64,
+    data String
+) ENGINE = ReplacingMergeTree ORDER BY (slot, signature, program_id, call_index, log_index)",
+                self.config.events_table
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (
+    signature String,
+    slot UInt64,
+    block_time Nullable(Int64),
+    wallet String,
+    owner Nullable(String),
+    mint String,
+    amount_diff Int128
+) ENGINE = ReplacingMergeTree ORDER BY (slot, signature, wallet, mint)",
+                self.config.token_transfers_table
+            ),
+        ]
+    }
+
+    /// Execute [`Self::tables_ddl`]
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        for ddl in self.tables_ddl() {
+            self.execute(&ddl, Vec::new()).await?;
+        }
+        Ok(())
+    }
+
+    async fn insert<R: Serialize>(&self, table: &str, rows: &[R]) -> Result<(), Error> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut body = Vec::new();
+        for row in rows {
+            serde_json::to_writer(&mut body, row)?;
+            body.push(b'\n');
+        }
+        self.execute(&format!("INSERT INTO {table} FORMAT JSONEachRow"), body)
+            .await
+    }
+
+    async fn execute(&self, query: &str, body: Vec<u8>) -> Result<(), Error> {
+        let mut url = self.url.clone();
+        url.query_pairs_mut().append_pair("query", query);
+        if let Some(database) = self.config.database.as_ref() {
+            url.query_pairs_mut().append_pair("database", database);
+        }
+
+        let mut request = self.client.post(url).body(body);
+        if let Some(user) = self.config.user.as_ref() {
+            request = request.basic_auth(user, self.config.password.as_ref());
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else {
+            Err(Error::Status {
+                status,
+                body: response.text().await.unwrap_or_default(),
+            })
+        }
+    }
+}
+
+impl Drop for ClickHouseSink {
+    fn drop(&mut self) {
+        let pending = self.pending.get_mut().len();
+        if pending != 0 {
+            error!("ClickHouse sink is dropped with {pending} rows waiting for flush");
+        }
+    }
+}
+
+#[cfg(test)]
+mod clickhouse_test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failed_flush_keeps_rows() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+        let meta = fixture.parse().unwrap();
+
+        // Nothing listens on port 1, so every insert fails
+        let sink = ClickHouseSink::new(
+            "http://127.0.0.1:1".parse().unwrap(),
+            ClickHouseConfig::default(),
+        );
+        sink.push(&signature, &meta).await.unwrap();
+        let rows = sink.pending_rows().await;
+        assert_eq!(rows, Tables::of_transaction(&signature, &meta).len());
+
+        assert!(matches!(sink.flush().await, Err(Error::Request(_))));
+        assert_eq!(sink.pending_rows().await, rows);
+        assert!(matches!(sink.close().await, Err(Error::Request(_))));
+        assert_eq!(sink.pending_rows().await, rows);
+    }
+
+    #[tokio::test]
+    async fn test_pending_limit_rejects_transaction() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+        let meta = fixture.parse().unwrap();
+        let rows = Tables::of_transaction(&signature, &meta).len();
+
+        let sink = ClickHouseSink::new(
+            "http://127.0.0.1:1".parse().unwrap(),
+            ClickHouseConfigBuilder::default()
+                .max_pending_rows(rows)
+                .build()
+                .unwrap(),
+        );
+        let consumer = sink.transaction_consumer::<()>();
+        let client = Arc::new(solana_client::nonblocking::rpc_client::RpcClient::new(
+            "http://127.0.0.1:1".to_owned(),
+        ));
+
+        consumer(signature, meta.clone(), Arc::clone(&client), Arc::new(()))
+            .await
+            .unwrap();
+        let err = consumer(signature, meta, client, Arc::new(()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, event_reader_service::Error::StorageError(_)));
+        assert_eq!(sink.pending_rows().await, rows);
+    }
+
+    #[tokio::test]
+    async fn test_failed_flush_of_full_batch_is_returned() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+        let meta = fixture.parse().unwrap();
+
+        let sink = ClickHouseSink::new(
+            "http://127.0.0.1:1".parse().unwrap(),
+            ClickHouseConfigBuilder::default()
+                .max_batch_rows(1)
+                .build()
+                .unwrap(),
+        );
+        assert!(matches!(
+            sink.push(&signature, &meta).await,
+            Err(Error::Request(_))
+        ));
+        assert_eq!(
+            sink.pending_rows().await,
+            Tables::of_transaction(&signature, &meta).len()
+        );
+    }
+}
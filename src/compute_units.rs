@@ -0,0 +1,148 @@
+//! Compute units profile of transaction, built by `Program ... consumed ...` logs
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    log_parser::{Error, Log, ProgramContext},
+    program_registry::{well_known_name, ProgramRegistry},
+};
+
+/// Compute units of one program invocation and its CPIs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvocationProfile {
+    pub program_ctx: ProgramContext,
+    /// Human name of program, see [`InvocationProfile::apply_registry`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program_name: Option<String>,
+    /// Consumed by invocation with all CPIs, `None` if consumed log is missed
+    pub consumed: Option<usize>,
+    /// Sum of `consumed` of direct CPIs
+    pub children_consumed: usize,
+    /// Consumed by invocation itself, without CPIs
+    pub self_consumed: Option<usize>,
+    /// Error, if invocation failed
+    pub err: Option<String>,
+    pub children: Vec<InvocationProfile>,
+}
+
+impl InvocationProfile {
+    fn new(program_ctx: ProgramContext) -> Self {
+        Self {
+            program_name: well_known_name(&program_ctx.program_id).map(str::to_owned),
+            program_ctx,
+            consumed: None,
+            children_consumed: 0,
+            self_consumed: None,
+            err: None,
+            children: vec![],
+        }
+    }
+
+    fn finalize(&mut self) {
+        self.children_consumed = self
+            .children
+            .iter()
+            .filter_map(|child| child.consumed)
+            .sum();
+        self.self_consumed = self
+            .consumed
+            .map(|consumed| consumed.saturating_sub(self.children_consumed));
+    }
+
+    /// Name this invocation & all CPIs by `registry` instead of well-known names
+    pub fn apply_registry(&mut self, registry: &ProgramRegistry) {
+        self.program_name = registry
+            .name(&self.program_ctx.program_id)
+            .map(str::to_owned);
+        self.children
+            .iter_mut()
+            .for_each(|child| child.apply_registry(registry));
+    }
+}
+
+/// Build tree of invocations with compute units from transaction logs
+///
+/// Unlike [`crate::log_parser::parse_events`], failed invocations are not an error,
+/// they are profiled with `err`
+pub fn profile(input: &[String]) -> Result<Vec<InvocationProfile>, Error> {
+    let mut roots = vec![];
+    let mut stack: Vec<InvocationProfile> = vec![];
+    let mut call_index_map = HashMap::new();
+
+    let mut finish = |stack: &mut Vec<InvocationProfile>, err: Option<String>| {
+        if let Some(mut invocation) = stack.pop() {
+            invocation.err = err.or(invocation.err.take());
+            invocation.finalize();
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(invocation),
+                None => roots.push(invocation),
+            }
+        }
+    };
+
+    for input_log in input {
+        match Log::new(input_log)? {
+            Log::ProgramInvoke { program_id, level } => {
+                let call_index = call_index_map.entry(program_id).or_insert(0);
+                stack.push(InvocationProfile::new(ProgramContext {
+                    program_id,
+                    program_call_index: *call_index,
+                    invoke_level: level,
+                }));
+                *call_index += 1;
+            }
+            Log::ProgramConsumed { consumed, .. } => {
+                if let Some(invocation) = stack.last_mut() {
+                    invocation.consumed = Some(consumed);
+                }
+            }
+            Log::ProgramResult { err, .. } => finish(&mut stack, err),
+            // Followed by `Program ... failed: ...` log, which will finish the invocation
+            Log::ProgramFailedComplete { err } => {
+                if let Some(invocation) = stack.last_mut() {
+                    invocation.err = Some(err);
+                }
+            }
+            Log::Truncated => break,
+            _ => {}
+        }
+    }
+
+    // Logs truncated, finish the rest of invocations
+    while !stack.is_empty() {
+        finish(&mut stack, None);
+    }
+
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod compute_units_test {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        let logs = [
+            "Program 11111111111111111111111111111111 invoke [1]",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 300 of 1000 compute units",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+            "Program 11111111111111111111111111111111 consumed 1000 of 1400 compute units",
+            "Program 11111111111111111111111111111111 success",
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+            "Program ComputeBudget111111111111111111111111111111 success",
+        ]
+        .map(str::to_owned);
+
+        let profile = profile(&logs).unwrap();
+        assert_eq!(profile.len(), 2);
+        assert_eq!(profile[0].program_name.as_deref(), Some("System Program"));
+        assert_eq!(profile[0].consumed, Some(1000));
+        assert_eq!(profile[0].children_consumed, 300);
+        assert_eq!(profile[0].self_consumed, Some(700));
+        assert_eq!(profile[0].children[0].self_consumed, Some(300));
+        assert_eq!(profile[1].consumed, None);
+    }
+}
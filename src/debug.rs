@@ -0,0 +1,200 @@
+//! Inspection of instructions for debugging of decomposers & decoders, which don't match
+//! expected instruction: discriminator, candidates of [`DecomposerRegistry`], accounts and
+//! hexdump of data
+
+use std::fmt::{self, Write};
+
+use crate::{
+    instruction_parser::{split_discriminator, DISCRIMINATOR_SIZE},
+    transaction_parser::{
+        DecomposerCandidate, DecomposerRegistry, DispatchMatch, Instruction, InstructionPosition,
+        ProgramContext, TransactionParsedMeta,
+    },
+};
+
+const HEXDUMP_LINE_SIZE: usize = 16;
+
+/// Hexdump of `data` in `hexdump -C` layout: offset, hex bytes & printable ASCII
+///
+/// ```
+/// use solana_events_parser::debug::hexdump;
+///
+/// assert_eq!(
+///     hexdump(b"anchor\x00\x01"),
+///     "00000000  61 6e 63 68 6f 72 00 01                          |anchor..|\n"
+/// );
+/// ```
+pub fn hexdump(data: &[u8]) -> String {
+    let mut dump = String::new();
+    for (index, line) in data.chunks(HEXDUMP_LINE_SIZE).enumerate() {
+        let _ = write!(dump, "{:08x} ", index * HEXDUMP_LINE_SIZE);
+        for position in 0..HEXDUMP_LINE_SIZE {
+            if position % 8 == 0 {
+                dump.push(' ');
+            }
+            match line.get(position) {
+                Some(byte) => {
+                    let _ = write!(dump, "{byte:02x} ");
+                }
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push('|');
+        dump.extend(line.iter().map(|byte| match byte {
+            0x20..=0x7e => *byte as char,
+            _ => '.',
+        }));
+        dump.push_str("|\n");
+    }
+    dump
+}
+
+/// Lowercase hex of discriminator, e.g. `f8c69e91e17587c8`
+pub fn discriminator_hex(discriminator: &[u8]) -> String {
+    discriminator.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+/// Everything known about why instruction is (not) matched, rendered by [`fmt::Display`]
+#[derive(Debug, Clone)]
+pub struct InstructionInspection<'r> {
+    pub program_ctx: ProgramContext,
+    pub position: Option<InstructionPosition>,
+    pub instruction: Instruction,
+    /// `None` if data is shorter than [`DISCRIMINATOR_SIZE`]
+    pub discriminator: Option<[u8; DISCRIMINATOR_SIZE]>,
+    /// Decomposers checked for instruction, see [`DecomposerRegistry::candidates`]
+    pub candidates: Vec<DecomposerCandidate<'r>>,
+    /// Discriminators of program known by registry, to compare with actual one
+    pub registered_discriminators: Vec<[u8; DISCRIMINATOR_SIZE]>,
+    /// Name of IDL instruction with the same discriminator, see
+    /// [`InstructionInspection::with_idl`]
+    pub idl_instruction: Option<String>,
+}
+
+impl<'r> InstructionInspection<'r> {
+    pub fn new(program_ctx: ProgramContext, instruction: Instruction) -> Self {
+        Self {
+            program_ctx,
+            position: None,
+            discriminator: split_discriminator(&instruction.data)
+                .and_then(|(discriminator, _)| discriminator.try_into().ok()),
+            instruction,
+            candidates: vec![],
+            registered_discriminators: vec![],
+            idl_instruction: None,
+        }
+    }
+
+    /// Inspection of instruction at `position` of parsed transaction, `None` if there is no
+    /// such instruction
+    pub fn of_transaction(
+        transaction: &TransactionParsedMeta,
+        position: InstructionPosition,
+    ) -> Option<Self> {
+        let (program_ctx, _) = transaction
+            .ix_positions
+            .iter()
+            .find(|(_, ix_position)| **ix_position == position)?;
+        let (instruction, _) = transaction.meta.get(program_ctx)?;
+
+        let mut inspection = Self::new(*program_ctx, instruction.clone());
+        inspection.position = Some(position);
+        Some(inspection)
+    }
+
+    pub fn with_registry(mut self, registry: &'r DecomposerRegistry) -> Self {
+        self.candidates = registry.candidates(&self.program_ctx, &self.instruction);
+        self.registered_discriminators = registry.discriminators(&self.program_ctx.program_id);
+        self
+    }
+
+    #[cfg(feature = "idl")]
+    pub fn with_idl(mut self, idl: &crate::idl::Idl) -> Self {
+        self.idl_instruction = self.discriminator.and_then(|discriminator| {
+            idl.instructions
+                .iter()
+                .find(|ix| crate::idl::Idl::instruction_discriminator(&ix.name) == discriminator)
+                .map(|ix| ix.name.clone())
+        });
+        self
+    }
+}
+
+impl fmt::Display for InstructionInspection<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Program: {} (call {}, invoke level {})",
+            self.program_ctx.program_id,
+            self.program_ctx.program_call_index,
+            self.program_ctx.invoke_level
+        )?;
+        match self.position {
+            Some(position) => writeln!(f, " at {position}")?,
+            None => writeln!(f)?,
+        }
+
+        match self.discriminator {
+            Some(discriminator) => writeln!(
+                f,
+                "Discriminator: {} {discriminator:?}",
+                discriminator_hex(&discriminator)
+            )?,
+            None => writeln!(
+                f,
+                "Discriminator: none, data is shorter than {DISCRIMINATOR_SIZE} bytes"
+            )?,
+        }
+        if let Some(idl_instruction) = self.idl_instruction.as_ref() {
+            writeln!(f, "IDL instruction: {idl_instruction}")?;
+        }
+
+        if !self.registered_discriminators.is_empty() {
+            writeln!(f, "Registered discriminators of program:")?;
+            for discriminator in &self.registered_discriminators {
+                let mark = match Some(*discriminator) == self.discriminator {
+                    true => " (matched)",
+                    false => "",
+                };
+                writeln!(f, "  {}{mark}", discriminator_hex(discriminator))?;
+            }
+        }
+        if !self.candidates.is_empty() {
+            writeln!(f, "Decomposers:")?;
+            for candidate in &self.candidates {
+                writeln!(
+                    f,
+                    "  {} by {}: {}",
+                    candidate.name.unwrap_or("<unnamed>"),
+                    match candidate.dispatch {
+                        DispatchMatch::Discriminator => "discriminator",
+                        DispatchMatch::Program => "program",
+                        DispatchMatch::Fallback => "fallback",
+                    },
+                    match candidate.is_decomposable {
+                        true => "decomposable",
+                        false => "not decomposable",
+                    }
+                )?;
+            }
+        }
+
+        writeln!(f, "Accounts ({}):", self.instruction.accounts.len())?;
+        for (index, account) in self.instruction.accounts.iter().enumerate() {
+            write!(f, "  {index:>3}: {}", account.pubkey)?;
+            if account.is_signer {
+                write!(f, " signer")?;
+            }
+            if account.is_writable {
+                write!(f, " writable")?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Data ({} bytes):", self.instruction.data.len())?;
+        write!(f, "{}", hexdump(&self.instruction.data))
+    }
+}
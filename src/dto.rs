@@ -0,0 +1,513 @@
+//! Serde-friendly mirror of [`TransactionParsedMeta`] for non-rust consumers
+//!
+//! All maps are keyed by strings, pubkeys are base58 and amounts are stringified (JS numbers
+//! can't hold `i128`). [`ProgramContext`] is keyed as `"<program_id>:<level>:<call_index>"`
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    num::ParseIntError,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash::ParseHashError, pubkey::ParsePubkeyError};
+
+use crate::{
+    instruction_parser::InstructionPosition,
+    log_parser::{Level, ProgramReturn},
+    transaction_parser::{
+        AccountMeta, ComputeBudget, Instruction, MessageMeta, ParseStamp, ProgramContext,
+        ProgramLog, Pubkey, Slot, TransactionParsedMeta, TransactionVersion, UnixTimestamp,
+        WalletContext,
+    },
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid program context key: {0}, expected `<program_id>:<level>:<call_index>`")]
+    InvalidProgramContextKey(String),
+    #[error(transparent)]
+    ParsePubkey(#[from] ParsePubkeyError),
+    #[error(transparent)]
+    ParseInt(#[from] ParseIntError),
+    #[error(transparent)]
+    Base58(#[from] bs58::decode::Error),
+    #[error(transparent)]
+    ParseHash(#[from] ParseHashError),
+}
+
+/// `"<program_id>:<level>:<call_index>"`
+pub fn program_ctx_key(ctx: &ProgramContext) -> String {
+    format!(
+        "{}:{}:{}",
+        ctx.program_id, ctx.invoke_level, ctx.program_call_index
+    )
+}
+
+/// Inverse of [`program_ctx_key`]
+pub fn parse_program_ctx_key(key: &str) -> Result<ProgramContext, Error> {
+    let mut parts = key.split(':');
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(program_id), Some(level), Some(call_index), None) => Ok(ProgramContext {
+            program_id: program_id.parse()?,
+            invoke_level: level.parse::<Level>()?,
+            program_call_index: call_index.parse()?,
+        }),
+        _ => Err(Error::InvalidProgramContextKey(key.to_owned())),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionParsedMetaDto {
+    /// Instructions with logs by [`program_ctx_key`]
+    pub meta: BTreeMap<String, InstructionWithLogsDto>,
+    pub slot: Slot,
+    pub block_time: Option<UnixTimestamp>,
+    /// Stringified lamports diff by account
+    pub lamports_changes: BTreeMap<String, String>,
+    /// Sorted by wallet address & mint
+    pub token_balances_changes: Vec<TokenBalanceChangeDto>,
+    /// [`program_ctx_key`] of parent by [`program_ctx_key`] of child
+    pub parent_ix: BTreeMap<String, String>,
+    /// [`program_ctx_key`] of top-level instructions in order of execution
+    #[serde(default)]
+    pub root_ixs: Vec<String>,
+    #[serde(default)]
+    pub compute_budget: ComputeBudget,
+    /// base58 of recent blockhash, or of nonce value if `durable_nonce` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent_blockhash: Option<String>,
+    /// base58 of nonce account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub durable_nonce: Option<String>,
+    /// `"legacy"` or number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<TransactionVersion>,
+    /// [`crate::transaction_parser::PARSER_VERSION`] of parser produced it, `0` if unknown
+    #[serde(default)]
+    pub parser_version: u32,
+    /// base58 of [`crate::transaction_parser::input_hash`] of parsed transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionWithLogsDto {
+    pub instruction: InstructionDto,
+    pub logs: Vec<ProgramLogDto>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<InstructionPosition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionDto {
+    pub program_id: String,
+    pub accounts: Vec<AccountMetaDto>,
+    /// base58 of instruction data, as in RPC responses
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountMetaDto {
+    pub pubkey: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBalanceChangeDto {
+    pub wallet_address: String,
+    pub wallet_owner: Option<String>,
+    pub token_mint: String,
+    /// Stringified token amount diff
+    pub amount: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum ProgramLogDto {
+    DeployedProgram(String),
+    UpgradedProgram(String),
+    Data(String),
+    Log(String),
+    Return {
+        program_id: String,
+        data: String,
+    },
+    /// [`program_ctx_key`] of invoked instruction
+    Invoke(String),
+    Consumed {
+        consumed: usize,
+        all: usize,
+    },
+    #[cfg(feature = "unknown_log")]
+    UnknownFormat {
+        unknown_log_string: String,
+    },
+    #[cfg(feature = "json_log")]
+    Json(serde_json::Value),
+}
+
+impl From<&Instruction> for InstructionDto {
+    fn from(ix: &Instruction) -> Self {
+        Self {
+            program_id: ix.program_id.to_string(),
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| AccountMetaDto {
+                    pubkey: meta.pubkey.to_string(),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: bs58::encode(&ix.data).into_string(),
+        }
+    }
+}
+
+impl TryFrom<InstructionDto> for Instruction {
+    type Error = Error;
+
+    fn try_from(ix: InstructionDto) -> Result<Self, Self::Error> {
+        Ok(Instruction {
+            program_id: ix.program_id.parse()?,
+            accounts: ix
+                .accounts
+                .into_iter()
+                .map(|meta| {
+                    Ok(AccountMeta {
+                        pubkey: meta.pubkey.parse()?,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+            data: bs58::decode(ix.data).into_vec()?,
+        })
+    }
+}
+
+impl From<&ProgramLog> for ProgramLogDto {
+    fn from(log: &ProgramLog) -> Self {
+        match log {
+            ProgramLog::DeployedProgram(program_id) => {
+                Self::DeployedProgram(program_id.to_string())
+            }
+            ProgramLog::UpgradedProgram(program_id) => {
+                Self::UpgradedProgram(program_id.to_string())
+            }
+            ProgramLog::Data(data) => Self::Data(data.clone()),
+            ProgramLog::Log(log) => Self::Log(log.clone()),
+            ProgramLog::Return(ProgramReturn { program_id, data }) => Self::Return {
+                program_id: program_id.to_string(),
+                data: data.clone(),
+            },
+            ProgramLog::Invoke(ctx) => Self::Invoke(program_ctx_key(ctx)),
+            ProgramLog::Consumed { consumed, all } => Self::Consumed {
+                consumed: *consumed,
+                all: *all,
+            },
+            #[cfg(feature = "unknown_log")]
+            ProgramLog::UnknownFormat { unknown_log_string } => Self::UnknownFormat {
+                unknown_log_string: unknown_log_string.clone(),
+            },
+            #[cfg(feature = "json_log")]
+            ProgramLog::Json(value) => Self::Json(value.clone()),
+        }
+    }
+}
+
+impl TryFrom<ProgramLogDto> for ProgramLog {
+    type Error = Error;
+
+    fn try_from(log: ProgramLogDto) -> Result<Self, Self::Error> {
+        Ok(match log {
+            ProgramLogDto::DeployedProgram(program_id) => {
+                Self::DeployedProgram(program_id.parse()?)
+            }
+            ProgramLogDto::UpgradedProgram(program_id) => {
+                Self::UpgradedProgram(program_id.parse()?)
+            }
+            ProgramLogDto::Data(data) => Self::Data(data),
+            ProgramLogDto::Log(log) => Self::Log(log),
+            ProgramLogDto::Return { program_id, data } => Self::Return(ProgramReturn {
+                program_id: program_id.parse()?,
+                data,
+            }),
+            ProgramLogDto::Invoke(key) => Self::Invoke(parse_program_ctx_key(&key)?),
+            ProgramLogDto::Consumed { consumed, all } => Self::Consumed { consumed, all },
+            #[cfg(feature = "unknown_log")]
+            ProgramLogDto::UnknownFormat { unknown_log_string } => {
+                Self::UnknownFormat { unknown_log_string }
+            }
+            #[cfg(feature = "json_log")]
+            ProgramLogDto::Json(value) => Self::Json(value),
+        })
+    }
+}
+
+impl From<&TransactionParsedMeta> for TransactionParsedMetaDto {
+    fn from(meta: &TransactionParsedMeta) -> Self {
+        Self {
+            meta: meta
+                .meta
+                .iter()
+                .map(|(ctx, (ix, logs))| {
+                    (
+                        program_ctx_key(ctx),
+                        InstructionWithLogsDto {
+                            instruction: ix.into(),
+                            logs: logs.iter().map(ProgramLogDto::from).collect(),
+                            position: meta.ix_positions.get(ctx).copied(),
+                        },
+                    )
+                })
+                .collect(),
+            slot: meta.slot,
+            block_time: meta.block_time,
+            lamports_changes: meta
+                .lamports_changes
+                .iter()
+                .map(|(account, diff)| (account.to_string(), diff.to_string()))
+                .collect(),
+            token_balances_changes: meta
+                .token_balances_changes
+                .iter()
+                .map(|(wallet, diff)| TokenBalanceChangeDto {
+                    wallet_address: wallet.wallet_address.to_string(),
+                    wallet_owner: wallet.wallet_owner.as_ref().map(Pubkey::to_string),
+                    token_mint: wallet.token_mint.to_string(),
+                    amount: diff.to_string(),
+                })
+                .sorted_by(|a, b| {
+                    (&a.wallet_address, &a.token_mint).cmp(&(&b.wallet_address, &b.token_mint))
+                })
+                .collect(),
+            parent_ix: meta
+                .parent_ix
+                .iter()
+                .map(|(child, parent)| (program_ctx_key(child), program_ctx_key(parent)))
+                .collect(),
+            root_ixs: meta.root_ixs.iter().map(program_ctx_key).collect(),
+            compute_budget: meta.compute_budget,
+            recent_blockhash: meta
+                .message
+                .recent_blockhash
+                .as_ref()
+                .map(ToString::to_string),
+            durable_nonce: meta.message.durable_nonce.as_ref().map(Pubkey::to_string),
+            version: meta.message.version.clone(),
+            parser_version: meta.stamp.parser_version,
+            input_hash: (meta.stamp != ParseStamp::default())
+                .then(|| meta.stamp.input_hash.to_string()),
+        }
+    }
+}
+
+impl From<TransactionParsedMeta> for TransactionParsedMetaDto {
+    fn from(meta: TransactionParsedMeta) -> Self {
+        Self::from(&meta)
+    }
+}
+
+/// Fails on malformed keys, pubkeys or amounts
+impl TryFrom<TransactionParsedMetaDto> for TransactionParsedMeta {
+    type Error = Error;
+
+    fn try_from(dto: TransactionParsedMetaDto) -> Result<Self, Self::Error> {
+        Ok(TransactionParsedMeta {
+            ix_positions: dto
+                .meta
+                .iter()
+                .filter_map(|(key, ix)| Some((key, ix.position?)))
+                .map(|(key, position)| Ok((parse_program_ctx_key(key)?, position)))
+                .collect::<Result<_, Error>>()?,
+            meta: dto
+                .meta
+                .into_iter()
+                .map(
+                    |(
+                        key,
+                        InstructionWithLogsDto {
+                            instruction, logs, ..
+                        },
+                    )| {
+                        Ok((
+                            parse_program_ctx_key(&key)?,
+                            (
+                                instruction.try_into()?,
+                                logs.into_iter()
+                                    .map(ProgramLog::try_from)
+                                    .collect::<Result<_, _>>()?,
+                            ),
+                        ))
+                    },
+                )
+                .collect::<Result<HashMap<_, _>, Error>>()?,
+            slot: dto.slot,
+            block_time: dto.block_time,
+            lamports_changes: dto
+                .lamports_changes
+                .into_iter()
+                .map(|(account, diff)| Ok((account.parse()?, diff.parse()?)))
+                .collect::<Result<_, Error>>()?,
+            token_balances_changes: dto
+                .token_balances_changes
+                .into_iter()
+                .map(|change| {
+                    Ok((
+                        WalletContext {
+                            wallet_address: change.wallet_address.parse()?,
+                            wallet_owner: change
+                                .wallet_owner
+                                .map(|owner| owner.parse())
+                                .transpose()?,
+                            token_mint: change.token_mint.parse()?,
+                        },
+                        change.amount.parse()?,
+                    ))
+                })
+                .collect::<Result<_, Error>>()?,
+            parent_ix: dto
+                .parent_ix
+                .into_iter()
+                .map(|(child, parent)| {
+                    Ok((
+                        parse_program_ctx_key(&child)?,
+                        parse_program_ctx_key(&parent)?,
+                    ))
+                })
+                .collect::<Result<_, Error>>()?,
+            root_ixs: dto
+                .root_ixs
+                .iter()
+                .map(|key| parse_program_ctx_key(key))
+                .collect::<Result<_, _>>()?,
+            compute_budget: dto.compute_budget,
+            message: MessageMeta {
+                recent_blockhash: dto.recent_blockhash.map(|hash| hash.parse()).transpose()?,
+                durable_nonce: dto.durable_nonce.map(|nonce| nonce.parse()).transpose()?,
+                version: dto.version,
+            },
+            stamp: ParseStamp {
+                parser_version: dto.parser_version,
+                input_hash: dto
+                    .input_hash
+                    .map(|hash| hash.parse())
+                    .transpose()?
+                    .unwrap_or_default(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod dto_test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let program_id = Pubkey::new_unique();
+        let root = ProgramContext {
+            program_id,
+            program_call_index: 0,
+            invoke_level: Level::new(1).unwrap(),
+        };
+        let child = ProgramContext {
+            program_id,
+            program_call_index: 1,
+            invoke_level: Level::new(2).unwrap(),
+        };
+        let ix = Instruction {
+            program_id,
+            accounts: vec![AccountMeta::new(Pubkey::new_unique(), true)],
+            data: vec![1, 2, 3],
+        };
+
+        let meta = TransactionParsedMeta {
+            meta: HashMap::from([
+                (
+                    root,
+                    (
+                        ix.clone(),
+                        vec![
+                            ProgramLog::Invoke(child),
+                            ProgramLog::Consumed {
+                                consumed: 10,
+                                all: 100,
+                            },
+                        ],
+                    ),
+                ),
+                (child, (ix, vec![ProgramLog::Data("AAAA".to_owned())])),
+            ]),
+            slot: 42,
+            block_time: None,
+            lamports_changes: HashMap::from([(program_id, i128::MIN)]),
+            token_balances_changes: HashMap::from([(
+                WalletContext {
+                    wallet_address: Pubkey::new_unique(),
+                    wallet_owner: None,
+                    token_mint: Pubkey::new_unique(),
+                },
+                u64::MAX as i128,
+            )]),
+            parent_ix: HashMap::from([(child, root)]),
+            root_ixs: vec![root],
+            compute_budget: ComputeBudget {
+                unit_limit: Some(200_000),
+                unit_price_micro_lamports: None,
+            },
+            ix_positions: HashMap::from([
+                (
+                    root,
+                    InstructionPosition {
+                        instruction_index: 0,
+                        inner_index: None,
+                    },
+                ),
+                (
+                    child,
+                    InstructionPosition {
+                        instruction_index: 0,
+                        inner_index: Some(0),
+                    },
+                ),
+            ]),
+            message: MessageMeta {
+                recent_blockhash: Some(solana_sdk::hash::Hash::new_unique()),
+                durable_nonce: Some(Pubkey::new_unique()),
+                version: Some(TransactionVersion::Number(0)),
+            },
+            stamp: ParseStamp {
+                parser_version: 1,
+                input_hash: solana_sdk::hash::Hash::new_unique(),
+            },
+        };
+
+        let dto = TransactionParsedMetaDto::from(&meta);
+        assert_eq!(dto.root_ixs, vec![format!("{program_id}:1:0")]);
+        assert_eq!(
+            dto.lamports_changes[&program_id.to_string()],
+            i128::MIN.to_string()
+        );
+
+        let json = serde_json::to_string(&dto).unwrap();
+        let restored = TransactionParsedMeta::try_from(
+            serde_json::from_str::<TransactionParsedMetaDto>(&json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(restored.meta, meta.meta);
+        assert_eq!(restored.lamports_changes, meta.lamports_changes);
+        assert_eq!(restored.token_balances_changes, meta.token_balances_changes);
+        assert_eq!(restored.parent_ix, meta.parent_ix);
+        assert_eq!(restored.root_ixs, meta.root_ixs);
+        assert_eq!(restored.compute_budget, meta.compute_budget);
+        assert_eq!(restored.ix_positions, meta.ix_positions);
+        assert_eq!(restored.message, meta.message);
+        assert_eq!(restored.stamp, meta.stamp);
+
+        assert!(parse_program_ctx_key("invalid").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Crate-level error over errors of parsers & [`crate::event_reader_service`]
+//!
+//! Errors of modules wrap each other (e.g. log binding error inside transaction parsing
+//! error inside reader error), so matching on them is fragile. [`ErrorCode`] of any of them
+//! is the code of the innermost one, stable across versions, so downstream services can
+//! branch on class of error or export it as metric label
+
+use std::fmt;
+
+#[cfg(feature = "event-reader")]
+use crate::event_reader_service;
+use crate::{instruction_parser, log_parser, transaction_parser};
+
+/// Stable numeric code of error, grouped by thousands:
+/// - `1xxx` - logs parsing & binding
+/// - `2xxx` - transaction & instruction parsing
+/// - `3xxx` - decoding of data into user types
+/// - `4xxx` - RPC
+/// - `5xxx` - events reader
+///
+/// Codes are never reused, new ones are only appended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+#[repr(u16)]
+pub enum ErrorCode {
+    /// Log line or its fields (pubkey, level) can't be parsed
+    InvalidLog = 1000,
+    /// Logs don't form valid tree of invocations
+    LogBinding = 1001,
+    /// Logs are truncated by runtime
+    LogTruncated = 1002,
+    /// Logged program failure
+    ProgramFailure = 1003,
+
+    /// Transaction has no `meta`, logs or inner instructions
+    MissingTransactionData = 2000,
+    /// Transaction, instruction data or pubkey can't be decoded
+    InvalidTransaction = 2001,
+    /// Logs & instructions of transaction don't match
+    LogsInstructionsMismatch = 2002,
+    /// Instruction consumer failed
+    InstructionConsume = 2003,
+
+    /// Discriminator, owner, accounts or borsh layout of data don't match user type
+    Decode = 3000,
+
+    /// RPC request failed
+    Rpc = 4000,
+    /// Transaction isn't found by RPC
+    TransactionNotFound = 4001,
+
+    /// Storage of reader failed
+    Storage = 5000,
+    /// Websocket subscription failed
+    Websocket = 5001,
+    /// Consumers exceeded timeout
+    ConsumerTimeout = 5002,
+    /// Task of reader panicked or was cancelled
+    Task = 5003,
+    /// Signature of notification can't be parsed
+    InvalidSignature = 5004,
+}
+
+impl ErrorCode {
+    pub fn as_u16(self) -> u16 {
+        self as u16
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{}", self.as_u16())
+    }
+}
+
+impl log_parser::Error {
+    pub fn code(&self) -> ErrorCode {
+        use log_parser::Error::*;
+        match self {
+            Base58Error(_) | ParseLevelError(_) | WrongPubkeySize(_) | BadLogLine(_) => {
+                ErrorCode::InvalidLog
+            }
+            BindEventError
+            | UnexpectedProgramResult { .. }
+            | MisplaceConsumed { .. }
+            | EmptyInvokeLogContext { .. }
+            | ErrorInRegexp => ErrorCode::LogBinding,
+            MixedMultilineLog { .. } => ErrorCode::InvalidLog,
+            LogTruncated { .. } => ErrorCode::LogTruncated,
+            ErrorLog { .. } | ErrorToCompleteLog { .. } => ErrorCode::ProgramFailure,
+        }
+    }
+}
+
+impl instruction_parser::Error {
+    pub fn code(&self) -> ErrorCode {
+        use instruction_parser::Error::*;
+        match self {
+            EmptyMetaInTransaction(_) | EmptyInnerInstructionInTransaction(_) => {
+                ErrorCode::MissingTransactionData
+            }
+            ErrorWhileDecodeTransaction(_)
+            | ErrorWhileDecodeData(_)
+            | ParsedInnerInstructionNotSupported
+            | PubkeyParseError(_) => ErrorCode::InvalidTransaction,
+        }
+    }
+}
+
+impl transaction_parser::Error {
+    pub fn code(&self) -> ErrorCode {
+        use transaction_parser::Error::*;
+        match self {
+            SolanaClientResult(_) => ErrorCode::Rpc,
+            LogParseError(err) => err.code(),
+            InstructionParsingError(err) => err.code(),
+            EmptyMetaInTransaction(_) | EmptyLogsInTransaction(_) => {
+                ErrorCode::MissingTransactionData
+            }
+            ParsePubkeyError(_)
+            | ErrorWhileDecodeTransaction(_)
+            | ParseIntError(_)
+            | WrongBalanceAccountConsistance(_) => ErrorCode::InvalidTransaction,
+            InstructionLogsConsistencyError { .. } | InstructionLogsOwnerError { .. } => {
+                ErrorCode::LogsInstructionsMismatch
+            }
+            WrongParserFound | ErrorWhileConsume(_) => ErrorCode::InstructionConsume,
+            DecodeError(err) => err.code(),
+            SerdeJsonError(_) => ErrorCode::InvalidTransaction,
+            InvalidAccountsCount { .. } => ErrorCode::Decode,
+        }
+    }
+}
+
+impl transaction_parser::DecodeError {
+    pub fn code(&self) -> ErrorCode {
+        ErrorCode::Decode
+    }
+}
+
+#[cfg(feature = "event-reader")]
+impl event_reader_service::Error {
+    pub fn code(&self) -> ErrorCode {
+        use event_reader_service::Error::*;
+        match self {
+            TokioJoinError(_) => ErrorCode::Task,
+            EventParserError(err) => err.code(),
+            SignatureParsingError(_) => ErrorCode::InvalidSignature,
+            WebsocketError(_) => ErrorCode::Websocket,
+            ClientError(_) | Client(_) => ErrorCode::Rpc,
+            StorageError(_) => ErrorCode::Storage,
+            TransactionNotFound(_) => ErrorCode::TransactionNotFound,
+            ConsumerTimeout { .. } => ErrorCode::ConsumerTimeout,
+        }
+    }
+}
+
+/// Any error of parsers & reader, with optional context chain
+#[derive(Debug, thiserror::Error)]
+pub enum EventsParserError {
+    #[error(transparent)]
+    LogParser(#[from] log_parser::Error),
+    #[error(transparent)]
+    InstructionParser(#[from] instruction_parser::Error),
+    #[error(transparent)]
+    TransactionParser(#[from] transaction_parser::Error),
+    #[error(transparent)]
+    Decode(#[from] transaction_parser::DecodeError),
+    #[cfg(feature = "event-reader")]
+    #[error(transparent)]
+    EventReader(#[from] event_reader_service::Error),
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<EventsParserError>,
+    },
+}
+
+impl EventsParserError {
+    /// Code of the innermost error, context doesn't change it
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::LogParser(err) => err.code(),
+            Self::InstructionParser(err) => err.code(),
+            Self::TransactionParser(err) => err.code(),
+            Self::Decode(err) => err.code(),
+            #[cfg(feature = "event-reader")]
+            Self::EventReader(err) => err.code(),
+            Self::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Wrap error with `context`, e.g. signature or program being processed
+    pub fn context(self, context: impl Into<String>) -> Self {
+        Self::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Contexts from the outermost one
+    pub fn contexts(&self) -> impl Iterator<Item = &str> {
+        let mut current = Some(self);
+        std::iter::from_fn(move || match current.take()? {
+            Self::Context { context, source } => {
+                current = Some(source);
+                Some(context.as_str())
+            }
+            _ => None,
+        })
+    }
+}
+
+/// [`EventsParserError::context`] for results of any error of crate
+pub trait ResultExt<T> {
+    fn context(self, context: impl Into<String>) -> Result<T, EventsParserError>;
+
+    fn with_context<C: Into<String>>(
+        self,
+        context: impl FnOnce() -> C,
+    ) -> Result<T, EventsParserError>;
+}
+
+impl<T, E: Into<EventsParserError>> ResultExt<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T, EventsParserError> {
+        self.map_err(|err| err.into().context(context))
+    }
+
+    fn with_context<C: Into<String>>(
+        self,
+        context: impl FnOnce() -> C,
+    ) -> Result<T, EventsParserError> {
+        self.map_err(|err| err.into().context(context()))
+    }
+}
+
+#[cfg(test)]
+mod error_test {
+    use super::*;
+
+    #[test]
+    fn test_error_code() {
+        let err =
+            transaction_parser::Error::LogParseError(log_parser::Error::LogTruncated { index: 3 });
+        assert_eq!(err.code(), ErrorCode::LogTruncated);
+
+        let err = Err::<(), _>(err)
+            .context("Parse transaction")
+            .context("Resync")
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::LogTruncated);
+        assert_eq!(err.code().as_u16(), 1002);
+        assert_eq!(
+            err.contexts().collect::<Vec<_>>(),
+            ["Resync", "Parse transaction"]
+        );
+        assert_eq!(
+            err.to_string(),
+            "Resync: Parse transaction: Logs truncated at index 3"
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! Filters of IDL decoded events, configurable without recompiling consumers
+//!
+//! [`Filter`] is built by API or parsed from expression:
+//!
+//! ```text
+//! program == 9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin && event == "SwapEvent" && amount > 1000
+//! ```
+//!
+//! - `program` - program id, `event` - name of event in IDL, other identifiers - fields of
+//!   decoded event, nested ones by dots (`pool.fee`)
+//! - operators: `==`, `!=`, `>`, `>=`, `<`, `<=`, `&&`, `||`, `!` and parentheses, `&&` binds
+//!   tighter than `||`
+//! - values: numbers, `true`/`false`, `"quoted"` or bare (e.g. base58 pubkey) strings
+//!
+//! Numbers are compared as numbers, even if decoded as strings (`u128`/`i128` fields).
+//! Comparison with missing field is always false
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{idl::Decoded, transaction_parser::Pubkey};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid filter at {position}: {message}")]
+pub struct ParseError {
+    /// Byte offset in expression
+    pub position: usize,
+    pub message: String,
+}
+
+/// Left side of comparison
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// Program id, base58
+    Program,
+    /// Name of event in IDL
+    Event,
+    /// Path in decoded event data
+    Data(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Integer(i128),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl From<i128> for Literal {
+    fn from(value: i128) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(value: i64) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<u64> for Literal {
+    fn from(value: u64) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<i32> for Literal {
+    fn from(value: i32) -> Self {
+        Self::Integer(value.into())
+    }
+}
+
+impl From<f64> for Literal {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for Literal {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Compare {
+        field: Field,
+        op: Op,
+        value: Literal,
+    },
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+}
+
+/// Builder of comparison with field, see [`Filter::field`]
+#[derive(Debug, Clone)]
+pub struct FieldFilter(Field);
+
+macro_rules! comparisons {
+    ($($name:ident => $op:ident),*) => {
+        impl FieldFilter {
+            $(
+                pub fn $name(self, value: impl Into<Literal>) -> Filter {
+                    Filter::Compare {
+                        field: self.0,
+                        op: Op::$op,
+                        value: value.into(),
+                    }
+                }
+            )*
+        }
+    };
+}
+comparisons!(eq => Eq, ne => Ne, gt => Gt, ge => Ge, lt => Lt, le => Le);
+
+impl Filter {
+    /// Events of `program_id`
+    pub fn program(program_id: Pubkey) -> Self {
+        FieldFilter(Field::Program).eq(program_id.to_string())
+    }
+
+    /// Events with IDL name `name`
+    pub fn event(name: impl Into<String>) -> Self {
+        FieldFilter(Field::Event).eq(name.into())
+    }
+
+    /// Comparison of field of decoded event by dotted `path`
+    pub fn field(path: &str) -> FieldFilter {
+        FieldFilter(Field::Data(path.split('.').map(str::to_owned).collect()))
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Does event decoded from logs of `program_id` match filter
+    pub fn matches(&self, program_id: &Pubkey, event: &Decoded) -> bool {
+        match self {
+            Self::Compare { field, op, value } => {
+                let actual = match field {
+                    Field::Program => Some(Value::String(program_id.to_string())),
+                    Field::Event => Some(Value::String(event.name.clone())),
+                    Field::Data(path) => path
+                        .iter()
+                        .try_fold(&event.data, |value, key| value.get(key))
+                        .cloned(),
+                };
+                actual
+                    .and_then(|actual| compare(&actual, value))
+                    .is_some_and(|ordering| match op {
+                        Op::Eq => ordering.is_eq(),
+                        Op::Ne => ordering.is_ne(),
+                        Op::Gt => ordering.is_gt(),
+                        Op::Ge => ordering.is_ge(),
+                        Op::Lt => ordering.is_lt(),
+                        Op::Le => ordering.is_le(),
+                    })
+            }
+            Self::And(left, right) => {
+                left.matches(program_id, event) && right.matches(program_id, event)
+            }
+            Self::Or(left, right) => {
+                left.matches(program_id, event) || right.matches(program_id, event)
+            }
+            Self::Not(filter) => !filter.matches(program_id, event),
+        }
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Self::Output {
+        Self::Not(Box::new(self))
+    }
+}
+
+/// Order of `actual` relative to `expected`, `None` if they are incomparable
+fn compare(actual: &Value, expected: &Literal) -> Option<Ordering> {
+    enum Number {
+        Integer(i128),
+        Float(f64),
+    }
+    let number = |value: &Value| match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from))
+            .map(Number::Integer)
+            .or_else(|| number.as_f64().map(Number::Float)),
+        Value::String(string) => string
+            .parse::<i128>()
+            .map(Number::Integer)
+            .ok()
+            .or_else(|| string.parse::<f64>().ok().map(Number::Float)),
+        _ => None,
+    };
+    let as_float = |number: Number| match number {
+        Number::Integer(integer) => integer as f64,
+        Number::Float(float) => float,
+    };
+
+    match expected {
+        Literal::Integer(expected) => match number(actual)? {
+            Number::Integer(actual) => Some(actual.cmp(expected)),
+            Number::Float(actual) => actual.partial_cmp(&(*expected as f64)),
+        },
+        Literal::Float(expected) => as_float(number(actual)?).partial_cmp(expected),
+        Literal::Bool(expected) => actual.as_bool().map(|actual| actual.cmp(expected)),
+        Literal::String(expected) => actual.as_str().map(|actual| actual.cmp(expected)),
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Program => write!(f, "program"),
+            Field::Event => write!(f, "event"),
+            Field::Data(path) => write!(f, "{}", path.join(".")),
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+        })
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Integer(value) => write!(f, "{value}"),
+            Literal::Float(value) => write!(f, "{value:?}"),
+            Literal::Bool(value) => write!(f, "{value}"),
+            Literal::String(value) => write!(f, "{value:?}"),
+        }
+    }
+}
+
+/// Expression, parsed back into the same [`Filter`]
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Compare { field, op, value } => write!(f, "{field} {op} {value}"),
+            Filter::And(left, right) => write!(f, "({left} && {right})"),
+            Filter::Or(left, right) => write!(f, "({left} || {right})"),
+            Filter::Not(filter) => write!(f, "!({filter})"),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input, position: 0 };
+        let filter = parser.or()?;
+        parser.skip_whitespace();
+        match parser.position == input.len() {
+            true => Ok(filter),
+            false => Err(parser.error("unexpected trailing input")),
+        }
+    }
+}
+
+/// Recursive descent parser of filter expressions
+struct Parser<'i> {
+    input: &'i str,
+    position: usize,
+}
+
+impl<'i> Parser<'i> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            position: self.position,
+            message: message.into(),
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `token` if input continues with it
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        match self.rest().starts_with(token) {
+            true => {
+                self.position += token.len();
+                true
+            }
+            false => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, ParseError> {
+        let mut filter = self.and()?;
+        while self.eat("||") {
+            filter = filter.or(self.and()?);
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, ParseError> {
+        let mut filter = self.unary()?;
+        while self.eat("&&") {
+            filter = filter.and(self.unary()?);
+        }
+        Ok(filter)
+    }
+
+    fn unary(&mut self) -> Result<Filter, ParseError> {
+        if self.eat("(") {
+            let filter = self.or()?;
+            return match self.eat(")") {
+                true => Ok(filter),
+                false => Err(self.error("expected `)`")),
+            };
+        }
+        // `!` of `!=` can't start expression, so no ambiguity
+        if self.eat("!") {
+            return Ok(!self.unary()?);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Filter, ParseError> {
+        let field = match self.word() {
+            Some("program") => Field::Program,
+            Some("event") => Field::Event,
+            Some(path) => Field::Data(path.split('.').map(str::to_owned).collect()),
+            None => return Err(self.error("expected field")),
+        };
+        let op = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| self.eat(token).then_some(op))
+        .ok_or_else(|| self.error("expected comparison operator"))?;
+        Ok(Filter::Compare {
+            field,
+            op,
+            value: self.literal()?,
+        })
+    }
+
+    /// Identifier, number or bare string
+    fn word(&mut self) -> Option<&'i str> {
+        self.skip_whitespace();
+        let rest = &self.input[self.position..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '-' | '+')))
+            .unwrap_or(rest.len());
+        self.position += len;
+        (len != 0).then_some(&rest[..len])
+    }
+
+    fn literal(&mut self) -> Result<Literal, ParseError> {
+        if self.eat("\"") {
+            let rest = self.rest();
+            let end = rest
+                .find('"')
+                .ok_or_else(|| self.error("unterminated string"))?;
+            let value = rest[..end].to_owned();
+            self.position += end + 1;
+            return Ok(Literal::String(value));
+        }
+        let word = self.word().ok_or_else(|| self.error("expected value"))?;
+        Ok(match word {
+            "true" => Literal::Bool(true),
+            "false" => Literal::Bool(false),
+            word => word
+                .parse::<i128>()
+                .map(Literal::Integer)
+                .or_else(|_| word.parse::<f64>().map(Literal::Float))
+                .unwrap_or_else(|_| Literal::String(word.to_owned())),
+        })
+    }
+}
+
+#[cfg(feature = "event-reader")]
+pub use recipient::FilteredRecipient;
+
+#[cfg(feature = "event-reader")]
+mod recipient {
+    use async_trait::async_trait;
+    use tracing::*;
+
+    use super::Filter;
+    use crate::{
+        event_reader_service::PassEvent, program_watcher::SharedIdl, transaction_parser::Pubkey,
+    };
+
+    /// [`PassEvent`] adapter passing into `recipient` only raw events of `program_id`,
+    /// which are decoded by `idl` and match `filter`
+    ///
+    /// Events unknown to IDL or failed to decode are dropped. IDL can be replaced while
+    /// running, e.g. by [`crate::program_watcher::ProgramWatcher`]
+    pub struct FilteredRecipient<R> {
+        pub recipient: R,
+        pub program_id: Pubkey,
+        pub idl: SharedIdl,
+        pub filter: Filter,
+    }
+
+    impl<R> FilteredRecipient<R> {
+        pub fn new(recipient: R, program_id: Pubkey, idl: SharedIdl, filter: Filter) -> Self {
+            Self {
+                recipient,
+                program_id,
+                idl,
+                filter,
+            }
+        }
+
+        /// Would `raw_event` be passed into recipient
+        pub fn is_passed(&self, raw_event: &[u8]) -> bool {
+            match self.idl.load().decode_event(raw_event) {
+                Ok(Some(decoded)) => self.filter.matches(&self.program_id, &decoded),
+                Ok(None) => {
+                    debug!("Event unknown to IDL is filtered out");
+                    false
+                }
+                Err(err) => {
+                    warn!("Error while decode event for filter: {err}");
+                    false
+                }
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<R> PassEvent for FilteredRecipient<R>
+    where
+        R: PassEvent + Send + Sync,
+    {
+        type Error = R::Error;
+
+        async fn pass_event(&self, raw_event: Vec<u8>) -> Result<(), Self::Error> {
+            match self.is_passed(&raw_event) {
+                true => self.recipient.pass_event(raw_event).await,
+                false => Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod event_filter_test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let program_id = Pubkey::new_unique();
+        let event = Decoded {
+            name: "SwapEvent".to_owned(),
+            data: json!({
+                "amount": 5000,
+                "big": u128::MAX.to_string(),
+                "pool": { "fee": 0.25, "open": true },
+            }),
+        };
+
+        let filter = format!(r#"program == {program_id} && event == "SwapEvent" && amount > 1000"#)
+            .parse::<Filter>()
+            .unwrap();
+        assert_eq!(
+            filter,
+            Filter::program(program_id)
+                .and(Filter::event("SwapEvent"))
+                .and(Filter::field("amount").gt(1000))
+        );
+        assert!(filter.matches(&program_id, &event));
+        assert!(!filter.matches(&Pubkey::new_unique(), &event));
+        assert_eq!(filter.to_string().parse::<Filter>().unwrap(), filter);
+
+        for (expression, expected) in [
+            ("amount >= 5000 && amount <= 5000", true),
+            ("amount < 1000 || pool.open == true", true),
+            ("!(pool.fee > 0.1)", false),
+            ("big > 1", true),
+            ("missing == 1", false),
+            ("missing != 1", false),
+            ("event != SwapEvent", false),
+        ] {
+            let filter = expression.parse::<Filter>().unwrap();
+            assert_eq!(
+                filter.matches(&program_id, &event),
+                expected,
+                "{expression}"
+            );
+        }
+
+        for expression in [
+            "",
+            "amount >",
+            "amount ~ 1",
+            "(amount > 1",
+            r#"event == "x"#,
+        ] {
+            assert!(expression.parse::<Filter>().is_err(), "{expression}");
+        }
+    }
+}
@@ -1,15 +1,16 @@
-use std::io;
+use std::{fmt, sync::Arc};
 
 pub use anchor_lang::{AnchorDeserialize, Discriminator, Owner};
-pub use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::hash::hashv;
+pub use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signature};
 
+use crate::{instruction_parser::split_discriminator, log_parser::level_to_bytes};
 pub use crate::{
-    log_parser::ProgramLog,
-    transaction_parser::{Error, TransactionParsedMeta},
+    instruction_parser::InstructionPosition,
+    log_parser::{ProgramContext, ProgramLog},
+    transaction_parser::{DecodeError, Error, TransactionParsedMeta},
 };
 
-const DISCRIMINATOR_SIZE: usize = 8;
-
 /// [`ParseEvent`] is a trait providing the method [`ParseEvent::parse_event`] to parse events
 /// from the [`crate::log_parser::ProgramLog`].
 ///
@@ -46,31 +47,475 @@ const DISCRIMINATOR_SIZE: usize = 8;
 /// ```
 ///
 /// The `parse_event` method takes a `program_id` and returns an `Option` which will be `None` if no event
-/// was parsed and `Some` with a `Result` containing either the parsed event or an error.
+/// was parsed and `Some` with a `Result` containing either the parsed event or a [`DecodeError`].
+///
+/// Payload which isn't valid base64 is `None` for `parse_event`, use
+/// [`ParseEvent::parse_event_with`] & strict [`DataDecoder`] to get it as
+/// [`DecodeError::InvalidBase64`]
 pub trait ParseEvent {
     fn parse_event<T: Discriminator + Owner + AnchorDeserialize>(
         &self,
         program_id: Pubkey,
-    ) -> Option<Result<T, io::Error>>;
+    ) -> Option<Result<T, DecodeError>> {
+        self.parse_event_with(program_id, &DataDecoder::default())
+    }
+
+    /// Same as [`ParseEvent::parse_event`], with payload decoded by `decoder`
+    fn parse_event_with<T: Discriminator + Owner + AnchorDeserialize>(
+        &self,
+        program_id: Pubkey,
+        decoder: &DataDecoder,
+    ) -> Option<Result<T, DecodeError>>;
 }
 
 impl ParseEvent for ProgramLog {
-    fn parse_event<E: Discriminator + Owner + AnchorDeserialize>(
+    fn parse_event_with<E: Discriminator + Owner + AnchorDeserialize>(
         &self,
         program_id: Pubkey,
-    ) -> Option<Result<E, io::Error>> {
+        decoder: &DataDecoder,
+    ) -> Option<Result<E, DecodeError>> {
         match self {
             ProgramLog::Data(log) if E::owner().eq(&program_id) => {
-                let bytes = base64::decode(log)
-                    .map_err(|_| tracing::warn!("Provided log line not decodable as bs64"))
-                    .ok()
-                    .filter(|bytes| bytes.len() >= DISCRIMINATOR_SIZE)?;
-                let (discriminantor, event) = bytes.split_at(DISCRIMINATOR_SIZE);
-                E::discriminator()
-                    .eq(discriminantor)
-                    .then(|| E::try_from_slice(event))
+                let bytes = match decoder.decode(log) {
+                    Ok(bytes) => bytes,
+                    Err(source) if decoder.strict => {
+                        return Some(Err(DecodeError::InvalidBase64 {
+                            program_id,
+                            program_ctx: None,
+                            source,
+                        }))
+                    }
+                    Err(_) => return None,
+                };
+                let (discriminantor, event) = split_discriminator(&bytes)?;
+                E::discriminator().eq(discriminantor).then(|| {
+                    E::try_from_slice(event).map_err(|source| DecodeError::Deserialize {
+                        program_id,
+                        program_ctx: None,
+                        discriminator: Some(E::discriminator()),
+                        type_name: std::any::type_name::<E>(),
+                        source,
+                    })
+                })
             }
             _ => None,
         }
     }
 }
+
+/// Padding of base64 payloads of `Program data:` logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Base64Padding {
+    /// Both padded & unpadded payloads are accepted, as by `base64::decode`
+    #[default]
+    Optional,
+    /// Payload must be padded, as emitted by `sol_log_data`
+    Required,
+    /// Payload must not be padded
+    Forbidden,
+}
+
+/// Callback of `Program data:` payload which isn't valid base64, e.g. to log or count them
+pub type OnUndecodableFn = Arc<dyn Send + Sync + Fn(&str, &base64::DecodeError)>;
+
+/// Decoder of base64 payloads of `Program data:` logs
+///
+/// ```
+/// use solana_events_parser::event_parser::{Base64Padding, DataDecoder};
+///
+/// let decoder = DataDecoder::new().with_padding(Base64Padding::Required);
+/// assert_eq!(decoder.decode("ZXZlbnQ=").unwrap(), b"event");
+/// assert!(decoder.decode("ZXZlbnQ").is_err());
+/// assert_eq!(DataDecoder::new().decode("ZXZlbnQ").unwrap(), b"event");
+/// ```
+#[derive(Clone, Default)]
+pub struct DataDecoder {
+    pub padding: Base64Padding,
+    /// Invalid payload is [`DecodeError::InvalidBase64`] for [`ParseEvent::parse_event_with`]
+    /// instead of `None`
+    pub strict: bool,
+    pub on_undecodable: Option<OnUndecodableFn>,
+}
+
+impl DataDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_padding(mut self, padding: Base64Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn on_undecodable(
+        mut self,
+        on_undecodable: impl Send + Sync + Fn(&str, &base64::DecodeError) + 'static,
+    ) -> Self {
+        self.on_undecodable = Some(Arc::new(on_undecodable));
+        self
+    }
+
+    /// Decode payload of `Program data:` log, invalid payload is passed into
+    /// [`DataDecoder::on_undecodable`]
+    pub fn decode(&self, data: &str) -> Result<Vec<u8>, base64::DecodeError> {
+        self.check_padding(data)
+            .and_then(|()| base64::decode_config(data, base64::STANDARD))
+            .map_err(|err| {
+                tracing::warn!("`Program data:` payload isn't valid base64: {err}");
+                if let Some(on_undecodable) = self.on_undecodable.as_ref() {
+                    on_undecodable(data, &err);
+                }
+                err
+            })
+    }
+
+    fn check_padding(&self, data: &str) -> Result<(), base64::DecodeError> {
+        match self.padding {
+            Base64Padding::Optional => Ok(()),
+            Base64Padding::Required if data.len() % 4 != 0 => {
+                Err(base64::DecodeError::InvalidLength)
+            }
+            Base64Padding::Required => Ok(()),
+            Base64Padding::Forbidden => match data.find('=') {
+                Some(index) => Err(base64::DecodeError::InvalidByte(index, b'=')),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+impl fmt::Debug for DataDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataDecoder")
+            .field("padding", &self.padding)
+            .field("strict", &self.strict)
+            .field("on_undecodable", &self.on_undecodable.is_some())
+            .finish()
+    }
+}
+
+/// Prefix of instruction data of self-CPI emitted by anchor `emit_cpi!`, followed by event
+/// with discriminator
+pub const EVENT_IX_TAG: [u8; 8] = 0x1d9acb512ea545e4u64.to_le_bytes();
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+/// Base64 chars fully defined by 8 bytes of discriminator, 6 bits per char
+const DISCRIMINATOR_BASE64_PREFIX_LEN: usize = 10;
+/// Base64 chars covering the whole discriminator, with bits of the following byte
+const DISCRIMINATOR_BASE64_LEN: usize = 12;
+
+/// Is there `Program data:` log with event of `discriminator` in raw `logs`, without parsing
+/// of logs. Payloads are matched by base64 prefix & only their first 12 chars are decoded, so
+/// it's cheap enough to check every transaction of block or every websocket notification,
+/// e.g. in [`EventConsumerFn`](crate::event_reader_service::EventConsumerFn)
+///
+/// Program of log isn't checked, events of other programs with the same discriminator match
+///
+/// ```
+/// use solana_events_parser::event_parser::contains_event_discriminator;
+///
+/// let discriminator = [1, 2, 3, 4, 5, 6, 7, 8];
+/// let logs = [
+///     "Program Fixture111111111111111111111111111111111111 invoke [1]".to_owned(),
+///     "Program data: AQIDBAUGBwgqAAAA".to_owned(),
+/// ];
+/// assert!(contains_event_discriminator(&logs, &discriminator));
+/// assert!(!contains_event_discriminator(&logs, &[1, 2, 3, 4, 5, 6, 7, 9]));
+/// ```
+pub fn contains_event_discriminator(logs: &[String], discriminator: &[u8; 8]) -> bool {
+    let encoded = base64::encode(discriminator);
+    let prefix = &encoded[..DISCRIMINATOR_BASE64_PREFIX_LEN];
+    logs.iter()
+        .filter_map(|log| log.strip_prefix(PROGRAM_DATA_PREFIX))
+        // `sol_log_data` of several slices logs them separated by space, event is the first
+        .filter_map(|data| data.split(' ').next())
+        .filter(|data| data.starts_with(prefix))
+        .any(|data| {
+            // The last 4 bits of discriminator are in the 11th char, mixed with the next byte
+            data.get(..DISCRIMINATOR_BASE64_LEN)
+                .and_then(|head| base64::decode(head).ok())
+                .is_some_and(|bytes| bytes.starts_with(discriminator))
+        })
+}
+
+/// How event was emitted, see [`InstructionWithEvents`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSource {
+    /// `Program data:` log of `emit!`
+    Log,
+    /// Self-CPI of `emit_cpi!` with this context
+    Cpi(ProgramContext),
+}
+
+/// Event with discriminator, emitted by instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmittedEvent {
+    pub source: EventSource,
+    pub data: Vec<u8>,
+}
+
+/// Instruction of transaction with events emitted by it, see [`instructions_with_events`]
+#[derive(Debug, Clone)]
+pub struct InstructionWithEvents {
+    pub program_ctx: ProgramContext,
+    pub position: Option<InstructionPosition>,
+    pub instruction: Instruction,
+    /// Events of `Program data:` logs, then events of `emit_cpi!` in order of execution
+    pub events: Vec<EmittedEvent>,
+}
+
+impl InstructionWithEvents {
+    /// Events decoded into `E`, skipping ones with another discriminator or if instruction
+    /// isn't of [`Owner`] of `E`
+    pub fn events_of<E: Discriminator + Owner + AnchorDeserialize>(
+        &self,
+    ) -> impl Iterator<Item = Result<E, DecodeError>> + '_ {
+        let program_id = self.program_ctx.program_id;
+        self.events
+            .iter()
+            .filter(move |_| E::owner() == program_id)
+            .filter_map(move |event| {
+                let (discriminator, data) = split_discriminator(&event.data)?;
+                E::discriminator().eq(discriminator).then(|| {
+                    E::try_from_slice(data).map_err(|source| DecodeError::Deserialize {
+                        program_id,
+                        program_ctx: Some(self.program_ctx),
+                        discriminator: Some(E::discriminator()),
+                        type_name: std::any::type_name::<E>(),
+                        source,
+                    })
+                })
+            })
+    }
+}
+
+/// Options of [`instructions_with_events`]
+#[derive(Debug, Clone, Default)]
+pub struct EventAttachment {
+    pub decoder: DataDecoder,
+    /// Attach events of `emit_cpi!` to instruction emitted them, instead of listing
+    /// self-CPIs with [`EVENT_IX_TAG`] as separate instructions
+    pub cpi_events: bool,
+}
+
+/// All instructions of transaction in order of execution, every one with events it emitted
+///
+/// Event is attached to the exact invocation emitted it, not to top-level instruction, so
+/// events of CPIs are attached to CPIs. Undecodable `Program data:` payloads are skipped
+/// and passed into [`DataDecoder::on_undecodable`]
+pub fn instructions_with_events(
+    transaction: &TransactionParsedMeta,
+    options: &EventAttachment,
+) -> Vec<InstructionWithEvents> {
+    let cpi_event = |ctx: &ProgramContext| {
+        let (instruction, _) = transaction.meta.get(ctx)?;
+        let parent = transaction.parent_ix.get(ctx)?;
+        let event = instruction.data.strip_prefix(EVENT_IX_TAG.as_slice())?;
+        (options.cpi_events && parent.program_id == ctx.program_id).then_some(event)
+    };
+
+    transaction
+        .contexts_in_execution_order()
+        .into_iter()
+        .filter(|ctx| cpi_event(ctx).is_none())
+        .filter_map(|ctx| {
+            let (instruction, logs) = transaction.meta.get(&ctx)?;
+            let logged = logs.iter().filter_map(|log| match log {
+                ProgramLog::Data(data) => Some(EmittedEvent {
+                    source: EventSource::Log,
+                    data: options.decoder.decode(data).ok()?,
+                }),
+                _ => None,
+            });
+            let emitted_by_cpi = transaction
+                .children_of(&ctx)
+                .into_iter()
+                .filter_map(|child| {
+                    Some(EmittedEvent {
+                        source: EventSource::Cpi(child),
+                        data: cpi_event(&child)?.to_vec(),
+                    })
+                });
+
+            Some(InstructionWithEvents {
+                program_ctx: ctx,
+                position: transaction.ix_positions.get(&ctx).copied(),
+                instruction: instruction.clone(),
+                events: logged.chain(emitted_by_cpi).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Stable id of event, the same for event received by live subscription & by resync, so
+/// duplicates can be dropped downstream by [`crate::storage::EventRegistry`]
+///
+/// ```
+/// use solana_events_parser::{
+///     event_parser::{EventId, Pubkey, Signature},
+///     log_parser::ProgramContext,
+/// };
+///
+/// let ctx = ProgramContext {
+///     program_id: Pubkey::new_unique(),
+///     program_call_index: 0,
+///     invoke_level: 1.try_into().unwrap(),
+/// };
+/// let id = EventId::new(&Signature::default(), &ctx, b"event");
+/// assert_eq!(id, EventId::new(&Signature::default(), &ctx, b"event"));
+/// assert_ne!(id, EventId::new(&Signature::default(), &ctx, b"other event"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(pub [u8; 32]);
+
+impl EventId {
+    /// SHA-256 of `signature`, `ctx` & event `payload`
+    ///
+    /// Identical payloads emitted by the same invocation have the same id
+    pub fn new(signature: &Signature, ctx: &ProgramContext, payload: &[u8]) -> Self {
+        Self(
+            hashv(&[
+                signature.as_ref(),
+                ctx.program_id.as_ref(),
+                &(ctx.program_call_index as u64).to_le_bytes(),
+                &level_to_bytes(ctx.invoke_level),
+                payload,
+            ])
+            .to_bytes(),
+        )
+    }
+}
+
+impl AsRef<[u8]> for EventId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for EventId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", bs58::encode(self.0).into_string())
+    }
+}
+
+#[cfg(test)]
+mod event_parser_test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_contains_event_discriminator() {
+        let discriminator = [0xf8, 0xc6, 0x9e, 0x91, 0xe1, 0x75, 0x87, 0xc8];
+        let data = |bytes: &[u8]| format!("Program data: {}", base64::encode(bytes));
+        let event = [discriminator.as_slice(), &[0xff; 32]].concat();
+
+        assert!(contains_event_discriminator(
+            &[data(&event), "Program log: data".to_owned()],
+            &discriminator
+        ));
+        // Event without payload, padded
+        assert!(contains_event_discriminator(
+            &[data(&discriminator)],
+            &discriminator
+        ));
+        // The same first 10 base64 chars, differs in the last 4 bits only
+        let mut other = discriminator;
+        other[7] ^= 0x01;
+        assert_eq!(
+            base64::encode(other)[..DISCRIMINATOR_BASE64_PREFIX_LEN],
+            base64::encode(discriminator)[..DISCRIMINATOR_BASE64_PREFIX_LEN]
+        );
+        assert!(!contains_event_discriminator(&[data(&event)], &other));
+        // Discriminator not at the start of payload, or truncated one
+        assert!(!contains_event_discriminator(
+            &[data(&[&[0], discriminator.as_slice()].concat())],
+            &discriminator
+        ));
+        assert!(!contains_event_discriminator(
+            &[data(&discriminator[..7])],
+            &discriminator
+        ));
+        assert!(!contains_event_discriminator(
+            &[format!("Program log: {}", base64::encode(event))],
+            &discriminator
+        ));
+    }
+
+    #[test]
+    fn test_instructions_with_events() {
+        let program_id = Pubkey::new_unique();
+        let ctx = |program_call_index, invoke_level: u32| ProgramContext {
+            program_id,
+            program_call_index,
+            invoke_level: invoke_level.try_into().unwrap(),
+        };
+        let instruction = |data: Vec<u8>| Instruction {
+            program_id,
+            accounts: vec![],
+            data,
+        };
+        let (root, cpi) = (ctx(0, 1), ctx(1, 2));
+        let transaction = TransactionParsedMeta {
+            meta: HashMap::from([
+                (
+                    root,
+                    (
+                        instruction(vec![1]),
+                        vec![ProgramLog::Data(base64::encode(b"logged"))],
+                    ),
+                ),
+                (
+                    cpi,
+                    (
+                        instruction([EVENT_IX_TAG.as_slice(), b"cpi"].concat()),
+                        vec![],
+                    ),
+                ),
+            ]),
+            slot: 0,
+            block_time: None,
+            lamports_changes: HashMap::new(),
+            token_balances_changes: HashMap::new(),
+            parent_ix: HashMap::from([(cpi, root)]),
+            root_ixs: vec![root],
+            compute_budget: Default::default(),
+            ix_positions: HashMap::new(),
+            message: Default::default(),
+            stamp: Default::default(),
+        };
+
+        let grouped = instructions_with_events(
+            &transaction,
+            &EventAttachment {
+                cpi_events: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped[0].program_ctx, root);
+        assert_eq!(
+            grouped[0].events,
+            [
+                EmittedEvent {
+                    source: EventSource::Log,
+                    data: b"logged".to_vec(),
+                },
+                EmittedEvent {
+                    source: EventSource::Cpi(cpi),
+                    data: b"cpi".to_vec(),
+                },
+            ]
+        );
+
+        let grouped = instructions_with_events(&transaction, &EventAttachment::default());
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[1].program_ctx, cpi);
+        assert!(grouped[1].events.is_empty());
+    }
+}
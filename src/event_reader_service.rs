@@ -1,24 +1,27 @@
 use std::{
-    fmt, result,
-    sync::{Arc, RwLock},
-    time::Duration,
+    collections::HashMap,
+    fmt,
+    num::NonZeroUsize,
+    result,
+    sync::{atomic::Ordering, Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use async_trait::async_trait;
-use futures::{future::BoxFuture, StreamExt};
-use non_empty_vec::{EmptyError, NonEmpty as NonEmptyVec};
+use futures::future::BoxFuture;
 use result_inspect::ResultInspectErr;
 use solana_client::{
+    client_error::ClientError,
     nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
-    rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    rpc_config::{RpcTransactionConfig, RpcTransactionLogsFilter},
 };
 use solana_sdk::commitment_config::CommitmentConfig;
 use tracing::{Instrument, *};
 
-pub use crate::transaction_parser::{Pubkey, Signature as SolanaSignature};
+pub use crate::transaction_parser::{Pubkey, Signature as SolanaSignature, Slot};
 use crate::{
-    storage,
-    transaction_parser::{BindTransactionInstructionLogs, TransactionParsedMeta},
+    event_parser::EventId, middleware::BoxedLayer, signature_stream, storage,
+    transaction_parser::TransactionParsedMeta,
 };
 
 macro_rules! unwrap_or_continue {
@@ -51,6 +54,22 @@ macro_rules! unwrap_or_continue {
         };
 }
 
+mod accounts;
+mod checkpoint;
+mod config;
+mod consumer;
+mod detection;
+mod fetch;
+mod handle;
+mod live;
+mod resync;
+mod retry;
+mod source;
+
+pub use self::{
+    accounts::*, checkpoint::*, config::*, consumer::*, handle::*, retry::*, source::*,
+};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
@@ -67,24 +86,81 @@ pub enum Error {
     StorageError(String),
     #[error(transparent)]
     Client(#[from] de_solana_client::Error),
+    #[error("Transaction {0} not found")]
+    TransactionNotFound(SolanaSignature),
+    #[error("Consumers of transaction {signature} exceeded timeout of {timeout:?}")]
+    ConsumerTimeout {
+        signature: SolanaSignature,
+        timeout: Duration,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// For infallible storages, e.g. in memory ones
+impl From<std::convert::Infallible> for Error {
+    fn from(err: std::convert::Infallible) -> Self {
+        match err {}
+    }
+}
+
+impl Error {
+    /// Error of RPC request, which caused this error
+    pub fn client_error(&self) -> Option<&ClientError> {
+        match self {
+            Error::ClientError(err)
+            | Error::EventParserError(crate::transaction_parser::Error::SolanaClientResult(err)) => {
+                Some(err)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Sink of events produced by [`EventsReader::transaction_consumer`]
+///
+/// By default events passed as already serialized bytes, typed events can be passed into
+/// bytes sink through [`crate::event_serializer::Serialized`] with chosen
+/// [`crate::event_serializer::EventSerializer`]. Reader doesn't pass events itself, so
+/// `transaction_consumer` can pass any event types implemented by [`EventsReader::event_recipient`]
 #[async_trait]
-pub trait PassEvent {
+pub trait PassEvent<T: Send + 'static = Vec<u8>> {
     type Error;
-    async fn pass_event(&self, raw_event: Vec<u8>) -> result::Result<(), Self::Error>;
+    async fn pass_event(&self, event: T) -> result::Result<(), Self::Error>;
 }
 
+/// Default [`EventsReader::parsed_tx_cache_ttl`]
+pub const DEFAULT_PARSED_TX_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Result of `event_consumer` over logs of live transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EventConsumeResult {
     ConsumeSuccess,
     TransactionNeeed,
+    /// Logs can't be consumed yet (e.g. dependent account isn't indexed), call
+    /// `event_consumer` again after `retry_after`. After [`EventsReader::max_event_deferrals`]
+    /// deferrals transaction is requested, as for [`EventConsumeResult::TransactionNeeed`]
+    Defer {
+        retry_after: Duration,
+    },
+    /// Only these events are consumed by logs, transaction is requested for the rest. Ids
+    /// are registered in [`EventsReader::event_registry`] (if any) before consuming of
+    /// transaction, so consumers deduplicating by it skip them
+    ConsumedEvents(Vec<EventId>),
 }
+
 pub type Event = Vec<String>;
+
 pub type EventConsumerFn = fn(Event) -> Result<EventConsumeResult>;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+/// Default of [`EventsReader::max_event_deferrals`]
+pub const DEFAULT_MAX_EVENT_DEFERRALS: usize = 10;
+
+/// Signatures per batch of resync. Batches are listed & consumed one by one, so memory of
+/// resync doesn't grow with count of signatures since resync pointer
+pub const RESYNC_BATCH_SIZE: usize = signature_stream::MAX_PAGE_SIZE;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, serde::Serialize)]
 pub enum ResyncOrder {
     Newest,
     Historical,
@@ -96,12 +172,16 @@ pub enum Rollback {
     None,
     Beginning,
     Signature(SolanaSignature),
+    /// Resync all transactions since slot
+    Slot(Slot),
 }
 
+/// Builder is owned, so generic parameters (e.g. storage error) aren't required to be `Clone`
 #[derive(derive_builder::Builder)]
+#[builder(pattern = "owned")]
 pub struct EventsReader<TransactionConsumerFn, EventRecipient, E>
 where
-    EventRecipient: PassEvent + Send + Sync + 'static,
+    EventRecipient: Send + Sync + 'static,
     TransactionConsumerFn: Send
         + Sync
         + Fn(
@@ -125,6 +205,7 @@ where
 
     pub pubsub_client: Option<Arc<PubsubClient>>,
 
+    /// Passed to `transaction_consumer` with every transaction
     pub event_recipient: Arc<EventRecipient>,
     #[builder(default = "Duration::from_secs(5)")]
     pub resync_duration: Duration,
@@ -136,19 +217,199 @@ where
     pub resync_order: ResyncOrder,
     #[builder(default = "Arc::new(RwLock::new(Rollback::None))")]
     pub resync_rollback: Arc<RwLock<Rollback>>,
-    pub live_events_transaction_request_param: TransactionRequestParams,
+    /// Retries of `getTransaction` of events from `logsSubscribe`
+    #[builder(default)]
+    pub live_events_transaction_request_param: RetryParams,
+    /// Retries of `getTransaction` while resync & gaps detection,
+    /// `live_events_transaction_request_param` if not provided
+    #[builder(default)]
+    pub resync_transaction_request_param: Option<RetryParams>,
+    /// Count of resyncs, after which transaction still failing to be fetched (with retryable
+    /// error, after all retries) is quarantined: registered as processed & skipped with
+    /// [`SkipReason::Quarantined`], so it can't block resync pointer forever. Quarantined
+    /// transactions are kept by [`storage::ResyncedTransactionsPtrStorage::quarantine_transaction`]
+    /// and processed again by resync after operator requested retry. Disabled by default
+    #[builder(default)]
+    pub resync_max_fetch_failures: Option<NonZeroUsize>,
+    /// Config of `getTransaction` requests, [`default_transaction_config`] with
+    /// `commitment_config` if not provided. Encoding must be binary one
+    #[builder(default)]
+    pub transaction_config: Option<RpcTransactionConfig>,
+    /// Decomposers & event handlers applied to every transaction before
+    /// `transaction_consumer`, can be changed while reader is running
+    #[builder(default)]
+    pub consumers: ConsumerRegistry,
+    /// Periodic check of transactions missed by websocket listener, disabled by default
+    #[builder(default)]
+    pub gap_detection: Option<GapDetection>,
+    /// Additional programs read by the same reader, with their own settings, but shared
+    /// RPC & pubsub clients, storage, `transaction_consumer` and `event_recipient`
+    #[builder(default)]
+    pub profiles: Vec<(Pubkey, ProgramProfile)>,
+    /// Check of consumed transactions against finalized chain, for programs read with
+    /// commitment below finalized. Disabled by default
+    #[builder(default)]
+    pub reorg_detection: Option<ReorgDetection>,
+    /// Filter of one `logsSubscribe` shared by all programs, instead of `Mentions` of every
+    /// program by its own subscription. Use `All`, `AllWithVotes` or multiple mentions (if
+    /// supported by RPC) for whole-cluster indexing, notifications are then consumed only
+    /// by programs invoked by transaction, with `commitment_config` of reader
+    #[builder(default)]
+    pub logs_filter: Option<RpcTransactionLogsFilter>,
+    /// Consume notifications of `Mentions` subscription of program only if program is
+    /// invoked by transaction, not just mentioned. Reduces noise for program ids, which are
+    /// also token mints or accounts of other programs. Always on with `logs_filter`
+    #[builder(default)]
+    pub filter_mentions_by_invoke: bool,
+    /// Replacement of `client` as source of transactions, e.g. for tests
+    #[builder(default)]
+    pub transaction_source: Option<Arc<dyn TransactionSource>>,
+    /// Replacement of `client` as source of signatures, e.g. for tests
+    #[builder(default)]
+    pub signature_source: Option<Arc<dyn SignatureSource>>,
+    /// Replacement of `pubsub_client` as source of live logs, e.g. for tests
+    #[builder(default)]
+    pub logs_source: Option<Arc<dyn LogsSource>>,
+    /// Live subscription, [`LiveSource::Logs`] by default
+    #[builder(default)]
+    pub live_source: LiveSource,
+    /// Replacement of `pubsub_client` as source of live blocks, e.g. for tests
+    #[builder(default)]
+    pub blocks_source: Option<Arc<dyn BlocksSource>>,
+    /// Which errors of transaction requests are retried & block resync pointer, all by
+    /// default. See [`RetryTransient`]
+    #[builder(default = "Arc::new(RetryAll)")]
+    pub retry_classifier: Arc<dyn RetryClassifier>,
+    /// Time source of all sleeps of reader, [`TokioClock`] by default
+    #[builder(default = "Arc::new(TokioClock)")]
+    pub clock: Arc<dyn Clock>,
+    /// Leases of transactions in storage shared by replicas of reader, transaction is
+    /// consumed only by replica claimed it. Disabled by default
+    #[builder(default)]
+    pub claims: Option<TransactionClaims<E>>,
+    /// Called on every transaction skipped by reader, e.g. to export metrics. Skips are
+    /// also counted by [`ReaderHandle::skipped`]
+    #[builder(default)]
+    pub on_skip: Option<OnSkipFn>,
+    /// Limit of time of consumers & `transaction_consumer` per transaction, exceeded ones
+    /// are cancelled, logged with warning and skipped with [`SkipReason::ConsumerTimeout`],
+    /// so stuck consumer can't stall websocket listener or resync chunk. Resync chunk with
+    /// timed out transaction is processed again. Disabled by default
+    ///
+    /// Applied as the innermost [`TimeoutLayer`], under all `layers`
+    #[builder(default)]
+    pub consumer_timeout: Option<Duration>,
+    /// Limit of [`EventConsumeResult::Defer`] of one transaction by `event_consumer`
+    #[builder(default = "DEFAULT_MAX_EVENT_DEFERRALS")]
+    pub max_event_deferrals: usize,
+    /// Registry of events consumed by `event_consumer` only, see
+    /// [`EventConsumeResult::ConsumedEvents`]
+    #[builder(default)]
+    pub event_registry: Option<Arc<dyn Send + Sync + storage::EventRegistry<Error = E>>>,
+    /// Cache of parsed transactions, so transactions requested again (e.g. resync after
+    /// restart) skip RPC & parsing. Failures of cache are only logged
+    #[builder(default)]
+    pub parsed_tx_cache: Option<Arc<dyn Send + Sync + storage::ParsedTxCache<Error = E>>>,
+    /// Lifetime of transactions in `parsed_tx_cache`, expired ones are ignored & evicted
+    /// every `parsed_tx_cache_ttl`. A day by default
+    #[builder(default = "DEFAULT_PARSED_TX_CACHE_TTL")]
+    pub parsed_tx_cache_ttl: Duration,
+    /// Limit of transactions consumed at the same time by all programs of reader, across
+    /// live & resync paths, so burst of transactions can't exhaust resources of consumers
+    /// (e.g. database connections). Unlimited by default
+    #[builder(default)]
+    pub max_concurrent_consumers_global: Option<NonZeroUsize>,
+    /// Limit of transactions consumed at the same time by every program, can be overridden
+    /// by [`ProgramProfile::max_concurrent_consumers`]. Unlimited by default
+    #[builder(default)]
+    pub max_concurrent_consumers_per_program: Option<NonZeroUsize>,
+    /// Middlewares around consuming of transaction (logging, metrics, retries, etc.), the
+    /// first one is the outermost, see [`crate::middleware`]
+    #[builder(default, setter(each(name = "layer")))]
+    pub layers: Vec<BoxedLayer>,
+    /// Resync pointer is moved only after all consumers of coordinator confirm slot of
+    /// the new pointer, so pointer can't race ahead of persistence of consumers. If they
+    /// don't confirm in `resync_duration`, pointer stays for the next resync. Registered
+    /// transactions above confirmed slot, which aren't consumed by this reader (e.g. before
+    /// restart), are consumed again by resync. Disabled by default
+    #[builder(default)]
+    pub checkpoints: Option<CheckpointCoordinator>,
+    /// Overrides of tunable settings, which can be replaced while reader is running, see
+    /// [`ReaderConfig`]
+    #[builder(default)]
+    pub config: SharedReaderConfig,
+    /// Consumed, but not yet finalized transactions
+    #[builder(setter(skip))]
+    unfinalized: Arc<Mutex<HashMap<SolanaSignature, detection::UnfinalizedTransaction>>>,
+    /// Slots of transactions consumed by this reader, but not yet confirmed by `checkpoints`
+    #[builder(setter(skip))]
+    unconfirmed: Arc<Mutex<HashMap<(Pubkey, SolanaSignature), Slot>>>,
+    /// Slots of reorgs by program, applied as [`Rollback::Slot`] by the next resync
+    #[builder(setter(skip))]
+    reorg_rollbacks: Arc<Mutex<HashMap<Pubkey, Slot>>>,
+    /// Count of failed resync fetches by signature, see `resync_max_fetch_failures`
+    #[builder(setter(skip))]
+    fetch_failures: Arc<Mutex<HashMap<SolanaSignature, usize>>>,
+    /// Programs with resolved profiles & semaphores of consumers, built once by
+    /// `EventsReader::programs`
+    #[builder(setter(skip))]
+    programs: OnceLock<Vec<Arc<Program>>>,
+    #[builder(setter(skip))]
+    handle: ReaderHandle,
+    #[builder(setter(skip))]
+    ingest: IngestChannel,
 }
 
-#[derive(Debug, Clone)]
-pub struct TransactionRequestParams {
-    pub attempts_count: usize,
-    pub attempt_timeout: Duration,
+/// Program of [`EventsReader`] with [`ProgramProfile`] resolved by reader settings
+///
+/// Settings tunable by [`ReaderConfig`] keep overrides of profile only, see
+/// `EventsReader::event_consumer` & others
+struct Program {
+    program_id: Pubkey,
+    commitment_config: CommitmentConfig,
+    event_consumer: Option<EventConsumerFn>,
+    consumers: ConsumerRegistry,
+    is_resync_enabled: bool,
+    resync_duration: Option<Duration>,
+    resync_signatures_chunk_size: Option<usize>,
+    resync_order: ResyncOrder,
+    is_rollback_enabled: bool,
+    max_concurrent_consumers: Option<NonZeroUsize>,
+    /// Semaphore of `max_concurrent_consumers`
+    consumer_permits: Option<Arc<tokio::sync::Semaphore>>,
+    /// Semaphore of `max_concurrent_consumers_global`, shared by all programs
+    global_consumer_permits: Option<Arc<tokio::sync::Semaphore>>,
+}
+
+/// Permits of program & global consumers concurrency limits, see
+/// [`Program::acquire_consumer_permits`]
+type ConsumerPermits = (
+    Option<tokio::sync::OwnedSemaphorePermit>,
+    Option<tokio::sync::OwnedSemaphorePermit>,
+);
+
+impl Program {
+    /// Wait for permits of consumers concurrency limits, consuming is allowed while they
+    /// are held. Permit of program is acquired first, so program waiting for its own limit
+    /// doesn't hold global permit. Permits are acquired before spawn of consuming task &
+    /// moved into it, so tasks aren't spawned beyond limits
+    async fn acquire_consumer_permits(&self) -> ConsumerPermits {
+        async fn acquire(
+            semaphore: Option<&Arc<tokio::sync::Semaphore>>,
+        ) -> Option<tokio::sync::OwnedSemaphorePermit> {
+            // Semaphores are never closed
+            Arc::clone(semaphore?).acquire_owned().await.ok()
+        }
+        let program_permit = acquire(self.consumer_permits.as_ref()).await;
+        let global_permit = acquire(self.global_consumer_permits.as_ref()).await;
+        (program_permit, global_permit)
+    }
 }
 
 impl<TransactionConsumerFn, EventRecipient, E>
     EventsReader<TransactionConsumerFn, EventRecipient, E>
 where
-    EventRecipient: PassEvent + Send + Sync + 'static,
+    EventRecipient: Send + Sync + 'static,
     TransactionConsumerFn: 'static
         + Send
         + Sync
@@ -161,396 +422,369 @@ where
     E: 'static + Send + Sync + fmt::Debug,
     Error: From<E>,
 {
-    pub async fn run(self: Arc<Self>) -> Result<()> {
-        let self_ref = Arc::clone(&self);
-        let program_id = self.program_id.to_string();
-        let listen_event = tokio::task::spawn(async move {
-            self_ref
-                .listen_events()
-                .instrument(span!(
-                    Level::ERROR,
-                    "Listen Events",
-                    program_id = program_id
-                ))
-                .await
-        });
-        let self_ref = Arc::clone(&self);
-        let program_id = self.program_id.to_string();
-        let resync_events = tokio::task::spawn(async move {
-            self_ref
-                .resync_events()
-                .instrument(span!(Level::ERROR, "Resync Event", program_id = program_id,))
-                .await
-        });
-
-        tokio::try_join!(flatten(listen_event), flatten(resync_events))
-            .map(|((), ())| ())
-            .inspect_err(|err| {
-                error!("Error while run main task: {err:?}");
-            })
+    /// Handle for pause, resume & resync triggering of this reader
+    pub fn handle(&self) -> ReaderHandle {
+        self.handle.clone()
     }
 
-    async fn listen_events(self: Arc<Self>) -> Result<()> {
-        info!("Launching websocket client");
+    /// Pusher of transactions into this reader, see [`LiveSource::Ingest`]. Ingested
+    /// transactions are consumed with any live source, while reader is running
+    pub fn ingestor(&self) -> TransactionIngestor {
+        TransactionIngestor {
+            sender: self.ingest.sender.clone(),
+        }
+    }
 
-        let pubsub_client = match self.pubsub_client.as_ref() {
-            Some(ps) => ps,
-            None => {
-                info!("Listen events job disabled");
-                return Ok(());
-            }
-        };
+    /// Handle of tunable settings of this reader, see [`ReaderConfig`]
+    pub fn config(&self) -> SharedReaderConfig {
+        Arc::clone(&self.config)
+    }
 
-        loop {
-            let (stream, _unsubscribe) = pubsub_client
-                .logs_subscribe(
-                    RpcTransactionLogsFilter::Mentions(vec![self.program_id.to_string()]),
-                    RpcTransactionLogsConfig {
-                        commitment: Some(self.commitment_config),
-                    },
-                )
-                .instrument(span!(Level::ERROR, "LogsSubscribe"))
-                .await
-                .inspect_err(|err| error!("Error while subs: {err:?}"))
-                .map_err(|err| Error::WebsocketError(err.to_string()))?;
-
-            let mut stream = stream.inspect(|subscription_response| {
-                info!(
-                    "Log subscription response received, transaction hash: {}",
-                    subscription_response.value.signature
-                );
-            });
-            info!("Start listening websocket events");
-            while let Some(subscription_response) = stream.next().await {
-                let tx_signature = unwrap_or_continue!(
-                    subscription_response
-                        .value
-                        .signature
-                        .parse::<SolanaSignature>()
-                        .map_err(|err: solana_sdk::signature::ParseSignatureError| {
-                            Error::SignatureParsingError(err.to_string())
-                        }),
-                    "Error while tx signature parsing: {err:?}"
-                );
-
-                if self
-                    .local_storage
-                    .is_transaction_registered(&self.program_id, &tx_signature)?
-                {
-                    info!("Transaction {tx_signature} already registered in event-parser, skip");
-                    continue;
+    /// Snapshot of effective configuration, with [`ReaderConfig`] overrides applied, e.g. to
+    /// print at startup or expose for support
+    pub fn describe(&self) -> ReaderDescription {
+        let programs = self
+            .programs()
+            .iter()
+            .map(|program| {
+                let consumers = program.consumers.load();
+                ProgramDescription {
+                    program_id: program.program_id.to_string(),
+                    commitment: program.commitment_config,
+                    is_resync_enabled: program.is_resync_enabled,
+                    resync_duration: self.resync_duration(program),
+                    resync_signatures_chunk_size: self.resync_signatures_chunk_size(program),
+                    resync_order: program.resync_order,
+                    max_concurrent_consumers: program.max_concurrent_consumers,
+                    decomposers: consumers.decomposers.len(),
+                    event_handlers: consumers.event_handlers.keys().cloned().collect(),
                 }
-
-                let self_clone = self.clone();
-                let transaction_str = tx_signature.to_string();
-                tokio::spawn(async move {
-                    info!("Transaction {tx_signature} not registered yet, processing");
-
-                    match (self_clone.event_consumer)(subscription_response.value.logs) {
-                        Ok(EventConsumeResult::ConsumeSuccess) => {
-                            info!(
-                                "Transaction {tx_signature} consumed successful by ws information only"
-                            );
-                        }
-                        Ok(EventConsumeResult::TransactionNeeed) => {
-                            info!("Transaction {tx_signature} direct RPC request needed");
-
-                            let transaction = match self_clone
-                                .get_transaction_by_signature(tx_signature)
-                                .await
-                            {
-                                Ok(tx) => tx,
-                                Err(err) => {
-                                    error!("Error while get transaction by signature: {err:?}, skip in live process");
-                                    return;
-                                }
-                            };
-
-                            let transaction_str = tx_signature.to_string();
-                            if let Err(err) = (self_clone.transaction_consumer)(
-                                tx_signature,
-                                transaction,
-                                Arc::clone(&self_clone.client),
-                                Arc::clone(&self_clone.event_recipient),
-                            )
-                            .instrument(span!(
-                                Level::ERROR,
-                                "Consume",
-                                tx_signature = transaction_str
-                            ))
-                            .await
-                            {
-                                error!(
-                                    "Error while consuming {err:?}",
-                                    err = err
-                                );
-                            } else {
-                                info!(
-                                    "Transaction consumed as part of websocket listener",
-                                );
-                            }
-                        }
-                        Err(err) => {
-                            error!("Error while events consuming {err:?}, skip via live process");
-                        }
-                    };
-
-                    if let Err(err) = self_clone
-                        .local_storage
-                        .register_transaction(&self_clone.program_id, &tx_signature)
-                    {
-                        error!("Error while register tx: {err:?}, skip via live process");
-                    } else {
-                        info!("Registered in local cache");
+            })
+            .collect();
+
+        ReaderDescription {
+            version: env!("CARGO_PKG_VERSION"),
+            programs,
+            storage_backend: self.local_storage.backend_name(),
+            live_source: match self.live_source {
+                LiveSource::Logs => {
+                    match (self.logs_source.is_some(), self.pubsub_client.is_some()) {
+                        (true, _) => "custom",
+                        (false, true) => "pubsub",
+                        (false, false) => "none",
                     }
-                 }.instrument(span!(Level::ERROR, "Live Processing", tx_signature = transaction_str)));
-            }
-
-            warn!("Listen task: stream empty, resubscribe");
+                }
+                LiveSource::Blocks => {
+                    match (self.blocks_source.is_some(), self.pubsub_client.is_some()) {
+                        (true, _) => "custom blocks",
+                        (false, true) => "pubsub blocks",
+                        (false, false) => "none",
+                    }
+                }
+                LiveSource::Ingest => "ingest",
+            },
+            logs_filter: self
+                .logs_filter
+                .as_ref()
+                .map(|filter| format!("{filter:?}")),
+            filter_mentions_by_invoke: self.filter_mentions_by_invoke,
+            live_retry_params: self.live_retry_params(),
+            resync_retry_params: self.resync_retry_params(),
+            resync_max_fetch_failures: self.resync_max_fetch_failures,
+            consumer_timeout: self.consumer_timeout,
+            max_event_deferrals: self.max_event_deferrals,
+            max_concurrent_consumers_global: self.max_concurrent_consumers_global,
+            layers: self.layers.len(),
+            is_gap_detection_enabled: self.gap_detection.is_some(),
+            is_reorg_detection_enabled: self.reorg_detection.is_some(),
+            is_claims_enabled: self.claims.is_some(),
+            is_checkpoints_enabled: self.checkpoints.is_some(),
+            is_paused: self.handle.is_paused(),
         }
     }
 
-    async fn get_unregistered_program_transactions(
+    fn record_skip(
         &self,
-    ) -> Result<(
-        u64,
-        result::Result<NonEmptyVec<SolanaSignature>, EmptyError>,
-        Option<SolanaSignature>,
-    )> {
-        use de_solana_client::GetTransactionsSignaturesForAddress;
-
-        let resync_last_slot = self.client.get_slot().await?;
-        let resync_start = self
-            .local_storage
-            .get_last_resynced_transaction(&self.program_id)?;
-        info!(
-            "Resync start from {}",
-            resync_start
-                .as_ref()
-                .map(|tx| format!("{tx} transaction"))
-                .unwrap_or("beginning".to_owned())
+        program_id: &Pubkey,
+        reason: SkipReason,
+        signature: Option<SolanaSignature>,
+    ) {
+        self.handle.skipped[reason as usize].fetch_add(1, Ordering::Relaxed);
+        debug!(
+            ?reason,
+            %program_id,
+            signature = signature.map(|signature| signature.to_string()),
+            "Transaction skipped"
         );
-        let all_signatures = <RpcClient as GetTransactionsSignaturesForAddress>::get_signatures_data_for_address_with_config(
-                &self.client,
-                &self.program_id,
-                self.commitment_config,
-                resync_start
-            )
-            .await?;
-
-        // If any of tx in resync batch failed, then not move last resync transaction pointer
-        let last_transaction = all_signatures.first().map(|d| d.signature);
-
-        let all_signatures: Vec<SolanaSignature> = if self.resync_order == ResyncOrder::Historical {
-            all_signatures
-                .into_iter()
-                .filter_map(|d| d.err.is_none().then_some(d.signature))
-                .rev()
-                .collect()
-        } else {
-            all_signatures
-                .into_iter()
-                .filter_map(|d| d.err.is_none().then_some(d.signature))
-                .collect()
+        if let Some(on_skip) = self.on_skip.as_ref() {
+            on_skip(reason, *program_id, signature);
+        }
+    }
+
+    /// Count failed resync fetch of `signature`, `true` if it reached
+    /// `resync_max_fetch_failures` & must be quarantined
+    fn count_fetch_failure(&self, signature: &SolanaSignature) -> bool {
+        let Some(max_fetch_failures) = self.resync_max_fetch_failures else {
+            return false;
+        };
+        let Ok(mut fetch_failures) = self.fetch_failures.lock() else {
+            return false;
+        };
+        let failures = fetch_failures.entry(*signature).or_default();
+        *failures += 1;
+        if *failures < max_fetch_failures.get() {
+            return false;
+        }
+        fetch_failures.remove(signature);
+        true
+    }
+
+    fn forget_fetch_failures(&self, signature: &SolanaSignature) {
+        if self.resync_max_fetch_failures.is_some() {
+            if let Ok(mut fetch_failures) = self.fetch_failures.lock() {
+                fetch_failures.remove(signature);
+            }
+        }
+    }
+
+    fn transaction_source(&self) -> &dyn TransactionSource {
+        self.transaction_source
+            .as_deref()
+            .unwrap_or(self.client.as_ref())
+    }
+
+    fn signature_source(&self) -> &dyn SignatureSource {
+        self.signature_source
+            .as_deref()
+            .unwrap_or(self.client.as_ref())
+    }
+
+    fn logs_source(&self) -> Option<&dyn LogsSource> {
+        self.logs_source.as_deref().or(self
+            .pubsub_client
+            .as_deref()
+            .map(|pubsub_client| pubsub_client as &dyn LogsSource))
+    }
+
+    fn blocks_source(&self) -> Option<&dyn BlocksSource> {
+        self.blocks_source.as_deref().or(self
+            .pubsub_client
+            .as_deref()
+            .map(|pubsub_client| pubsub_client as &dyn BlocksSource))
+    }
+
+    fn programs(&self) -> &[Arc<Program>] {
+        self.programs.get_or_init(|| self.build_programs())
+    }
+
+    fn build_programs(&self) -> Vec<Arc<Program>> {
+        let semaphore =
+            |permits: NonZeroUsize| Arc::new(tokio::sync::Semaphore::new(permits.get()));
+        let global_consumer_permits = self.max_concurrent_consumers_global.map(semaphore);
+
+        let main = Program {
+            program_id: self.program_id,
+            commitment_config: self.commitment_config,
+            event_consumer: None,
+            consumers: self.consumers.clone(),
+            is_resync_enabled: self.is_resync_enabled,
+            resync_duration: None,
+            resync_signatures_chunk_size: None,
+            resync_order: self.resync_order,
+            is_rollback_enabled: true,
+            max_concurrent_consumers: self.max_concurrent_consumers_per_program,
+            consumer_permits: self.max_concurrent_consumers_per_program.map(semaphore),
+            global_consumer_permits: global_consumer_permits.clone(),
         };
+        let profiles = self.profiles.iter().map(|(program_id, profile)| Program {
+            program_id: *program_id,
+            commitment_config: profile.commitment_config.unwrap_or(self.commitment_config),
+            event_consumer: profile.event_consumer,
+            consumers: profile
+                .consumers
+                .clone()
+                .unwrap_or_else(|| self.consumers.clone()),
+            is_resync_enabled: profile.is_resync_enabled.unwrap_or(self.is_resync_enabled),
+            resync_duration: profile.resync_duration,
+            resync_signatures_chunk_size: profile.resync_signatures_chunk_size,
+            resync_order: profile.resync_order.unwrap_or(self.resync_order),
+            is_rollback_enabled: false,
+            max_concurrent_consumers: profile
+                .max_concurrent_consumers
+                .or(self.max_concurrent_consumers_per_program),
+            consumer_permits: profile
+                .max_concurrent_consumers
+                .or(self.max_concurrent_consumers_per_program)
+                .map(semaphore),
+            global_consumer_permits: global_consumer_permits.clone(),
+        });
 
-        Ok((
-            resync_last_slot,
-            NonEmptyVec::try_from(
-                self.local_storage
-                    .filter_unregistered_transactions(&self.program_id, &all_signatures)?,
-            ),
-            last_transaction,
-        ))
+        std::iter::once(main)
+            .chain(profiles)
+            .map(Arc::new)
+            .collect()
     }
 
-    async fn resync_events(self: &Arc<Self>) -> Result<()> {
-        if !self.is_resync_enabled {
-            return Ok(());
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        match serde_json::to_string(&self.describe()) {
+            Ok(description) => info!("Start reader: {description}"),
+            Err(err) => warn!("Error while serialize description of reader: {err:?}"),
+        }
+        let programs = self.programs().to_vec();
+
+        let mut tasks = vec![];
+        let (self_ref, programs_ref) = (Arc::clone(&self), programs.clone());
+        tasks.push(tokio::task::spawn(
+            self_ref.listen_ingested(programs_ref).instrument(span!(
+                Level::ERROR,
+                "Listen Ingested",
+                program_id = "*"
+            )),
+        ));
+
+        if self.parsed_tx_cache.is_some() {
+            let self_ref = Arc::clone(&self);
+            tasks.push(tokio::task::spawn(
+                self_ref
+                    .evict_parsed_transactions()
+                    .instrument(span!(Level::ERROR, "Evict Parsed Transactions")),
+            ));
         }
 
-        'resync: loop {
-            tokio::time::sleep(self.resync_duration).await;
-            info!("Start resync for program {}", self.program_id);
-
-            let (resync_last_slot, signatures, mut last_transaction) = unwrap_or_continue!(
-                self.get_unregistered_program_transactions().await,
-                "Error while get unregistered program signature: {err:?}"
-            );
-            let signatures = match signatures {
-                Ok(non_empty_signatures) => non_empty_signatures,
-                Err(EmptyError) => {
-                    (self.resync_ptr_setter)(resync_last_slot).await?;
-                    self.set_last_resynced_transaction(last_transaction)?;
-                    info!("Resync ended: no new transactions");
-                    continue 'resync;
-                }
-            };
-
-            info!(
-                "Find new {} transactions, start processing",
-                signatures.len()
-            );
-
-            let signatures_chunks = signatures
-                .as_slice()
-                .chunks(
-                    self.resync_signatures_chunk_size
-                        .unwrap_or_else(|| signatures.len().get()),
-                )
-                .enumerate();
-
-            let mut tasks = Vec::new();
-            for (index, signatures_chunk) in signatures_chunks {
-                let self_clone = self.clone();
-                let signatures_chunk = signatures_chunk.to_vec();
-
-                tasks.push(async move {
-                    let mut is_chunk_successfull_processed = true;
-
-                    for tx_signature in signatures_chunk.into_iter() {
-                        info!(
-                            "Unprocessed (by ws) transaction find while resynchronization process, transaction hash: {}",
-                            tx_signature.to_string()
-                        );
-
-                        let transaction = unwrap_or_continue!(
-                            self_clone.get_transaction_by_signature(tx_signature).await,
-                            error_action = {
-                                is_chunk_successfull_processed = false;
-                            },
-                            "Error while get transaction by signature: {err:?}"
-                        );
-
-                        let transaction_str = tx_signature.to_string();
-                        if let Err(err) = (self_clone.transaction_consumer)(
-                            tx_signature,
-                            transaction,
-                            Arc::clone(&self_clone.client),
-                            Arc::clone(&self_clone.event_recipient),
-                        )
+        let is_logs_source = self.live_source == LiveSource::Logs;
+        if let Some(filter) = self.logs_filter.clone().filter(|_| is_logs_source) {
+            let (self_ref, programs_ref) = (Arc::clone(&self), programs.clone());
+            tasks.push(tokio::task::spawn(
+                async move {
+                    let commitment_config = self_ref.commitment_config;
+                    self_ref
+                        .listen_events(&programs_ref, filter, commitment_config, true)
                         .await
-                        {
-                            error!("Error while transaction {transaction_str} consuming {err:?}", err = err);
-                            is_chunk_successfull_processed = false;
-                        } else {
-                            info!("Transaction {tx_signature} consumed as part of resync process");
-                        }
-
-                        self_clone
-                            .local_storage
-                            .register_transaction(&self_clone.program_id, &tx_signature)?;
-                    }
-
-                    Result::Ok(is_chunk_successfull_processed)
                 }
-                    .instrument(span!(
-                        Level::ERROR,
-                        "Register chunk",
-                        chunk_index = index,
-                    ))
-                );
-            }
+                .instrument(span!(Level::ERROR, "Listen Events", program_id = "*")),
+            ));
+        }
 
-            let mut tasks_success = true;
-            let mut completion_stream = tasks
-                .into_iter()
-                .map(tokio::spawn)
-                .collect::<futures::stream::FuturesUnordered<_>>();
-
-            while let Some(task) = completion_stream.next().await {
-                tasks_success &= match task {
-                    Ok(Ok(true)) => true,
-                    Ok(Ok(false)) => {
-                        last_transaction.take();
-                        true
-                    }
-                    Ok(Err(err)) => {
-                        error!("Error while resync task: {err:?}");
-                        false
-                    }
-                    Err(err) => {
-                        error!("Error while join resync task: {err:?}");
-                        false
-                    }
-                };
-            }
+        for program in programs {
+            let program_id = program.program_id.to_string();
 
-            if !tasks_success {
-                warn!("Some of resync tasks failed, not move resync ptr");
-                continue 'resync;
-            }
-
-            if let Some(last_transaction) = last_transaction {
-                info!("resync successful ended, ptr will moved to {last_transaction}");
-            } else {
-                info!("resync successful ended, not new ptr for move");
+            if self.live_source == LiveSource::Blocks {
+                let (self_ref, program_ref) = (Arc::clone(&self), Arc::clone(&program));
+                tasks.push(tokio::task::spawn(
+                    async move { self_ref.listen_blocks(program_ref).await }.instrument(span!(
+                        Level::ERROR,
+                        "Listen Blocks",
+                        program_id = program_id.clone()
+                    )),
+                ));
+            } else if is_logs_source && self.logs_filter.is_none() {
+                let (self_ref, program_ref) = (Arc::clone(&self), Arc::clone(&program));
+                tasks.push(tokio::task::spawn(
+                    async move {
+                        let filter = RpcTransactionLogsFilter::Mentions(vec![program_ref
+                            .program_id
+                            .to_string()]);
+                        let commitment_config = program_ref.commitment_config;
+                        let filter_by_invoke = self_ref.filter_mentions_by_invoke;
+                        self_ref
+                            .listen_events(
+                                &[program_ref],
+                                filter,
+                                commitment_config,
+                                filter_by_invoke,
+                            )
+                            .await
+                    }
+                    .instrument(span!(
+                        Level::ERROR,
+                        "Listen Events",
+                        program_id = program_id
+                    )),
+                ));
             }
-            self.set_last_resynced_transaction(last_transaction)?;
 
-            (self.resync_ptr_setter)(resync_last_slot).await?;
+            let (self_ref, program_ref) = (Arc::clone(&self), Arc::clone(&program));
+            tasks.push(tokio::task::spawn(
+                async move { self_ref.resync_events(&program_ref).await }.instrument(span!(
+                    Level::ERROR,
+                    "Resync Event",
+                    program_id = program_id,
+                )),
+            ));
+
+            let (self_ref, program_ref) = (Arc::clone(&self), Arc::clone(&program));
+            let reorg_program_id = program_id.clone();
+            tasks.push(tokio::task::spawn(
+                async move { self_ref.detect_reorgs(&program_ref).await }.instrument(span!(
+                    Level::ERROR,
+                    "Reorg Detection",
+                    program_id = reorg_program_id
+                )),
+            ));
+
+            let (self_ref, program_ref) = (Arc::clone(&self), Arc::clone(&program));
+            tasks.push(tokio::task::spawn(
+                async move { self_ref.detect_gaps(&program_ref).await }.instrument(span!(
+                    Level::ERROR,
+                    "Gap Detection",
+                    program_id = program_id
+                )),
+            ));
         }
+
+        futures::future::try_join_all(tasks.into_iter().map(flatten))
+            .await
+            .map(|_| ())
+            .inspect_err(|err| {
+                error!("Error while run main task: {err:?}");
+            })
     }
 
-    fn set_last_resynced_transaction(
-        self: &Arc<Self>,
-        last_transaction: Option<SolanaSignature>,
-    ) -> Result<()> {
-        let next_resync_ptr = match self.resync_rollback.write().as_deref() {
-            Ok(Rollback::Beginning) => {
-                info!("Reset last resynced tx");
-                self.local_storage
-                    .reset_last_resynced_transaction(&self.program_id)?;
-                return Ok(());
-            }
-            Ok(Rollback::None) => last_transaction,
-            Err(err) => {
-                error!("Error while lock rollback: {err:?}");
-                last_transaction
-            }
-            Ok(Rollback::Signature(signature)) => Some(*signature),
-        };
+    fn event_consumer(&self, program: &Program) -> EventConsumerFn {
+        program
+            .event_consumer
+            .or(self.config.load().event_consumer)
+            .unwrap_or(self.event_consumer)
+    }
 
-        if let Some(last_transaction) = next_resync_ptr {
-            info!("Set last resynced tx to {last_transaction} transaction");
-            self.local_storage
-                .set_last_resynced_transaction(&self.program_id, &last_transaction)?;
-        }
+    fn resync_duration(&self, program: &Program) -> Duration {
+        program
+            .resync_duration
+            .or(self.config.load().resync_duration)
+            .unwrap_or(self.resync_duration)
+    }
 
-        Ok(())
+    fn resync_signatures_chunk_size(&self, program: &Program) -> Option<usize> {
+        program
+            .resync_signatures_chunk_size
+            .or(self.config.load().resync_signatures_chunk_size)
+            .or(self.resync_signatures_chunk_size)
     }
 
-    async fn get_transaction_by_signature(
-        &self,
-        tx_signature: SolanaSignature,
-    ) -> Result<TransactionParsedMeta> {
-        let TransactionRequestParams {
-            mut attempts_count,
-            attempt_timeout,
-        } = self.live_events_transaction_request_param.clone();
-
-        loop {
-            match self
-                .client
-                .bind_transaction_instructions_logs(tx_signature, self.commitment_config)
-                .await
-                .map_err(Error::EventParserError)
-            {
-                Ok(tx) => return Ok(tx),
-                Err(err) => {
-                    attempts_count -= 1;
-                    if attempts_count == 0 {
-                        return Err(err);
-                    }
+    fn live_retry_params(&self) -> RetryParams {
+        self.config
+            .load()
+            .live_events_transaction_request_param
+            .unwrap_or(self.live_events_transaction_request_param)
+    }
 
-                    warn!("Error while request {tx_signature}, attempts left: {attempts_count}");
-                    tokio::time::sleep(attempt_timeout).await;
-                }
-            }
-        }
+    fn resync_retry_params(&self) -> RetryParams {
+        let config = self.config.load();
+        config
+            .resync_transaction_request_param
+            .or(self.resync_transaction_request_param)
+            .or(config.live_events_transaction_request_param)
+            .unwrap_or(self.live_events_transaction_request_param)
     }
 }
 
+fn unix_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
 async fn flatten<T, E>(
     handle: tokio::task::JoinHandle<result::Result<T, E>>,
 ) -> result::Result<T, E>
@@ -563,3 +797,88 @@ where
         Err(err) => Err(err.into()),
     }
 }
+
+#[cfg(test)]
+mod event_reader_service_test {
+    use super::*;
+    use crate::{
+        event_parser::ParseEvent,
+        mock_source::{invocation, MemoryStorage, MockChain, MockClock},
+    };
+
+    const TYPED_PROGRAM_ID: Pubkey = Pubkey::new_from_array([3; 32]);
+
+    #[derive(Debug, PartialEq, anchor_lang::AnchorDeserialize)]
+    struct Swap {
+        amount: u64,
+    }
+    impl anchor_lang::Owner for Swap {
+        fn owner() -> Pubkey {
+            TYPED_PROGRAM_ID
+        }
+    }
+    impl anchor_lang::Discriminator for Swap {
+        const DISCRIMINATOR: [u8; 8] = [4; 8];
+    }
+
+    /// Recipient of typed events only, without `PassEvent<Vec<u8>>`
+    struct SwapRecipient(tokio::sync::mpsc::UnboundedSender<Swap>);
+
+    #[async_trait]
+    impl PassEvent<Swap> for SwapRecipient {
+        type Error = tokio::sync::mpsc::error::SendError<Swap>;
+
+        async fn pass_event(&self, event: Swap) -> result::Result<(), Self::Error> {
+            self.0.send(event)
+        }
+    }
+
+    fn pass_swaps(
+        _signature: SolanaSignature,
+        transaction: TransactionParsedMeta,
+        _client: Arc<RpcClient>,
+        recipient: Arc<SwapRecipient>,
+    ) -> BoxFuture<'static, Result<()>> {
+        Box::pin(async move {
+            let swaps = transaction
+                .meta
+                .into_values()
+                .flat_map(|(_, logs)| logs)
+                .filter_map(|log| log.parse_event::<Swap>(TYPED_PROGRAM_ID)?.ok());
+            for swap in swaps.collect::<Vec<_>>() {
+                let _ = recipient.pass_event(swap).await;
+            }
+            Ok(())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_typed_event_recipient() {
+        let chain = Arc::new(MockChain::new());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                TYPED_PROGRAM_ID,
+                pass_swaps,
+                Arc::new(SwapRecipient(sender)),
+                Arc::new(MemoryStorage::new()),
+                Arc::new(MockClock::new()),
+            )
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        let data = [[4; 8].as_slice(), &5u64.to_le_bytes()].concat();
+        chain
+            .push(invocation(
+                TYPED_PROGRAM_ID,
+                1,
+                &[format!("Program data: {}", base64::encode(data))],
+            ))
+            .unwrap();
+        let swap = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await;
+        assert_eq!(swap.unwrap(), Some(Swap { amount: 5 }));
+        task.abort();
+    }
+}
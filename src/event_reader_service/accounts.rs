@@ -0,0 +1,398 @@
+//! [`AccountsReader`]: consuming of account updates from `accountSubscribe` or
+//! `programSubscribe`, deduplicated by slot & data hash
+
+use std::{fmt, sync::Arc};
+
+use anchor_lang::AccountDeserialize;
+use async_trait::async_trait;
+use futures::{
+    future::BoxFuture,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use result_inspect::ResultInspectErr;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    nonblocking::pubsub_client::PubsubClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::RpcFilterType,
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig};
+use tracing::{Instrument, *};
+
+use crate::{
+    storage,
+    transaction_parser::{Pubkey, Slot},
+};
+
+use super::{Error, Result};
+
+/// Set of accounts which updates will be consumed by [`AccountsReader`]
+#[derive(Debug, Clone)]
+pub enum AccountsSubscription {
+    /// `accountSubscribe` for each of accounts
+    Accounts(Vec<Pubkey>),
+    /// `programSubscribe` for all accounts owned by `program_id` and matched by `filters`
+    Program {
+        program_id: Pubkey,
+        filters: Option<Vec<RpcFilterType>>,
+    },
+}
+
+pub type AccountConsumerFn<A> =
+    Arc<dyn Send + Sync + Fn(Pubkey, Slot, A) -> BoxFuture<'static, Result<()>>>;
+
+/// Optional subsystem, that can be runned alongside with [`EventsReader`]
+///
+/// Listen updates of [`AccountsSubscription`] accounts by websocket, deserialize them
+/// into `A` and pass into `account_consumer`. Any Borsh account type can be used
+/// by manual implementation of [`AccountDeserialize`]
+///
+/// Updates deduplicated by [`storage::AccountUpdateVersion`] with help of `local_storage`, so
+/// update is skipped if the same or newer one is already registered. Update is registered only
+/// after `account_consumer` succeeded, so failed update is consumed again when redelivered
+#[derive(derive_builder::Builder)]
+pub struct AccountsReader<A, E>
+where
+    A: AccountDeserialize + Send + 'static,
+    E: 'static + Send + Sync,
+    Error: From<E>,
+{
+    pub subscription: AccountsSubscription,
+
+    #[builder(default = "CommitmentConfig::finalized()")]
+    pub commitment_config: CommitmentConfig,
+
+    #[builder(default, setter(strip_option))]
+    pub pubsub_client: Option<Arc<PubsubClient>>,
+
+    /// Replacement of `pubsub_client` as source of account updates, e.g. for tests
+    #[builder(default)]
+    pub accounts_source: Option<Arc<dyn AccountsSource>>,
+
+    pub account_consumer: AccountConsumerFn<A>,
+    pub local_storage: Arc<dyn Send + Sync + storage::RegisterAccountUpdate<Error = E>>,
+}
+
+impl<A, E> AccountsReader<A, E>
+where
+    A: AccountDeserialize + Send + 'static,
+    E: 'static + Send + Sync + fmt::Debug,
+    Error: From<E>,
+{
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        info!("Launching accounts websocket client");
+
+        let account_config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(self.commitment_config),
+            ..Default::default()
+        };
+
+        loop {
+            let mut stream = stream::select_all(
+                self.accounts_source()?
+                    .accounts_subscribe(&self.subscription, account_config.clone())
+                    .instrument(span!(Level::ERROR, "AccountsSubscribe"))
+                    .await?,
+            );
+
+            info!("Start listening accounts updates");
+            while let Some((account, slot, ui_account)) = stream.next().await {
+                let data = match ui_account.decode::<Account>() {
+                    Some(decoded) => decoded.data,
+                    None => {
+                        error!("Error while decode account {account} data, skip");
+                        continue;
+                    }
+                };
+                let version = storage::AccountUpdateVersion {
+                    slot,
+                    data_hash: solana_sdk::hash::hash(&data).to_bytes(),
+                };
+                if unwrap_or_continue!(
+                    self.local_storage
+                        .is_account_update_registered(&account, &version)
+                        .map_err(Error::from),
+                    "Error while check account update: {err:?}"
+                ) {
+                    info!("Update of {account} at {slot} slot already registered, skip");
+                    continue;
+                }
+
+                let decoded = unwrap_or_continue!(
+                    A::try_deserialize(&mut data.as_slice()),
+                    "Error while deserialize account {account}: {err:?}"
+                );
+
+                let account_str = account.to_string();
+                if let Err(err) = (self.account_consumer)(account, slot, decoded)
+                    .instrument(span!(
+                        Level::ERROR,
+                        "Consume account",
+                        account = account_str
+                    ))
+                    .await
+                {
+                    error!("Error while account update consuming {err:?}", err = err);
+                    continue;
+                }
+
+                if unwrap_or_continue!(
+                    self.local_storage
+                        .register_account_update(&account, version)
+                        .map_err(Error::from),
+                    "Error while register account update: {err:?}"
+                ) {
+                    info!("Update of {account} at {slot} slot consumed");
+                } else {
+                    info!("Update of {account} at {slot} slot consumed, newer one registered");
+                }
+            }
+
+            warn!("Accounts listen task: stream empty, resubscribe");
+        }
+    }
+
+    fn accounts_source(&self) -> Result<&dyn AccountsSource> {
+        self.accounts_source
+            .as_deref()
+            .or(self
+                .pubsub_client
+                .as_deref()
+                .map(|pubsub_client| pubsub_client as &dyn AccountsSource))
+            .ok_or_else(|| {
+                Error::WebsocketError(
+                    "Neither `pubsub_client` nor `accounts_source` set".to_owned(),
+                )
+            })
+    }
+}
+
+/// Source of account updates of [`AccountsReader`], [`AccountsReader::pubsub_client`]
+/// by default
+#[async_trait]
+pub trait AccountsSource: Send + Sync {
+    /// Streams of `(account, slot, account)` updates, resubscribed when all of them ended
+    async fn accounts_subscribe(
+        &self,
+        subscription: &AccountsSubscription,
+        config: RpcAccountInfoConfig,
+    ) -> Result<Vec<BoxStream<'_, (Pubkey, Slot, UiAccount)>>>;
+}
+
+#[async_trait]
+impl AccountsSource for PubsubClient {
+    async fn accounts_subscribe(
+        &self,
+        subscription: &AccountsSubscription,
+        config: RpcAccountInfoConfig,
+    ) -> Result<Vec<BoxStream<'_, (Pubkey, Slot, UiAccount)>>> {
+        match subscription {
+            AccountsSubscription::Accounts(accounts) => {
+                let mut streams = Vec::with_capacity(accounts.len());
+                for account in accounts.iter().copied() {
+                    let (stream, _unsubscribe) = self
+                        .account_subscribe(&account, Some(config.clone()))
+                        .await
+                        .inspect_err(|err| error!("Error while subs {account}: {err:?}"))
+                        .map_err(|err| Error::WebsocketError(err.to_string()))?;
+
+                    streams.push(
+                        stream
+                            .map(move |response| (account, response.context.slot, response.value))
+                            .boxed(),
+                    );
+                }
+                Ok(streams)
+            }
+            AccountsSubscription::Program {
+                program_id,
+                filters,
+            } => {
+                let (stream, _unsubscribe) = self
+                    .program_subscribe(
+                        program_id,
+                        Some(RpcProgramAccountsConfig {
+                            filters: filters.clone(),
+                            account_config: config,
+                            with_context: Some(true),
+                        }),
+                    )
+                    .await
+                    .inspect_err(|err| error!("Error while subs: {err:?}"))
+                    .map_err(|err| Error::WebsocketError(err.to_string()))?;
+
+                Ok(vec![stream
+                    .filter_map(|response| async move {
+                        match response.value.pubkey.parse::<Pubkey>() {
+                            Ok(account) => {
+                                Some((account, response.context.slot, response.value.account))
+                            }
+                            Err(err) => {
+                                error!("Error while parse account pubkey: {err:?}");
+                                None
+                            }
+                        }
+                    })
+                    .boxed()])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod accounts_test {
+    use std::{
+        collections::HashMap,
+        result,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        },
+    };
+
+    use super::*;
+
+    /// Raw data of account
+    #[derive(Debug, Clone, PartialEq)]
+    struct RawAccount(Vec<u8>);
+
+    impl AccountDeserialize for RawAccount {
+        fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+            Ok(Self(buf.to_vec()))
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryAccountUpdates(Mutex<HashMap<Pubkey, storage::AccountUpdateVersion>>);
+
+    impl storage::RegisterAccountUpdate for MemoryAccountUpdates {
+        type Error = std::convert::Infallible;
+
+        fn register_account_update(
+            &self,
+            account: &Pubkey,
+            version: storage::AccountUpdateVersion,
+        ) -> result::Result<bool, Self::Error> {
+            let mut updates = self.0.lock().unwrap();
+            if updates
+                .get(account)
+                .is_some_and(|registered| !version.is_newer_than(registered))
+            {
+                return Ok(false);
+            }
+            updates.insert(*account, version);
+            Ok(true)
+        }
+
+        fn get_last_account_update(
+            &self,
+            account: &Pubkey,
+        ) -> result::Result<Option<storage::AccountUpdateVersion>, Self::Error> {
+            Ok(self.0.lock().unwrap().get(account).copied())
+        }
+    }
+
+    /// Yields `updates` once, later subscriptions never end
+    struct ScriptedAccounts(Mutex<Option<Vec<(Pubkey, Slot, UiAccount)>>>);
+
+    #[async_trait]
+    impl AccountsSource for ScriptedAccounts {
+        async fn accounts_subscribe(
+            &self,
+            _subscription: &AccountsSubscription,
+            _config: RpcAccountInfoConfig,
+        ) -> Result<Vec<BoxStream<'_, (Pubkey, Slot, UiAccount)>>> {
+            Ok(vec![match self.0.lock().unwrap().take() {
+                Some(updates) => stream::iter(updates).boxed(),
+                None => stream::pending().boxed(),
+            }])
+        }
+    }
+
+    /// Runs [`AccountsReader`] over `updates`, `account_consumer` fails on first call. Returns
+    /// `(slot, data)` of the first `calls` calls of consumer
+    async fn consume_account_updates(
+        account: Pubkey,
+        updates: Vec<(Slot, Vec<u8>)>,
+        calls: usize,
+    ) -> Vec<(Slot, Vec<u8>, bool)> {
+        let updates = updates
+            .into_iter()
+            .map(|(slot, data)| {
+                let ui_account = UiAccount::encode(
+                    &account,
+                    &Account {
+                        data,
+                        ..Default::default()
+                    },
+                    UiAccountEncoding::Base64,
+                    None,
+                    None,
+                );
+                (account, slot, ui_account)
+            })
+            .collect();
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let is_failed = Arc::new(AtomicBool::new(false));
+        let reader = Arc::new(
+            AccountsReaderBuilder::<RawAccount, std::convert::Infallible>::default()
+                .subscription(AccountsSubscription::Accounts(vec![account]))
+                .accounts_source(Some(Arc::new(ScriptedAccounts(Mutex::new(Some(updates))))))
+                .account_consumer(Arc::new(move |_account, slot, RawAccount(data)| {
+                    let is_ok = is_failed.swap(true, Ordering::SeqCst);
+                    sender.send((slot, data, is_ok)).unwrap();
+                    Box::pin(async move {
+                        match is_ok {
+                            true => Ok(()),
+                            false => Err(Error::WebsocketError("consumer failed".to_owned())),
+                        }
+                    })
+                }))
+                .local_storage(Arc::new(MemoryAccountUpdates::default()))
+                .build()
+                .unwrap(),
+        );
+
+        let run = tokio::spawn(reader.run());
+        let mut consumed = Vec::with_capacity(calls);
+        while consumed.len() < calls {
+            consumed.push(receiver.recv().await.unwrap());
+        }
+        tokio::task::yield_now().await;
+        assert!(receiver.try_recv().is_err(), "Unexpected consumer call");
+        run.abort();
+        consumed
+    }
+
+    #[tokio::test]
+    async fn test_accounts_reader_redelivers_failed_update() {
+        let consumed = consume_account_updates(
+            Pubkey::new_unique(),
+            vec![(10, vec![1]), (10, vec![1]), (10, vec![1]), (9, vec![0])],
+            2,
+        )
+        .await;
+        assert_eq!(consumed, [(10, vec![1], false), (10, vec![1], true)]);
+    }
+
+    #[tokio::test]
+    async fn test_accounts_reader_delivers_same_slot_write() {
+        let consumed = consume_account_updates(
+            Pubkey::new_unique(),
+            vec![(9, vec![0]), (10, vec![1]), (10, vec![2]), (10, vec![2])],
+            3,
+        )
+        .await;
+        assert_eq!(
+            consumed,
+            [
+                (9, vec![0], false),
+                (10, vec![1], true),
+                (10, vec![2], true)
+            ]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! [`CheckpointCoordinator`]: resync pointer moved only after all consumers confirmed
+//! persistence
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::transaction_parser::Slot;
+
+/// Durable checkpoints of consumers, see [`EventsReader::checkpoints`]
+///
+/// Every consumer registers itself by [`CheckpointCoordinator::register`] and confirms slot
+/// by [`ConsumerCheckpoint::confirm`] once transactions up to this slot are persisted (e.g.
+/// DB transaction is committed). Transactions ignored by consumer must be confirmed too,
+/// otherwise resync pointer is never moved past them
+#[derive(Debug, Clone)]
+pub struct CheckpointCoordinator(Arc<tokio::sync::watch::Sender<BTreeMap<String, Option<Slot>>>>);
+
+impl Default for CheckpointCoordinator {
+    fn default() -> Self {
+        Self(Arc::new(tokio::sync::watch::channel(BTreeMap::new()).0))
+    }
+}
+
+impl CheckpointCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register consumer `name`, pointer isn't moved until it confirms checkpoint. Consumer
+    /// registered again keeps its checkpoint
+    pub fn register(&self, name: impl Into<String>) -> ConsumerCheckpoint {
+        let name = name.into();
+        self.0.send_modify(|checkpoints| {
+            checkpoints.entry(name.clone()).or_default();
+        });
+        ConsumerCheckpoint {
+            name,
+            coordinator: self.clone(),
+        }
+    }
+
+    /// Stop waiting for checkpoints of consumer `name`
+    pub fn unregister(&self, name: &str) {
+        self.0.send_modify(|checkpoints| {
+            checkpoints.remove(name);
+        });
+    }
+
+    /// Slot confirmed by all consumers, `None` if some of them haven't confirmed any yet.
+    /// [`Slot::MAX`] if there are no consumers
+    pub fn confirmed_slot(&self) -> Option<Slot> {
+        Self::min_checkpoint(&self.0.borrow())
+    }
+
+    /// Wait until all consumers confirm `slot`
+    pub async fn wait_confirmed(&self, slot: Slot) {
+        // Sender is owned by `self`, so channel can't be closed
+        let _ = self
+            .0
+            .subscribe()
+            .wait_for(|checkpoints| Self::min_checkpoint(checkpoints) >= Some(slot))
+            .await;
+    }
+
+    fn min_checkpoint(checkpoints: &BTreeMap<String, Option<Slot>>) -> Option<Slot> {
+        checkpoints
+            .values()
+            .try_fold(Slot::MAX, |min, checkpoint| Some(min.min((*checkpoint)?)))
+    }
+}
+
+/// Checkpoint of one consumer of [`CheckpointCoordinator`]
+#[derive(Debug, Clone)]
+pub struct ConsumerCheckpoint {
+    name: String,
+    coordinator: CheckpointCoordinator,
+}
+
+impl ConsumerCheckpoint {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Transactions up to `slot` are persisted by consumer, checkpoint never moves back
+    pub fn confirm(&self, slot: Slot) {
+        self.coordinator.0.send_modify(|checkpoints| {
+            let checkpoint = checkpoints.entry(self.name.clone()).or_default();
+            *checkpoint = Some(checkpoint.map_or(slot, |checkpoint| checkpoint.max(slot)));
+        });
+    }
+}
+
+#[cfg(test)]
+mod checkpoint_test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checkpoint_coordinator() {
+        let coordinator = CheckpointCoordinator::new();
+        assert_eq!(coordinator.confirmed_slot(), Some(Slot::MAX));
+
+        let db = coordinator.register("db");
+        let cache = coordinator.register("cache");
+        assert_eq!(coordinator.confirmed_slot(), None);
+
+        db.confirm(10);
+        cache.confirm(5);
+        cache.confirm(3);
+        assert_eq!(coordinator.confirmed_slot(), Some(5));
+
+        let wait = tokio::spawn({
+            let coordinator = coordinator.clone();
+            async move { coordinator.wait_confirmed(10).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!wait.is_finished());
+
+        cache.confirm(12);
+        wait.await.unwrap();
+        assert_eq!(coordinator.confirmed_slot(), Some(10));
+
+        coordinator.unregister("db");
+        assert_eq!(coordinator.confirmed_slot(), Some(12));
+    }
+}
@@ -0,0 +1,294 @@
+//! Settings of [`EventsReader`]: program profiles, overrides replaced at runtime, claims,
+//! gap & reorg detection, and description of reader for diagnostics
+
+use std::{num::NonZeroUsize, sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use futures::future::BoxFuture;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::{
+    storage,
+    transaction_parser::{Pubkey, Signature as SolanaSignature},
+};
+
+use super::{ConsumerRegistry, EventConsumerFn, Result, ResyncOrder, RetryParams};
+
+/// Storage & duration of transaction leases, see [`EventsReader::claims`]
+///
+/// Transactions failed or claimed by another replica aren't registered by reader. Claim is
+/// released if consuming failed, so transaction is consumed again on resync by any replica
+/// not moved resync pointer past it. Claim of consumed transaction is completed by
+/// [`storage::ClaimTransaction::complete_claim`] and never expires, so replica consumed it is
+/// the only one
+pub struct TransactionClaims<E> {
+    pub storage: Arc<dyn Send + Sync + storage::ClaimTransaction<Error = E>>,
+    /// Duration of claim, must be longer than consuming of transaction
+    pub ttl: Duration,
+}
+
+impl<E> Clone for TransactionClaims<E> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: Arc::clone(&self.storage),
+            ttl: self.ttl,
+        }
+    }
+}
+
+/// Settings of program read by [`EventsReader`] in addition to `program_id`, see
+/// [`EventsReader::profiles`]
+///
+/// Unset fields are taken from reader. [`EventsReader::resync_rollback`] is applied only to
+/// `program_id` of reader
+#[derive(Clone, Default)]
+pub struct ProgramProfile {
+    pub commitment_config: Option<CommitmentConfig>,
+    /// Prefilter of websocket logs
+    pub event_consumer: Option<EventConsumerFn>,
+    pub consumers: Option<ConsumerRegistry>,
+    pub is_resync_enabled: Option<bool>,
+    pub resync_duration: Option<Duration>,
+    pub resync_signatures_chunk_size: Option<usize>,
+    pub resync_order: Option<ResyncOrder>,
+    pub max_concurrent_consumers: Option<NonZeroUsize>,
+}
+
+/// Tunable settings of [`EventsReader`], replaced at runtime through
+/// [`EventsReader::config`] without restart of reader, e.g. by config pushed by ops
+///
+/// Set fields override settings the reader was built with, unset ones keep them. Fields
+/// set by [`ProgramProfile`] take precedence over both. Changes are applied starting from
+/// the next use of setting: next resync, next chunk or next transaction request, websocket
+/// subscriptions are not restarted
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use solana_events_parser::event_reader_service::{ReaderConfig, SharedReaderConfig};
+///
+/// let config = SharedReaderConfig::default();
+/// config.store(std::sync::Arc::new(ReaderConfig {
+///     resync_duration: Some(Duration::from_secs(30)),
+///     resync_signatures_chunk_size: Some(100),
+///     ..Default::default()
+/// }));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReaderConfig {
+    pub resync_duration: Option<Duration>,
+    pub resync_signatures_chunk_size: Option<usize>,
+    pub live_events_transaction_request_param: Option<RetryParams>,
+    pub resync_transaction_request_param: Option<RetryParams>,
+    /// Prefilter of websocket logs
+    pub event_consumer: Option<EventConsumerFn>,
+}
+
+/// [`ReaderConfig`] shared by [`EventsReader`] & its owner
+pub type SharedReaderConfig = Arc<ArcSwap<ReaderConfig>>;
+
+/// Snapshot of effective configuration of [`EventsReader`], see [`EventsReader::describe`]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReaderDescription {
+    /// Version of this crate
+    pub version: &'static str,
+    pub programs: Vec<ProgramDescription>,
+    /// See [`storage::ResyncedTransactionsPtrStorage::backend_name`]
+    pub storage_backend: String,
+    /// `pubsub`, `custom` ([`EventsReader::logs_source`]), `pubsub blocks`, `custom blocks`
+    /// ([`EventsReader::blocks_source`]), `ingest` ([`EventsReader::ingestor`]) or `none`
+    pub live_source: &'static str,
+    pub logs_filter: Option<String>,
+    pub filter_mentions_by_invoke: bool,
+    pub live_retry_params: RetryParams,
+    pub resync_retry_params: RetryParams,
+    pub resync_max_fetch_failures: Option<NonZeroUsize>,
+    pub consumer_timeout: Option<Duration>,
+    pub max_event_deferrals: usize,
+    pub max_concurrent_consumers_global: Option<NonZeroUsize>,
+    pub layers: usize,
+    pub is_gap_detection_enabled: bool,
+    pub is_reorg_detection_enabled: bool,
+    pub is_claims_enabled: bool,
+    pub is_checkpoints_enabled: bool,
+    pub is_paused: bool,
+}
+
+/// Effective settings of one program of [`EventsReader`], see [`ReaderDescription`]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProgramDescription {
+    pub program_id: String,
+    pub commitment: CommitmentConfig,
+    pub is_resync_enabled: bool,
+    pub resync_duration: Duration,
+    pub resync_signatures_chunk_size: Option<usize>,
+    pub resync_order: ResyncOrder,
+    pub max_concurrent_consumers: Option<NonZeroUsize>,
+    pub decomposers: usize,
+    /// Names of event handlers of [`ConsumerRegistry`]
+    pub event_handlers: Vec<String>,
+}
+
+/// Callback of [`ReorgDetection`], called with program id & transactions dropped by reorg
+pub type OnReorgFn =
+    Arc<dyn Send + Sync + Fn(Pubkey, Vec<SolanaSignature>) -> BoxFuture<'static, Result<()>>>;
+
+/// Config of chain reorganization detection of [`EventsReader`]
+///
+/// Transactions consumed with commitment below finalized are remembered with their slot and
+/// hash of its block, observed before finalization. Every `interval` they are checked by
+/// `getSignatureStatuses`, after their slot is finalized. Transactions which are absent in
+/// finalized chain, finalized in another slot or in another block of the same slot are
+/// reverted: passed into `on_reorg`, so consumer can revert their side effects, and
+/// unregistered. Then the next resync of program rolls its pointer back to the slot of the
+/// oldest of them (as [`Rollback::Slot`] of [`EventsReader::resync_rollback`], once), so
+/// re-included transactions and the ones which replaced them are consumed by resync after it.
+/// Reverted transactions are consumed again only if resync of program is enabled
+///
+/// If `on_reorg` fails, transactions are reported again by the next check. Unfinalized
+/// transactions are kept in memory only, so they are not checked after restart
+#[derive(Clone)]
+pub struct ReorgDetection {
+    pub interval: Duration,
+    pub on_reorg: OnReorgFn,
+}
+
+/// Config of signature gap detection of [`EventsReader`]
+///
+/// Every `interval` newest `page_size` signatures of program are compared with registered
+/// ones. Signature, unregistered on two checks in a row, is considered as missed by websocket
+/// listener: it's logged with warning, counted in [`ReaderHandle::missed_transactions`] and,
+/// if `fetch_missed` is set, fetched & consumed right away instead of waiting for resync
+#[derive(Debug, Clone)]
+pub struct GapDetection {
+    pub interval: Duration,
+    pub page_size: usize,
+    pub fetch_missed: bool,
+}
+
+impl Default for GapDetection {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            page_size: 100,
+            fetch_missed: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_test {
+    use futures::future;
+
+    use super::*;
+    use crate::{
+        event_reader_service::EventConsumeResult,
+        mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock},
+        storage::RegisterTransaction,
+    };
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([10; 32]);
+    const PROFILED_PROGRAM_ID: Pubkey = Pubkey::new_from_array([11; 32]);
+    const PREFILTERED_PROGRAM_ID: Pubkey = Pubkey::new_from_array([12; 32]);
+
+    #[tokio::test]
+    async fn test_program_profiles() {
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let (event_sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+
+        let consumers = ConsumerRegistry::default();
+        consumers.add_event_handler(
+            "events",
+            Arc::new(move |_, program_ctx, _| {
+                let _ = event_sender.send(program_ctx.program_id);
+                Box::pin(future::ready(Ok(())))
+            }),
+        );
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .profiles(vec![
+                (
+                    PROFILED_PROGRAM_ID,
+                    ProgramProfile {
+                        consumers: Some(consumers),
+                        is_resync_enabled: Some(false),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    PREFILTERED_PROGRAM_ID,
+                    ProgramProfile {
+                        event_consumer: Some(|_| Ok(EventConsumeResult::ConsumeSuccess)),
+                        ..Default::default()
+                    },
+                ),
+            ])
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        while chain.subscribers() < 3 {
+            tokio::task::yield_now().await;
+        }
+
+        // Consumers of profile are applied only to transactions of its program
+        let data = [format!("Program data: {}", base64::encode([1; 8]))];
+        let main = chain.push(invocation(PROGRAM_ID, 1, &data)).unwrap();
+        let profiled = chain
+            .push(invocation(PROFILED_PROGRAM_ID, 2, &data))
+            .unwrap();
+        let mut signatures = vec![];
+        for _ in 0..2 {
+            let signature = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+            signatures.push(signature.unwrap().unwrap());
+        }
+        signatures.sort();
+        let mut expected = vec![main, profiled];
+        expected.sort();
+        assert_eq!(signatures, expected);
+        assert_eq!(events.try_recv().unwrap(), PROFILED_PROGRAM_ID);
+        assert!(events.try_recv().is_err());
+
+        // Transaction consumed by `event_consumer` of profile isn't requested
+        let prefiltered = chain
+            .push(invocation(PREFILTERED_PROGRAM_ID, 3, &[]))
+            .unwrap();
+        while !storage
+            .is_transaction_registered(&PREFILTERED_PROGRAM_ID, &prefiltered)
+            .unwrap()
+        {
+            tokio::task::yield_now().await;
+        }
+        assert!(consumed.try_recv().is_err());
+
+        // Resync is disabled by profile only for its program
+        let missed_profiled = chain
+            .push_missed(invocation(PROFILED_PROGRAM_ID, 4, &[]))
+            .unwrap();
+        let missed = chain.push_missed(invocation(PROGRAM_ID, 5, &[])).unwrap();
+        let mut resyncs = 0;
+        let resynced = loop {
+            assert!(resyncs < 100, "missed transaction isn't resynced");
+            clock.advance(Duration::from_secs(1));
+            resyncs += 1;
+            if let Ok(resynced) =
+                tokio::time::timeout(Duration::from_millis(10), consumed.recv()).await
+            {
+                break resynced;
+            }
+        };
+        assert_eq!(resynced, Some(missed));
+        assert!(!storage
+            .is_transaction_registered(&PROFILED_PROGRAM_ID, &missed_profiled)
+            .unwrap());
+        task.abort();
+    }
+}
@@ -0,0 +1,742 @@
+//! Consuming of transactions: registry of decomposers & event handlers, exactly-once
+//! consumers, claims & limits of concurrent consumers
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use arc_swap::ArcSwap;
+use futures::future::BoxFuture;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use tracing::*;
+
+use crate::{
+    event_parser::{DataDecoder, EventId},
+    log_parser::{self, ProgramContext, ProgramLog},
+    middleware::{self, Layer, TimeoutLayer, TransactionConsumer},
+    storage,
+    transaction_parser::{
+        BoxedDecomposer, DecomposerRegistry, SharedDecomposer, Signature as SolanaSignature,
+        TransactionParsedMeta,
+    },
+};
+
+use super::{
+    detection::UnfinalizedTransaction, ConsumerPermits, Error, Event, EventConsumeResult,
+    EventsReader, Program, Result, SkipReason,
+};
+
+/// Handler of `Program data:` events, called with base64 decoded event (discriminator
+/// included) of every program invocation
+pub type EventHandlerFn = Arc<
+    dyn Send
+        + Sync
+        + Fn(SolanaSignature, ProgramContext, Vec<u8>) -> BoxFuture<'static, Result<()>>,
+>;
+
+/// Snapshot of [`ConsumerRegistry`]
+#[derive(Clone, Default)]
+pub struct Consumers {
+    pub decomposers: DecomposerRegistry,
+    pub event_handlers: BTreeMap<String, EventHandlerFn>,
+    /// Decoder of events passed into `event_handlers`
+    pub data_decoder: DataDecoder,
+}
+
+/// Decomposers & event handlers of [`EventsReader`], which can be added or removed while
+/// reader is running (e.g. after config reload)
+///
+/// Clones share the same registry. Every transaction is consumed by snapshot of registry
+/// taken before consuming, so changes are applied starting from the next transaction
+#[derive(Clone, Default)]
+pub struct ConsumerRegistry(Arc<ArcSwap<Consumers>>);
+
+impl ConsumerRegistry {
+    pub fn new(consumers: Consumers) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(consumers)))
+    }
+
+    pub fn load(&self) -> Arc<Consumers> {
+        self.0.load_full()
+    }
+
+    /// Replace all consumers at once
+    pub fn store(&self, consumers: Consumers) {
+        self.0.store(Arc::new(consumers));
+    }
+
+    fn update(&self, change: impl Fn(&mut Consumers)) {
+        self.0.rcu(|consumers| {
+            let mut consumers = Consumers::clone(consumers);
+            change(&mut consumers);
+            consumers
+        });
+    }
+
+    /// Add decomposer, replacing previously added one with the same name
+    pub fn add_decomposer(&self, name: &str, decomposer: BoxedDecomposer) {
+        info!("Add decomposer {name}");
+        let decomposer = SharedDecomposer::from(decomposer);
+        self.update(|consumers| {
+            consumers
+                .decomposers
+                .register_named(name, Arc::clone(&decomposer));
+        });
+    }
+
+    pub fn remove_decomposer(&self, name: &str) {
+        info!("Remove decomposer {name}");
+        self.update(|consumers| {
+            consumers.decomposers.unregister(name);
+        });
+    }
+
+    /// Add event handler, replacing previously added one with the same name
+    pub fn add_event_handler(&self, name: &str, handler: EventHandlerFn) {
+        info!("Add event handler {name}");
+        self.update(|consumers| {
+            consumers
+                .event_handlers
+                .insert(name.to_owned(), Arc::clone(&handler));
+        });
+    }
+
+    pub fn remove_event_handler(&self, name: &str) {
+        info!("Remove event handler {name}");
+        self.update(|consumers| {
+            consumers.event_handlers.remove(name);
+        });
+    }
+
+    /// Replace decoder of events, e.g. to collect undecodable `Program data:` payloads
+    pub fn set_data_decoder(&self, data_decoder: DataDecoder) {
+        self.update(|consumers| {
+            consumers.data_decoder = data_decoder.clone();
+        });
+    }
+
+    /// Consume transaction by current snapshot: decompose and consume instructions, then pass
+    /// events into all event handlers
+    pub async fn consume(
+        &self,
+        signature: SolanaSignature,
+        transaction: &TransactionParsedMeta,
+    ) -> Result<()> {
+        let consumers = self.load();
+
+        if !consumers.decomposers.is_empty() {
+            for ix in transaction.find_and_decompose_ix_with_registry(&consumers.decomposers)? {
+                ix.consume_ix().await?;
+            }
+        }
+
+        if !consumers.event_handlers.is_empty() {
+            for (program_ctx, (_ix, logs)) in transaction.meta.iter() {
+                for log in logs {
+                    let ProgramLog::Data(data) = log else {
+                        continue;
+                    };
+                    let event = unwrap_or_continue!(
+                        consumers.data_decoder.decode(data),
+                        "Error while decode event of {signature}: {err:?}"
+                    );
+                    for handler in consumers.event_handlers.values() {
+                        handler(signature, *program_ctx, event.clone()).await?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wrapper of side-effectful consumer (payments, notifications, etc.) that calls it once for
+/// every `(signature, ProgramContext)`, even if transaction is consumed by both live & resync
+/// paths or by several replicas sharing `storage`
+///
+/// Idempotency key is registered in `storage` by atomic
+/// [`register_idempotency_key`](storage::RegisterIdempotencyKey::register_idempotency_key)
+/// before call of consumer & removed if consumer failed, so failed side effect will be retried
+/// by the next consuming. Keys are prefixed by `name`, so different consumers of the same
+/// instruction don't conflict
+///
+/// Delivery is at-most-once: if process crashes after registration of key but before
+/// consumer completes, the side effect is never retried. For exactly-once the consumer has to
+/// commit its side effect together with the key, e.g. in one database transaction, and use
+/// this wrapper only for deduplication
+pub struct IdempotentConsumer<S, F> {
+    name: String,
+    storage: Arc<S>,
+    consumer: F,
+}
+
+impl<S, F> IdempotentConsumer<S, F>
+where
+    S: storage::RegisterIdempotencyKey + Send + Sync,
+    Error: From<S::Error>,
+{
+    pub fn new(name: impl Into<String>, storage: Arc<S>, consumer: F) -> Self {
+        Self {
+            name: name.into(),
+            storage,
+            consumer,
+        }
+    }
+
+    /// Key of `(signature, program_ctx)`: variable-length `name` & invoke level are prefixed
+    /// by their lengths, so keys of different consumers or contexts can't be equal
+    pub fn idempotency_key(
+        &self,
+        signature: &SolanaSignature,
+        program_ctx: &ProgramContext,
+    ) -> Vec<u8> {
+        let level = log_parser::level_to_bytes(program_ctx.invoke_level);
+        [
+            &(self.name.len() as u64).to_le_bytes(),
+            self.name.as_bytes(),
+            signature.as_ref(),
+            program_ctx.program_id.as_ref(),
+            &(program_ctx.program_call_index as u64).to_le_bytes(),
+            &(level.len() as u64).to_le_bytes(),
+            &level,
+        ]
+        .concat()
+    }
+
+    /// Call consumer if it's not called for `(signature, program_ctx)` before
+    ///
+    /// Returns `false` if consuming skipped
+    pub async fn consume<T>(
+        &self,
+        signature: SolanaSignature,
+        program_ctx: ProgramContext,
+        value: T,
+    ) -> Result<bool>
+    where
+        F: Fn(SolanaSignature, ProgramContext, T) -> BoxFuture<'static, Result<()>>,
+    {
+        let key = self.idempotency_key(&signature, &program_ctx);
+
+        if !self.storage.register_idempotency_key(&key)? {
+            debug!(
+                "{signature} {program_ctx:?} already consumed by {}, skip",
+                self.name
+            );
+            return Ok(false);
+        }
+
+        if let Err(err) = (self.consumer)(signature, program_ctx, value).await {
+            self.storage.unregister_idempotency_key(&key)?;
+            return Err(err);
+        }
+        Ok(true)
+    }
+}
+
+impl<S, F> IdempotentConsumer<S, F>
+where
+    S: storage::RegisterIdempotencyKey + Send + Sync + 'static,
+    Error: From<S::Error>,
+    F: Send
+        + Sync
+        + 'static
+        + Fn(SolanaSignature, ProgramContext, Vec<u8>) -> BoxFuture<'static, Result<()>>,
+{
+    /// Use as handler of [`ConsumerRegistry`]
+    pub fn into_event_handler(self) -> EventHandlerFn {
+        let consumer = Arc::new(self);
+        Arc::new(move |signature, program_ctx, event| {
+            let consumer = Arc::clone(&consumer);
+            Box::pin(async move {
+                consumer.consume(signature, program_ctx, event).await?;
+                Ok(())
+            })
+        })
+    }
+}
+
+impl<TransactionConsumerFn, EventRecipient, E>
+    EventsReader<TransactionConsumerFn, EventRecipient, E>
+where
+    EventRecipient: Send + Sync + 'static,
+    TransactionConsumerFn: 'static
+        + Send
+        + Sync
+        + Fn(
+            SolanaSignature,
+            TransactionParsedMeta,
+            Arc<RpcClient>,
+            Arc<EventRecipient>,
+        ) -> BoxFuture<'static, Result<()>>,
+    E: 'static + Send + Sync + fmt::Debug,
+    Error: From<E>,
+{
+    /// Pass transaction into consumers of program, then into `transaction_consumer`, while
+    /// `_permits` of program are held
+    ///
+    /// Returns `false` if transaction is claimed by another replica, see
+    /// [`EventsReader::is_registrable`]
+    pub(super) async fn consume_transaction(
+        self: &Arc<Self>,
+        program: &Program,
+        tx_signature: SolanaSignature,
+        transaction: TransactionParsedMeta,
+        _permits: &ConsumerPermits,
+    ) -> Result<bool> {
+        let Some(claims) = self.claims.as_ref() else {
+            return self
+                .consume_claimed_transaction(program, tx_signature, transaction)
+                .await
+                .map(|()| true);
+        };
+
+        if !claims
+            .storage
+            .try_claim(&program.program_id, &tx_signature, claims.ttl)?
+        {
+            info!("Transaction {tx_signature} claimed by another reader, skip");
+            self.record_skip(
+                &program.program_id,
+                SkipReason::ClaimedByOther,
+                Some(tx_signature),
+            );
+            return Ok(false);
+        }
+
+        match self
+            .consume_claimed_transaction(program, tx_signature, transaction)
+            .await
+        {
+            Ok(()) => {
+                claims
+                    .storage
+                    .complete_claim(&program.program_id, &tx_signature)?;
+                Ok(true)
+            }
+            Err(err) => {
+                claims
+                    .storage
+                    .release_claim(&program.program_id, &tx_signature)?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Should transaction be registered after [`EventsReader::consume_transaction`] with
+    /// `result`. With [`EventsReader::claims`] transactions failed or claimed by another
+    /// replica aren't registered, so they are consumed on resync by any replica if
+    /// consuming failed
+    pub(super) fn is_registrable(&self, result: &Result<bool>) -> bool {
+        match result {
+            Ok(is_consumed) => *is_consumed,
+            Err(_) => self.claims.is_none(),
+        }
+    }
+
+    async fn consume_claimed_transaction(
+        self: &Arc<Self>,
+        program: &Program,
+        tx_signature: SolanaSignature,
+        transaction: TransactionParsedMeta,
+    ) -> Result<()> {
+        let slot = transaction.slot;
+        self.transaction_consumer_of(program)(tx_signature, transaction).await?;
+
+        if self.checkpoints.is_some() {
+            if let Ok(mut unconfirmed) = self.unconfirmed.lock() {
+                unconfirmed.insert((program.program_id, tx_signature), slot);
+            }
+        }
+        if self.reorg_detection.is_some() && !program.commitment_config.is_finalized() {
+            if let Ok(mut unfinalized) = self.unfinalized.lock() {
+                unfinalized.insert(
+                    tx_signature,
+                    UnfinalizedTransaction {
+                        program_id: program.program_id,
+                        slot,
+                        blockhash: None,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Consumers of program & `transaction_consumer`, wrapped by `consumer_timeout` &
+    /// `layers`
+    fn transaction_consumer_of(self: &Arc<Self>, program: &Program) -> TransactionConsumer {
+        let (self_clone, consumers) = (Arc::clone(self), program.consumers.clone());
+        let mut consumer: TransactionConsumer = Arc::new(move |tx_signature, transaction| {
+            let (self_clone, consumers) = (Arc::clone(&self_clone), consumers.clone());
+            Box::pin(async move {
+                consumers.consume(tx_signature, &transaction).await?;
+                (self_clone.transaction_consumer)(
+                    tx_signature,
+                    transaction,
+                    Arc::clone(&self_clone.client),
+                    Arc::clone(&self_clone.event_recipient),
+                )
+                .await
+            })
+        });
+        if let Some(timeout) = self.consumer_timeout {
+            consumer = TimeoutLayer::new(timeout, Arc::clone(&self.clock)).layer(consumer);
+        }
+        middleware::apply_layers(&self.layers, consumer)
+    }
+
+    /// Consume `logs` by `event_consumer`, calling it again while it defers, up to
+    /// [`EventsReader::max_event_deferrals`] times
+    pub(super) async fn consume_logs(
+        &self,
+        program: &Program,
+        signature: SolanaSignature,
+        logs: Event,
+    ) -> Result<EventConsumeResult> {
+        let event_consumer = self.event_consumer(program);
+        let mut deferrals = 0;
+        loop {
+            match event_consumer(logs.clone())? {
+                EventConsumeResult::Defer { retry_after }
+                    if deferrals < self.max_event_deferrals =>
+                {
+                    deferrals += 1;
+                    debug!("Consuming of {signature} deferred for {retry_after:?}, deferral {deferrals}");
+                    self.clock.sleep(retry_after).await;
+                }
+                EventConsumeResult::Defer { .. } => {
+                    warn!(
+                        "Consuming of {signature} deferred {deferrals} times, request transaction"
+                    );
+                    return Ok(EventConsumeResult::TransactionNeeed);
+                }
+                result => return Ok(result),
+            }
+        }
+    }
+
+    /// Register events consumed by `event_consumer` in `event_registry`. Failures are only
+    /// logged, such events may be consumed again by consumers of transaction
+    pub(super) fn register_consumed_events(
+        &self,
+        signature: SolanaSignature,
+        event_ids: &[EventId],
+    ) {
+        let Some(registry) = self.event_registry.as_ref() else {
+            return;
+        };
+        for event_id in event_ids {
+            if let Err(err) = registry.register_event(event_id.as_ref()) {
+                error!("Error while register event {event_id} of {signature}: {err:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod consumer_test {
+    use std::{
+        num::NonZeroU32,
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use futures::future;
+
+    use super::*;
+    use crate::{
+        event_reader_service::TransactionClaims,
+        mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock},
+        storage::{ClaimTransaction, RegisterTransaction},
+        transaction_parser::Pubkey,
+    };
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([8; 32]);
+
+    type IdempotentRecorder = IdempotentConsumer<
+        MemoryStorage,
+        Box<
+            dyn Send
+                + Sync
+                + Fn(SolanaSignature, ProgramContext, Vec<u8>) -> BoxFuture<'static, Result<()>>,
+        >,
+    >;
+
+    fn recorder(
+        name: &str,
+        storage: &Arc<MemoryStorage>,
+        sender: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
+    ) -> IdempotentRecorder {
+        IdempotentConsumer::new(
+            name,
+            Arc::clone(storage),
+            Box::new(move |_, _, event| {
+                let _ = sender.send(event);
+                Box::pin(future::ready(Ok(())))
+            }),
+        )
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (long, short) = (
+            recorder("abcd", &storage, sender.clone()),
+            recorder("a", &storage, sender),
+        );
+
+        // Without lengths, these keys are the same bytes split into fields differently
+        let (signature, program_ctx) = (
+            SolanaSignature::from([1; 64]),
+            ProgramContext {
+                program_id: Pubkey::new_from_array([2; 32]),
+                program_call_index: 0,
+                invoke_level: NonZeroU32::MIN,
+            },
+        );
+        let shifted_signature = [b"bcd".as_slice(), &[1; 61]].concat();
+        let shifted_program_id = [[1; 3].as_slice(), &[2; 29]].concat();
+        let shifted_ctx = ProgramContext {
+            program_id: Pubkey::try_from(shifted_program_id.as_slice()).unwrap(),
+            program_call_index: usize::from_le_bytes([2, 2, 2, 0, 0, 0, 0, 0]),
+            invoke_level: NonZeroU32::new(1 << 24).unwrap(),
+        };
+        let shifted_signature = SolanaSignature::try_from(shifted_signature.as_slice()).unwrap();
+
+        assert_ne!(
+            long.idempotency_key(&signature, &program_ctx),
+            short.idempotency_key(&shifted_signature, &shifted_ctx)
+        );
+        assert_eq!(
+            long.idempotency_key(&signature, &program_ctx),
+            recorder("abcd", &storage, tokio::sync::mpsc::unbounded_channel().0)
+                .idempotency_key(&signature, &program_ctx)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_consumer_retry() {
+        let storage = Arc::new(MemoryStorage::new());
+        let is_failing = Arc::new(AtomicBool::new(true));
+        let consumer = IdempotentConsumer::new("payment", Arc::clone(&storage), {
+            let is_failing = Arc::clone(&is_failing);
+            move |signature, _, _: ()| {
+                let result = match is_failing.load(Ordering::Relaxed) {
+                    true => Err(Error::TransactionNotFound(signature)),
+                    false => Ok(()),
+                };
+                Box::pin(future::ready(result)) as BoxFuture<'static, Result<()>>
+            }
+        });
+        let (signature, program_ctx) = (
+            SolanaSignature::new_unique(),
+            ProgramContext {
+                program_id: PROGRAM_ID,
+                program_call_index: 0,
+                invoke_level: NonZeroU32::MIN,
+            },
+        );
+
+        // Key of failed consuming is removed, so it's consumed again
+        assert!(consumer.consume(signature, program_ctx, ()).await.is_err());
+        is_failing.store(false, Ordering::Relaxed);
+        assert!(consumer.consume(signature, program_ctx, ()).await.unwrap());
+        assert!(!consumer.consume(signature, program_ctx, ()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_consumer_instances() {
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let (first, second) = (
+            recorder("payment", &storage, sender.clone()),
+            recorder("payment", &storage, sender),
+        );
+        let (signature, program_ctx) = (
+            SolanaSignature::new_unique(),
+            ProgramContext {
+                program_id: PROGRAM_ID,
+                program_call_index: 0,
+                invoke_level: NonZeroU32::MIN,
+            },
+        );
+
+        // Instances don't share any lock, only check & set of storage
+        let (first, second) = tokio::join!(
+            first.consume(signature, program_ctx, vec![1]),
+            second.consume(signature, program_ctx, vec![2]),
+        );
+        assert!(first.unwrap() ^ second.unwrap());
+        assert!(events.try_recv().is_ok());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_consumer_replicas() {
+        let chain = Arc::new(MockChain::new());
+        let keys = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let (event_sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+
+        // Replicas consume every transaction by their own, but share idempotency keys
+        let mut tasks = vec![];
+        for _ in 0..2 {
+            let consumers = ConsumerRegistry::default();
+            consumers.add_event_handler(
+                "payment",
+                recorder("payment", &keys, event_sender.clone()).into_event_handler(),
+            );
+            let reader = chain
+                .events_reader_builder(
+                    PROGRAM_ID,
+                    send_signature,
+                    Arc::new(sender.clone()),
+                    Arc::new(MemoryStorage::new()),
+                    Arc::new(MockClock::new()),
+                )
+                .consumers(consumers)
+                .build()
+                .unwrap();
+            tasks.push(tokio::spawn(Arc::new(reader).run()));
+        }
+        while chain.subscribers() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let data = base64::encode([9; 16]);
+        let signature = chain
+            .push(invocation(
+                PROGRAM_ID,
+                1,
+                &[format!("Program data: {data}")],
+            ))
+            .unwrap();
+        for _ in 0..2 {
+            let replica = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+            assert_eq!(replica.unwrap(), Some(signature));
+        }
+        assert_eq!(events.try_recv().unwrap(), vec![9; 16]);
+        assert!(events.try_recv().is_err());
+        tasks.iter().for_each(|task| task.abort());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_claims() {
+        let chain = Arc::new(MockChain::new());
+        let claims = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+
+        // Replicas receive every transaction by their own, but share claims
+        let mut handles = vec![];
+        let mut tasks = vec![];
+        for _ in 0..2 {
+            let reader = chain
+                .events_reader_builder(
+                    PROGRAM_ID,
+                    send_signature,
+                    Arc::new(sender.clone()),
+                    Arc::new(MemoryStorage::new()),
+                    Arc::new(MockClock::new()),
+                )
+                .claims(Some(TransactionClaims {
+                    storage: claims.clone(),
+                    ttl: Duration::from_secs(60),
+                }))
+                .build()
+                .unwrap();
+            handles.push(reader.handle());
+            tasks.push(tokio::spawn(Arc::new(reader).run()));
+        }
+        while chain.subscribers() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        let signature = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        let claimed = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+        assert_eq!(claimed.unwrap(), Some(signature));
+        let skipped = || {
+            handles
+                .iter()
+                .map(|handle| handle.skipped(SkipReason::ClaimedByOther))
+                .sum::<u64>()
+        };
+        while skipped() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(skipped(), 1);
+        assert!(consumed.try_recv().is_err());
+        tasks.iter().for_each(|task| task.abort());
+    }
+
+    fn fail_transaction(
+        signature: SolanaSignature,
+        _transaction: TransactionParsedMeta,
+        _client: Arc<RpcClient>,
+        _recipient: Arc<tokio::sync::mpsc::UnboundedSender<SolanaSignature>>,
+    ) -> BoxFuture<'static, Result<()>> {
+        Box::pin(future::ready(Err(Error::TransactionNotFound(signature))))
+    }
+
+    #[tokio::test]
+    async fn test_failed_claim_consumed_by_other_replica() {
+        let chain = Arc::new(MockChain::new());
+        let claims = Arc::new(MemoryStorage::new());
+        let claims_config = TransactionClaims {
+            storage: claims.clone(),
+            ttl: Duration::from_secs(60),
+        };
+
+        let failing_storage = Arc::new(MemoryStorage::new());
+        let failing = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                fail_transaction,
+                Arc::new(tokio::sync::mpsc::unbounded_channel().0),
+                failing_storage.clone(),
+                Arc::new(MockClock::new()),
+            )
+            .claims(Some(claims_config.clone()))
+            .build()
+            .unwrap();
+        let failing_handle = failing.handle();
+        let failing_task = tokio::spawn(Arc::new(failing).run());
+        chain.wait_subscribed().await;
+
+        let signature = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        while failing_handle.skipped(SkipReason::ConsumerFailure) == 0 {
+            tokio::task::yield_now().await;
+        }
+        // Claim is released & transaction isn't registered by failed replica
+        assert!(!failing_storage
+            .is_transaction_registered(&PROGRAM_ID, &signature)
+            .unwrap());
+
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let clock = Arc::new(MockClock::new());
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::clone(&clock),
+            )
+            .claims(Some(claims_config))
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        while chain.subscribers() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        clock.advance(Duration::from_secs(1));
+        let resynced = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+        assert_eq!(resynced.unwrap(), Some(signature));
+        // Claim of consumed transaction doesn't expire
+        assert!(!claims
+            .try_claim(&PROGRAM_ID, &signature, Duration::ZERO)
+            .unwrap());
+        failing_task.abort();
+        task.abort();
+    }
+}
@@ -0,0 +1,505 @@
+//! Periodic checks of consumed transactions: gaps of live subscription & reorgs of
+//! unfinalized transactions
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{atomic::Ordering, Arc},
+};
+
+use futures::future::BoxFuture;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
+use tracing::*;
+
+use crate::transaction_parser::{
+    Pubkey, Signature as SolanaSignature, Slot, TransactionParsedMeta,
+};
+
+use super::{Error, EventsReader, Program, Result, SkipReason};
+
+/// Transaction consumed with commitment below finalized, checked by reorg detection
+#[derive(Debug, Clone, Copy)]
+pub(super) struct UnfinalizedTransaction {
+    pub(super) program_id: Pubkey,
+    pub(super) slot: Slot,
+    /// Hash of block of `slot` observed before finalization
+    pub(super) blockhash: Option<Hash>,
+}
+
+impl<TransactionConsumerFn, EventRecipient, E>
+    EventsReader<TransactionConsumerFn, EventRecipient, E>
+where
+    EventRecipient: Send + Sync + 'static,
+    TransactionConsumerFn: 'static
+        + Send
+        + Sync
+        + Fn(
+            SolanaSignature,
+            TransactionParsedMeta,
+            Arc<RpcClient>,
+            Arc<EventRecipient>,
+        ) -> BoxFuture<'static, Result<()>>,
+    E: 'static + Send + Sync + fmt::Debug,
+    Error: From<E>,
+{
+    pub(super) async fn detect_gaps(self: &Arc<Self>, program: &Program) -> Result<()> {
+        let config = match (&self.gap_detection, self.logs_source()) {
+            (Some(config), Some(_)) => config.clone(),
+            _ => return Ok(()),
+        };
+
+        let mut suspected = HashSet::new();
+        let mut reported = HashSet::new();
+        loop {
+            self.clock.sleep(config.interval).await;
+            self.handle.wait_resumed().await;
+
+            let signatures = unwrap_or_continue!(
+                self.signature_source()
+                    .get_signatures_for_address(
+                        &program.program_id,
+                        GetConfirmedSignaturesForAddress2Config {
+                            limit: Some(config.page_size),
+                            commitment: Some(program.commitment_config),
+                            ..Default::default()
+                        },
+                    )
+                    .await,
+                "Error while get signatures for gap detection: {err:?}"
+            )
+            .into_iter()
+            .filter(|status| status.err.is_none())
+            .filter_map(|status| status.signature.parse::<SolanaSignature>().ok())
+            .collect::<Vec<_>>();
+
+            let unregistered = self
+                .local_storage
+                .filter_unregistered_transactions(&program.program_id, &signatures)?;
+            reported.retain(|signature| unregistered.contains(signature));
+
+            // Newest signatures can be still in processing by listener, so they are only
+            // suspected until the next check
+            let (missed, pending): (Vec<_>, Vec<_>) = unregistered
+                .into_iter()
+                .filter(|signature| !reported.contains(signature))
+                .partition(|signature| suspected.contains(signature));
+            suspected = pending.into_iter().collect();
+
+            if missed.is_empty() {
+                debug!("No gaps found in {} signatures", signatures.len());
+                continue;
+            }
+
+            self.handle
+                .missed_transactions
+                .fetch_add(missed.len() as u64, Ordering::Relaxed);
+            warn!(
+                missed = missed.len(),
+                "Gap detected, transactions missed by websocket listener: {}",
+                missed
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            if !config.fetch_missed {
+                reported.extend(missed);
+                continue;
+            }
+
+            for tx_signature in missed {
+                let transaction = match self
+                    .get_transaction_by_signature(program, tx_signature, self.resync_retry_params())
+                    .await
+                {
+                    Ok(transaction) => transaction,
+                    Err(err) => {
+                        error!("Error while get missed transaction by signature: {err:?}");
+                        self.record_skip(
+                            &program.program_id,
+                            SkipReason::of_fetch_error(&err),
+                            Some(tx_signature),
+                        );
+                        reported.insert(tx_signature);
+                        continue;
+                    }
+                };
+
+                let permits = program.acquire_consumer_permits().await;
+                let result = self
+                    .consume_transaction(program, tx_signature, transaction, &permits)
+                    .await;
+                match &result {
+                    Ok(true) => {
+                        info!("Missed transaction {tx_signature} consumed by gap detection")
+                    }
+                    Ok(false) => {}
+                    Err(err) => {
+                        error!("Error while missed transaction {tx_signature} consuming {err:?}");
+                        self.record_skip(
+                            &program.program_id,
+                            SkipReason::of_consume_error(err),
+                            Some(tx_signature),
+                        );
+                    }
+                }
+
+                if self.is_registrable(&result) {
+                    self.local_storage
+                        .register_transaction(&program.program_id, &tx_signature)?;
+                }
+            }
+        }
+    }
+
+    pub(super) async fn detect_reorgs(self: &Arc<Self>, program: &Program) -> Result<()> {
+        let config = match &self.reorg_detection {
+            Some(config) if !program.commitment_config.is_finalized() => config.clone(),
+            _ => return Ok(()),
+        };
+
+        loop {
+            self.clock.sleep(config.interval).await;
+
+            let finalized_slot = unwrap_or_continue!(
+                self.signature_source()
+                    .get_slot(CommitmentConfig::finalized())
+                    .await,
+                "Error while get finalized slot: {err:?}"
+            );
+            self.observe_blockhashes(program).await;
+            let candidates = match self.unfinalized.lock() {
+                Ok(unfinalized) => unfinalized
+                    .iter()
+                    .filter(|(_, transaction)| {
+                        transaction.program_id == program.program_id
+                            && transaction.slot <= finalized_slot
+                    })
+                    .map(|(signature, transaction)| (*signature, *transaction))
+                    .collect::<Vec<_>>(),
+                Err(err) => {
+                    error!("Error while lock unfinalized transactions: {err:?}");
+                    continue;
+                }
+            };
+
+            let mut finalized = vec![];
+            let mut reverted = vec![];
+            let mut finalized_blockhashes = HashMap::new();
+            // `getSignatureStatuses` accepts up to 256 signatures
+            for chunk in candidates.chunks(256) {
+                let signatures = chunk
+                    .iter()
+                    .map(|(signature, _)| *signature)
+                    .collect::<Vec<_>>();
+                let statuses = unwrap_or_continue!(
+                    self.signature_source()
+                        .get_signature_statuses(&signatures)
+                        .await,
+                    "Error while get signature statuses: {err:?}"
+                );
+
+                for ((signature, transaction), status) in chunk.iter().zip(statuses) {
+                    let is_canonical = match status {
+                        Some(status)
+                            if status.satisfies_commitment(CommitmentConfig::finalized()) =>
+                        {
+                            status.slot == transaction.slot
+                                && self
+                                    .is_blockhash_finalized(transaction, &mut finalized_blockhashes)
+                                    .await
+                        }
+                        Some(_) => continue,
+                        None => false,
+                    };
+                    match is_canonical {
+                        true => finalized.push(*signature),
+                        false => reverted.push((*signature, transaction.slot)),
+                    }
+                }
+            }
+
+            if let Ok(mut unfinalized) = self.unfinalized.lock() {
+                for signature in finalized.iter() {
+                    unfinalized.remove(signature);
+                }
+            }
+
+            let Some(reorg_slot) = reverted.iter().map(|(_, slot)| *slot).min() else {
+                continue;
+            };
+            let reverted = reverted
+                .into_iter()
+                .map(|(signature, _)| signature)
+                .collect::<Vec<_>>();
+            warn!(
+                reverted = reverted.len(),
+                "Reorg detected since {reorg_slot} slot, reverted transactions: {}",
+                reverted
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            // Transactions stay unfinalized, so reorg is reported again by the next check
+            unwrap_or_continue!(
+                (config.on_reorg)(program.program_id, reverted.clone()).await,
+                "Error while reorg consuming: {err:?}"
+            );
+
+            if let Ok(mut unfinalized) = self.unfinalized.lock() {
+                for signature in reverted.iter() {
+                    unfinalized.remove(signature);
+                }
+            }
+            // Re-included transactions & the ones which replaced them are consumed by resync
+            // since the slot of reorg
+            for signature in reverted.iter() {
+                self.local_storage
+                    .unregister_transaction(&program.program_id, signature)?;
+            }
+            if let Ok(mut rollbacks) = self.reorg_rollbacks.lock() {
+                let slot = rollbacks.entry(program.program_id).or_insert(reorg_slot);
+                *slot = (*slot).min(reorg_slot);
+            }
+        }
+    }
+
+    /// Remember hashes of blocks of unfinalized transactions of `program`, which are not
+    /// observed yet. Errors are logged, so blocks are requested again by the next check
+    async fn observe_blockhashes(&self, program: &Program) {
+        let slots = match self.unfinalized.lock() {
+            Ok(unfinalized) => unfinalized
+                .values()
+                .filter(|transaction| {
+                    transaction.program_id == program.program_id && transaction.blockhash.is_none()
+                })
+                .map(|transaction| transaction.slot)
+                .collect::<HashSet<_>>(),
+            Err(err) => {
+                error!("Error while lock unfinalized transactions: {err:?}");
+                return;
+            }
+        };
+
+        for slot in slots {
+            let blockhash = match self
+                .signature_source()
+                .get_blockhash(slot, program.commitment_config)
+                .await
+            {
+                Ok(blockhash) => blockhash,
+                Err(err) => {
+                    warn!("Error while get block of {slot} slot: {err:?}");
+                    continue;
+                }
+            };
+            if let Ok(mut unfinalized) = self.unfinalized.lock() {
+                unfinalized
+                    .values_mut()
+                    .filter(|transaction| {
+                        transaction.program_id == program.program_id
+                            && transaction.slot == slot
+                            && transaction.blockhash.is_none()
+                    })
+                    .for_each(|transaction| transaction.blockhash = Some(blockhash));
+            }
+        }
+    }
+
+    /// Whether observed block of `transaction` is finalized, `true` if block isn't observed
+    /// or finalized one can't be fetched, as transaction is already checked by slot
+    async fn is_blockhash_finalized(
+        &self,
+        transaction: &UnfinalizedTransaction,
+        finalized_blockhashes: &mut HashMap<Slot, Hash>,
+    ) -> bool {
+        let Some(observed) = transaction.blockhash else {
+            return true;
+        };
+        let finalized = match finalized_blockhashes.get(&transaction.slot) {
+            Some(finalized) => *finalized,
+            None => match self
+                .signature_source()
+                .get_blockhash(transaction.slot, CommitmentConfig::finalized())
+                .await
+            {
+                Ok(finalized) => *finalized_blockhashes
+                    .entry(transaction.slot)
+                    .or_insert(finalized),
+                Err(err) => {
+                    warn!(
+                        "Error while get finalized block of {} slot: {err:?}",
+                        transaction.slot
+                    );
+                    return true;
+                }
+            },
+        };
+        observed == finalized
+    }
+}
+
+#[cfg(test)]
+mod detection_test {
+    use std::time::Duration;
+
+    use futures::future;
+
+    use super::*;
+    use crate::{
+        event_reader_service::{GapDetection, ReorgDetection},
+        mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock},
+        transaction_parser::Pubkey,
+    };
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([6; 32]);
+
+    #[tokio::test]
+    async fn test_gap_detection() {
+        let interval = Duration::from_secs(10);
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::clone(&clock),
+            )
+            .is_resync_enabled(false)
+            .gap_detection(Some(GapDetection {
+                interval,
+                page_size: 10,
+                fetch_missed: true,
+            }))
+            .build()
+            .unwrap();
+        let handle = reader.handle();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        let live = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        let consumed = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await;
+        assert_eq!(consumed.unwrap(), Some(live));
+
+        // Missed transaction is only suspected by the first check, so it's fetched by one
+        // of the next ones
+        let missed = chain.push_missed(invocation(PROGRAM_ID, 2, &[])).unwrap();
+        let mut checks = 0;
+        let consumed = loop {
+            assert!(checks < 100, "missed transaction isn't consumed");
+            clock.advance(interval);
+            checks += 1;
+            if let Ok(consumed) =
+                tokio::time::timeout(Duration::from_millis(10), receiver.recv()).await
+            {
+                break consumed;
+            }
+        };
+        assert_eq!(consumed, Some(missed));
+        assert!(checks >= 2);
+        assert_eq!(handle.missed_transactions(), 1);
+
+        // Consumed missed transaction is registered, so it isn't reported again
+        for _ in 0..3 {
+            clock.advance(interval);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(handle.missed_transactions(), 1);
+        assert!(receiver.try_recv().is_err());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_reorg_detection() {
+        let interval = Duration::from_secs(10);
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (reorg_sender, mut reorgs) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::clone(&clock),
+            )
+            .commitment_config(CommitmentConfig::confirmed())
+            .reorg_detection(Some(ReorgDetection {
+                interval,
+                on_reorg: Arc::new(move |program_id, signatures| {
+                    let _ = reorg_sender.send((program_id, signatures));
+                    Box::pin(future::ready(Ok(())))
+                }),
+            }))
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        chain.set_finalized_slot(Some(0));
+        let pushed = [5, 6, 7].map(|slot| chain.push(invocation(PROGRAM_ID, slot, &[])).unwrap());
+        let mut consumed = HashSet::new();
+        while consumed.len() < pushed.len() {
+            let signature = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await;
+            consumed.insert(signature.unwrap().unwrap());
+        }
+        // Let live transactions be registered, then blocks be observed before finalization
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        clock.advance(interval);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The first transaction is re-included into another slot, the second one into
+        // another block of the same slot
+        let [moved, forked, stable] = pushed;
+        assert!(chain.reinclude(moved, 8));
+        assert!(chain.reinclude(forked, 6));
+        chain.set_finalized_slot(None);
+        clock.advance(interval);
+
+        let (program_id, mut reverted) =
+            tokio::time::timeout(Duration::from_secs(5), reorgs.recv())
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(program_id, PROGRAM_ID);
+        reverted.sort();
+        let mut expected = vec![moved, forked];
+        expected.sort();
+        assert_eq!(reverted, expected);
+
+        // Reverted transactions are unregistered & consumed again by resync after rollback
+        let mut reconsumed = HashSet::new();
+        let mut resyncs = 0;
+        while reconsumed.len() < 2 {
+            assert!(resyncs < 100, "reverted transactions aren't consumed again");
+            clock.advance(Duration::from_secs(1));
+            resyncs += 1;
+            if let Ok(signature) =
+                tokio::time::timeout(Duration::from_millis(10), receiver.recv()).await
+            {
+                reconsumed.insert(signature.unwrap());
+            }
+        }
+        assert_eq!(reconsumed, HashSet::from([moved, forked]));
+        assert!(!reconsumed.contains(&stable));
+
+        // Consumed again in their new blocks, so they are finalized without another reorg
+        for _ in 0..3 {
+            clock.advance(interval);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(reorgs.try_recv().is_err());
+        assert!(receiver.try_recv().is_err());
+        task.abort();
+    }
+}
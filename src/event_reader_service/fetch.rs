@@ -0,0 +1,116 @@
+//! Requests of transactions with retries & cache of parsed transactions
+
+use std::{fmt, sync::Arc};
+
+use futures::future::BoxFuture;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use tracing::*;
+
+use crate::transaction_parser::{
+    default_transaction_config, Signature as SolanaSignature, TransactionParsedMeta,
+};
+
+use super::{unix_timestamp_ms, Error, ErrorClass, EventsReader, Program, Result, RetryParams};
+
+impl<TransactionConsumerFn, EventRecipient, E>
+    EventsReader<TransactionConsumerFn, EventRecipient, E>
+where
+    EventRecipient: Send + Sync + 'static,
+    TransactionConsumerFn: 'static
+        + Send
+        + Sync
+        + Fn(
+            SolanaSignature,
+            TransactionParsedMeta,
+            Arc<RpcClient>,
+            Arc<EventRecipient>,
+        ) -> BoxFuture<'static, Result<()>>,
+    E: 'static + Send + Sync + fmt::Debug,
+    Error: From<E>,
+{
+    pub(super) async fn get_transaction_by_signature(
+        &self,
+        program: &Program,
+        tx_signature: SolanaSignature,
+        retry_params: RetryParams,
+    ) -> Result<TransactionParsedMeta> {
+        let cache = self.parsed_tx_cache.as_ref();
+        let config = self.transaction_config(program);
+        let ttl = self.parsed_tx_cache_ttl.as_millis() as u64;
+        match cache.map(|cache| cache.get_parsed_transaction(&tx_signature, &config)) {
+            Some(Ok(Some((_, cached_at))))
+                if unix_timestamp_ms().saturating_sub(cached_at) > ttl =>
+            {
+                debug!("{tx_signature} cache of parsed transactions expired")
+            }
+            Some(Ok(Some((tx, _)))) if !tx.is_outdated() => {
+                debug!("{tx_signature} found in cache of parsed transactions");
+                return Ok(tx);
+            }
+            Some(Ok(Some(_))) => debug!("{tx_signature} cached by older parser, parse again"),
+            Some(Err(err)) => error!("Error while get {tx_signature} from cache: {err:?}"),
+            Some(Ok(None)) | None => {}
+        }
+
+        let tx = self
+            .request_transaction(program, tx_signature, retry_params)
+            .await?;
+        if let Some(Err(err)) = cache.map(|cache| {
+            cache.put_parsed_transaction(&tx_signature, &config, &tx, unix_timestamp_ms())
+        }) {
+            error!("Error while put {tx_signature} into cache: {err:?}");
+        }
+        Ok(tx)
+    }
+
+    fn transaction_config(&self, program: &Program) -> RpcTransactionConfig {
+        self.transaction_config
+            .unwrap_or_else(|| default_transaction_config(program.commitment_config))
+    }
+
+    /// Remove transactions expired by `parsed_tx_cache_ttl` from `parsed_tx_cache`
+    pub(super) async fn evict_parsed_transactions(self: Arc<Self>) -> Result<()> {
+        let Some(cache) = self.parsed_tx_cache.as_ref() else {
+            return Ok(());
+        };
+        let ttl = self.parsed_tx_cache_ttl.as_millis() as u64;
+        loop {
+            match cache.evict_parsed_transactions(unix_timestamp_ms().saturating_sub(ttl)) {
+                Ok(evicted) => debug!("Evicted {evicted} expired parsed transactions"),
+                Err(err) => error!("Error while evict parsed transactions: {err:?}"),
+            }
+            self.clock.sleep(self.parsed_tx_cache_ttl).await;
+        }
+    }
+
+    async fn request_transaction(
+        &self,
+        program: &Program,
+        tx_signature: SolanaSignature,
+        retry_params: RetryParams,
+    ) -> Result<TransactionParsedMeta> {
+        let attempts_count = retry_params.attempts_count;
+        let mut attempt = 1;
+        loop {
+            match self
+                .transaction_source()
+                .get_transaction(tx_signature, self.transaction_config(program))
+                .await
+            {
+                Ok(tx) => return Ok(tx),
+                Err(err) if self.retry_classifier.classify(&err) == ErrorClass::Fatal => {
+                    return Err(err);
+                }
+                Err(err) if attempt >= attempts_count.get() => return Err(err),
+                Err(_) => {
+                    warn!(
+                        "Error while request {tx_signature}, attempts left: {}",
+                        attempts_count.get() - attempt
+                    );
+                    self.clock.sleep(retry_params.delay(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
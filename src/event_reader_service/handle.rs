@@ -0,0 +1,263 @@
+//! [`ReaderHandle`]: pause & resume, manual resync, skip counters and resync progress
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tracing::*;
+
+use crate::transaction_parser::{Pubkey, Signature as SolanaSignature};
+
+use super::Error;
+
+/// Control handle of running [`EventsReader`], see [`EventsReader::handle`]
+///
+/// While paused, websocket subscription stays alive, but received transactions are not
+/// consumed until resume, resync is not started and running resync stops before next
+/// transaction
+#[derive(Debug, Clone)]
+pub struct ReaderHandle {
+    paused: Arc<tokio::sync::watch::Sender<bool>>,
+    pub(super) resync_now: Arc<tokio::sync::Notify>,
+    pub(super) missed_transactions: Arc<AtomicU64>,
+    /// Counters by index in [`SkipReason::ALL`]
+    pub(super) skipped: Arc<[AtomicU64; SkipReason::ALL.len()]>,
+    resync_progress: Arc<tokio::sync::watch::Sender<BTreeMap<Pubkey, ResyncProgress>>>,
+}
+
+impl Default for ReaderHandle {
+    fn default() -> Self {
+        Self {
+            paused: Arc::new(tokio::sync::watch::channel(false).0),
+            resync_now: Arc::new(tokio::sync::Notify::new()),
+            missed_transactions: Arc::default(),
+            skipped: Arc::default(),
+            resync_progress: Arc::new(tokio::sync::watch::channel(BTreeMap::new()).0),
+        }
+    }
+}
+
+impl ReaderHandle {
+    pub fn pause(&self) {
+        info!("Pause events reader");
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        info!("Resume events reader");
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Start resync without waiting of `resync_duration`, if reader is paused - right after
+    /// resume
+    pub fn trigger_resync_now(&self) {
+        self.resync_now.notify_one();
+    }
+
+    /// Count of transactions missed by websocket listener, found by [`GapDetection`]
+    pub fn missed_transactions(&self) -> u64 {
+        self.missed_transactions.load(Ordering::Relaxed)
+    }
+
+    /// Count of transactions skipped by `reason`
+    pub fn skipped(&self, reason: SkipReason) -> u64 {
+        self.skipped[reason as usize].load(Ordering::Relaxed)
+    }
+
+    /// Progress of the running or the last resync of `program_id`, `None` if resync wasn't
+    /// started yet
+    pub fn resync_progress(&self, program_id: &Pubkey) -> Option<ResyncProgress> {
+        self.resync_progress.borrow().get(program_id).cloned()
+    }
+
+    /// Receiver of progress of resyncs of all programs, notified on every change, e.g. for
+    /// dashboards of backfill
+    pub fn subscribe_resync_progress(
+        &self,
+    ) -> tokio::sync::watch::Receiver<BTreeMap<Pubkey, ResyncProgress>> {
+        self.resync_progress.subscribe()
+    }
+
+    pub(super) async fn wait_resumed(&self) {
+        // Sender is owned by `self`, so channel can't be closed
+        let _ = self.paused.subscribe().wait_for(|paused| !paused).await;
+    }
+
+    pub(super) fn update_resync_progress(
+        &self,
+        program_id: &Pubkey,
+        update: impl FnOnce(&mut ResyncProgress),
+    ) {
+        self.resync_progress
+            .send_modify(|progress| update(progress.entry(*program_id).or_default()));
+    }
+}
+
+/// Progress of resync of program, see [`ReaderHandle::resync_progress`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResyncProgress {
+    /// Resync is running, otherwise progress is of the last finished resync
+    pub is_running: bool,
+    pub started_at: Option<tokio::time::Instant>,
+    /// Signatures since resync pointer, known in [`ResyncOrder::Historical`] only, after all
+    /// of them are listed
+    pub total_signatures: Option<usize>,
+    /// Signatures already passed to processing
+    pub walked_signatures: usize,
+    /// Unregistered transactions found so far
+    pub found: usize,
+    /// Consumed ones of `found`
+    pub processed: usize,
+    /// Not consumed ones of `found`, including skipped & quarantined
+    pub failed: usize,
+    /// Index of the last started chunk of `resync_signatures_chunk_size` in current batch
+    pub current_chunk: Option<usize>,
+}
+
+impl ResyncProgress {
+    /// Estimated time to the end of running resync by rate of walked signatures, `None` if
+    /// total count of signatures isn't known yet
+    pub fn eta(&self) -> Option<Duration> {
+        let (started_at, total) = (self.started_at?, self.total_signatures?);
+        if !self.is_running || self.walked_signatures == 0 {
+            return None;
+        }
+        let left = total.saturating_sub(self.walked_signatures);
+        let per_signature = started_at.elapsed().as_secs_f64() / self.walked_signatures as f64;
+        Some(Duration::from_secs_f64(per_signature * left as f64))
+    }
+}
+
+/// Reason of transaction dropped by [`EventsReader`], see [`EventsReader::on_skip`] &
+/// [`ReaderHandle::skipped`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkipReason {
+    /// Signature of websocket notification can't be parsed
+    InvalidSignature,
+    /// Transaction is already registered, e.g. consumed by resync
+    AlreadyRegistered,
+    /// Transaction request failed
+    FetchFailure,
+    /// Transaction is fetched, but can't be parsed
+    ParseFailure,
+    /// `event_consumer`, consumers or `transaction_consumer` failed
+    ConsumerFailure,
+    /// Transaction is claimed by another replica, see [`EventsReader::claims`]
+    ClaimedByOther,
+    /// Consumers didn't finish in [`EventsReader::consumer_timeout`] and were cancelled
+    ConsumerTimeout,
+    /// Transaction failed to be fetched by [`EventsReader::resync_max_fetch_failures`]
+    /// resyncs
+    Quarantined,
+}
+
+impl SkipReason {
+    pub const ALL: [SkipReason; 8] = [
+        SkipReason::InvalidSignature,
+        SkipReason::AlreadyRegistered,
+        SkipReason::FetchFailure,
+        SkipReason::ParseFailure,
+        SkipReason::ConsumerFailure,
+        SkipReason::ClaimedByOther,
+        SkipReason::ConsumerTimeout,
+        SkipReason::Quarantined,
+    ];
+
+    /// Reason of skip by error of transaction request
+    pub fn of_fetch_error(err: &Error) -> Self {
+        match err {
+            Error::EventParserError(crate::transaction_parser::Error::SolanaClientResult(_)) => {
+                SkipReason::FetchFailure
+            }
+            Error::EventParserError(_) => SkipReason::ParseFailure,
+            _ => SkipReason::FetchFailure,
+        }
+    }
+
+    /// Reason of skip by error of transaction consuming
+    pub fn of_consume_error(err: &Error) -> Self {
+        match err {
+            Error::ConsumerTimeout { .. } => SkipReason::ConsumerTimeout,
+            _ => SkipReason::ConsumerFailure,
+        }
+    }
+}
+
+/// Callback of skipped transaction: reason, program & signature (if parsed)
+pub type OnSkipFn = Arc<dyn Send + Sync + Fn(SkipReason, Pubkey, Option<SolanaSignature>)>;
+
+#[cfg(test)]
+mod handle_test {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock};
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([5; 32]);
+
+    #[test]
+    fn test_resync_progress_eta() {
+        let mut progress = ResyncProgress {
+            is_running: true,
+            started_at: Some(tokio::time::Instant::now() - Duration::from_secs(10)),
+            walked_signatures: 100,
+            ..Default::default()
+        };
+        assert_eq!(progress.eta(), None);
+
+        progress.total_signatures = Some(300);
+        let eta = progress.eta().unwrap();
+        assert!(eta >= Duration::from_secs(20) && eta < Duration::from_secs(21));
+
+        progress.is_running = false;
+        assert_eq!(progress.eta(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume() {
+        let chain = Arc::new(MockChain::new());
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::new(MockClock::new()),
+            )
+            .build()
+            .unwrap();
+        let handle = reader.handle();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        handle.pause();
+        assert!(handle.is_paused());
+        let live = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        let missed = chain.push_missed(invocation(PROGRAM_ID, 2, &[])).unwrap();
+        handle.trigger_resync_now();
+        let consumed = tokio::time::timeout(Duration::from_millis(100), receiver.recv()).await;
+        assert!(consumed.is_err(), "consumed while paused: {consumed:?}");
+
+        // Live notification received before resume & resync triggered while paused are
+        // both processed after resume
+        handle.resume();
+        assert!(!handle.is_paused());
+        let mut consumed = HashSet::new();
+        while consumed.len() < 2 {
+            let signature = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await;
+            consumed.insert(signature.unwrap().unwrap());
+        }
+        assert_eq!(consumed, HashSet::from([live, missed]));
+        task.abort();
+    }
+}
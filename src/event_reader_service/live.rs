@@ -0,0 +1,638 @@
+//! Live paths of reader: `logsSubscribe`, `blockSubscribe` and ingested transactions
+
+use std::{fmt, sync::Arc};
+
+use futures::{future::BoxFuture, StreamExt};
+use result_inspect::ResultInspectErr;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{
+        RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcTransactionLogsConfig,
+        RpcTransactionLogsFilter,
+    },
+    rpc_response::Response,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionDetails, UiTransactionEncoding,
+};
+use tracing::{Instrument, *};
+
+use crate::{
+    log_parser,
+    transaction_parser::{parse_transaction, Signature as SolanaSignature, TransactionParsedMeta},
+};
+
+use super::{Error, EventConsumeResult, EventsReader, Program, Result, SkipReason};
+
+impl<TransactionConsumerFn, EventRecipient, E>
+    EventsReader<TransactionConsumerFn, EventRecipient, E>
+where
+    EventRecipient: Send + Sync + 'static,
+    TransactionConsumerFn: 'static
+        + Send
+        + Sync
+        + Fn(
+            SolanaSignature,
+            TransactionParsedMeta,
+            Arc<RpcClient>,
+            Arc<EventRecipient>,
+        ) -> BoxFuture<'static, Result<()>>,
+    E: 'static + Send + Sync + fmt::Debug,
+    Error: From<E>,
+{
+    /// Listen `logsSubscribe` with `filter` & consume transactions of `programs`
+    ///
+    /// If `filter_by_invoke` is set, notification is consumed only by programs invoked by
+    /// transaction, otherwise - by all `programs`
+    pub(super) async fn listen_events(
+        self: Arc<Self>,
+        programs: &[Arc<Program>],
+        filter: RpcTransactionLogsFilter,
+        commitment_config: CommitmentConfig,
+        filter_by_invoke: bool,
+    ) -> Result<()> {
+        info!("Launching websocket client");
+
+        let logs_source = match self.logs_source() {
+            Some(logs_source) => logs_source,
+            None => {
+                info!("Listen events job disabled");
+                return Ok(());
+            }
+        };
+
+        loop {
+            let stream = logs_source
+                .logs_subscribe(
+                    filter.clone(),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(commitment_config),
+                    },
+                )
+                .instrument(span!(Level::ERROR, "LogsSubscribe"))
+                .await
+                .inspect_err(|err| error!("Error while subs: {err:?}"))?;
+
+            let mut stream = stream.inspect(|subscription_response| {
+                info!(
+                    "Log subscription response received, transaction hash: {}",
+                    subscription_response.value.signature
+                );
+            });
+            info!("Start listening websocket events");
+            while let Some(subscription_response) = stream.next().await {
+                if self.handle.is_paused() {
+                    info!("Events reader paused, wait for resume");
+                    self.handle.wait_resumed().await;
+                }
+
+                let tx_signature = match subscription_response
+                    .value
+                    .signature
+                    .parse::<SolanaSignature>()
+                {
+                    Ok(tx_signature) => tx_signature,
+                    Err(err) => {
+                        error!("Error while tx signature parsing: {err:?}");
+                        for program in programs.iter() {
+                            self.record_skip(
+                                &program.program_id,
+                                SkipReason::InvalidSignature,
+                                None,
+                            );
+                        }
+                        continue;
+                    }
+                };
+
+                let targets = programs.iter().filter(|program| {
+                    let is_target = !filter_by_invoke
+                        || log_parser::is_program_invoked(
+                            &program.program_id,
+                            &subscription_response.value.logs,
+                        );
+                    if !is_target {
+                        debug!(
+                            "Transaction {tx_signature} doesn't invoke {}, skip",
+                            program.program_id
+                        );
+                    }
+                    is_target
+                });
+                for program in targets {
+                    if self
+                        .local_storage
+                        .is_transaction_registered(&program.program_id, &tx_signature)?
+                    {
+                        info!(
+                            "Transaction {tx_signature} already registered in event-parser, skip"
+                        );
+                        self.record_skip(
+                            &program.program_id,
+                            SkipReason::AlreadyRegistered,
+                            Some(tx_signature),
+                        );
+                        continue;
+                    }
+
+                    let self_clone = self.clone();
+                    let program = Arc::clone(program);
+                    let logs = subscription_response.value.logs.clone();
+                    let transaction_str = tx_signature.to_string();
+                    let permits = program.acquire_consumer_permits().await;
+                    tokio::spawn(async move {
+                        info!("Transaction {tx_signature} not registered yet, processing");
+
+                        let is_transaction_needed = match self_clone
+                            .consume_logs(&program, tx_signature, logs)
+                            .await
+                        {
+                            Ok(EventConsumeResult::ConsumeSuccess) => {
+                                info!(
+                                    "Transaction {tx_signature} consumed successful by ws information only"
+                                );
+                                false
+                            }
+                            Ok(EventConsumeResult::ConsumedEvents(event_ids)) => {
+                                info!(
+                                    "Transaction {tx_signature} partially consumed by ws information: {} events",
+                                    event_ids.len()
+                                );
+                                self_clone.register_consumed_events(tx_signature, &event_ids);
+                                true
+                            }
+                            Ok(
+                                EventConsumeResult::TransactionNeeed
+                                | EventConsumeResult::Defer { .. },
+                            ) => true,
+                            Err(err) => {
+                                error!("Error while events consuming {err:?}, skip via live process");
+                                self_clone.record_skip(
+                                    &program.program_id,
+                                    SkipReason::ConsumerFailure,
+                                    Some(tx_signature),
+                                );
+                                false
+                            }
+                        };
+
+                        if is_transaction_needed {
+                            info!("Transaction {tx_signature} direct RPC request needed");
+
+                            let transaction = match self_clone
+                                .get_transaction_by_signature(
+                                    &program,
+                                    tx_signature,
+                                    self_clone.live_retry_params(),
+                                )
+                                .await
+                            {
+                                Ok(tx) => tx,
+                                Err(err) => {
+                                    error!("Error while get transaction by signature: {err:?}, skip in live process");
+                                    self_clone.record_skip(
+                                        &program.program_id,
+                                        SkipReason::of_fetch_error(&err),
+                                        Some(tx_signature),
+                                    );
+                                    return;
+                                }
+                            };
+
+                            let transaction_str = tx_signature.to_string();
+                            let result = self_clone
+                                .consume_transaction(&program, tx_signature, transaction, &permits)
+                                .instrument(span!(
+                                    Level::ERROR,
+                                    "Consume",
+                                    tx_signature = transaction_str
+                                ))
+                                .await;
+                            match &result {
+                                Ok(true) => info!("Transaction consumed as part of websocket listener"),
+                                Ok(false) => {}
+                                Err(err) => {
+                                    error!("Error while consuming {err:?}");
+                                    self_clone.record_skip(
+                                        &program.program_id,
+                                        SkipReason::of_consume_error(err),
+                                        Some(tx_signature),
+                                    );
+                                }
+                            }
+                            if !self_clone.is_registrable(&result) {
+                                info!("Transaction {tx_signature} isn't registered, left for resync");
+                                return;
+                            }
+                        }
+
+                        if let Err(err) = self_clone
+                            .local_storage
+                            .register_transaction(&program.program_id, &tx_signature)
+                        {
+                            error!("Error while register tx: {err:?}, skip via live process");
+                        } else {
+                            info!("Registered in local cache");
+                        }
+                     }.instrument(span!(Level::ERROR, "Live Processing", tx_signature = transaction_str)));
+                }
+            }
+
+            warn!("Listen task: stream empty, resubscribe");
+        }
+    }
+
+    /// Listen `blockSubscribe` of `program` & consume its transactions from notifications
+    pub(super) async fn listen_blocks(self: Arc<Self>, program: Arc<Program>) -> Result<()> {
+        info!("Launching websocket client");
+
+        let Some(blocks_source) = self.blocks_source() else {
+            info!("Listen blocks job disabled");
+            return Ok(());
+        };
+        let filter =
+            RpcBlockSubscribeFilter::MentionsAccountOrProgram(program.program_id.to_string());
+        let config = RpcBlockSubscribeConfig {
+            commitment: Some(program.commitment_config),
+            encoding: Some(UiTransactionEncoding::Base64),
+            transaction_details: Some(TransactionDetails::Full),
+            show_rewards: Some(false),
+            max_supported_transaction_version: Some(0),
+        };
+
+        loop {
+            let mut stream = blocks_source
+                .block_subscribe(filter.clone(), config.clone())
+                .instrument(span!(Level::ERROR, "BlockSubscribe"))
+                .await
+                .inspect_err(|err| error!("Error while subs: {err:?}"))?;
+
+            info!("Start listening websocket blocks");
+            while let Some(Response { value: update, .. }) = stream.next().await {
+                if self.handle.is_paused() {
+                    info!("Events reader paused, wait for resume");
+                    self.handle.wait_resumed().await;
+                }
+
+                if let Some(err) = update.err {
+                    warn!(
+                        "Error of block notification at slot {}: {err:?}",
+                        update.slot
+                    );
+                    continue;
+                }
+                let Some(block) = update.block else {
+                    debug!("Block notification at slot {} without block", update.slot);
+                    continue;
+                };
+                info!(
+                    "Block {} received, transactions: {}",
+                    update.slot,
+                    block.transactions.as_ref().map_or(0, Vec::len)
+                );
+                for transaction in block.transactions.into_iter().flatten() {
+                    self.spawn_live_transaction(
+                        std::slice::from_ref(&program),
+                        EncodedConfirmedTransactionWithStatusMeta {
+                            slot: update.slot,
+                            transaction,
+                            block_time: block.block_time,
+                        },
+                        self.filter_mentions_by_invoke,
+                    )
+                    .await?;
+                }
+            }
+
+            warn!("Listen blocks task: stream empty, resubscribe");
+        }
+    }
+
+    /// Parse transaction received with meta (by `blockSubscribe` or ingestion) & consume it
+    /// by `programs` in background, as live transactions of `logsSubscribe` are.
+    /// Transactions failed to be parsed aren't registered, so resync processes them again
+    ///
+    /// If `filter_by_invoke` is set, transaction is consumed only by programs invoked by it
+    async fn spawn_live_transaction(
+        self: &Arc<Self>,
+        programs: &[Arc<Program>],
+        confirmed_transaction: EncodedConfirmedTransactionWithStatusMeta,
+        filter_by_invoke: bool,
+    ) -> Result<()> {
+        let slot = confirmed_transaction.slot;
+        let Some(tx_signature) = confirmed_transaction
+            .transaction
+            .transaction
+            .decode()
+            .and_then(|tx| tx.signatures.first().copied())
+        else {
+            error!("Transaction of slot {slot} can't be decoded, skip");
+            for program in programs {
+                self.record_skip(&program.program_id, SkipReason::InvalidSignature, None);
+            }
+            return Ok(());
+        };
+
+        let mut targets = vec![];
+        for program in programs {
+            if self
+                .local_storage
+                .is_transaction_registered(&program.program_id, &tx_signature)?
+            {
+                info!("Transaction {tx_signature} already registered in event-parser, skip");
+                self.record_skip(
+                    &program.program_id,
+                    SkipReason::AlreadyRegistered,
+                    Some(tx_signature),
+                );
+            } else {
+                targets.push(program);
+            }
+        }
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        let transaction = match parse_transaction(tx_signature, &confirmed_transaction) {
+            Ok(transaction) => transaction,
+            Err(err) => {
+                error!("Error while parse {tx_signature} of slot {slot}: {err:?}, skip in live process");
+                for program in targets {
+                    self.record_skip(
+                        &program.program_id,
+                        SkipReason::ParseFailure,
+                        Some(tx_signature),
+                    );
+                }
+                return Ok(());
+            }
+        };
+
+        for program in targets {
+            if filter_by_invoke
+                && !transaction
+                    .meta
+                    .keys()
+                    .any(|ctx| ctx.program_id == program.program_id)
+            {
+                debug!(
+                    "Transaction {tx_signature} doesn't invoke {}, skip",
+                    program.program_id
+                );
+                continue;
+            }
+
+            let (self_clone, program) = (Arc::clone(self), Arc::clone(program));
+            let (transaction, transaction_str) = (transaction.clone(), tx_signature.to_string());
+            let permits = program.acquire_consumer_permits().await;
+            tokio::spawn(
+                async move {
+                    let result = self_clone
+                        .consume_transaction(&program, tx_signature, transaction, &permits)
+                        .await;
+                    match &result {
+                        Ok(true) => info!("Transaction consumed as part of live process"),
+                        Ok(false) => {}
+                        Err(err) => {
+                            error!("Error while consuming {err:?}");
+                            self_clone.record_skip(
+                                &program.program_id,
+                                SkipReason::of_consume_error(err),
+                                Some(tx_signature),
+                            );
+                        }
+                    }
+                    if !self_clone.is_registrable(&result) {
+                        info!("Transaction {tx_signature} isn't registered, left for resync");
+                        return;
+                    }
+
+                    if let Err(err) = self_clone
+                        .local_storage
+                        .register_transaction(&program.program_id, &tx_signature)
+                    {
+                        error!("Error while register tx: {err:?}, skip via live process");
+                    } else {
+                        info!("Registered in local cache");
+                    }
+                }
+                .instrument(span!(
+                    Level::ERROR,
+                    "Live Processing",
+                    tx_signature = transaction_str
+                )),
+            );
+        }
+        Ok(())
+    }
+
+    /// Consume transactions pushed by [`EventsReader::ingestor`] by programs invoked by them
+    pub(super) async fn listen_ingested(
+        self: Arc<Self>,
+        programs: Vec<Arc<Program>>,
+    ) -> Result<()> {
+        let Some(mut receiver) = self.ingest.take_receiver() else {
+            info!("Ingestion job disabled, transactions are ingested by another run");
+            return Ok(());
+        };
+
+        while let Some(confirmed_transaction) = receiver.recv().await {
+            if self.handle.is_paused() {
+                info!("Events reader paused, wait for resume");
+                self.handle.wait_resumed().await;
+            }
+            self.spawn_live_transaction(&programs, confirmed_transaction, true)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod live_test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        event_parser::EventId,
+        event_reader_service::{Event, LiveSource},
+        mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock},
+        storage::{EventRegistry, RegisterTransaction},
+        transaction_parser::Pubkey,
+    };
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([15; 32]);
+
+    static DEFERRED_ONCE_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static ALWAYS_DEFERRED_CALLS: AtomicUsize = AtomicUsize::new(0);
+    static CONSUMED_EVENTS: Mutex<Vec<EventId>> = Mutex::new(Vec::new());
+
+    fn defer_once(_logs: Event) -> Result<EventConsumeResult> {
+        match DEFERRED_ONCE_CALLS.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(EventConsumeResult::Defer {
+                retry_after: Duration::from_secs(1),
+            }),
+            _ => Ok(EventConsumeResult::ConsumeSuccess),
+        }
+    }
+
+    fn always_defer(_logs: Event) -> Result<EventConsumeResult> {
+        ALWAYS_DEFERRED_CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(EventConsumeResult::Defer {
+            retry_after: Duration::from_secs(1),
+        })
+    }
+
+    fn consume_events(_logs: Event) -> Result<EventConsumeResult> {
+        Ok(EventConsumeResult::ConsumedEvents(
+            CONSUMED_EVENTS.lock().unwrap().clone(),
+        ))
+    }
+
+    async fn wait_calls(calls: &AtomicUsize, count: usize) {
+        while calls.load(Ordering::SeqCst) < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deferred_logs() {
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .is_resync_enabled(false)
+            .event_consumer(defer_once)
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        // Deferred logs are consumed again after `retry_after`, without request of transaction
+        let signature = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        wait_calls(&DEFERRED_ONCE_CALLS, 1).await;
+        tokio::task::yield_now().await;
+        assert!(!storage
+            .is_transaction_registered(&PROGRAM_ID, &signature)
+            .unwrap());
+
+        clock.advance(Duration::from_secs(1));
+        while !storage
+            .is_transaction_registered(&PROGRAM_ID, &signature)
+            .unwrap()
+        {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(DEFERRED_ONCE_CALLS.load(Ordering::SeqCst), 2);
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_max_event_deferrals() {
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::clone(&clock),
+            )
+            .is_resync_enabled(false)
+            .event_consumer(always_defer)
+            .max_event_deferrals(1)
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        // Transaction is requested, once logs are deferred more than allowed
+        let signature = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        wait_calls(&ALWAYS_DEFERRED_CALLS, 1).await;
+        clock.advance(Duration::from_secs(1));
+        let requested = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+        assert_eq!(requested.unwrap(), Some(signature));
+        assert_eq!(ALWAYS_DEFERRED_CALLS.load(Ordering::SeqCst), 2);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_consumed_events() {
+        let chain = Arc::new(MockChain::new());
+        let registry = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::new(MockClock::new()),
+            )
+            .is_resync_enabled(false)
+            .event_consumer(consume_events)
+            .event_registry(Some(Arc::clone(&registry) as _))
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        // Events consumed by logs are registered, the rest of transaction is still consumed
+        let event_id = EventId([16; 32]);
+        CONSUMED_EVENTS.lock().unwrap().push(event_id);
+        let signature = chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        let requested = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+        assert_eq!(requested.unwrap(), Some(signature));
+        assert!(registry.is_event_registered(event_id.as_ref()).unwrap());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_blocks_live_source() {
+        let chain = Arc::new(MockChain::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::new(MockClock::new()),
+            )
+            .is_resync_enabled(false)
+            .live_source(LiveSource::Blocks)
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+        tokio::task::yield_now().await;
+        assert_eq!(chain.subscribers(), 1, "logs are subscribed with blocks");
+
+        // Only transactions of blocks mentioning program are consumed
+        let other = Pubkey::new_unique();
+        chain.push(invocation(other, 1, &[])).unwrap();
+        let signature = chain.push(invocation(PROGRAM_ID, 2, &[])).unwrap();
+        let live = tokio::time::timeout(Duration::from_secs(5), consumed.recv()).await;
+        assert_eq!(live.unwrap(), Some(signature));
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+    }
+}
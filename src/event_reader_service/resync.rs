@@ -0,0 +1,1017 @@
+//! Resync of transactions missed by live paths, batch by batch from resync pointer
+
+use std::{collections::HashSet, fmt, num::NonZeroUsize, result, sync::Arc};
+
+use futures::{future::BoxFuture, StreamExt, TryStreamExt};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use tracing::{Instrument, *};
+
+use crate::{
+    signature_stream::{SignatureStream, SignatureStreamConfig},
+    storage::{QuarantinedTransaction, ResyncPointer, ResyncPointerMove, ResyncPointerMoveReason},
+    transaction_parser::{Pubkey, Signature as SolanaSignature, TransactionParsedMeta},
+};
+
+use super::{
+    unix_timestamp_ms, Error, ErrorClass, EventsReader, Program, Result, ResyncOrder,
+    ResyncProgress, Rollback, SkipReason, RESYNC_BATCH_SIZE,
+};
+
+impl<TransactionConsumerFn, EventRecipient, E>
+    EventsReader<TransactionConsumerFn, EventRecipient, E>
+where
+    EventRecipient: Send + Sync + 'static,
+    TransactionConsumerFn: 'static
+        + Send
+        + Sync
+        + Fn(
+            SolanaSignature,
+            TransactionParsedMeta,
+            Arc<RpcClient>,
+            Arc<EventRecipient>,
+        ) -> BoxFuture<'static, Result<()>>,
+    E: 'static + Send + Sync + fmt::Debug,
+    Error: From<E>,
+{
+    /// Signatures of program older than `before` (from the newest if `None`) and newer than
+    /// `pointer`, newest first
+    ///
+    /// Paging stops on pointer signature or on the first signature older than pointer slot,
+    /// so resync works even if pointer signature is already pruned by RPC provider
+    fn signatures_between(
+        &self,
+        program: &Program,
+        before: Option<SolanaSignature>,
+        pointer: &ResyncPointer,
+    ) -> SignatureStream<'_> {
+        SignatureStream::new(
+            self.signature_source(),
+            program.program_id,
+            SignatureStreamConfig {
+                before,
+                until: pointer.signature,
+                min_slot: pointer.slot,
+                commitment: Some(program.commitment_config),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Resync from the newest signature to `pointer`, by batches of [`RESYNC_BATCH_SIZE`]
+    ///
+    /// Returns next resync pointer (`None` if some of transactions must be resynced again)
+    /// and count of consumed transactions
+    async fn resync_newest(
+        self: &Arc<Self>,
+        program: &Arc<Program>,
+        pointer: &ResyncPointer,
+    ) -> Result<(Option<ResyncPointer>, usize)> {
+        let mut last_transaction = None;
+        let mut is_consumed = true;
+        let mut processed = 0;
+
+        let mut batches = self
+            .signatures_between(program, None, pointer)
+            .try_chunks(RESYNC_BATCH_SIZE);
+        while let Some(batch) = batches.try_next().await.map_err(|err| err.1)? {
+            if last_transaction.is_none() {
+                last_transaction = batch.first().map(|data| ResyncPointer {
+                    signature: Some(data.signature),
+                    slot: Some(data.slot),
+                });
+            }
+            self.handle
+                .update_resync_progress(&program.program_id, |progress| {
+                    progress.walked_signatures += batch.len()
+                });
+            let (count, consumed) = self.resync_batch(program, batch).await?;
+            processed += count;
+            is_consumed &= consumed;
+        }
+
+        Ok((last_transaction.filter(|_| is_consumed), processed))
+    }
+
+    /// Resync from `pointer` to the newest signature, by batches of [`RESYNC_BATCH_SIZE`]
+    ///
+    /// Signatures are listed from the newest only, so at first every [`RESYNC_BATCH_SIZE`]-th
+    /// of them is remembered as cut, then batches between cuts are listed again from the
+    /// oldest one. Memory is bounded by count of cuts instead of all signatures since pointer
+    ///
+    /// Returns next resync pointer (`None` if some of transactions must be resynced again)
+    /// and count of consumed transactions
+    async fn resync_historical(
+        self: &Arc<Self>,
+        program: &Arc<Program>,
+        pointer: &ResyncPointer,
+    ) -> Result<(Option<ResyncPointer>, usize)> {
+        let mut cuts = Vec::new();
+        let mut signatures = self.signatures_between(program, None, pointer);
+        let mut index = 0;
+        while let Some(data) = signatures.try_next().await? {
+            if index % RESYNC_BATCH_SIZE == 0 {
+                cuts.push(data);
+            }
+            index += 1;
+        }
+        drop(signatures);
+        debug!(
+            "Resync of {index} signatures is split into {} batches",
+            cuts.len()
+        );
+        self.handle
+            .update_resync_progress(&program.program_id, |progress| {
+                progress.total_signatures = Some(index)
+            });
+
+        let last_transaction = cuts.first().map(|data| ResyncPointer {
+            signature: Some(data.signature),
+            slot: Some(data.slot),
+        });
+        let mut is_consumed = true;
+        let mut processed = 0;
+
+        let mut until = *pointer;
+        for cut in cuts.into_iter().rev() {
+            let cut_pointer = ResyncPointer {
+                signature: Some(cut.signature),
+                slot: Some(cut.slot),
+            };
+            let mut batch = self
+                .signatures_between(program, Some(cut.signature), &until)
+                .try_collect::<Vec<_>>()
+                .await?;
+            batch.reverse();
+            batch.push(cut);
+
+            self.handle
+                .update_resync_progress(&program.program_id, |progress| {
+                    progress.walked_signatures += batch.len()
+                });
+            let (count, consumed) = self.resync_batch(program, batch).await?;
+            processed += count;
+            is_consumed &= consumed;
+            until = cut_pointer;
+        }
+
+        Ok((last_transaction.filter(|_| is_consumed), processed))
+    }
+
+    /// Successful transactions of `batch` to consume: unregistered ones and, with
+    /// [`EventsReader::checkpoints`], registered ones above confirmed slot which aren't
+    /// consumed by this reader. They could be registered before crash of reader, while
+    /// consumers haven't persisted them yet
+    fn unconsumed_signatures(
+        &self,
+        program: &Program,
+        batch: Vec<de_solana_client::SignatureData>,
+    ) -> Result<Vec<SolanaSignature>> {
+        let batch = batch
+            .into_iter()
+            .filter(|d| d.err.is_none())
+            .collect::<Vec<_>>();
+        let unregistered = self.local_storage.filter_unregistered_transactions(
+            &program.program_id,
+            &batch.iter().map(|d| d.signature).collect::<Vec<_>>(),
+        )?;
+        let Some(checkpoints) = self.checkpoints.as_ref() else {
+            return Ok(unregistered);
+        };
+
+        let confirmed_slot = checkpoints.confirmed_slot().unwrap_or_default();
+        let mut unconfirmed = match self.unconfirmed.lock() {
+            Ok(unconfirmed) => unconfirmed,
+            Err(err) => {
+                error!("Error while lock unconfirmed transactions: {err:?}");
+                return Ok(unregistered);
+            }
+        };
+        unconfirmed.retain(|_, slot| *slot > confirmed_slot);
+
+        let unregistered = unregistered.into_iter().collect::<HashSet<_>>();
+        Ok(batch
+            .into_iter()
+            .filter(|d| {
+                unregistered.contains(&d.signature)
+                    || (d.slot > confirmed_slot
+                        && !unconfirmed.contains_key(&(program.program_id, d.signature)))
+            })
+            .map(|d| d.signature)
+            .collect())
+    }
+
+    /// Consume [`Self::unconsumed_signatures`] of `batch` in order of batch, by chunks of
+    /// `resync_signatures_chunk_size` in parallel
+    ///
+    /// Returns count of unregistered transactions and whether all of them were consumed
+    async fn resync_batch(
+        self: &Arc<Self>,
+        program: &Arc<Program>,
+        batch: Vec<de_solana_client::SignatureData>,
+    ) -> Result<(usize, bool)> {
+        let signatures = self.unconsumed_signatures(program, batch)?;
+        if signatures.is_empty() {
+            return Ok((0, true));
+        }
+
+        info!(
+            "Find new {} transactions, start processing",
+            signatures.len()
+        );
+        self.handle
+            .update_resync_progress(&program.program_id, |progress| {
+                progress.found += signatures.len()
+            });
+
+        let signatures_chunks = signatures
+            .chunks(
+                self.resync_signatures_chunk_size(program)
+                    .unwrap_or(signatures.len()),
+            )
+            .enumerate();
+
+        let mut tasks = Vec::new();
+        for (index, signatures_chunk) in signatures_chunks {
+            let self_clone = self.clone();
+            let program = Arc::clone(program);
+            let signatures_chunk = signatures_chunk.to_vec();
+
+            // Permits are held by chunk task while it consumes its transactions
+            let permits = program.acquire_consumer_permits().await;
+            tasks.push(tokio::spawn(async move {
+                let mut is_chunk_successfull_processed = true;
+                self_clone
+                    .handle
+                    .update_resync_progress(&program.program_id, |progress| {
+                        progress.current_chunk = Some(index)
+                    });
+
+                for tx_signature in signatures_chunk.into_iter() {
+                    self_clone.handle.wait_resumed().await;
+                    info!(
+                        "Unprocessed (by ws) transaction find while resynchronization process, transaction hash: {}",
+                        tx_signature.to_string()
+                    );
+
+                    let transaction = match self_clone
+                        .get_transaction_by_signature(&program, tx_signature, self_clone.resync_retry_params())
+                        .await
+                    {
+                        Ok(transaction) => {
+                            self_clone.forget_fetch_failures(&tx_signature);
+                            transaction
+                        }
+                        Err(err) => {
+                            self_clone.record_skip(
+                                &program.program_id,
+                                SkipReason::of_fetch_error(&err),
+                                Some(tx_signature),
+                            );
+                            match self_clone.retry_classifier.classify(&err) {
+                                ErrorClass::Retryable if self_clone.count_fetch_failure(&tx_signature) => {
+                                    error!("Transaction {tx_signature} failed to be fetched too many times: {err:?}, quarantine");
+                                    self_clone.record_skip(
+                                        &program.program_id,
+                                        SkipReason::Quarantined,
+                                        Some(tx_signature),
+                                    );
+                                    self_clone.local_storage.quarantine_transaction(
+                                        &program.program_id,
+                                        &QuarantinedTransaction {
+                                            signature: tx_signature,
+                                            reason: format!("{err:?}"),
+                                            attempts: self_clone
+                                                .resync_retry_params()
+                                                .attempts_count()
+                                                .get()
+                                                .saturating_mul(
+                                                    self_clone
+                                                        .resync_max_fetch_failures
+                                                        .map_or(1, NonZeroUsize::get),
+                                                ),
+                                            timestamp_ms: unix_timestamp_ms(),
+                                            retry_requested: false,
+                                        },
+                                    )?;
+                                }
+                                ErrorClass::Retryable => {
+                                    error!("Error while get transaction by signature: {err:?}");
+                                    is_chunk_successfull_processed = false;
+                                }
+                                ErrorClass::Fatal => {
+                                    error!("Fatal error while get transaction by signature: {err:?}, skip");
+                                    self_clone
+                                        .local_storage
+                                        .register_transaction(&program.program_id, &tx_signature)?;
+                                }
+                            }
+                            self_clone.handle.update_resync_progress(&program.program_id, |progress| {
+                                progress.failed += 1
+                            });
+                            continue;
+                        }
+                    };
+
+                    let transaction_str = tx_signature.to_string();
+                    let result = self_clone
+                        .consume_transaction(&program, tx_signature, transaction, &permits)
+                        .await;
+                    match &result {
+                        Ok(true) => {
+                            info!("Transaction {tx_signature} consumed as part of resync process");
+                            self_clone.handle.update_resync_progress(&program.program_id, |progress| {
+                                progress.processed += 1
+                            });
+                        }
+                        // Claimed by another replica, consuming of it doesn't block resync ptr
+                        Ok(false) => {}
+                        Err(err) => {
+                            error!("Error while transaction {transaction_str} consuming {err:?}", err = err);
+                            self_clone.record_skip(
+                                &program.program_id,
+                                SkipReason::of_consume_error(err),
+                                Some(tx_signature),
+                            );
+                            is_chunk_successfull_processed = false;
+                            self_clone.handle.update_resync_progress(&program.program_id, |progress| {
+                                progress.failed += 1
+                            });
+                        }
+                    }
+
+                    if self_clone.is_registrable(&result) {
+                        self_clone
+                            .local_storage
+                            .register_transaction(&program.program_id, &tx_signature)?;
+                    }
+                }
+
+                Result::Ok(is_chunk_successfull_processed)
+            }
+                .instrument(span!(
+                    Level::ERROR,
+                    "Register chunk",
+                    chunk_index = index,
+                ))
+            ));
+        }
+
+        let mut result = Ok(true);
+        let mut completion_stream = tasks
+            .into_iter()
+            .collect::<futures::stream::FuturesUnordered<_>>();
+
+        while let Some(task) = completion_stream.next().await {
+            match task
+                .map_err(Error::TokioJoinError)
+                .and_then(|result| result)
+            {
+                Ok(is_chunk_consumed) => {
+                    if let Ok(is_consumed) = result.as_mut() {
+                        *is_consumed &= is_chunk_consumed;
+                    }
+                }
+                Err(err) => {
+                    error!("Error while resync task: {err:?}");
+                    result = Err(err);
+                }
+            }
+        }
+
+        result.map(|is_consumed| (signatures.len(), is_consumed))
+    }
+
+    /// `pointer`, if [`EventsReader::checkpoints`] are disabled or consumers confirmed its
+    /// slot in `resync_duration`
+    async fn confirmed_pointer(
+        &self,
+        program: &Program,
+        pointer: ResyncPointer,
+    ) -> Option<ResyncPointer> {
+        let (Some(checkpoints), Some(slot)) = (self.checkpoints.as_ref(), pointer.slot) else {
+            return Some(pointer);
+        };
+        tokio::select! {
+            _ = checkpoints.wait_confirmed(slot) => Some(pointer),
+            _ = self.clock.sleep(self.resync_duration(program)) => {
+                warn!(
+                    "Consumers haven't confirmed checkpoint of {slot} slot, not move resync ptr"
+                );
+                None
+            }
+        }
+    }
+
+    pub(super) async fn resync_events(self: &Arc<Self>, program: &Arc<Program>) -> Result<()> {
+        if !program.is_resync_enabled {
+            return Ok(());
+        }
+
+        'resync: loop {
+            tokio::select! {
+                _ = self.clock.sleep(self.resync_duration(program)) => {}
+                _ = self.handle.resync_now.notified() => info!("Resync triggered manually"),
+            }
+            self.handle.wait_resumed().await;
+            info!("Start resync for program {}", program.program_id);
+
+            if let Err(err) = self.retry_quarantined(program).await {
+                error!("Error while retry quarantined transactions: {err:?}");
+            }
+
+            let resync_last_slot = unwrap_or_continue!(
+                self.signature_source()
+                    .get_slot(self.client.commitment())
+                    .await,
+                "Error while get slot of resync: {err:?}"
+            );
+            let resync_start = unwrap_or_continue!(
+                self.local_storage
+                    .get_resync_pointer(&program.program_id)
+                    .map_err(Error::from),
+                "Error while get resync pointer: {err:?}"
+            );
+            info!(
+                "Resync start from {}",
+                match resync_start {
+                    ResyncPointer {
+                        signature: Some(signature),
+                        ..
+                    } => format!("{signature} transaction"),
+                    ResyncPointer {
+                        signature: None,
+                        slot: Some(slot),
+                    } => format!("{slot} slot"),
+                    ResyncPointer {
+                        signature: None,
+                        slot: None,
+                    } => "beginning".to_owned(),
+                }
+            );
+
+            self.handle
+                .update_resync_progress(&program.program_id, |progress| {
+                    *progress = ResyncProgress {
+                        is_running: true,
+                        started_at: Some(tokio::time::Instant::now()),
+                        ..Default::default()
+                    }
+                });
+
+            // If any of tx in resync failed, then not move last resync transaction pointer
+            let resync = match program.resync_order {
+                ResyncOrder::Newest => self.resync_newest(program, &resync_start).await,
+                ResyncOrder::Historical => self.resync_historical(program, &resync_start).await,
+            };
+            self.handle
+                .update_resync_progress(&program.program_id, |progress| {
+                    progress.is_running = false;
+                    progress.current_chunk = None;
+                });
+            let (last_transaction, processed) = match resync {
+                Ok(resync) => resync,
+                Err(err) => {
+                    warn!("Error while resync: {err:?}, not move resync ptr");
+                    continue 'resync;
+                }
+            };
+
+            let last_transaction = match last_transaction {
+                Some(pointer) => self.confirmed_pointer(program, pointer).await,
+                None => None,
+            };
+
+            match (processed, last_transaction.as_ref()) {
+                (0, _) => info!("Resync ended: no new transactions"),
+                (_, Some(last_transaction)) => {
+                    info!("resync successful ended, ptr will moved to {last_transaction:?}")
+                }
+                (_, None) => info!("resync successful ended, not new ptr for move"),
+            }
+            self.set_last_resynced_transaction(program, last_transaction)?;
+
+            (self.resync_ptr_setter)(resync_last_slot).await?;
+        }
+    }
+
+    /// Process again quarantined transactions of `program` with retry requested by operator.
+    /// Transaction is released from quarantine if consumed, otherwise it's quarantined again
+    /// until the next request
+    async fn retry_quarantined(self: &Arc<Self>, program: &Arc<Program>) -> Result<()> {
+        let quarantined = self
+            .local_storage
+            .get_quarantined_transactions(&program.program_id)?;
+        for mut transaction in quarantined.into_iter().filter(|t| t.retry_requested) {
+            let tx_signature = transaction.signature;
+            info!("Retry quarantined transaction {tx_signature}");
+
+            let result = match self
+                .get_transaction_by_signature(program, tx_signature, self.resync_retry_params())
+                .await
+            {
+                Ok(meta) => {
+                    let permits = program.acquire_consumer_permits().await;
+                    self.consume_transaction(program, tx_signature, meta, &permits)
+                        .await
+                }
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(_) => {
+                    info!("Quarantined transaction {tx_signature} consumed, release it");
+                    self.local_storage
+                        .release_quarantined_transaction(&program.program_id, &tx_signature)?;
+                }
+                Err(err) => {
+                    error!("Error while retry quarantined transaction {tx_signature}: {err:?}");
+                    transaction.reason = format!("{err:?}");
+                    transaction.attempts += 1;
+                    transaction.timestamp_ms = unix_timestamp_ms();
+                    transaction.retry_requested = false;
+                    self.local_storage
+                        .quarantine_transaction(&program.program_id, &transaction)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_last_resynced_transaction(
+        self: &Arc<Self>,
+        program: &Program,
+        last_transaction: Option<ResyncPointer>,
+    ) -> Result<()> {
+        let rollback = match (
+            program.is_rollback_enabled,
+            self.resync_rollback.write().as_deref(),
+        ) {
+            (false, _) => Rollback::None,
+            (true, Ok(rollback)) => *rollback,
+            (true, Err(err)) => {
+                error!("Error while lock rollback: {err:?}");
+                Rollback::None
+            }
+        };
+
+        // Rollback after reorg is applied once, unless pointer is already before reorg
+        let reorg_slot = match self.reorg_rollbacks.lock() {
+            Ok(mut rollbacks) => rollbacks.remove(&program.program_id),
+            Err(err) => {
+                error!("Error while lock reorg rollbacks: {err:?}");
+                None
+            }
+        };
+        let (rollback, is_reorg) = match (rollback, reorg_slot) {
+            (Rollback::Beginning, _) | (_, None) => (rollback, false),
+            (Rollback::Slot(slot), Some(reorg_slot)) => {
+                (Rollback::Slot(slot.min(reorg_slot)), reorg_slot < slot)
+            }
+            (Rollback::Signature(_), Some(reorg_slot)) => (Rollback::Slot(reorg_slot), true),
+            (Rollback::None, Some(reorg_slot)) => {
+                let pointer = match last_transaction {
+                    Some(pointer) => pointer,
+                    None => self.local_storage.get_resync_pointer(&program.program_id)?,
+                };
+                match pointer {
+                    ResyncPointer {
+                        signature: None,
+                        slot: None,
+                    } => (Rollback::None, false),
+                    ResyncPointer {
+                        slot: Some(slot), ..
+                    } if slot < reorg_slot => (Rollback::None, false),
+                    _ => (Rollback::Slot(reorg_slot), true),
+                }
+            }
+        };
+
+        let reason = match rollback {
+            Rollback::Signature(_) => ResyncPointerMoveReason::Rollback,
+            _ => ResyncPointerMoveReason::Resync,
+        };
+        let next_resync_ptr = match rollback {
+            Rollback::Beginning => {
+                info!("Reset last resynced tx");
+                return self.move_resync_pointer(
+                    &program.program_id,
+                    ResyncPointerMoveReason::Rollback,
+                    || {
+                        self.local_storage
+                            .reset_last_resynced_transaction(&program.program_id)
+                    },
+                );
+            }
+            Rollback::Slot(slot) => {
+                info!("Reset resync pointer to {slot} slot");
+                return self.move_resync_pointer(
+                    &program.program_id,
+                    match is_reorg {
+                        true => ResyncPointerMoveReason::Reorg,
+                        false => ResyncPointerMoveReason::Rollback,
+                    },
+                    || {
+                        self.local_storage
+                            .reset_resync_pointer_to_slot(&program.program_id, slot)
+                    },
+                );
+            }
+            Rollback::None => last_transaction,
+            Rollback::Signature(signature) => Some(ResyncPointer {
+                signature: Some(signature),
+                slot: None,
+            }),
+        };
+
+        if let Some(last_transaction) = next_resync_ptr {
+            info!("Set resync pointer to {last_transaction:?}");
+            self.move_resync_pointer(&program.program_id, reason, || {
+                self.local_storage
+                    .set_resync_pointer(&program.program_id, &last_transaction)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Move resync pointer of `program_id` by `apply` & record movement into pointer
+    /// history of storage
+    pub(super) fn move_resync_pointer(
+        &self,
+        program_id: &Pubkey,
+        reason: ResyncPointerMoveReason,
+        apply: impl FnOnce() -> result::Result<(), E>,
+    ) -> Result<()> {
+        let old = self.local_storage.get_resync_pointer(program_id)?;
+        apply()?;
+        let new = self.local_storage.get_resync_pointer(program_id)?;
+
+        if old != new {
+            self.local_storage.record_resync_pointer_move(
+                program_id,
+                &ResyncPointerMove {
+                    old,
+                    new,
+                    timestamp_ms: unix_timestamp_ms(),
+                    reason,
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod resync_test {
+    use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+    use async_trait::async_trait;
+    use solana_client::{
+        client_error::{ClientError, ClientErrorKind},
+        rpc_config::RpcTransactionConfig,
+        rpc_request::{RpcError, RpcResponseErrorData},
+    };
+
+    use super::*;
+    use crate::{
+        event_reader_service::{
+            CheckpointCoordinator, RetryParams, RetryTransient, TransactionSource,
+        },
+        mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock},
+        storage::{RegisterTransaction, ResyncedTransactionsPtrStorage},
+        transaction_parser::Slot,
+    };
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([13; 32]);
+
+    /// Advance `clock` by resync duration of [`MockChain::events_reader_builder`] until
+    /// `is_done`
+    async fn resync_until(clock: &MockClock, mut is_done: impl FnMut() -> bool) {
+        let resyncs = async {
+            while !is_done() {
+                clock.advance(Duration::from_secs(1));
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(30), resyncs)
+            .await
+            .expect("resync isn't done");
+    }
+
+    #[tokio::test]
+    async fn test_slot_resync_pointer() {
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+
+        // Signature of pointer is pruned by RPC, so resync is anchored on its slot
+        storage
+            .set_resync_pointer(
+                &PROGRAM_ID,
+                &ResyncPointer {
+                    signature: Some(SolanaSignature::new_unique()),
+                    slot: Some(5),
+                },
+            )
+            .unwrap();
+        let old = chain.push_missed(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        let new = chain.push_missed(invocation(PROGRAM_ID, 10, &[])).unwrap();
+
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+
+        let new_pointer = ResyncPointer {
+            signature: Some(new),
+            slot: Some(10),
+        };
+        resync_until(&clock, || {
+            storage.get_resync_pointer(&PROGRAM_ID).unwrap() == new_pointer
+        })
+        .await;
+        assert_eq!(consumed.try_recv().unwrap(), new);
+        assert!(consumed.try_recv().is_err());
+        assert!(!storage
+            .is_transaction_registered(&PROGRAM_ID, &old)
+            .unwrap());
+
+        // Reset to slot makes the next resync process all transactions since it
+        storage
+            .reset_resync_pointer_to_slot(&PROGRAM_ID, 0)
+            .unwrap();
+        resync_until(&clock, || {
+            storage
+                .is_transaction_registered(&PROGRAM_ID, &old)
+                .unwrap()
+                && storage.get_resync_pointer(&PROGRAM_ID).unwrap() == new_pointer
+        })
+        .await;
+        assert_eq!(consumed.try_recv().unwrap(), old);
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_transaction_consumed_after_restart() {
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let signature = chain.push_missed(invocation(PROGRAM_ID, 10, &[])).unwrap();
+
+        // Consumer of the first reader never confirms transaction, which is registered
+        let checkpoints = CheckpointCoordinator::new();
+        let _db = checkpoints.register("db");
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .checkpoints(Some(checkpoints))
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        resync_until(&clock, || {
+            storage
+                .is_transaction_registered(&PROGRAM_ID, &signature)
+                .unwrap()
+        })
+        .await;
+        assert_eq!(consumed.try_recv().unwrap(), signature);
+
+        // Unconfirmed transaction isn't consumed again by the same reader
+        for _ in 0..3 {
+            clock.advance(Duration::from_secs(1));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+
+        // After crash consumer confirms only its durable checkpoint before transaction
+        let checkpoints = CheckpointCoordinator::new();
+        let db = checkpoints.register("db");
+        db.confirm(5);
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .checkpoints(Some(checkpoints))
+            .build()
+            .unwrap();
+        let task = tokio::spawn(Arc::new(reader).run());
+        let mut received = None;
+        resync_until(&clock, || {
+            received = received.or(consumed.try_recv().ok());
+            received.is_some()
+        })
+        .await;
+        assert_eq!(received, Some(signature));
+
+        db.confirm(10);
+        let pointer = ResyncPointer {
+            signature: Some(signature),
+            slot: Some(10),
+        };
+        resync_until(&clock, || {
+            storage.get_resync_pointer(&PROGRAM_ID).unwrap() == pointer
+        })
+        .await;
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+    }
+
+    /// [`MockChain`] failing requests of some transactions with JSON-RPC error
+    struct FailingSource {
+        chain: Arc<MockChain>,
+        /// Error code & count of left failures by transaction
+        failures: Mutex<HashMap<SolanaSignature, (i64, usize)>>,
+        requests: Mutex<HashMap<SolanaSignature, usize>>,
+    }
+
+    impl FailingSource {
+        fn requests(&self, signature: &SolanaSignature) -> usize {
+            let requests = self.requests.lock().unwrap();
+            requests.get(signature).copied().unwrap_or_default()
+        }
+    }
+
+    #[async_trait]
+    impl TransactionSource for FailingSource {
+        async fn get_transaction(
+            &self,
+            signature: SolanaSignature,
+            config: RpcTransactionConfig,
+        ) -> Result<TransactionParsedMeta> {
+            *self.requests.lock().unwrap().entry(signature).or_default() += 1;
+            let code = match self.failures.lock().unwrap().get_mut(&signature) {
+                Some((code, left)) if *left > 0 => {
+                    *left -= 1;
+                    Some(*code)
+                }
+                _ => None,
+            };
+            let Some(code) = code else {
+                return self.chain.get_transaction(signature, config).await;
+            };
+            Err(
+                ClientError::from(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                    code,
+                    message: "Mock failure".to_owned(),
+                    data: RpcResponseErrorData::Empty,
+                }))
+                .into(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_classifier() {
+        const NODE_BEHIND: i64 = -32005;
+        const INVALID_PARAMS: i64 = -32602;
+
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let source = Arc::new(FailingSource {
+            chain: Arc::clone(&chain),
+            failures: Mutex::default(),
+            requests: Mutex::default(),
+        });
+
+        let transient = chain.push_missed(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        let fatal = chain.push_missed(invocation(PROGRAM_ID, 2, &[])).unwrap();
+        let failures = [(transient, (NODE_BEHIND, 2)), (fatal, (INVALID_PARAMS, 1))];
+        source.failures.lock().unwrap().extend(failures);
+
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .transaction_source(Some(Arc::clone(&source) as Arc<dyn TransactionSource>))
+            .retry_classifier(Arc::new(RetryTransient))
+            .resync_transaction_request_param(Some(RetryParams::new(
+                NonZeroUsize::new(3).unwrap(),
+                Duration::ZERO,
+            )))
+            .build()
+            .unwrap();
+        let handle = reader.handle();
+        let task = tokio::spawn(Arc::new(reader).run());
+
+        // Transient error is retried, fatal one is skipped without blocking of pointer
+        let pointer = ResyncPointer {
+            signature: Some(fatal),
+            slot: Some(2),
+        };
+        resync_until(&clock, || {
+            storage.get_resync_pointer(&PROGRAM_ID).unwrap() == pointer
+        })
+        .await;
+        assert_eq!(consumed.try_recv().unwrap(), transient);
+        assert!(consumed.try_recv().is_err());
+        assert_eq!(
+            (source.requests(&transient), source.requests(&fatal)),
+            (3, 1)
+        );
+        assert!(storage
+            .is_transaction_registered(&PROGRAM_ID, &fatal)
+            .unwrap());
+
+        // Transaction failing after all retries blocks pointer until the next resync
+        let blocked = chain.push_missed(invocation(PROGRAM_ID, 3, &[])).unwrap();
+        source
+            .failures
+            .lock()
+            .unwrap()
+            .insert(blocked, (NODE_BEHIND, usize::MAX));
+        resync_until(&clock, || {
+            handle.resync_progress(&PROGRAM_ID).is_some_and(|progress| {
+                !progress.is_running && progress.found == 1 && progress.failed == 1
+            })
+        })
+        .await;
+        assert_eq!(source.requests(&blocked), 3);
+        assert_eq!(storage.get_resync_pointer(&PROGRAM_ID).unwrap(), pointer);
+        assert!(!storage
+            .is_transaction_registered(&PROGRAM_ID, &blocked)
+            .unwrap());
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_paged_historical_resync() {
+        let chain = Arc::new(MockChain::new());
+        let clock = Arc::new(MockClock::new());
+        let storage = Arc::new(MemoryStorage::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+
+        // Cuts are taken at the newest signature & every `RESYNC_BATCH_SIZE`-th older one, so
+        // the oldest batch is partial
+        let count = 2 * RESYNC_BATCH_SIZE + 1;
+        let signatures = (0..count)
+            .map(|slot| chain.push_missed(invocation(PROGRAM_ID, slot as Slot, &[])))
+            .collect::<result::Result<Vec<_>, _>>()
+            .unwrap();
+
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::clone(&storage),
+                Arc::clone(&clock),
+            )
+            .build()
+            .unwrap();
+        let handle = reader.handle();
+        let task = tokio::spawn(Arc::new(reader).run());
+
+        let pointer = ResyncPointer {
+            signature: signatures.last().copied(),
+            slot: Some(count as Slot - 1),
+        };
+        resync_until(&clock, || {
+            storage.get_resync_pointer(&PROGRAM_ID).unwrap() == pointer
+        })
+        .await;
+        let mut resynced = vec![];
+        while let Ok(signature) = consumed.try_recv() {
+            resynced.push(signature);
+        }
+        assert_eq!(resynced, signatures);
+
+        let progress = handle.resync_progress(&PROGRAM_ID).unwrap();
+        assert_eq!(progress.total_signatures, Some(count));
+        assert_eq!(
+            (
+                progress.walked_signatures,
+                progress.found,
+                progress.processed
+            ),
+            (count, count, count)
+        );
+        task.abort();
+    }
+}
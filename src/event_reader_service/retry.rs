@@ -0,0 +1,273 @@
+//! Retry policy of transaction requests, classification of errors and time source of sleeps
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use async_trait::async_trait;
+use solana_client::{client_error::ClientErrorKind, rpc_request::RpcError};
+use solana_sdk::transaction::TransactionError;
+
+use super::Error;
+
+/// Class of error of transaction request, see [`RetryClassifier`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Request is retried, failed transaction blocks move of resync pointer
+    Retryable,
+    /// Request isn't retried, transaction is skipped without blocking of resync pointer
+    Fatal,
+}
+
+/// Classification of errors of transaction requests made by [`EventsReader`]
+pub trait RetryClassifier: Send + Sync {
+    fn classify(&self, err: &Error) -> ErrorClass;
+}
+
+impl<F: Send + Sync + Fn(&Error) -> ErrorClass> RetryClassifier for F {
+    fn classify(&self, err: &Error) -> ErrorClass {
+        self(err)
+    }
+}
+
+/// Source of time for schedules of [`EventsReader`]: resync cadence, retries & checks
+/// intervals, so they can be tested without real sleeps
+#[async_trait]
+pub trait Clock: Send + Sync {
+    async fn sleep(&self, duration: Duration);
+}
+
+/// [`tokio::time`] clock, default [`EventsReader::clock`]. Follows `tokio::time::pause`
+/// & `tokio::time::advance` in tests with paused time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+#[async_trait]
+impl Clock for TokioClock {
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+/// Every error is retryable, default [`EventsReader::retry_classifier`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryAll;
+
+impl RetryClassifier for RetryAll {
+    fn classify(&self, _err: &Error) -> ErrorClass {
+        ErrorClass::Retryable
+    }
+}
+
+/// Only transient RPC errors are retryable, others (e.g. parsing errors) are fatal
+///
+/// Transient are: io errors, HTTP 429 & 5xx, `null` response of not yet available
+/// transaction, JSON-RPC errors of block not available (`-32004`), node behind (`-32005`)
+/// & min context slot not reached (`-32016`) and blockhash not found
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryTransient;
+
+impl RetryTransient {
+    /// JSON-RPC error codes of transient errors
+    pub const RETRYABLE_CODES: &'static [i64] = &[-32004, -32005, -32016];
+}
+
+impl RetryClassifier for RetryTransient {
+    fn classify(&self, err: &Error) -> ErrorClass {
+        let Some(client_error) = err.client_error() else {
+            return ErrorClass::Fatal;
+        };
+        if client_error.get_transaction_error() == Some(TransactionError::BlockhashNotFound) {
+            return ErrorClass::Retryable;
+        }
+
+        let is_transient = match client_error.kind() {
+            ClientErrorKind::Io(_) | ClientErrorKind::SerdeJson(_) => true,
+            ClientErrorKind::Reqwest(err) => {
+                err.is_timeout()
+                    || err.is_connect()
+                    || err
+                        .status()
+                        .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+            }
+            ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+                Self::RETRYABLE_CODES.contains(code)
+            }
+            _ => false,
+        };
+        match is_transient {
+            true => ErrorClass::Retryable,
+            false => ErrorClass::Fatal,
+        }
+    }
+}
+
+/// Retry policy of transaction requests
+///
+/// Count of attempts includes the first request, so it can't be zero:
+/// ```
+/// use solana_events_parser::event_reader_service::RetryParamsBuilder;
+///
+/// assert!(RetryParamsBuilder::default().attempts_count(0).build().is_err());
+/// let params = RetryParamsBuilder::default().attempts_count(1).build().unwrap();
+/// assert_eq!(params.attempts_count().get(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, derive_builder::Builder)]
+pub struct RetryParams {
+    #[builder(
+        setter(custom),
+        field(
+            type = "Option<usize>",
+            build = "NonZeroUsize::new(self.attempts_count.unwrap_or(10)).ok_or_else(|| \
+                     RetryParamsBuilderError::ValidationError(\
+                     \"`attempts_count` must be positive\".to_owned()))?"
+        )
+    )]
+    pub(super) attempts_count: NonZeroUsize,
+    /// Delay before the second attempt
+    #[builder(default = "Duration::from_millis(500)")]
+    attempt_timeout: Duration,
+    /// Multiplier of delay for every next attempt, `1` (constant delay) by default
+    #[builder(default = "1")]
+    backoff_factor: u32,
+    /// Upper bound of growing delay
+    #[builder(default, setter(strip_option))]
+    max_attempt_timeout: Option<Duration>,
+}
+
+impl RetryParamsBuilder {
+    /// Count of attempts including the first one, `10` by default
+    pub fn attempts_count(&mut self, attempts_count: usize) -> &mut Self {
+        self.attempts_count = Some(attempts_count);
+        self
+    }
+}
+
+impl RetryParams {
+    pub fn new(attempts_count: NonZeroUsize, attempt_timeout: Duration) -> Self {
+        Self {
+            attempts_count,
+            attempt_timeout,
+            backoff_factor: 1,
+            max_attempt_timeout: None,
+        }
+    }
+
+    pub fn attempts_count(&self) -> NonZeroUsize {
+        self.attempts_count
+    }
+
+    pub fn attempt_timeout(&self) -> Duration {
+        self.attempt_timeout
+    }
+
+    /// Delay after failed `attempt` (starting from `1`) before the next one
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let exponent = u32::try_from(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        let delay = self
+            .attempt_timeout
+            .saturating_mul(self.backoff_factor.saturating_pow(exponent));
+        match self.max_attempt_timeout {
+            Some(max_attempt_timeout) => delay.min(max_attempt_timeout),
+            None => delay,
+        }
+    }
+}
+
+impl Default for RetryParams {
+    fn default() -> Self {
+        RetryParamsBuilder::default()
+            .build()
+            .expect("All fields have valid default values")
+    }
+}
+
+#[cfg(test)]
+mod retry_test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use solana_client::rpc_config::RpcTransactionConfig;
+
+    use super::*;
+    use crate::{
+        event_reader_service::{Result, SkipReason, TransactionSource},
+        mock_source::{invocation, send_signature, MemoryStorage, MockChain, MockClock},
+        transaction_parser::{Pubkey, Signature as SolanaSignature, TransactionParsedMeta},
+    };
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([14; 32]);
+
+    /// Source of transactions, which are never available, counting requests
+    #[derive(Default)]
+    struct Unavailable(AtomicUsize);
+
+    #[async_trait]
+    impl TransactionSource for Unavailable {
+        async fn get_transaction(
+            &self,
+            signature: SolanaSignature,
+            _config: RpcTransactionConfig,
+        ) -> Result<TransactionParsedMeta> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Err(Error::TransactionNotFound(signature))
+        }
+    }
+
+    #[test]
+    fn test_retry_params_delay() {
+        let params = RetryParamsBuilder::default()
+            .attempt_timeout(Duration::from_millis(100))
+            .backoff_factor(2)
+            .max_attempt_timeout(Duration::from_millis(500))
+            .build()
+            .unwrap();
+        let delays = (1..=5).map(|attempt| params.delay(attempt).as_millis());
+        assert_eq!(delays.collect::<Vec<_>>(), [100, 200, 400, 500, 500]);
+        assert_eq!(params.delay(usize::MAX), Duration::from_millis(500));
+
+        let constant = RetryParams::default();
+        assert_eq!(constant.delay(1), constant.delay(10));
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempts() {
+        assert!(RetryParamsBuilder::default()
+            .attempts_count(0)
+            .build()
+            .is_err());
+
+        let chain = Arc::new(MockChain::new());
+        let (sender, mut consumed) = tokio::sync::mpsc::unbounded_channel();
+        let source = Arc::new(Unavailable::default());
+        let params = RetryParamsBuilder::default()
+            .attempts_count(1)
+            .attempt_timeout(Duration::ZERO)
+            .build()
+            .unwrap();
+        let reader = chain
+            .events_reader_builder(
+                PROGRAM_ID,
+                send_signature,
+                Arc::new(sender),
+                Arc::new(MemoryStorage::new()),
+                Arc::new(MockClock::new()),
+            )
+            .transaction_source(Some(Arc::clone(&source) as Arc<dyn TransactionSource>))
+            .live_events_transaction_request_param(params)
+            .build()
+            .unwrap();
+        let handle = reader.handle();
+        let task = tokio::spawn(Arc::new(reader).run());
+        chain.wait_subscribed().await;
+
+        // The only attempt is the first request, failed one isn't retried
+        chain.push(invocation(PROGRAM_ID, 1, &[])).unwrap();
+        while handle.skipped(SkipReason::FetchFailure) == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(source.0.load(Ordering::Relaxed), 1);
+        assert!(consumed.try_recv().is_err());
+        task.abort();
+    }
+}
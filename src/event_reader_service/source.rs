@@ -0,0 +1,240 @@
+//! Sources of transactions, signatures, logs & blocks, replaceable for tests, and ingestion
+//! of transactions pushed from outside
+
+use std::{result, sync::Mutex};
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcBlockConfig, RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcTransactionConfig,
+        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    rpc_response::{
+        Response, RpcBlockUpdate, RpcConfirmedTransactionStatusWithSignature, RpcLogsResponse,
+    },
+};
+use solana_sdk::{commitment_config::CommitmentConfig, hash::Hash};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionDetails, TransactionStatus,
+};
+
+use crate::transaction_parser::{
+    BindTransactionInstructionLogs, Pubkey, Signature as SolanaSignature, Slot,
+    TransactionParsedMeta,
+};
+
+use super::{Error, Result};
+
+/// Source of transactions consumed by [`EventsReader`], [`EventsReader::client`] by default
+#[async_trait]
+pub trait TransactionSource: Send + Sync {
+    async fn get_transaction(
+        &self,
+        signature: SolanaSignature,
+        config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta>;
+}
+
+#[async_trait]
+impl TransactionSource for RpcClient {
+    async fn get_transaction(
+        &self,
+        signature: SolanaSignature,
+        config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta> {
+        Ok(self
+            .bind_transaction_instructions_logs_with_config(signature, config)
+            .await?)
+    }
+}
+
+/// Source of signatures for resync, gap & reorg detection of [`EventsReader`],
+/// [`EventsReader::client`] by default
+#[async_trait]
+pub trait SignatureSource: Send + Sync {
+    async fn get_slot(&self, commitment_config: CommitmentConfig) -> Result<Slot>;
+
+    /// Signatures of transactions with `address`, newest first
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>>;
+
+    /// Statuses searched in whole history, `None` for unknown signature
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[SolanaSignature],
+    ) -> Result<Vec<Option<TransactionStatus>>>;
+
+    /// Hash of block of `slot`
+    async fn get_blockhash(&self, slot: Slot, commitment_config: CommitmentConfig) -> Result<Hash>;
+}
+
+#[async_trait]
+impl SignatureSource for RpcClient {
+    async fn get_slot(&self, commitment_config: CommitmentConfig) -> Result<Slot> {
+        Ok(self.get_slot_with_commitment(commitment_config).await?)
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        Ok(self
+            .get_signatures_for_address_with_config(address, config)
+            .await?)
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[SolanaSignature],
+    ) -> Result<Vec<Option<TransactionStatus>>> {
+        Ok(self
+            .get_signature_statuses_with_history(signatures)
+            .await?
+            .value)
+    }
+
+    async fn get_blockhash(&self, slot: Slot, commitment_config: CommitmentConfig) -> Result<Hash> {
+        let block = self
+            .get_block_with_config(
+                slot,
+                RpcBlockConfig {
+                    transaction_details: Some(TransactionDetails::None),
+                    rewards: Some(false),
+                    commitment: Some(commitment_config),
+                    max_supported_transaction_version: Some(0),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        block.blockhash.parse().map_err(|err| {
+            ClientError::from(ClientErrorKind::Custom(format!(
+                "Invalid blockhash {} of {slot} slot: {err}",
+                block.blockhash
+            )))
+            .into()
+        })
+    }
+}
+
+/// Source of live transactions logs of [`EventsReader`], [`EventsReader::pubsub_client`]
+/// by default
+#[async_trait]
+pub trait LogsSource: Send + Sync {
+    async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> Result<BoxStream<'_, Response<RpcLogsResponse>>>;
+}
+
+#[async_trait]
+impl LogsSource for PubsubClient {
+    async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> Result<BoxStream<'_, Response<RpcLogsResponse>>> {
+        let (stream, _unsubscribe) = PubsubClient::logs_subscribe(self, filter, config)
+            .await
+            .map_err(|err| Error::WebsocketError(err.to_string()))?;
+        Ok(stream)
+    }
+}
+
+/// Live subscription of [`EventsReader`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum LiveSource {
+    /// `logsSubscribe`, transactions are requested by `getTransaction` if `event_consumer`
+    /// needs them
+    #[default]
+    Logs,
+    /// `blockSubscribe` of every program, transactions with meta are parsed right from
+    /// notifications, without `getTransaction` & `event_consumer`. Available on RPCs with
+    /// `--rpc-pubsub-enable-block-subscription` only, `logs_filter` is ignored
+    Blocks,
+    /// No websocket subscription, live transactions with meta are pushed by
+    /// [`EventsReader::ingestor`], e.g. from webhooks of managed RPC (see
+    /// [`crate::webhook_ingest`])
+    Ingest,
+}
+
+/// Capacity of channel of [`TransactionIngestor`], ingestion waits while it's full
+pub const INGEST_CHANNEL_CAPACITY: usize = 1024;
+
+/// Channel of transactions pushed into [`EventsReader`], receiver is taken by running reader
+pub(super) struct IngestChannel {
+    pub(super) sender: tokio::sync::mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>,
+    receiver: Mutex<Option<tokio::sync::mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>>>,
+}
+
+impl Default for IngestChannel {
+    fn default() -> Self {
+        let (sender, receiver) = tokio::sync::mpsc::channel(INGEST_CHANNEL_CAPACITY);
+        Self {
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+}
+
+impl IngestChannel {
+    pub(super) fn take_receiver(
+        &self,
+    ) -> Option<tokio::sync::mpsc::Receiver<EncodedConfirmedTransactionWithStatusMeta>> {
+        self.receiver.lock().expect("Lock poisoned").take()
+    }
+}
+
+/// Pushes transactions with meta (as returned by `getTransaction`) into [`EventsReader`],
+/// which consumes them by programs invoked by transaction, as live ones
+#[derive(Debug, Clone)]
+pub struct TransactionIngestor {
+    pub(super) sender: tokio::sync::mpsc::Sender<EncodedConfirmedTransactionWithStatusMeta>,
+}
+
+impl TransactionIngestor {
+    /// Waits while channel is full, fails only if reader is dropped. Transaction must be in
+    /// binary encoding
+    pub async fn ingest(
+        &self,
+        transaction: EncodedConfirmedTransactionWithStatusMeta,
+    ) -> result::Result<
+        (),
+        tokio::sync::mpsc::error::SendError<EncodedConfirmedTransactionWithStatusMeta>,
+    > {
+        self.sender.send(transaction).await
+    }
+}
+
+/// Source of live blocks of [`EventsReader`] with [`LiveSource::Blocks`],
+/// [`EventsReader::pubsub_client`] by default
+#[async_trait]
+pub trait BlocksSource: Send + Sync {
+    async fn block_subscribe(
+        &self,
+        filter: RpcBlockSubscribeFilter,
+        config: RpcBlockSubscribeConfig,
+    ) -> Result<BoxStream<'_, Response<RpcBlockUpdate>>>;
+}
+
+#[async_trait]
+impl BlocksSource for PubsubClient {
+    async fn block_subscribe(
+        &self,
+        filter: RpcBlockSubscribeFilter,
+        config: RpcBlockSubscribeConfig,
+    ) -> Result<BoxStream<'_, Response<RpcBlockUpdate>>> {
+        let (stream, _unsubscribe) = PubsubClient::block_subscribe(self, filter, Some(config))
+            .await
+            .map_err(|err| Error::WebsocketError(err.to_string()))?;
+        Ok(stream)
+    }
+}
@@ -0,0 +1,882 @@
+//! Ready-made [`PassEvent`](crate::event_reader_service::PassEvent) implementations,
+//! that deliver raw events read by [`crate::event_reader_service`] into external systems
+
+#[cfg(feature = "nats")]
+pub mod nats {
+    use std::{collections::HashMap, sync::Arc};
+
+    use anchor_lang::Discriminator;
+    use async_trait::async_trait;
+    use tracing::*;
+
+    use crate::{
+        event_reader_service::PassEvent,
+        instruction_parser::{split_discriminator, DISCRIMINATOR_SIZE},
+        transaction_parser::Pubkey,
+    };
+
+    /// Subject template used by [`NatsEventRecipient`] by default
+    pub const DEFAULT_SUBJECT_TEMPLATE: &str = "events.{program_id}.{event_name}";
+
+    pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum Error {
+        #[error("Event data too short for discriminator: {0} bytes")]
+        TruncatedEvent(usize),
+        #[error("Error while publish event: {0}")]
+        Publish(BoxError),
+    }
+
+    /// Acknowledge of message persisted by JetStream
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct PublishAck {
+        pub stream: String,
+        pub sequence: u64,
+    }
+
+    /// Publisher into NATS JetStream, used by [`NatsEventRecipient`]
+    ///
+    /// Every `async-nats` version with JetStream requires `zeroize >= 1.4`, which conflicts
+    /// with `zeroize < 1.4` of solana 1.17, so the client isn't a dependency of this crate.
+    /// Implement it over `async_nats::jetstream::Context` in a workspace with the same
+    /// `[patch.crates-io]` of `curve25519-dalek` & `aes-gcm-siv` as solana itself:
+    ///
+    /// ```ignore
+    /// #[async_trait]
+    /// impl JetStreamPublish for Publisher {
+    ///     async fn publish(&self, subject: String, payload: Vec<u8>) -> Result<PublishAck, BoxError> {
+    ///         let ack = self.0.publish(subject, payload.into()).await?.await?;
+    ///         Ok(PublishAck { stream: ack.stream, sequence: ack.sequence })
+    ///     }
+    /// }
+    /// ```
+    #[async_trait]
+    pub trait JetStreamPublish: Send + Sync {
+        /// Publish `payload` into `subject` and wait for acknowledge of server
+        async fn publish(&self, subject: String, payload: Vec<u8>) -> Result<PublishAck, BoxError>;
+    }
+
+    /// [`PassEvent`] implementation over NATS JetStream
+    ///
+    /// Each event published into subject rendered from `subject_template`, where
+    /// `{program_id}` replaced by program id and `{event_name}` by name registered
+    /// for event discriminator (base58 of discriminator if name not registered).
+    /// [`PassEvent::pass_event`] returns only after server acknowledges publish
+    pub struct NatsEventRecipient {
+        publisher: Arc<dyn JetStreamPublish>,
+        program_id: Pubkey,
+        subject_template: String,
+        event_names: HashMap<[u8; DISCRIMINATOR_SIZE], String>,
+    }
+
+    impl NatsEventRecipient {
+        pub fn new(publisher: Arc<dyn JetStreamPublish>, program_id: Pubkey) -> Self {
+            Self {
+                publisher,
+                program_id,
+                subject_template: DEFAULT_SUBJECT_TEMPLATE.to_owned(),
+                event_names: HashMap::new(),
+            }
+        }
+
+        pub fn with_subject_template(mut self, subject_template: impl Into<String>) -> Self {
+            self.subject_template = subject_template.into();
+            self
+        }
+
+        /// Register `{event_name}` for anchor event `E`
+        pub fn with_event<E: Discriminator>(self, event_name: impl Into<String>) -> Self {
+            self.with_event_name(E::discriminator(), event_name)
+        }
+
+        /// Register `{event_name}` for events with `discriminator`
+        pub fn with_event_name(
+            mut self,
+            discriminator: [u8; DISCRIMINATOR_SIZE],
+            event_name: impl Into<String>,
+        ) -> Self {
+            self.event_names.insert(discriminator, event_name.into());
+            self
+        }
+
+        /// Render subject for the event with `discriminator`
+        pub fn subject(&self, discriminator: &[u8]) -> String {
+            let event_name = <[u8; DISCRIMINATOR_SIZE]>::try_from(discriminator)
+                .ok()
+                .and_then(|discriminator| self.event_names.get(&discriminator).cloned())
+                .unwrap_or_else(|| bs58::encode(discriminator).into_string());
+
+            self.subject_template
+                .replace("{program_id}", &self.program_id.to_string())
+                .replace("{event_name}", &event_name)
+        }
+    }
+
+    #[async_trait]
+    impl PassEvent for NatsEventRecipient {
+        type Error = Error;
+
+        async fn pass_event(&self, raw_event: Vec<u8>) -> Result<(), Self::Error> {
+            let (discriminator, _) =
+                split_discriminator(&raw_event).ok_or(Error::TruncatedEvent(raw_event.len()))?;
+            let subject = self.subject(discriminator);
+
+            let ack = self
+                .publisher
+                .publish(subject.clone(), raw_event)
+                .await
+                .map_err(Error::Publish)?;
+            debug!(
+                "Event published into {subject}, stream: {}, sequence: {}",
+                ack.stream, ack.sequence
+            );
+
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod nats_test {
+        use std::sync::Mutex;
+
+        use super::*;
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(String, Vec<u8>)>>);
+
+        #[async_trait]
+        impl JetStreamPublish for Recorder {
+            async fn publish(
+                &self,
+                subject: String,
+                payload: Vec<u8>,
+            ) -> Result<PublishAck, BoxError> {
+                let mut published = self.0.lock().unwrap();
+                published.push((subject, payload));
+                Ok(PublishAck {
+                    stream: "events".to_owned(),
+                    sequence: published.len() as u64,
+                })
+            }
+        }
+
+        #[tokio::test]
+        async fn test_nats_recipient() {
+            let program_id = Pubkey::new_unique();
+            let publisher = Arc::new(Recorder::default());
+            let recipient = NatsEventRecipient::new(publisher.clone(), program_id)
+                .with_event_name([1; 8], "Swap");
+
+            let (swap, other) = ([[1; 8], [0; 8]].concat(), [2; 8].to_vec());
+            recipient.pass_event(swap.clone()).await.unwrap();
+            recipient.pass_event(other.clone()).await.unwrap();
+            assert!(matches!(
+                recipient.pass_event(vec![1; 7]).await,
+                Err(Error::TruncatedEvent(7))
+            ));
+
+            assert_eq!(
+                *publisher.0.lock().unwrap(),
+                [
+                    (format!("events.{program_id}.Swap"), swap),
+                    (
+                        format!("events.{program_id}.{}", bs58::encode([2; 8]).into_string()),
+                        other
+                    ),
+                ]
+            );
+        }
+    }
+}
+
+#[cfg(feature = "webhook")]
+pub mod webhook {
+    use std::{
+        num::NonZeroUsize,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use async_trait::async_trait;
+    use hmac::{Hmac, Mac};
+    use reqwest::{header::CONTENT_TYPE, Client, StatusCode, Url};
+    use serde::Serialize;
+    use sha2::Sha256;
+    use tokio::sync::Semaphore;
+    use tracing::*;
+
+    use crate::{
+        event_reader_service::PassEvent, instruction_parser::split_discriminator,
+        transaction_parser::Pubkey,
+    };
+
+    pub const SIGNATURE_HEADER: &str = "X-Signature";
+    pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum Error {
+        #[error("Event data too short for discriminator: {0} bytes")]
+        TruncatedEvent(usize),
+        #[error(transparent)]
+        Serialize(#[from] serde_json::Error),
+        #[error("Invalid HMAC secret: {0}")]
+        InvalidSecret(String),
+        #[error(transparent)]
+        Request(#[from] reqwest::Error),
+        #[error("Webhook responded with {0} status")]
+        Status(StatusCode),
+    }
+
+    /// Body of request sent by [`WebhookEventRecipient`]
+    #[derive(Debug, Serialize)]
+    pub struct WebhookPayload {
+        pub program_id: String,
+        /// base58 of event discriminator
+        pub discriminator: String,
+        /// base64 of the whole raw event (with discriminator)
+        pub data: String,
+    }
+
+    #[derive(Debug, Clone, derive_builder::Builder)]
+    pub struct WebhookConfig {
+        /// Secret for `HMAC-SHA256` signature of `{timestamp}.{body}`, if `None` - the
+        /// requests are not signed
+        #[builder(default)]
+        pub secret: Option<Vec<u8>>,
+        /// Count of attempts including the first one
+        #[builder(
+            setter(custom),
+            field(
+                type = "Option<usize>",
+                build = "NonZeroUsize::new(self.attempts_count.unwrap_or(5)).ok_or_else(|| \
+                         WebhookConfigBuilderError::ValidationError(\
+                         \"`attempts_count` must be positive\".to_owned()))?"
+            )
+        )]
+        pub attempts_count: NonZeroUsize,
+        /// Timeout before second attempt, doubled for each next one
+        #[builder(default = "Duration::from_millis(500)")]
+        pub initial_backoff: Duration,
+        #[builder(default = "Duration::from_secs(30)")]
+        pub max_backoff: Duration,
+        /// Maximum number of requests in flight at the same time
+        #[builder(
+            setter(custom),
+            field(
+                type = "Option<usize>",
+                build = "NonZeroUsize::new(self.concurrency_limit.unwrap_or(16)).ok_or_else(|| \
+                         WebhookConfigBuilderError::ValidationError(\
+                         \"`concurrency_limit` must be positive\".to_owned()))?"
+            )
+        )]
+        pub concurrency_limit: NonZeroUsize,
+    }
+
+    impl WebhookConfigBuilder {
+        /// `5` by default, must be positive
+        pub fn attempts_count(&mut self, attempts_count: usize) -> &mut Self {
+            self.attempts_count = Some(attempts_count);
+            self
+        }
+
+        /// `16` by default, must be positive
+        pub fn concurrency_limit(&mut self, concurrency_limit: usize) -> &mut Self {
+            self.concurrency_limit = Some(concurrency_limit);
+            self
+        }
+    }
+
+    impl Default for WebhookConfig {
+        fn default() -> Self {
+            WebhookConfigBuilder::default()
+                .build()
+                .expect("All fields have default values")
+        }
+    }
+
+    /// [`PassEvent`] implementation that POSTs [`WebhookPayload`] as JSON to `url`
+    ///
+    /// If `secret` is provided, the `X-Timestamp` header contains unix timestamp in
+    /// seconds and `X-Signature` contains `sha256=<hex of HMAC-SHA256("{timestamp}.{body}")>`.
+    /// Network errors, `429` and `5xx` responses are retried with exponential backoff
+    pub struct WebhookEventRecipient {
+        client: Client,
+        url: Url,
+        program_id: Pubkey,
+        config: WebhookConfig,
+        concurrency: Arc<Semaphore>,
+    }
+
+    impl WebhookEventRecipient {
+        pub fn new(url: Url, program_id: Pubkey, config: WebhookConfig) -> Self {
+            Self::with_client(Client::new(), url, program_id, config)
+        }
+
+        pub fn with_client(
+            client: Client,
+            url: Url,
+            program_id: Pubkey,
+            config: WebhookConfig,
+        ) -> Self {
+            Self {
+                client,
+                url,
+                program_id,
+                concurrency: Arc::new(Semaphore::new(config.concurrency_limit.get())),
+                config,
+            }
+        }
+
+        /// Hex of `HMAC-SHA256("{timestamp}.{body}")`
+        pub fn sign(secret: &[u8], timestamp: u64, body: &[u8]) -> Result<String, Error> {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                .map_err(|err| Error::InvalidSecret(err.to_string()))?;
+            mac.update(timestamp.to_string().as_bytes());
+            mac.update(b".");
+            mac.update(body);
+            Ok(hex::encode(mac.finalize().into_bytes()))
+        }
+
+        async fn send(&self, body: &[u8]) -> Result<(), Error> {
+            let mut request = self
+                .client
+                .post(self.url.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.to_vec());
+
+            if let Some(secret) = self.config.secret.as_ref() {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                request = request
+                    .header(TIMESTAMP_HEADER, timestamp.to_string())
+                    .header(
+                        SIGNATURE_HEADER,
+                        format!("sha256={}", Self::sign(secret, timestamp, body)?),
+                    );
+            }
+
+            let status = request.send().await?.status();
+            if status.is_success() {
+                Ok(())
+            } else {
+                Err(Error::Status(status))
+            }
+        }
+    }
+
+    fn is_retryable(err: &Error) -> bool {
+        match err {
+            Error::Request(_) => true,
+            Error::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            _ => false,
+        }
+    }
+
+    #[async_trait]
+    impl PassEvent for WebhookEventRecipient {
+        type Error = Error;
+
+        async fn pass_event(&self, raw_event: Vec<u8>) -> Result<(), Self::Error> {
+            let (discriminator, _) =
+                split_discriminator(&raw_event).ok_or(Error::TruncatedEvent(raw_event.len()))?;
+            let body = serde_json::to_vec(&WebhookPayload {
+                program_id: self.program_id.to_string(),
+                discriminator: bs58::encode(discriminator).into_string(),
+                data: base64::encode(&raw_event),
+            })?;
+
+            let _permit = self
+                .concurrency
+                .acquire()
+                .await
+                .expect("Semaphore is never closed");
+
+            let mut attempts_count = self.config.attempts_count.get();
+            let mut backoff = self.config.initial_backoff;
+            loop {
+                match self.send(&body).await {
+                    Ok(()) => return Ok(()),
+                    Err(err) => {
+                        attempts_count -= 1;
+                        if attempts_count == 0 || !is_retryable(&err) {
+                            return Err(err);
+                        }
+
+                        warn!("Error while send webhook: {err:?}, attempts left: {attempts_count}");
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(self.config.max_backoff);
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod webhook_test {
+        use std::sync::Mutex;
+
+        use tokio::{
+            io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+            net::TcpListener,
+        };
+
+        use super::*;
+
+        /// Received request: headers in lowercase & body
+        type Request = (Vec<(String, String)>, Vec<u8>);
+
+        /// HTTP server responding with `statuses` in turn, returns its url & received requests
+        async fn serve(statuses: Vec<u16>) -> (Url, Arc<Mutex<Vec<Request>>>) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/hook", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+
+            tokio::spawn({
+                let requests = requests.clone();
+                async move {
+                    for status in statuses {
+                        let (stream, _) = listener.accept().await.unwrap();
+                        let mut stream = BufReader::new(stream);
+
+                        let mut headers = vec![];
+                        let mut line = String::new();
+                        stream.read_line(&mut line).await.unwrap();
+                        loop {
+                            line.clear();
+                            stream.read_line(&mut line).await.unwrap();
+                            match line.trim_end().split_once(':') {
+                                Some((name, value)) => {
+                                    headers.push((name.to_lowercase(), value.trim().to_owned()))
+                                }
+                                None => break,
+                            }
+                        }
+                        let length = headers
+                            .iter()
+                            .find(|(name, _)| name == "content-length")
+                            .map_or(0, |(_, value)| value.parse().unwrap());
+                        let mut body = vec![0; length];
+                        stream.read_exact(&mut body).await.unwrap();
+                        requests.lock().unwrap().push((headers, body));
+
+                        let response = format!(
+                            "HTTP/1.1 {status} Status\r\nContent-Length: 0\r\n\
+                             Connection: close\r\n\r\n"
+                        );
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    }
+                }
+            });
+
+            (url.parse().unwrap(), requests)
+        }
+
+        fn recipient(url: Url, program_id: Pubkey) -> WebhookEventRecipient {
+            WebhookEventRecipient::new(
+                url,
+                program_id,
+                WebhookConfigBuilder::default()
+                    .secret(Some(b"secret".to_vec()))
+                    .attempts_count(3)
+                    .initial_backoff(Duration::from_millis(1))
+                    .build()
+                    .unwrap(),
+            )
+        }
+
+        #[test]
+        fn test_concurrency_limit() {
+            assert!(WebhookConfigBuilder::default()
+                .concurrency_limit(0)
+                .build()
+                .is_err());
+            assert_eq!(WebhookConfig::default().concurrency_limit.get(), 16);
+        }
+
+        #[test]
+        fn test_attempts_count() {
+            assert!(WebhookConfigBuilder::default()
+                .attempts_count(0)
+                .build()
+                .is_err());
+            assert_eq!(WebhookConfig::default().attempts_count.get(), 5);
+        }
+
+        #[tokio::test]
+        async fn test_webhook_retries() {
+            let program_id = Pubkey::new_unique();
+            let event = [[1; 8], [2; 8]].concat();
+
+            let (url, requests) = serve(vec![503, 429, 200]).await;
+            recipient(url, program_id)
+                .pass_event(event.clone())
+                .await
+                .unwrap();
+
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 3);
+            let (headers, body) = &requests[2];
+            let payload = serde_json::from_slice::<serde_json::Value>(body).unwrap();
+            assert_eq!(payload["program_id"], program_id.to_string());
+            assert_eq!(payload["discriminator"], bs58::encode([1; 8]).into_string());
+            assert_eq!(payload["data"], base64::encode(&event));
+
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.clone())
+                    .unwrap()
+            };
+            let timestamp = header(TIMESTAMP_HEADER).parse().unwrap();
+            assert_eq!(
+                header(SIGNATURE_HEADER),
+                format!(
+                    "sha256={}",
+                    WebhookEventRecipient::sign(b"secret", timestamp, body).unwrap()
+                )
+            );
+        }
+
+        #[tokio::test]
+        async fn test_webhook_statuses() {
+            let event = [1; 8].to_vec();
+
+            let (url, requests) = serve(vec![400]).await;
+            let result = recipient(url, Pubkey::new_unique())
+                .pass_event(event.clone())
+                .await;
+            assert!(matches!(
+                result,
+                Err(Error::Status(StatusCode::BAD_REQUEST))
+            ));
+            assert_eq!(requests.lock().unwrap().len(), 1);
+
+            let (url, requests) = serve(vec![500, 502, 503]).await;
+            let result = recipient(url, Pubkey::new_unique()).pass_event(event).await;
+            assert!(matches!(
+                result,
+                Err(Error::Status(StatusCode::SERVICE_UNAVAILABLE))
+            ));
+            assert_eq!(requests.lock().unwrap().len(), 3);
+        }
+    }
+}
+
+/// Fan-out of events into several recipients by [`Routing`] policy, e.g. to feed a broker
+/// and a webhook by the same reader
+///
+/// ```
+/// use solana_events_parser::event_recipient::fanout::{FanOut, Routing};
+/// # use solana_events_parser::event_reader_service::PassEvent;
+/// # struct Sink;
+/// # #[async_trait::async_trait]
+/// # impl PassEvent for Sink {
+/// #     type Error = ();
+/// #     async fn pass_event(&self, _: Vec<u8>) -> Result<(), ()> { Ok(()) }
+/// # }
+/// # let (kafka, webhook) = (Sink, Sink);
+///
+/// const SWAP_EVENT: [u8; 8] = [64, 198, 205, 232, 38, 8, 113, 226];
+///
+/// // All events into `kafka`, only swaps into `webhook`
+/// let recipient = FanOut::new(Routing::ByEventType)
+///     .with_recipient("kafka", kafka)
+///     .with_recipient_for("webhook", webhook, [SWAP_EVENT]);
+/// assert_eq!(recipient.recipients_of(&SWAP_EVENT).count(), 2);
+/// ```
+pub mod fanout {
+    use std::{collections::HashSet, fmt};
+
+    use async_trait::async_trait;
+    use futures::future;
+    use solana_sdk::hash::hash;
+    use tracing::*;
+
+    use crate::{
+        event_reader_service::PassEvent,
+        instruction_parser::{split_discriminator, DISCRIMINATOR_SIZE},
+        transaction_parser::Signature,
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum Error {
+        /// Names & errors of failed recipients, the rest received event
+        #[error("Event isn't passed into recipients: {0:?}")]
+        Recipients(Vec<(String, String)>),
+    }
+
+    /// Which recipients of [`FanOut`] receive event
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum Routing {
+        /// Every recipient receives every event
+        #[default]
+        Broadcast,
+        /// Exactly one recipient, chosen by hash of [`RouteEvent::routing_key`], so events
+        /// of one transaction always go into the same recipient (e.g. partition)
+        HashBySignature,
+        /// Recipients registered for discriminator of event, and wildcard ones (registered
+        /// without events)
+        ByEventType,
+    }
+
+    /// Event routed by [`FanOut`]
+    pub trait RouteEvent: Clone + Send + Sync + 'static {
+        /// Key of [`Routing::HashBySignature`]
+        fn routing_key(&self) -> &[u8];
+
+        /// Key of [`Routing::ByEventType`], `None` for events shorter than discriminator
+        fn discriminator(&self) -> Option<&[u8]>;
+    }
+
+    /// Raw event is routed by its own bytes, as signature of transaction is unknown
+    impl RouteEvent for Vec<u8> {
+        fn routing_key(&self) -> &[u8] {
+            self
+        }
+
+        fn discriminator(&self) -> Option<&[u8]> {
+            split_discriminator(self).map(|(discriminator, _)| discriminator)
+        }
+    }
+
+    /// Raw event with signature of transaction emitted it, for
+    /// [`Routing::HashBySignature`] by signature
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SignedEvent {
+        pub signature: Signature,
+        pub raw_event: Vec<u8>,
+    }
+
+    impl RouteEvent for SignedEvent {
+        fn routing_key(&self) -> &[u8] {
+            self.signature.as_ref()
+        }
+
+        fn discriminator(&self) -> Option<&[u8]> {
+            self.raw_event.discriminator()
+        }
+    }
+
+    #[async_trait]
+    trait DynPassEvent<T>: Send + Sync {
+        async fn pass(&self, event: T) -> Result<(), String>;
+    }
+
+    #[async_trait]
+    impl<T, R> DynPassEvent<T> for R
+    where
+        T: Send + 'static,
+        R: PassEvent<T> + Send + Sync,
+        R::Error: fmt::Debug,
+    {
+        async fn pass(&self, event: T) -> Result<(), String> {
+            self.pass_event(event)
+                .await
+                .map_err(|err| format!("{err:?}"))
+        }
+    }
+
+    struct Route<T> {
+        name: String,
+        recipient: Box<dyn DynPassEvent<T>>,
+        /// `None` for wildcard recipient
+        events: Option<HashSet<[u8; DISCRIMINATOR_SIZE]>>,
+    }
+
+    /// [`PassEvent`] passing every event into recipients chosen by [`Routing`]
+    ///
+    /// Recipients are called concurrently. Event is passed into every chosen recipient even
+    /// if some of them fail, failures are returned together by [`Error::Recipients`]
+    pub struct FanOut<T = Vec<u8>> {
+        routing: Routing,
+        routes: Vec<Route<T>>,
+    }
+
+    impl<T: RouteEvent> FanOut<T> {
+        pub fn new(routing: Routing) -> Self {
+            Self {
+                routing,
+                routes: vec![],
+            }
+        }
+
+        /// Add wildcard recipient, receiving events of any type
+        pub fn with_recipient<R>(mut self, name: impl Into<String>, recipient: R) -> Self
+        where
+            R: PassEvent<T> + Send + Sync + 'static,
+            R::Error: fmt::Debug,
+        {
+            self.routes.push(Route {
+                name: name.into(),
+                recipient: Box::new(recipient),
+                events: None,
+            });
+            self
+        }
+
+        /// Add recipient of events with `discriminators` only, for [`Routing::ByEventType`].
+        /// Other policies ignore `discriminators`
+        pub fn with_recipient_for<R>(
+            mut self,
+            name: impl Into<String>,
+            recipient: R,
+            discriminators: impl IntoIterator<Item = [u8; DISCRIMINATOR_SIZE]>,
+        ) -> Self
+        where
+            R: PassEvent<T> + Send + Sync + 'static,
+            R::Error: fmt::Debug,
+        {
+            self.routes.push(Route {
+                name: name.into(),
+                recipient: Box::new(recipient),
+                events: Some(discriminators.into_iter().collect()),
+            });
+            self
+        }
+
+        /// Names of recipients of event with `discriminator` by [`Routing::ByEventType`]
+        pub fn recipients_of<'s>(
+            &'s self,
+            discriminator: &'s [u8],
+        ) -> impl Iterator<Item = &'s str> {
+            self.routes
+                .iter()
+                .filter(move |route| route.accepts(Some(discriminator)))
+                .map(|route| route.name.as_str())
+        }
+
+        fn routes_of(&self, event: &T) -> Vec<&Route<T>> {
+            match self.routing {
+                Routing::Broadcast => self.routes.iter().collect(),
+                Routing::HashBySignature if self.routes.is_empty() => vec![],
+                Routing::HashBySignature => {
+                    let hash = hash(event.routing_key()).to_bytes();
+                    let index = u64::from_le_bytes(hash[..8].try_into().expect("Hash is 32 bytes"))
+                        % self.routes.len() as u64;
+                    vec![&self.routes[index as usize]]
+                }
+                Routing::ByEventType => self
+                    .routes
+                    .iter()
+                    .filter(|route| route.accepts(event.discriminator()))
+                    .collect(),
+            }
+        }
+    }
+
+    impl<T> Route<T> {
+        fn accepts(&self, discriminator: Option<&[u8]>) -> bool {
+            match (self.events.as_ref(), discriminator) {
+                (None, _) => true,
+                (Some(events), Some(discriminator)) => {
+                    <[u8; DISCRIMINATOR_SIZE]>::try_from(discriminator)
+                        .is_ok_and(|discriminator| events.contains(&discriminator))
+                }
+                (Some(_), None) => false,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<T: RouteEvent> PassEvent<T> for FanOut<T> {
+        type Error = Error;
+
+        async fn pass_event(&self, event: T) -> Result<(), Self::Error> {
+            let routes = self.routes_of(&event);
+            if routes.is_empty() {
+                debug!("No recipient for event, drop it");
+                return Ok(());
+            }
+
+            let results = future::join_all(
+                routes
+                    .iter()
+                    .map(|route| route.recipient.pass(event.clone())),
+            )
+            .await;
+            let failures = routes
+                .iter()
+                .zip(results)
+                .filter_map(|(route, result)| Some((route.name.clone(), result.err()?)))
+                .inspect(|(name, err)| error!("Error while pass event into {name}: {err}"))
+                .collect::<Vec<_>>();
+            match failures.is_empty() {
+                true => Ok(()),
+                false => Err(Error::Recipients(failures)),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod fanout_test {
+        use std::sync::{Arc, Mutex};
+
+        use super::*;
+
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<Vec<u8>>>>);
+
+        #[async_trait]
+        impl PassEvent for Recorder {
+            type Error = ();
+
+            async fn pass_event(&self, event: Vec<u8>) -> Result<(), ()> {
+                self.0.lock().unwrap().push(event);
+                Ok(())
+            }
+        }
+
+        struct Failing;
+
+        #[async_trait]
+        impl PassEvent for Failing {
+            type Error = &'static str;
+
+            async fn pass_event(&self, _: Vec<u8>) -> Result<(), Self::Error> {
+                Err("unavailable")
+            }
+        }
+
+        #[tokio::test]
+        async fn test_fanout() {
+            let (swap, other) = ([1; 8].to_vec(), [2; 8].to_vec());
+
+            let (all, swaps) = (Recorder::default(), Recorder::default());
+            let by_type = FanOut::new(Routing::ByEventType)
+                .with_recipient("all", all.clone())
+                .with_recipient_for("swaps", swaps.clone(), [[1; 8]]);
+            by_type.pass_event(swap.clone()).await.unwrap();
+            by_type.pass_event(other.clone()).await.unwrap();
+            assert_eq!(*all.0.lock().unwrap(), [swap.clone(), other.clone()]);
+            assert_eq!(*swaps.0.lock().unwrap(), vec![swap.clone()]);
+
+            let (first, second) = (Recorder::default(), Recorder::default());
+            let hashed = FanOut::new(Routing::HashBySignature)
+                .with_recipient("first", first.clone())
+                .with_recipient("second", second.clone());
+            for _ in 0..3 {
+                hashed.pass_event(swap.clone()).await.unwrap();
+            }
+            let counts = [
+                first.0.lock().unwrap().len(),
+                second.0.lock().unwrap().len(),
+            ];
+            assert!(counts == [3, 0] || counts == [0, 3]);
+
+            let broadcast = FanOut::new(Routing::Broadcast)
+                .with_recipient("all", all.clone())
+                .with_recipient("failing", Failing);
+            match broadcast.pass_event(other.clone()).await {
+                Err(Error::Recipients(failures)) => assert_eq!(failures[0].0, "failing"),
+                result => panic!("Unexpected {result:?}"),
+            }
+            assert_eq!(all.0.lock().unwrap().len(), 3);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Serialization of typed events before passing them into bytes sinks
+//! (e.g. [`crate::event_recipient`]). The format is chosen per sink: wrap the sink
+//! into [`Serialized`] with the needed [`EventSerializer`]
+
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::event_reader_service::PassEvent;
+
+/// Serialize event of type `T` into bytes
+pub trait EventSerializer<T> {
+    type Error: fmt::Debug;
+
+    fn serialize(&self, event: &T) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// [`EventSerializer`] into JSON with [`serde_json`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonSerializer;
+
+impl<T: serde::Serialize> EventSerializer<T> for JsonSerializer {
+    type Error = serde_json::Error;
+
+    fn serialize(&self, event: &T) -> Result<Vec<u8>, Self::Error> {
+        serde_json::to_vec(event)
+    }
+}
+
+/// [`EventSerializer`] with [`bincode`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BincodeSerializer;
+
+impl<T: serde::Serialize> EventSerializer<T> for BincodeSerializer {
+    type Error = bincode::Error;
+
+    fn serialize(&self, event: &T) -> Result<Vec<u8>, Self::Error> {
+        bincode::serialize(event)
+    }
+}
+
+/// [`EventSerializer`] with [`borsh`], the same format as anchor events use on-chain
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BorshSerializer;
+
+impl<T: borsh::BorshSerialize> EventSerializer<T> for BorshSerializer {
+    type Error = std::io::Error;
+
+    fn serialize(&self, event: &T) -> Result<Vec<u8>, Self::Error> {
+        event.try_to_vec()
+    }
+}
+
+/// [`EventSerializer`] into protobuf with [`prost`]
+#[cfg(feature = "protobuf")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProtobufSerializer;
+
+#[cfg(feature = "protobuf")]
+impl<T: prost::Message> EventSerializer<T> for ProtobufSerializer {
+    type Error = std::convert::Infallible;
+
+    fn serialize(&self, event: &T) -> Result<Vec<u8>, Self::Error> {
+        Ok(event.encode_to_vec())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SerializedError<S: fmt::Debug, R: fmt::Debug> {
+    #[error("Error while serialize event: {0:?}")]
+    Serialize(S),
+    #[error("Error while pass serialized event: {0:?}")]
+    Recipient(R),
+}
+
+/// Adapter that implements [`PassEvent<T>`] for bytes sink `R`
+/// by serialization of each event with `S`
+#[derive(Debug, Clone)]
+pub struct Serialized<R, S> {
+    pub recipient: R,
+    pub serializer: S,
+}
+
+impl<R, S> Serialized<R, S> {
+    pub fn new(recipient: R, serializer: S) -> Self {
+        Self {
+            recipient,
+            serializer,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, R, S> PassEvent<T> for Serialized<R, S>
+where
+    T: Send + Sync + 'static,
+    R: PassEvent + Send + Sync,
+    R::Error: fmt::Debug,
+    S: EventSerializer<T> + Send + Sync,
+{
+    type Error = SerializedError<S::Error, R::Error>;
+
+    async fn pass_event(&self, event: T) -> Result<(), Self::Error> {
+        let raw_event = self
+            .serializer
+            .serialize(&event)
+            .map_err(SerializedError::Serialize)?;
+        self.recipient
+            .pass_event(raw_event)
+            .await
+            .map_err(SerializedError::Recipient)
+    }
+}
@@ -0,0 +1,497 @@
+//! Flat tables of parsed transactions for columnar storages (Arrow, Parquet, ClickHouse,
+//! etc.), so backfills can land directly in data lakes
+//!
+//! Every [`TransactionParsedMeta`] is split into three tables with primitive columns only:
+//!
+//! | Table | Row per | Key |
+//! |---|---|---|
+//! | [`InstructionRow`] | instruction (top-level or CPI) | `(signature, program_id, call_index)` |
+//! | [`EventRow`] | `Program data:` log | `(signature, program_id, call_index, log_index)` |
+//! | [`TokenTransferRow`] | token balance change of wallet | `(signature, wallet, mint)` |
+//!
+//! Pubkeys & signatures are base58 strings, instruction data is base58 (same as RPC),
+//! event data is base64 (as logged). Rows are in order of execution.
+//!
+//! Arrow record batches & Parquet files of tables are built by [`arrow`] (`arrow` feature).
+//! Rows are `Serialize` as well, so they fit any serde based writer (e.g. CSV, NDJSON)
+
+use serde::{Deserialize, Serialize};
+
+use crate::transaction_parser::{
+    ProgramContext, ProgramLog, Signature, Slot, TransactionParsedMeta,
+};
+
+/// Version of tables layout, increased on incompatible changes of columns
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionRow {
+    pub signature: String,
+    pub slot: Slot,
+    pub block_time: Option<i64>,
+    pub program_id: String,
+    /// How many times `program_id` was called before in the transaction
+    pub call_index: u64,
+    /// `1` for top-level instruction
+    pub invoke_level: u32,
+    /// Index of top-level instruction in transaction message, `None` if position is unknown
+    pub instruction_index: Option<u64>,
+    /// Index in flattened list of inner instructions, `None` for top-level instruction
+    pub inner_index: Option<u64>,
+    pub parent_program_id: Option<String>,
+    pub parent_call_index: Option<u64>,
+    /// Base58 of instruction data
+    pub data: String,
+    /// Base58 of accounts, in order of instruction
+    pub accounts: Vec<String>,
+    /// Count of all logs of invocation, without logs of CPIs
+    pub logs_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventRow {
+    pub signature: String,
+    pub slot: Slot,
+    pub block_time: Option<i64>,
+    pub program_id: String,
+    pub call_index: u64,
+    pub invoke_level: u32,
+    /// Index of log among all logs of invocation
+    pub log_index: u64,
+    /// Base64 payload of `Program data:`
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTransferRow {
+    pub signature: String,
+    pub slot: Slot,
+    pub block_time: Option<i64>,
+    /// Token account
+    pub wallet: String,
+    pub owner: Option<String>,
+    pub mint: String,
+    /// Change of token account balance in base units, negative for outgoing transfer
+    pub amount_diff: i128,
+}
+
+/// Rows of one or many transactions, see [`Tables::push`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tables {
+    pub instructions: Vec<InstructionRow>,
+    pub events: Vec<EventRow>,
+    pub token_transfers: Vec<TokenTransferRow>,
+}
+
+impl Tables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rows of single transaction
+    pub fn of_transaction(signature: &Signature, meta: &TransactionParsedMeta) -> Self {
+        let mut tables = Self::new();
+        tables.push(signature, meta);
+        tables
+    }
+
+    /// Append rows of transaction
+    pub fn push(&mut self, signature: &Signature, meta: &TransactionParsedMeta) {
+        let signature = signature.to_string();
+
+        let mut contexts = meta.meta.keys().copied().collect::<Vec<_>>();
+        contexts.sort_by_key(|ctx| {
+            let position = meta.ix_positions.get(ctx);
+            (position.is_none(), position.copied(), *ctx)
+        });
+
+        for ctx in contexts {
+            let (ix, logs) = &meta.meta[&ctx];
+            let position = meta.ix_positions.get(&ctx);
+            let parent = meta.parent_ix.get(&ctx);
+            self.instructions.push(InstructionRow {
+                signature: signature.clone(),
+                slot: meta.slot,
+                block_time: meta.block_time,
+                program_id: ctx.program_id.to_string(),
+                call_index: ctx.program_call_index as u64,
+                invoke_level: ctx.invoke_level.get(),
+                instruction_index: position.map(|position| position.instruction_index as u64),
+                inner_index: position
+                    .and_then(|position| position.inner_index)
+                    .map(|inner_index| inner_index as u64),
+                parent_program_id: parent.map(|parent| parent.program_id.to_string()),
+                parent_call_index: parent.map(|parent| parent.program_call_index as u64),
+                data: bs58::encode(&ix.data).into_string(),
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|meta| meta.pubkey.to_string())
+                    .collect(),
+                logs_count: logs.len() as u64,
+            });
+            self.events.extend(event_rows(&signature, meta, &ctx, logs));
+        }
+
+        let mut token_transfers = meta
+            .token_balances_changes
+            .iter()
+            .map(|(wallet, diff)| TokenTransferRow {
+                signature: signature.clone(),
+                slot: meta.slot,
+                block_time: meta.block_time,
+                wallet: wallet.wallet_address.to_string(),
+                owner: wallet.wallet_owner.map(|owner| owner.to_string()),
+                mint: wallet.token_mint.to_string(),
+                amount_diff: *diff,
+            })
+            .collect::<Vec<_>>();
+        token_transfers
+            .sort_by(|a, b| (&a.wallet, &a.mint, &a.owner).cmp(&(&b.wallet, &b.mint, &b.owner)));
+        self.token_transfers.extend(token_transfers);
+    }
+
+    /// Count of rows in all tables
+    pub fn len(&self) -> usize {
+        self.instructions.len() + self.events.len() + self.token_transfers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take accumulated rows, e.g. to flush batch into file
+    pub fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+}
+
+fn event_rows<'l>(
+    signature: &'l str,
+    meta: &'l TransactionParsedMeta,
+    ctx: &'l ProgramContext,
+    logs: &'l [ProgramLog],
+) -> impl Iterator<Item = EventRow> + 'l {
+    logs.iter()
+        .enumerate()
+        .filter_map(move |(log_index, log)| match log {
+            ProgramLog::Data(data) => Some(EventRow {
+                signature: signature.to_owned(),
+                slot: meta.slot,
+                block_time: meta.block_time,
+                program_id: ctx.program_id.to_string(),
+                call_index: ctx.program_call_index as u64,
+                invoke_level: ctx.invoke_level.get(),
+                log_index: log_index as u64,
+                data: data.clone(),
+            }),
+            _ => None,
+        })
+}
+
+/// Arrow record batches of [`Tables`] & Parquet writer of them
+///
+/// Columns have names & order of row fields, optional fields are nullable, pubkeys &
+/// payloads are `Utf8`, `accounts` is `List<Utf8>`. Arrow has no 128-bit integer, so
+/// `amount_diff: i128` is `Decimal128(38, 0)`: zero scale keeps it exact integer, and 38
+/// digits (max precision of `Decimal128`) hold any difference of `u64` token amounts.
+/// Parquet stores it as `DECIMAL(38, 0)` of 16 bytes
+#[cfg(feature = "arrow")]
+pub mod arrow {
+    use std::{io::Write, sync::Arc};
+
+    use arrow_array::{
+        builder::{ListBuilder, StringBuilder},
+        Array, ArrayRef, Decimal128Array, Int64Array, RecordBatch, StringArray, UInt32Array,
+        UInt64Array,
+    };
+    use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+    use parquet::{arrow::ArrowWriter, errors::ParquetError, file::properties::WriterProperties};
+
+    use super::{EventRow, InstructionRow, Tables, TokenTransferRow};
+
+    /// Precision of `amount_diff` decimal, with zero scale
+    pub const AMOUNT_DIFF_PRECISION: u8 = 38;
+
+    pub fn instructions_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("signature", DataType::Utf8, false),
+            Field::new("slot", DataType::UInt64, false),
+            Field::new("block_time", DataType::Int64, true),
+            Field::new("program_id", DataType::Utf8, false),
+            Field::new("call_index", DataType::UInt64, false),
+            Field::new("invoke_level", DataType::UInt32, false),
+            Field::new("instruction_index", DataType::UInt64, true),
+            Field::new("inner_index", DataType::UInt64, true),
+            Field::new("parent_program_id", DataType::Utf8, true),
+            Field::new("parent_call_index", DataType::UInt64, true),
+            Field::new("data", DataType::Utf8, false),
+            Field::new("accounts", DataType::new_list(DataType::Utf8, true), false),
+            Field::new("logs_count", DataType::UInt64, false),
+        ]))
+    }
+
+    pub fn events_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("signature", DataType::Utf8, false),
+            Field::new("slot", DataType::UInt64, false),
+            Field::new("block_time", DataType::Int64, true),
+            Field::new("program_id", DataType::Utf8, false),
+            Field::new("call_index", DataType::UInt64, false),
+            Field::new("invoke_level", DataType::UInt32, false),
+            Field::new("log_index", DataType::UInt64, false),
+            Field::new("data", DataType::Utf8, false),
+        ]))
+    }
+
+    pub fn token_transfers_schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("signature", DataType::Utf8, false),
+            Field::new("slot", DataType::UInt64, false),
+            Field::new("block_time", DataType::Int64, true),
+            Field::new("wallet", DataType::Utf8, false),
+            Field::new("owner", DataType::Utf8, true),
+            Field::new("mint", DataType::Utf8, false),
+            Field::new(
+                "amount_diff",
+                DataType::Decimal128(AMOUNT_DIFF_PRECISION, 0),
+                false,
+            ),
+        ]))
+    }
+
+    /// Column of `value` of every row
+    fn column<'r, R, T, A>(rows: &'r [R], value: impl Fn(&'r R) -> T) -> ArrayRef
+    where
+        A: Array + From<Vec<T>> + 'static,
+    {
+        Arc::new(A::from(rows.iter().map(value).collect::<Vec<_>>()))
+    }
+
+    pub fn instructions_batch(rows: &[InstructionRow]) -> Result<RecordBatch, ArrowError> {
+        let mut accounts = ListBuilder::new(StringBuilder::new());
+        for row in rows {
+            accounts.append_value(row.accounts.iter().map(Some));
+        }
+        RecordBatch::try_new(
+            instructions_schema(),
+            vec![
+                column::<_, _, StringArray>(rows, |row| row.signature.as_str()),
+                column::<_, _, UInt64Array>(rows, |row| row.slot),
+                column::<_, _, Int64Array>(rows, |row| row.block_time),
+                column::<_, _, StringArray>(rows, |row| row.program_id.as_str()),
+                column::<_, _, UInt64Array>(rows, |row| row.call_index),
+                column::<_, _, UInt32Array>(rows, |row| row.invoke_level),
+                column::<_, _, UInt64Array>(rows, |row| row.instruction_index),
+                column::<_, _, UInt64Array>(rows, |row| row.inner_index),
+                column::<_, _, StringArray>(rows, |row| row.parent_program_id.as_deref()),
+                column::<_, _, UInt64Array>(rows, |row| row.parent_call_index),
+                column::<_, _, StringArray>(rows, |row| row.data.as_str()),
+                Arc::new(accounts.finish()),
+                column::<_, _, UInt64Array>(rows, |row| row.logs_count),
+            ],
+        )
+    }
+
+    pub fn events_batch(rows: &[EventRow]) -> Result<RecordBatch, ArrowError> {
+        RecordBatch::try_new(
+            events_schema(),
+            vec![
+                column::<_, _, StringArray>(rows, |row| row.signature.as_str()),
+                column::<_, _, UInt64Array>(rows, |row| row.slot),
+                column::<_, _, Int64Array>(rows, |row| row.block_time),
+                column::<_, _, StringArray>(rows, |row| row.program_id.as_str()),
+                column::<_, _, UInt64Array>(rows, |row| row.call_index),
+                column::<_, _, UInt32Array>(rows, |row| row.invoke_level),
+                column::<_, _, UInt64Array>(rows, |row| row.log_index),
+                column::<_, _, StringArray>(rows, |row| row.data.as_str()),
+            ],
+        )
+    }
+
+    pub fn token_transfers_batch(rows: &[TokenTransferRow]) -> Result<RecordBatch, ArrowError> {
+        let amount_diff =
+            Decimal128Array::from(rows.iter().map(|row| row.amount_diff).collect::<Vec<_>>())
+                .with_precision_and_scale(AMOUNT_DIFF_PRECISION, 0)?;
+        // Out of precision values are readable by Arrow, but not by other Parquet readers
+        amount_diff.validate_decimal_precision(AMOUNT_DIFF_PRECISION)?;
+        RecordBatch::try_new(
+            token_transfers_schema(),
+            vec![
+                column::<_, _, StringArray>(rows, |row| row.signature.as_str()),
+                column::<_, _, UInt64Array>(rows, |row| row.slot),
+                column::<_, _, Int64Array>(rows, |row| row.block_time),
+                column::<_, _, StringArray>(rows, |row| row.wallet.as_str()),
+                column::<_, _, StringArray>(rows, |row| row.owner.as_deref()),
+                column::<_, _, StringArray>(rows, |row| row.mint.as_str()),
+                Arc::new(amount_diff),
+            ],
+        )
+    }
+
+    /// Parquet file per table of [`Tables`]
+    ///
+    /// Rows are buffered into row groups by [`ArrowWriter`], so [`ParquetWriter::close`] must
+    /// be called to flush them & write footers, files are invalid without it
+    pub struct ParquetWriter<W: Write + Send> {
+        instructions: ArrowWriter<W>,
+        events: ArrowWriter<W>,
+        token_transfers: ArrowWriter<W>,
+    }
+
+    impl<W: Write + Send> ParquetWriter<W> {
+        /// `properties` (e.g. compression) are applied to all files
+        pub fn try_new(
+            instructions: W,
+            events: W,
+            token_transfers: W,
+            properties: Option<WriterProperties>,
+        ) -> Result<Self, ParquetError> {
+            Ok(Self {
+                instructions: ArrowWriter::try_new(
+                    instructions,
+                    instructions_schema(),
+                    properties.clone(),
+                )?,
+                events: ArrowWriter::try_new(events, events_schema(), properties.clone())?,
+                token_transfers: ArrowWriter::try_new(
+                    token_transfers,
+                    token_transfers_schema(),
+                    properties,
+                )?,
+            })
+        }
+
+        /// Append rows of `tables`, e.g. taken by [`Tables::take`]
+        pub fn write(&mut self, tables: &Tables) -> Result<(), ParquetError> {
+            self.instructions
+                .write(&instructions_batch(&tables.instructions)?)?;
+            self.events.write(&events_batch(&tables.events)?)?;
+            self.token_transfers
+                .write(&token_transfers_batch(&tables.token_transfers)?)?;
+            Ok(())
+        }
+
+        /// Flush buffered rows & write footers, returns writers of instructions, events and
+        /// token transfers
+        pub fn close(self) -> Result<(W, W, W), ParquetError> {
+            Ok((
+                self.instructions.into_inner()?,
+                self.events.into_inner()?,
+                self.token_transfers.into_inner()?,
+            ))
+        }
+    }
+
+    #[cfg(test)]
+    mod arrow_test {
+        use std::{env, fs::File, process};
+
+        use arrow_array::cast::AsArray;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        use super::*;
+        use crate::fixtures::Fixture;
+
+        #[test]
+        fn test_parquet_roundtrip() {
+            let mut tables = Tables::new();
+            for name in ["cpi_event", "token_2022_transfer_checked"] {
+                let (fixture, signature) = Fixture::load(name);
+                tables.push(&signature, &fixture.parse().unwrap());
+            }
+            assert!(!tables.token_transfers.is_empty());
+
+            let directory =
+                env::temp_dir().join(format!("solana-events-parser-export-{}", process::id()));
+            std::fs::create_dir_all(&directory).unwrap();
+            let paths = ["instructions", "events", "token_transfers"]
+                .map(|table| directory.join(format!("{table}.parquet")));
+            let [instructions, events, token_transfers] =
+                paths.clone().map(|path| File::create(path).unwrap());
+
+            let mut writer =
+                ParquetWriter::try_new(instructions, events, token_transfers, None).unwrap();
+            writer.write(&tables).unwrap();
+            writer.close().unwrap();
+
+            // Rows of fixtures fit into single batch of reader
+            let [instructions, events, token_transfers] = paths.clone().map(|path| {
+                let mut reader =
+                    ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+                        .unwrap()
+                        .build()
+                        .unwrap();
+                let batch = reader.next().unwrap().unwrap();
+                assert!(reader.next().is_none());
+                batch
+            });
+            std::fs::remove_dir_all(&directory).unwrap();
+
+            assert_eq!(
+                instructions,
+                instructions_batch(&tables.instructions).unwrap()
+            );
+            assert_eq!(events, events_batch(&tables.events).unwrap());
+            assert_eq!(
+                token_transfers,
+                token_transfers_batch(&tables.token_transfers).unwrap()
+            );
+            let amount_diff = token_transfers
+                .column_by_name("amount_diff")
+                .unwrap()
+                .as_primitive::<arrow_array::types::Decimal128Type>();
+            assert_eq!(
+                amount_diff.values().to_vec(),
+                tables
+                    .token_transfers
+                    .iter()
+                    .map(|row| row.amount_diff)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod export_test {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+        let meta = fixture.parse().unwrap();
+
+        let tables = Tables::of_transaction(&signature, &meta);
+        assert_eq!(tables.instructions.len(), meta.meta.len());
+        assert_eq!(
+            tables.events.len(),
+            meta.meta
+                .values()
+                .flat_map(|(_, logs)| logs)
+                .filter(|log| matches!(log, ProgramLog::Data(_)))
+                .count()
+        );
+        assert_eq!(
+            tables.token_transfers.len(),
+            meta.token_balances_changes.len()
+        );
+
+        // Top-level instruction goes before its CPIs
+        let cpi = tables
+            .instructions
+            .iter()
+            .position(|row| row.parent_program_id.is_some())
+            .unwrap();
+        let parent = tables
+            .instructions
+            .iter()
+            .position(|row| {
+                Some(&row.program_id) == tables.instructions[cpi].parent_program_id.as_ref()
+                    && Some(row.call_index) == tables.instructions[cpi].parent_call_index
+            })
+            .unwrap();
+        assert!(parent < cpi);
+        assert_eq!(tables.instructions[cpi].invoke_level, 2);
+    }
+}
@@ -0,0 +1,168 @@
+//! `extern "C"` API over [`crate::log_parser::parse_events`] and [`crate::idl::Idl`]
+//!
+//! All functions take & return NUL-terminated UTF-8 JSON strings. The result is always an
+//! envelope: `{"ok": <value>}` or `{"error": "<message>"}`. Returned strings are owned by
+//! the caller and must be released by [`sep_free_string`]. C header is generated by
+//! `cbindgen --config cbindgen.toml --output include/solana_events_parser.h`, shared library
+//! is built by `cargo rustc --release --lib --crate-type cdylib --features ffi`
+
+use std::{
+    collections::BTreeMap,
+    ffi::{c_char, CStr, CString},
+    fmt::Display,
+    ptr,
+};
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    dto::{program_ctx_key, ProgramLogDto},
+    idl::Idl,
+    log_parser::parse_events,
+};
+
+fn envelope<T: Serialize, E: Display>(result: Result<T, E>) -> *mut c_char {
+    let json = match result.map(|ok| serde_json::to_value(ok)) {
+        Ok(Ok(ok)) => json!({ "ok": ok }),
+        Ok(Err(err)) => json!({ "error": err.to_string() }),
+        Err(err) => json!({ "error": err.to_string() }),
+    };
+    // serde_json escapes control characters, so there are no NUL bytes inside
+    CString::new(json.to_string())
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+/// `input` must be null or valid NUL-terminated string
+unsafe fn read_str<'s>(input: *const c_char, name: &str) -> Result<&'s str, String> {
+    if input.is_null() {
+        return Err(format!("`{name}` is null"));
+    }
+    CStr::from_ptr(input)
+        .to_str()
+        .map_err(|err| format!("`{name}` is not UTF-8: {err}"))
+}
+
+/// Bind logs of transaction, `logs_json` is JSON array of log lines
+///
+/// `ok` is object of logs (in format of [`ProgramLogDto`]) by
+/// `"<program_id>:<level>:<call_index>"` key
+///
+/// # Safety
+/// `logs_json` must be null or valid NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sep_parse_events(logs_json: *const c_char) -> *mut c_char {
+    envelope((|| {
+        let logs = serde_json::from_str::<Vec<String>>(read_str(logs_json, "logs_json")?)
+            .map_err(|err| format!("Error while parsing logs: {err}"))?;
+        let bound = parse_events(&logs).map_err(|err| format!("{err:?}"))?;
+        Ok::<_, String>(
+            bound
+                .iter()
+                .map(|(ctx, logs)| {
+                    (
+                        program_ctx_key(ctx),
+                        logs.iter().map(ProgramLogDto::from).collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<BTreeMap<_, _>>(),
+        )
+    })())
+}
+
+/// Parse anchor IDL json into handle for [`sep_idl_decode`], null on error.
+/// Handle must be released by [`sep_idl_free`]
+///
+/// # Safety
+/// `idl_json` must be null or valid NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sep_idl_new(idl_json: *const c_char) -> *mut Idl {
+    read_str(idl_json, "idl_json")
+        .ok()
+        .and_then(|json| Idl::from_json(json).ok())
+        .map(|idl| Box::into_raw(Box::new(idl)))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+/// `idl` must be null or returned by [`sep_idl_new`] and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn sep_idl_free(idl: *mut Idl) {
+    if !idl.is_null() {
+        drop(Box::from_raw(idl));
+    }
+}
+
+/// Decode base64 `data` of `kind` (`instruction`, `event` or `account`) by IDL
+///
+/// `ok` is `{"name": ..., "data": ...}` or `null` if discriminator is unknown for IDL
+///
+/// # Safety
+/// `idl` must be null or live handle from [`sep_idl_new`], `kind` & `data_base64` must be
+/// null or valid NUL-terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn sep_idl_decode(
+    idl: *const Idl,
+    kind: *const c_char,
+    data_base64: *const c_char,
+) -> *mut c_char {
+    envelope((|| {
+        let idl = idl.as_ref().ok_or("`idl` is null")?;
+        let data = base64::decode(read_str(data_base64, "data_base64")?)
+            .map_err(|err| format!("Error while decoding base64: {err}"))?;
+        match read_str(kind, "kind")? {
+            "instruction" => idl.decode_instruction(&data),
+            "event" => idl.decode_event(&data),
+            "account" => idl.decode_account(&data),
+            other => return Err(format!("Unknown kind: {other}")),
+        }
+        .map_err(|err| err.to_string())
+    })())
+}
+
+/// Release string returned by this library
+///
+/// # Safety
+/// `output` must be null or returned by this library and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn sep_free_string(output: *mut c_char) {
+    if !output.is_null() {
+        drop(CString::from_raw(output));
+    }
+}
+
+#[cfg(test)]
+mod ffi_test {
+    use super::*;
+
+    unsafe fn call(output: *mut c_char) -> serde_json::Value {
+        let json = serde_json::from_str(CStr::from_ptr(output).to_str().unwrap()).unwrap();
+        sep_free_string(output);
+        json
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let logs = CString::new(
+            r#"[
+                "Program 11111111111111111111111111111111 invoke [1]",
+                "Program log: hello",
+                "Program 11111111111111111111111111111111 success"
+            ]"#,
+        )
+        .unwrap();
+
+        let output = unsafe { call(sep_parse_events(logs.as_ptr())) };
+        assert_eq!(
+            output,
+            json!({ "ok": { "11111111111111111111111111111111:1:0": [
+                { "kind": "Log", "value": "hello" }
+            ]}})
+        );
+
+        let output = unsafe { call(sep_parse_events(ptr::null())) };
+        assert!(output["error"].is_string());
+    }
+}
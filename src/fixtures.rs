@@ -0,0 +1,224 @@
+//! Recorded transactions for offline parsing & golden tests
+//!
+//! Fixture is a JSON file with transaction as returned by `getTransaction` RPC method, so a
+//! failing transaction can be contributed as-is. Golden tests parse every fixture from
+//! `fixtures/transactions` and compare the result with `fixtures/expected` file with the
+//! same name, run them with `UPDATE_FIXTURES=1` to (re)write expected files. New fixtures
+//! are recorded by [`capture`] (`capture` CLI subcommand)
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::{
+    dto::TransactionParsedMetaDto,
+    transaction_parser::{
+        self, input_hash, parse_transaction, EncodedConfirmedTransactionWithStatusMeta, Hash,
+        RpcClient, Signature, TransactionParsedMeta, UiTransactionEncoding,
+    },
+};
+
+/// Encodings stored in [`Fixture::encodings`] by [`capture`]
+pub const CAPTURED_ENCODINGS: &[UiTransactionEncoding] = &[
+    UiTransactionEncoding::Base58,
+    UiTransactionEncoding::Json,
+    UiTransactionEncoding::JsonParsed,
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Signature(#[from] solana_sdk::signature::ParseSignatureError),
+    #[error(transparent)]
+    Parse(Box<transaction_parser::Error>),
+    #[error(transparent)]
+    Rpc(Box<solana_client::client_error::ClientError>),
+}
+
+impl From<solana_client::client_error::ClientError> for Error {
+    fn from(err: solana_client::client_error::ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
+
+impl From<transaction_parser::Error> for Error {
+    fn from(err: transaction_parser::Error) -> Self {
+        Self::Parse(Box::new(err))
+    }
+}
+
+/// Self-contained recorded transaction
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    /// Edge case covered by fixture
+    #[serde(default)]
+    pub description: String,
+    pub signature: String,
+    /// Transaction in `base64`, `base58` or `binary` encoding
+    pub transaction: EncodedConfirmedTransactionWithStatusMeta,
+    /// The same transaction in other encodings by encoding name (e.g. `jsonParsed`), not
+    /// used by parser, stored for reproducing encoding specific issues
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub encodings: BTreeMap<String, EncodedConfirmedTransactionWithStatusMeta>,
+}
+
+impl Fixture {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Load `fixtures/transactions/{name}.json` of this crate with its parsed signature
+    #[cfg(test)]
+    pub(crate) fn load(name: &str) -> (Self, Signature) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/transactions")
+            .join(format!("{name}.json"));
+        let fixture =
+            Self::from_file(&path).unwrap_or_else(|err| panic!("Can't load {path:?}: {err}"));
+        let signature = fixture
+            .signature
+            .parse()
+            .unwrap_or_else(|err| panic!("Wrong signature of {path:?}: {err}"));
+        (fixture, signature)
+    }
+
+    pub fn to_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(fs::write(path, serde_json::to_string_pretty(self)? + "\n")?)
+    }
+
+    /// Content address of fixture: [`input_hash`] of its transaction, the same as
+    /// [`ParseStamp::input_hash`](transaction_parser::ParseStamp::input_hash) of parse result
+    pub fn content_hash(&self) -> Result<Hash, Error> {
+        Ok(input_hash(self.signature.parse()?, &self.transaction)?)
+    }
+
+    pub fn parse(&self) -> Result<TransactionParsedMeta, Error> {
+        Ok(parse_transaction(
+            self.signature.parse::<Signature>()?,
+            &self.transaction,
+        )?)
+    }
+
+    /// Stable JSON of parse result: `{"ok": <TransactionParsedMetaDto>}` or
+    /// `{"error": "<error>"}`
+    pub fn golden_output(&self) -> Value {
+        match self.parse() {
+            Ok(parsed) => json!({ "ok": TransactionParsedMetaDto::from(parsed) }),
+            Err(err) => json!({ "error": format!("{err:?}") }),
+        }
+    }
+}
+
+/// Fetch transaction as [`Fixture`], in `base64` and [`CAPTURED_ENCODINGS`]
+///
+/// Only `base64` request is required, the other encodings are skipped on error (e.g. some
+/// providers reject `base58` for big transactions)
+pub async fn capture(
+    client: &RpcClient,
+    signature: Signature,
+    commitment_config: CommitmentConfig,
+    description: impl Into<String>,
+) -> Result<Fixture, Error> {
+    let get_transaction = |encoding| {
+        client.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(encoding),
+                max_supported_transaction_version: Some(0),
+                commitment: Some(commitment_config),
+            },
+        )
+    };
+
+    let transaction = get_transaction(UiTransactionEncoding::Base64).await?;
+
+    let mut encodings = BTreeMap::new();
+    for encoding in CAPTURED_ENCODINGS {
+        let name = serde_json::to_value(encoding)?
+            .as_str()
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("{encoding:?}"));
+        match get_transaction(*encoding).await {
+            Ok(transaction) => {
+                encodings.insert(name, transaction);
+            }
+            Err(err) => tracing::warn!("Error while fetch {signature} in {name} encoding: {err}"),
+        }
+    }
+
+    Ok(Fixture {
+        description: description.into(),
+        signature: signature.to_string(),
+        transaction,
+        encodings,
+    })
+}
+
+#[cfg(test)]
+mod fixtures_test {
+    use std::{env, path::PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn test_fixtures() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let update = env::var_os("UPDATE_FIXTURES").is_some();
+
+        let mut paths = fs::read_dir(root.join("transactions"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        assert!(!paths.is_empty());
+
+        let mut mismatched = vec![];
+        for path in paths {
+            let fixture = Fixture::from_file(&path).unwrap();
+            if let Ok(parsed) = fixture.parse() {
+                let signature = fixture.signature.parse().unwrap();
+                assert!(parsed.stamp.is_current_for(signature, &fixture.transaction));
+                assert_eq!(parsed.stamp.input_hash, fixture.content_hash().unwrap());
+            }
+            let output = fixture.golden_output();
+            let expected_path = root.join("expected").join(path.file_name().unwrap());
+
+            if update {
+                fs::write(
+                    &expected_path,
+                    serde_json::to_string_pretty(&output).unwrap() + "\n",
+                )
+                .unwrap();
+                continue;
+            }
+
+            let expected: Value =
+                serde_json::from_str(&fs::read_to_string(&expected_path).unwrap_or_else(|err| {
+                    panic!("Can't read {expected_path:?}: {err}, run with UPDATE_FIXTURES=1")
+                }))
+                .unwrap();
+            if expected != output {
+                mismatched.push((path, serde_json::to_string_pretty(&output).unwrap()));
+            }
+        }
+
+        assert!(
+            mismatched.is_empty(),
+            "{} mismatched fixtures, run with UPDATE_FIXTURES=1 if changes are expected:\n{}",
+            mismatched.len(),
+            mismatched
+                .iter()
+                .map(|(path, output)| format!("Fixture {path:?} output:\n{output}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+}
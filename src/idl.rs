@@ -0,0 +1,623 @@
+//! Runtime decoder of anchor instructions, events and accounts, based on the program
+//! IDL (anchor `0.29` JSON format), for the cases when generated rust types are not available
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use anchor_lang::{idl::IdlAccount as AnchorIdlAccount, AccountDeserialize};
+use flate2::read::ZlibDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use sha2::{Digest, Sha256};
+use solana_client::{client_error::ClientError, nonblocking::rpc_client::RpcClient};
+
+use crate::{
+    instruction_parser::{split_discriminator, DISCRIMINATOR_SIZE},
+    log_parser::ProgramLog,
+    transaction_parser::Pubkey,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Type `{0}` not defined in IDL")]
+    UndefinedType(String),
+    #[error("Unexpected end of data while decoding `{0}`")]
+    UnexpectedEof(String),
+    #[error("Invalid variant index {index} of `{name}` enum")]
+    InvalidVariant { name: String, index: u8 },
+    #[error("Invalid `{0}` value")]
+    InvalidValue(&'static str),
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error("IDL account of {0} program not found")]
+    IdlAccountNotFound(Pubkey),
+}
+
+/// Size of header of anchor IDL account: discriminator, authority & length of compressed IDL
+pub const IDL_ACCOUNT_HEADER_SIZE: usize = DISCRIMINATOR_SIZE + 32 + 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Idl {
+    pub version: String,
+    pub name: String,
+    #[serde(default)]
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<IdlTypeDefinition>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDefinition>,
+    #[serde(default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(default)]
+    pub errors: Vec<IdlErrorCode>,
+    #[serde(default)]
+    pub metadata: Option<IdlMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccountItem>,
+    #[serde(default)]
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IdlAccountItem {
+    /// Nested accounts struct
+    Accounts(IdlAccounts),
+    Account(IdlAccount),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlAccounts {
+    pub name: String,
+    pub accounts: Vec<IdlAccountItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlAccount {
+    pub name: String,
+    #[serde(default)]
+    pub is_mut: bool,
+    #[serde(default)]
+    pub is_signer: bool,
+    #[serde(default)]
+    pub is_optional: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    pub fields: Vec<IdlEventField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlEventField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+    #[serde(default)]
+    pub index: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlTypeDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefinitionTy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum IdlTypeDefinitionTy {
+    Struct { fields: Vec<IdlField> },
+    Enum { variants: Vec<IdlEnumVariant> },
+    Alias { value: IdlType },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Option<IdlEnumFields>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IdlEnumFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlErrorCode {
+    pub code: u32,
+    pub name: String,
+    #[serde(default)]
+    pub msg: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdlMetadata {
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    U128,
+    I128,
+    Bytes,
+    String,
+    PublicKey,
+    Defined(String),
+    Option(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+}
+
+/// Instruction, event or account decoded by [`Idl`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Decoded {
+    pub name: String,
+    pub data: Value,
+}
+
+/// First 8 bytes of `sha256("{namespace}:{name}")`, the way anchor calculates discriminators
+pub fn sighash(namespace: &str, name: &str) -> [u8; DISCRIMINATOR_SIZE] {
+    let hash = Sha256::digest(format!("{namespace}:{name}").as_bytes());
+    let mut discriminator = [0u8; DISCRIMINATOR_SIZE];
+    discriminator.copy_from_slice(&hash[..DISCRIMINATOR_SIZE]);
+    discriminator
+}
+
+/// IDL keeps instruction names in camelCase, but anchor calculates discriminator by
+/// snake_case name of the handler
+fn to_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(name.len() + 4);
+    for (index, ch) in chars.iter().enumerate() {
+        if ch.is_uppercase() && index > 0 {
+            let prev = chars[index - 1];
+            let is_next_lower = chars.get(index + 1).is_some_and(|next| next.is_lowercase());
+            if prev.is_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_uppercase() && is_next_lower)
+            {
+                result.push('_');
+            }
+        }
+        result.extend(ch.to_lowercase());
+    }
+    result
+}
+
+impl Idl {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Decode data of IDL account created by `anchor idl init`: header followed by zlib
+    /// compressed IDL JSON
+    pub fn from_onchain_account(data: &[u8]) -> Result<Self, Error> {
+        let header = AnchorIdlAccount::try_deserialize(&mut &data[..])
+            .map_err(|_| Error::InvalidValue("IDL account"))?;
+        let compressed = data
+            .get(IDL_ACCOUNT_HEADER_SIZE..)
+            .and_then(|data| data.get(..header.data_len as usize))
+            .ok_or_else(|| Error::UnexpectedEof("IDL account".to_owned()))?;
+
+        let mut json = String::new();
+        ZlibDecoder::new(compressed).read_to_string(&mut json)?;
+        Self::from_json(&json)
+    }
+
+    /// Program id from IDL metadata, if present
+    pub fn program_id(&self) -> Option<Pubkey> {
+        self.metadata.as_ref()?.address.as_ref()?.parse().ok()
+    }
+
+    pub fn instruction_discriminator(name: &str) -> [u8; DISCRIMINATOR_SIZE] {
+        sighash("global", &to_snake_case(name))
+    }
+
+    pub fn event_discriminator(name: &str) -> [u8; DISCRIMINATOR_SIZE] {
+        sighash("event", name)
+    }
+
+    pub fn account_discriminator(name: &str) -> [u8; DISCRIMINATOR_SIZE] {
+        sighash("account", name)
+    }
+
+    /// Decode instruction data. Returns `None` if discriminator is unknown for IDL
+    pub fn decode_instruction(&self, data: &[u8]) -> Result<Option<Decoded>, Error> {
+        let Some((discriminator, data)) = split_discriminator(data) else {
+            return Ok(None);
+        };
+        let Some(instruction) = self
+            .instructions
+            .iter()
+            .find(|ix| Self::instruction_discriminator(&ix.name).eq(discriminator))
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Decoded {
+            name: instruction.name.clone(),
+            data: Decoder::new(self, data).decode_fields(&instruction.args)?,
+        }))
+    }
+
+    /// Decode event data. Returns `None` if discriminator is unknown for IDL
+    pub fn decode_event(&self, data: &[u8]) -> Result<Option<Decoded>, Error> {
+        let Some((discriminator, data)) = split_discriminator(data) else {
+            return Ok(None);
+        };
+        let Some(event) = self
+            .events
+            .iter()
+            .find(|event| Self::event_discriminator(&event.name).eq(discriminator))
+        else {
+            return Ok(None);
+        };
+
+        let mut decoder = Decoder::new(self, data);
+        let mut fields = Map::new();
+        for field in event.fields.iter() {
+            fields.insert(field.name.clone(), decoder.decode(&field.ty)?);
+        }
+
+        Ok(Some(Decoded {
+            name: event.name.clone(),
+            data: Value::Object(fields),
+        }))
+    }
+
+    /// Decode account data. Returns `None` if discriminator is unknown for IDL
+    pub fn decode_account(&self, data: &[u8]) -> Result<Option<Decoded>, Error> {
+        let Some((discriminator, data)) = split_discriminator(data) else {
+            return Ok(None);
+        };
+        let Some(account) = self
+            .accounts
+            .iter()
+            .find(|account| Self::account_discriminator(&account.name).eq(discriminator))
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Decoded {
+            name: account.name.clone(),
+            data: Decoder::new(self, data).decode_definition(account)?,
+        }))
+    }
+
+    /// Decode event emitted by `emit!` into `Program data:` log
+    pub fn decode_program_log(&self, log: &ProgramLog) -> Result<Option<Decoded>, Error> {
+        match log {
+            ProgramLog::Data(data) => match base64::decode(data) {
+                Ok(data) => self.decode_event(&data),
+                Err(_) => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn find_type(&self, name: &str) -> Result<&IdlTypeDefinition, Error> {
+        self.types
+            .iter()
+            .chain(self.accounts.iter())
+            .find(|ty| ty.name.eq(name))
+            .ok_or_else(|| Error::UndefinedType(name.to_owned()))
+    }
+}
+
+/// Borsh decoder into [`serde_json::Value`]
+///
+/// Integers wider than 64 bits are represented as strings, `bytes` as base64,
+/// public keys as base58
+struct Decoder<'idl, 'data> {
+    idl: &'idl Idl,
+    data: &'data [u8],
+}
+
+macro_rules! decode_number {
+    ($decoder:expr, $ty:ty) => {{
+        let bytes = $decoder.take(std::mem::size_of::<$ty>(), stringify!($ty))?;
+        <$ty>::from_le_bytes(bytes.try_into().expect("Size checked in take"))
+    }};
+}
+
+impl<'idl, 'data> Decoder<'idl, 'data> {
+    fn new(idl: &'idl Idl, data: &'data [u8]) -> Self {
+        Self { idl, data }
+    }
+
+    fn take(&mut self, len: usize, what: &str) -> Result<&'data [u8], Error> {
+        if self.data.len() < len {
+            return Err(Error::UnexpectedEof(what.to_owned()));
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn decode_len(&mut self) -> Result<usize, Error> {
+        Ok(decode_number!(self, u32) as usize)
+    }
+
+    fn decode(&mut self, ty: &IdlType) -> Result<Value, Error> {
+        Ok(match ty {
+            IdlType::Bool => match decode_number!(self, u8) {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => return Err(Error::InvalidValue("bool")),
+            },
+            IdlType::U8 => Value::from(decode_number!(self, u8)),
+            IdlType::I8 => Value::from(decode_number!(self, i8)),
+            IdlType::U16 => Value::from(decode_number!(self, u16)),
+            IdlType::I16 => Value::from(decode_number!(self, i16)),
+            IdlType::U32 => Value::from(decode_number!(self, u32)),
+            IdlType::I32 => Value::from(decode_number!(self, i32)),
+            IdlType::U64 => Value::from(decode_number!(self, u64)),
+            IdlType::I64 => Value::from(decode_number!(self, i64)),
+            IdlType::U128 => Value::String(decode_number!(self, u128).to_string()),
+            IdlType::I128 => Value::String(decode_number!(self, i128).to_string()),
+            IdlType::F32 => Number::from_f64(decode_number!(self, f32) as f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            IdlType::F64 => Number::from_f64(decode_number!(self, f64))
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            IdlType::Bytes => {
+                let len = self.decode_len()?;
+                Value::String(base64::encode(self.take(len, "bytes")?))
+            }
+            IdlType::String => {
+                let len = self.decode_len()?;
+                Value::String(
+                    String::from_utf8(self.take(len, "string")?.to_vec())
+                        .map_err(|_| Error::InvalidValue("string"))?,
+                )
+            }
+            IdlType::PublicKey => {
+                Value::String(bs58::encode(self.take(32, "publicKey")?).into_string())
+            }
+            IdlType::Option(inner) => match decode_number!(self, u8) {
+                0 => Value::Null,
+                1 => self.decode(inner)?,
+                _ => return Err(Error::InvalidValue("option")),
+            },
+            IdlType::Vec(inner) => {
+                let len = self.decode_len()?;
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.decode(inner))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            IdlType::Array(inner, len) => Value::Array(
+                (0..*len)
+                    .map(|_| self.decode(inner))
+                    .collect::<Result<_, _>>()?,
+            ),
+            IdlType::Defined(name) => {
+                let definition = self.idl.find_type(name)?;
+                self.decode_definition(definition)?
+            }
+        })
+    }
+
+    fn decode_fields(&mut self, fields: &[IdlField]) -> Result<Value, Error> {
+        let mut result = Map::new();
+        for field in fields {
+            result.insert(field.name.clone(), self.decode(&field.ty)?);
+        }
+        Ok(Value::Object(result))
+    }
+
+    fn decode_definition(&mut self, definition: &IdlTypeDefinition) -> Result<Value, Error> {
+        match &definition.ty {
+            IdlTypeDefinitionTy::Struct { fields } => self.decode_fields(fields),
+            IdlTypeDefinitionTy::Alias { value } => self.decode(value),
+            IdlTypeDefinitionTy::Enum { variants } => {
+                let index = decode_number!(self, u8);
+                let variant =
+                    variants
+                        .get(index as usize)
+                        .ok_or_else(|| Error::InvalidVariant {
+                            name: definition.name.clone(),
+                            index,
+                        })?;
+
+                Ok(match &variant.fields {
+                    None => Value::String(variant.name.clone()),
+                    Some(IdlEnumFields::Named(fields)) => {
+                        let mut result = Map::new();
+                        result.insert(variant.name.clone(), self.decode_fields(fields)?);
+                        Value::Object(result)
+                    }
+                    Some(IdlEnumFields::Tuple(types)) => {
+                        let mut result = Map::new();
+                        result.insert(
+                            variant.name.clone(),
+                            Value::Array(
+                                types
+                                    .iter()
+                                    .map(|ty| self.decode(ty))
+                                    .collect::<Result<_, _>>()?,
+                            ),
+                        );
+                        Value::Object(result)
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Fetch IDL of `program_id` from its anchor IDL account, e.g. for programs without local
+/// IDL file. IDL without address in metadata gets `program_id` as address
+pub async fn fetch_onchain(rpc: &RpcClient, program_id: &Pubkey) -> Result<Idl, Error> {
+    let account = rpc
+        .get_account_with_commitment(&AnchorIdlAccount::address(program_id), rpc.commitment())
+        .await?
+        .value
+        .ok_or(Error::IdlAccountNotFound(*program_id))?;
+
+    let mut idl = Idl::from_onchain_account(&account.data)?;
+    if idl.program_id().is_none() {
+        idl.metadata = Some(IdlMetadata {
+            address: Some(program_id.to_string()),
+        });
+    }
+    Ok(idl)
+}
+
+#[cfg(test)]
+mod idl_test {
+    use std::io::Write;
+
+    use borsh::BorshSerialize;
+    use flate2::{write::ZlibEncoder, Compression};
+    use serde_json::json;
+
+    use super::*;
+
+    const IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "example",
+        "instructions": [{
+            "name": "setAuthority",
+            "accounts": [{ "name": "state", "isMut": true, "isSigner": false }],
+            "args": [
+                { "name": "authority", "type": "publicKey" },
+                { "name": "limit", "type": { "option": "u64" } },
+                { "name": "mode", "type": { "defined": "Mode" } }
+            ]
+        }],
+        "types": [{
+            "name": "Mode",
+            "type": {
+                "kind": "enum",
+                "variants": [{ "name": "Off" }, { "name": "On", "fields": [{ "name": "level", "type": "u8" }] }]
+            }
+        }],
+        "events": [{
+            "name": "Transferred",
+            "fields": [
+                { "name": "amount", "type": "u128", "index": false },
+                { "name": "memo", "type": "string", "index": false }
+            ]
+        }]
+    }"#;
+
+    #[test]
+    fn test_snake_case() {
+        assert_eq!(to_snake_case("setAuthority"), "set_authority");
+        assert_eq!(to_snake_case("initializeV2"), "initialize_v2");
+        assert_eq!(to_snake_case("initialize"), "initialize");
+    }
+
+    #[test]
+    fn test_decode_instruction() {
+        let idl = Idl::from_json(IDL).unwrap();
+        let authority = Pubkey::new_unique();
+
+        let mut data = Idl::instruction_discriminator("setAuthority").to_vec();
+        BorshSerialize::serialize(&authority, &mut data).unwrap();
+        BorshSerialize::serialize(&Some(10u64), &mut data).unwrap();
+        data.extend([1u8, 7u8]);
+
+        assert_eq!(
+            idl.decode_instruction(&data).unwrap(),
+            Some(Decoded {
+                name: "setAuthority".to_owned(),
+                data: json!({
+                    "authority": authority.to_string(),
+                    "limit": 10,
+                    "mode": { "On": { "level": 7 } },
+                }),
+            })
+        );
+        assert_eq!(idl.decode_instruction(&[0u8; 8]).unwrap(), None);
+        assert!(idl.decode_instruction(&data[..20]).is_err());
+    }
+
+    #[test]
+    fn test_decode_event() {
+        let idl = Idl::from_json(IDL).unwrap();
+
+        let mut data = Idl::event_discriminator("Transferred").to_vec();
+        BorshSerialize::serialize(&u128::MAX, &mut data).unwrap();
+        BorshSerialize::serialize(&"hello".to_owned(), &mut data).unwrap();
+
+        assert_eq!(
+            idl.decode_program_log(&ProgramLog::Data(base64::encode(&data)))
+                .unwrap(),
+            Some(Decoded {
+                name: "Transferred".to_owned(),
+                data: json!({ "amount": u128::MAX.to_string(), "memo": "hello" }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_from_onchain_account() {
+        let mut compressed = ZlibEncoder::new(Vec::new(), Compression::default());
+        compressed.write_all(IDL.as_bytes()).unwrap();
+        let compressed = compressed.finish().unwrap();
+
+        let mut data = <AnchorIdlAccount as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+        data.extend(Pubkey::new_unique().to_bytes());
+        data.extend((compressed.len() as u32).to_le_bytes());
+        data.extend(&compressed);
+        // Account is allocated with space for future IDL updates
+        data.extend([0; 64]);
+
+        let idl = Idl::from_onchain_account(&data).unwrap();
+        assert_eq!(idl.name, "example");
+        assert_eq!(idl.events.len(), 1);
+
+        assert!(Idl::from_onchain_account(&data[..IDL_ACCOUNT_HEADER_SIZE + 4]).is_err());
+        data[0] ^= 1;
+        assert!(Idl::from_onchain_account(&data).is_err());
+    }
+}
@@ -1,7 +1,12 @@
-use std::{collections::HashMap, fmt::Debug, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
 
 pub use solana_client::rpc_client::RpcClient;
-use solana_sdk::pubkey::ParsePubkeyError;
 pub use solana_sdk::{
     clock::UnixTimestamp,
     instruction::{AccountMeta, Instruction},
@@ -10,13 +15,18 @@ pub use solana_sdk::{
     signature::Signature,
     slot_history::Slot,
 };
+use solana_sdk::{pubkey::ParsePubkeyError, transaction::TransactionError};
 pub use solana_transaction_status::{
     option_serializer::OptionSerializer, EncodedTransactionWithStatusMeta, UiInstruction,
     UiTransactionEncoding,
 };
 use solana_transaction_status::{UiLoadedAddresses, UiTransactionStatusMeta};
 
-pub use crate::log_parser::{self, ProgramContext, ProgramLog};
+use crate::parse_options::FailurePolicy;
+pub use crate::{
+    log_parser::{self, ProgramContext, ProgramLog},
+    parse_options::ParseOptions,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -34,7 +44,18 @@ pub enum Error {
     PubkeyParseError(#[from] ParsePubkeyError),
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+/// Size of anchor discriminator prefix in instruction & event data
+pub const DISCRIMINATOR_SIZE: usize = 8;
+
+/// Split `data` into discriminator and payload.
+///
+/// Returns `None` if `data` is shorter than [`DISCRIMINATOR_SIZE`]; typed decoders
+/// report this case as [`crate::transaction_parser::DecodeError::TruncatedData`]
+pub fn split_discriminator(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    (data.len() >= DISCRIMINATOR_SIZE).then(|| data.split_at(DISCRIMINATOR_SIZE))
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
 pub struct InstructionContext {
     pub program_id: Pubkey,
     pub call_index: usize,
@@ -42,6 +63,45 @@ pub struct InstructionContext {
 
 pub type OuterInstructionProgramId = Option<Pubkey>;
 
+/// Position of instruction in transaction, as referenced by explorers & Geyser
+///
+/// Displayed as `<instruction_index>` for top-level instruction and
+/// `<instruction_index>#<inner_index>` for inner one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InstructionPosition {
+    /// Index of top-level instruction in transaction message
+    pub instruction_index: usize,
+    /// Index in flattened list of inner instructions of top-level one, `None` for top-level
+    pub inner_index: Option<usize>,
+}
+
+impl fmt::Display for InstructionPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inner_index {
+            Some(inner_index) => write!(f, "{}#{}", self.instruction_index, inner_index),
+            None => write!(f, "{}", self.instruction_index),
+        }
+    }
+}
+
+impl FromStr for InstructionPosition {
+    type Err = std::num::ParseIntError;
+
+    /// Parse position in format of [`fmt::Display`]: `3` or `3#1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.split_once('#') {
+            Some((instruction_index, inner_index)) => Self {
+                instruction_index: instruction_index.parse()?,
+                inner_index: Some(inner_index.parse()?),
+            },
+            None => Self {
+                instruction_index: s.parse()?,
+                inner_index: None,
+            },
+        })
+    }
+}
+
 pub trait GetLoadedAccounts {
     fn get_loaded_accounts(&self) -> Option<Result<Vec<Pubkey>, Error>>;
 }
@@ -73,6 +133,64 @@ impl GetLoadedAccounts for EncodedTransactionWithStatusMeta {
     }
 }
 
+/// Cheap checks of transaction without binding of instructions & logs, e.g. to skip vote
+/// and irrelevant transactions of block before [`crate::transaction_parser::parse_transaction`]
+///
+/// Transactions which can't be decoded are neither votes nor contain any program, so they
+/// are skipped too
+pub trait ClassifyTransaction {
+    /// All transaction instructions are of vote program
+    fn is_vote(&self) -> bool;
+
+    /// `program_id` is in accounts of transaction (static or loaded by lookup tables). Program
+    /// can be invoked, even by CPI, only if it's passed in accounts, so transaction without
+    /// it can be skipped, but transaction with it doesn't necessarily invoke it
+    fn contains_program(&self, program_id: &Pubkey) -> bool;
+
+    /// Not a vote & contains any of `program_ids`
+    fn is_relevant(&self, program_ids: &[Pubkey]) -> bool {
+        !self.is_vote()
+            && program_ids
+                .iter()
+                .any(|program_id| self.contains_program(program_id))
+    }
+}
+
+impl ClassifyTransaction for EncodedTransactionWithStatusMeta {
+    fn is_vote(&self) -> bool {
+        let Some(tx) = self.transaction.decode() else {
+            return false;
+        };
+        let (keys, instructions) = (tx.message.static_account_keys(), tx.message.instructions());
+        !instructions.is_empty()
+            && instructions.iter().all(|ix| {
+                keys.get(ix.program_id_index as usize) == Some(&solana_sdk::vote::program::id())
+            })
+    }
+
+    fn contains_program(&self, program_id: &Pubkey) -> bool {
+        let Some(tx) = self.transaction.decode() else {
+            return false;
+        };
+        if tx.message.static_account_keys().contains(program_id) {
+            return true;
+        }
+        match &self.meta {
+            Some(UiTransactionStatusMeta {
+                loaded_addresses: OptionSerializer::Some(UiLoadedAddresses { writable, readonly }),
+                ..
+            }) => {
+                let program_id = program_id.to_string();
+                writable
+                    .iter()
+                    .chain(readonly)
+                    .any(|key| *key == program_id)
+            }
+            _ => false,
+        }
+    }
+}
+
 /// [`BindInstructions`] trait provides a method to bind an `Instruction` to its context.
 pub trait BindInstructions {
     /// Bind instructions the transaction into separate contexts.
@@ -82,7 +200,23 @@ pub trait BindInstructions {
     fn bind_instructions(
         &self,
         signature: Signature,
-    ) -> Result<HashMap<InstructionContext, (Instruction, OuterInstructionProgramId)>, Error>;
+    ) -> Result<
+        HashMap<InstructionContext, (Instruction, OuterInstructionProgramId, InstructionPosition)>,
+        Error,
+    >;
+
+    /// Same as [`BindInstructions::bind_instructions`], with [`ParseOptions`] applicable to
+    /// instructions
+    fn bind_instructions_with_options(
+        &self,
+        signature: Signature,
+        _options: &ParseOptions,
+    ) -> Result<
+        HashMap<InstructionContext, (Instruction, OuterInstructionProgramId, InstructionPosition)>,
+        Error,
+    > {
+        self.bind_instructions(signature)
+    }
 }
 impl BindInstructions for EncodedTransactionWithStatusMeta {
     /// Bind instructions the transaction into separate contexts.
@@ -94,10 +228,24 @@ impl BindInstructions for EncodedTransactionWithStatusMeta {
     /// all instructions of the transaction and binds each instruction to its context. The context
     /// is created by using the program id of the instruction and a call index which is incrementing
     /// with each call of the same program id.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            %signature,
+            instructions_count = tracing::field::Empty,
+            inner_instructions_count = tracing::field::Empty,
+            elapsed_us = tracing::field::Empty,
+        )
+    )]
     fn bind_instructions(
         &self,
         signature: Signature,
-    ) -> Result<HashMap<InstructionContext, (Instruction, OuterInstructionProgramId)>, Error> {
+    ) -> Result<
+        HashMap<InstructionContext, (Instruction, OuterInstructionProgramId, InstructionPosition)>,
+        Error,
+    > {
+        let started_at = std::time::Instant::now();
         let tx = self.transaction.decode().ok_or_else(|| {
             tracing::error!("Can't decode transaction");
             Error::ErrorWhileDecodeTransaction(signature)
@@ -178,6 +326,10 @@ impl BindInstructions for EncodedTransactionWithStatusMeta {
                         data: compiled_ix.data.clone(),
                     },
                     None,
+                    InstructionPosition {
+                        instruction_index: ix_index,
+                        inner_index: None,
+                    },
                 ),
             );
             if let Some(invokes) = inner_instructions.get(&ix_index) {
@@ -218,24 +370,82 @@ impl BindInstructions for EncodedTransactionWithStatusMeta {
                         ix_index,
                         ctx
                     );
-                    result.insert(ctx, (invoke_ix, Some(program_id)));
+                    result.insert(
+                        ctx,
+                        (
+                            invoke_ix,
+                            Some(program_id),
+                            InstructionPosition {
+                                instruction_index: ix_index,
+                                inner_index: Some(invoke_index),
+                            },
+                        ),
+                    );
                 }
             }
         }
 
+        let span = tracing::Span::current();
+        span.record("instructions_count", msg.instructions().len());
+        span.record(
+            "inner_instructions_count",
+            result.len() - msg.instructions().len(),
+        );
+        span.record("elapsed_us", started_at.elapsed().as_micros() as u64);
+
+        Ok(result)
+    }
+
+    /// With [`FailurePolicy::Partial`] transaction instructions after failed one are dropped,
+    /// as they were not executed
+    fn bind_instructions_with_options(
+        &self,
+        signature: Signature,
+        options: &ParseOptions,
+    ) -> Result<
+        HashMap<InstructionContext, (Instruction, OuterInstructionProgramId, InstructionPosition)>,
+        Error,
+    > {
+        let mut result = self.bind_instructions(signature)?;
+        if options.failures == FailurePolicy::Partial {
+            if let Some(TransactionError::InstructionError(failed_ix_index, _)) =
+                self.meta.as_ref().and_then(|meta| meta.err.as_ref())
+            {
+                result.retain(|_, (_, _, position)| {
+                    position.instruction_index <= *failed_ix_index as usize
+                });
+            }
+        }
         Ok(result)
     }
 }
 
+/// Transaction instructions in order, as seen by programs through Instructions sysvar
+/// (`load_instruction_at_checked`), including precompiles which don't produce logs
+pub fn sysvar_instructions(
+    transaction: &EncodedTransactionWithStatusMeta,
+    signature: Signature,
+) -> Result<Vec<Instruction>, Error> {
+    let mut instructions = transaction
+        .bind_instructions(signature)?
+        .into_values()
+        .filter(|(_, _, position)| position.inner_index.is_none())
+        .collect::<Vec<_>>();
+    instructions.sort_by_key(|(_, _, position)| *position);
+    Ok(instructions.into_iter().map(|(ix, _, _)| ix).collect())
+}
+
 #[cfg(feature = "anchor")]
 mod anchor {
     use std::io;
 
     use anchor_lang::{AnchorDeserialize, Discriminator, Owner};
 
-    use super::Instruction;
+    use super::{split_discriminator, Instruction};
 
     pub trait ParseInstruction {
+        /// Returns `None` if instruction not belongs to `T` (owner or discriminator not match,
+        /// or data is too short to contain discriminator)
         fn parse_instruction<T: Discriminator + Owner + AnchorDeserialize>(
             &self,
         ) -> Option<Result<T, io::Error>>;
@@ -245,8 +455,7 @@ mod anchor {
         fn parse_instruction<I: Discriminator + Owner + AnchorDeserialize>(
             &self,
         ) -> Option<Result<I, io::Error>> {
-            const DISCRIMINATOR_SIZE: usize = 8;
-            let (discriminantor, event) = self.data.split_at(DISCRIMINATOR_SIZE);
+            let (discriminantor, event) = split_discriminator(&self.data)?;
             (I::owner().eq(&self.program_id) && I::discriminator().eq(discriminantor))
                 .then(|| I::try_from_slice(event))
         }
@@ -254,3 +463,57 @@ mod anchor {
 }
 #[cfg(feature = "anchor")]
 pub use anchor::*;
+
+#[cfg(test)]
+mod instruction_parser_test {
+    use solana_sdk::{message::Message, transaction::Transaction};
+    use solana_transaction_status::{Encodable, UiTransactionEncoding};
+
+    use super::*;
+
+    fn encoded(instructions: &[Instruction]) -> EncodedTransactionWithStatusMeta {
+        let payer = Pubkey::new_unique();
+        let tx = Transaction::new_unsigned(Message::new(instructions, Some(&payer)));
+        EncodedTransactionWithStatusMeta {
+            transaction: tx.encode(UiTransactionEncoding::Base64),
+            meta: None,
+            version: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_transaction() {
+        let program_id = Pubkey::new_unique();
+        let vote = encoded(&[Instruction::new_with_bytes(
+            solana_sdk::vote::program::id(),
+            &[],
+            vec![],
+        )]);
+        assert!(vote.is_vote());
+        assert!(!vote.contains_program(&program_id));
+
+        let tx = encoded(&[
+            Instruction::new_with_bytes(solana_sdk::vote::program::id(), &[], vec![]),
+            Instruction::new_with_bytes(program_id, &[], vec![]),
+        ]);
+        assert!(!tx.is_vote());
+        assert!(tx.is_relevant(&[Pubkey::new_unique(), program_id]));
+        assert!(!tx.is_relevant(&[Pubkey::new_unique()]));
+    }
+
+    #[test]
+    fn test_split_discriminator() {
+        assert_eq!(split_discriminator(&[]), None);
+        assert_eq!(split_discriminator(&[1; DISCRIMINATOR_SIZE - 1]), None);
+
+        let (discriminator, payload) = split_discriminator(&[1; DISCRIMINATOR_SIZE]).unwrap();
+        assert_eq!(discriminator, [1; DISCRIMINATOR_SIZE]);
+        assert!(payload.is_empty());
+
+        let data = [[1; DISCRIMINATOR_SIZE].as_slice(), &[2, 3]].concat();
+        assert_eq!(
+            split_discriminator(&data),
+            Some(([1; DISCRIMINATOR_SIZE].as_slice(), [2, 3].as_slice()))
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! Compact representation of parsed logs for whole-block workloads.
+//!
+//! Program ids are replaced by [`ProgramSymbol`] (index in [`Interner`] table) and log
+//! strings are shared as [`Arc<str>`], so the same 32-byte pubkey or repeated log line
+//! (e.g. `Instruction: Transfer`) is stored once per [`Interner`] instead of once per
+//! context / log
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::log_parser::{
+    self, Error, Level, ParseOptions, ProgramContext, ProgramLog, ProgramReturn, Pubkey,
+};
+
+/// Index of program id in [`Interner`]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
+pub struct ProgramSymbol(u32);
+
+/// [`ProgramContext`] with interned program id
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, PartialOrd, Ord)]
+pub struct InternedProgramContext {
+    pub program: ProgramSymbol,
+    pub program_call_index: u32,
+    pub invoke_level: Level,
+}
+
+/// [`ProgramLog`] with interned program ids & strings
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum InternedProgramLog {
+    DeployedProgram(ProgramSymbol),
+    UpgradedProgram(ProgramSymbol),
+    Data(Arc<str>),
+    Log(Arc<str>),
+    Return {
+        program: ProgramSymbol,
+        data: Arc<str>,
+    },
+    Invoke(InternedProgramContext),
+    Consumed {
+        consumed: usize,
+        all: usize,
+    },
+    #[cfg(feature = "unknown_log")]
+    UnknownFormat {
+        unknown_log_string: Arc<str>,
+    },
+    #[cfg(feature = "json_log")]
+    Json(serde_json::Value),
+}
+
+pub type InternedEvents = HashMap<InternedProgramContext, Vec<InternedProgramLog>>;
+
+/// Symbol table shared by all transactions of a batch (block, slot range, etc.)
+#[derive(Debug, Default)]
+pub struct Interner {
+    programs: Vec<Pubkey>,
+    symbols: HashMap<Pubkey, ProgramSymbol>,
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count of distinct program ids
+    pub fn programs_count(&self) -> usize {
+        self.programs.len()
+    }
+
+    /// Count of distinct strings
+    pub fn strings_count(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn intern_program(&mut self, program_id: Pubkey) -> ProgramSymbol {
+        *self.symbols.entry(program_id).or_insert_with(|| {
+            self.programs.push(program_id);
+            ProgramSymbol(
+                u32::try_from(self.programs.len() - 1).expect("More than u32::MAX programs"),
+            )
+        })
+    }
+
+    pub fn intern_str(&mut self, value: &str) -> Arc<str> {
+        match self.strings.get(value) {
+            Some(interned) => interned.clone(),
+            None => {
+                let interned: Arc<str> = Arc::from(value);
+                self.strings.insert(interned.clone());
+                interned
+            }
+        }
+    }
+
+    /// Program id of symbol, `None` if symbol is from another [`Interner`]
+    pub fn program(&self, symbol: ProgramSymbol) -> Option<Pubkey> {
+        self.programs.get(symbol.0 as usize).copied()
+    }
+
+    pub fn intern_context(&mut self, ctx: &ProgramContext) -> InternedProgramContext {
+        InternedProgramContext {
+            program: self.intern_program(ctx.program_id),
+            program_call_index: u32::try_from(ctx.program_call_index)
+                .expect("More than u32::MAX calls of program"),
+            invoke_level: ctx.invoke_level,
+        }
+    }
+
+    pub fn intern_log(&mut self, log: &ProgramLog) -> InternedProgramLog {
+        match log {
+            ProgramLog::DeployedProgram(program_id) => {
+                InternedProgramLog::DeployedProgram(self.intern_program(*program_id))
+            }
+            ProgramLog::UpgradedProgram(program_id) => {
+                InternedProgramLog::UpgradedProgram(self.intern_program(*program_id))
+            }
+            ProgramLog::Data(data) => InternedProgramLog::Data(self.intern_str(data)),
+            ProgramLog::Log(log) => InternedProgramLog::Log(self.intern_str(log)),
+            ProgramLog::Return(ProgramReturn { program_id, data }) => InternedProgramLog::Return {
+                program: self.intern_program(*program_id),
+                data: self.intern_str(data),
+            },
+            ProgramLog::Invoke(ctx) => InternedProgramLog::Invoke(self.intern_context(ctx)),
+            ProgramLog::Consumed { consumed, all } => InternedProgramLog::Consumed {
+                consumed: *consumed,
+                all: *all,
+            },
+            #[cfg(feature = "unknown_log")]
+            ProgramLog::UnknownFormat { unknown_log_string } => InternedProgramLog::UnknownFormat {
+                unknown_log_string: self.intern_str(unknown_log_string),
+            },
+            #[cfg(feature = "json_log")]
+            ProgramLog::Json(value) => InternedProgramLog::Json(value.clone()),
+        }
+    }
+
+    pub fn intern_events(
+        &mut self,
+        events: &HashMap<ProgramContext, Vec<ProgramLog>>,
+    ) -> InternedEvents {
+        events
+            .iter()
+            .map(|(ctx, logs)| {
+                (
+                    self.intern_context(ctx),
+                    logs.iter().map(|log| self.intern_log(log)).collect(),
+                )
+            })
+            .collect()
+    }
+
+    pub fn resolve_context(&self, ctx: &InternedProgramContext) -> Option<ProgramContext> {
+        Some(ProgramContext {
+            program_id: self.program(ctx.program)?,
+            program_call_index: ctx.program_call_index as usize,
+            invoke_level: ctx.invoke_level,
+        })
+    }
+
+    pub fn resolve_log(&self, log: &InternedProgramLog) -> Option<ProgramLog> {
+        Some(match log {
+            InternedProgramLog::DeployedProgram(program) => {
+                ProgramLog::DeployedProgram(self.program(*program)?)
+            }
+            InternedProgramLog::UpgradedProgram(program) => {
+                ProgramLog::UpgradedProgram(self.program(*program)?)
+            }
+            InternedProgramLog::Data(data) => ProgramLog::Data(data.to_string()),
+            InternedProgramLog::Log(log) => ProgramLog::Log(log.to_string()),
+            InternedProgramLog::Return { program, data } => ProgramLog::Return(ProgramReturn {
+                program_id: self.program(*program)?,
+                data: data.to_string(),
+            }),
+            InternedProgramLog::Invoke(ctx) => ProgramLog::Invoke(self.resolve_context(ctx)?),
+            InternedProgramLog::Consumed { consumed, all } => ProgramLog::Consumed {
+                consumed: *consumed,
+                all: *all,
+            },
+            #[cfg(feature = "unknown_log")]
+            InternedProgramLog::UnknownFormat { unknown_log_string } => ProgramLog::UnknownFormat {
+                unknown_log_string: unknown_log_string.to_string(),
+            },
+            #[cfg(feature = "json_log")]
+            InternedProgramLog::Json(value) => ProgramLog::Json(value.clone()),
+        })
+    }
+
+    /// Back to [`log_parser::parse_events`] output, `None` if events are from another
+    /// [`Interner`]
+    pub fn resolve_events(
+        &self,
+        events: &InternedEvents,
+    ) -> Option<HashMap<ProgramContext, Vec<ProgramLog>>> {
+        events
+            .iter()
+            .map(|(ctx, logs)| {
+                Some((
+                    self.resolve_context(ctx)?,
+                    logs.iter()
+                        .map(|log| self.resolve_log(log))
+                        .collect::<Option<_>>()?,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// [`log_parser::parse_events`] with result interned in `interner`
+pub fn parse_events_interned(
+    interner: &mut Interner,
+    input: &[String],
+) -> Result<InternedEvents, Error> {
+    parse_events_interned_with_options(interner, input, &ParseOptions::default())
+}
+
+/// [`log_parser::parse_events_with_options`] with result interned in `interner`
+pub fn parse_events_interned_with_options(
+    interner: &mut Interner,
+    input: &[String],
+    options: &ParseOptions,
+) -> Result<InternedEvents, Error> {
+    log_parser::parse_events_with_options(input, options)
+        .map(|events| interner.intern_events(&events))
+}
+
+#[cfg(test)]
+mod intern_test {
+    use super::*;
+
+    #[test]
+    fn test_interning() {
+        let logs = [
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+            "Program log: Instruction: Buy",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [2]",
+            "Program log: Instruction: Buy",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+        ]
+        .map(str::to_owned);
+
+        let mut interner = Interner::new();
+        let first = parse_events_interned(&mut interner, &logs).unwrap();
+        let second = parse_events_interned(&mut interner, &logs).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(interner.programs_count(), 1);
+        assert_eq!(interner.strings_count(), 1);
+        assert_eq!(
+            interner.resolve_events(&first),
+            Some(log_parser::parse_events(&logs).unwrap())
+        );
+        assert_eq!(Interner::new().resolve_events(&first), None);
+    }
+}
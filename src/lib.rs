@@ -4,6 +4,10 @@
 #[cfg(feature = "anchor")]
 pub mod event_parser;
 
+/// Decode anchor accounts into rust structure, the same way as events
+#[cfg(feature = "anchor")]
+pub mod account_parser;
+
 /// Bind instructions into [`HashMap<InstructionContext, (Instruction, OuterInstruction)>`]
 ///
 /// Allows [`solana_transaction_status::EncodedTransactionWithStatusMeta`] to be broken down
@@ -16,14 +20,88 @@ pub mod instruction_parser;
 #[cfg(feature = "solana")]
 pub mod transaction_parser;
 
+/// Crate-level error with stable numeric codes over errors of parsers & reader
+#[cfg(feature = "solana")]
+pub mod error;
+
+/// Discriminators, decomposer candidates & hexdumps of instructions, for debugging of
+/// decomposers which don't match
+#[cfg(feature = "solana")]
+pub mod debug;
+
+/// Blocking versions of transaction binding traits, over [`solana_client::rpc_client::RpcClient`]
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
+/// String-keyed mirror of [`transaction_parser::TransactionParsedMeta`], convenient for
+/// JSON consumers
+#[cfg(feature = "solana")]
+pub mod dto;
+
+/// Flat tables (instructions, events, token transfers) of parsed transactions for columnar
+/// storages
+#[cfg(feature = "export")]
+pub mod export;
+
+/// Recorded transactions for offline parsing & golden tests
+#[cfg(feature = "solana")]
+pub mod fixtures;
+
 /// Parses logs of solana programs based on regular expressions.
 pub mod log_parser;
 
+/// Pubkey, signature & slot types: `solana_sdk` ones, or lightweight interchangeable
+/// replacements without `solana` feature
+pub mod types;
+
+/// Options shared by log, instruction & transaction parsers
+pub mod parse_options;
+
+/// Interned program ids & log strings, to cut memory of whole-block parsing
+#[cfg(feature = "intern")]
+pub mod intern;
+
+/// Per-invocation compute units profile, based on parsed logs
+pub mod compute_units;
+
+/// Human names of well-known & user registered programs
+pub mod program_registry;
+
+/// Human-readable (optionally ANSI colored) tree rendering of parsed transactions & logs
+pub mod render;
+
+/// Decomposers for instructions of native & SPL programs (System, Token, Token-2022, ATA,
+/// Stake, ComputeBudget), which are not based on anchor discriminators
 #[cfg(feature = "solana")]
-pub use crate::transaction_parser::{BindTransactionInstructionLogs, BindTransactionLogs};
+pub mod native_instruction_parser;
+
+/// Decomposers & NFT events of Metaplex Token Metadata and Bubblegum (compressed NFT)
+#[cfg(feature = "metaplex")]
+pub mod metaplex;
+
+/// Runtime decoding of anchor instructions, events and accounts by program IDL
+#[cfg(feature = "idl")]
+pub mod idl;
+
+/// Filter expressions (builder API & string DSL) over events decoded by [`idl`]
+#[cfg(feature = "idl")]
+pub mod event_filter;
+
+/// C API (JSON in/out) over log binding & IDL decoding, for reuse from other languages
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "solana")]
+pub use crate::{
+    error::{ErrorCode, EventsParserError},
+    instruction_parser::ClassifyTransaction,
+    transaction_parser::{BindTransactionInstructionLogs, BindTransactionLogs},
+};
 
 #[cfg(feature = "anchor")]
-pub use crate::{event_parser::ParseEvent, instruction_parser::ParseInstruction};
+pub use crate::{
+    account_parser::ParseAccount, event_parser::ParseEvent, instruction_parser::ParseInstruction,
+};
 
 /// Set of abstractions for storage management used in [`event_reader_service`]
 #[cfg(feature = "storage")]
@@ -33,5 +111,45 @@ pub mod storage;
 #[cfg(feature = "event-reader")]
 pub mod event_reader_service;
 
+/// Composable middlewares around transaction consuming of
+/// [`event_reader_service::EventsReader`]
+#[cfg(feature = "event-reader")]
+pub mod middleware;
+
+/// Pluggable serialization of typed events for [`event_reader_service::PassEvent`]
+#[cfg(feature = "event-reader")]
+pub mod event_serializer;
+
+/// Implementations of [`event_reader_service::PassEvent`] for external systems
+#[cfg(feature = "event-reader")]
+pub mod event_recipient;
+
+/// Ingestion of transactions from webhooks of managed RPC providers into
+/// [`event_reader_service::EventsReader`], without websocket subscription
+#[cfg(feature = "event-reader")]
+pub mod webhook_ingest;
+
+/// Paginated `getSignaturesForAddress` as stream, shared by resync & backfill tools
+#[cfg(feature = "event-reader")]
+pub mod signature_stream;
+
+/// Detection of program upgrades by deploy slot, with hot-reload of program [`idl::Idl`]
+#[cfg(all(feature = "event-reader", feature = "idl"))]
+pub mod program_watcher;
+
+/// Transaction consumer inserting [`export`] tables into ClickHouse
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
+
+/// In-memory sources of [`event_reader_service::EventsReader`] over recorded
+/// [`fixtures`], for deterministic tests without live endpoints
+#[cfg(feature = "event-reader")]
+pub mod mock_source;
+
+/// Harness running [`event_reader_service::EventsReader`] against local
+/// `solana-test-validator`, for integration tests of consumers
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+
 #[cfg(feature = "solana")]
 pub use de_solana_client;
@@ -1,12 +1,16 @@
-use std::{collections::HashMap, fmt::Debug, num::NonZeroU8, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug},
+    num::NonZeroU32,
+    str::FromStr,
+};
 
 use lazy_static::lazy_static;
-#[cfg(not(feature = "solana"))]
-pub use pubkey::Pubkey;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-#[cfg(feature = "solana")]
-pub use solana_sdk::pubkey::Pubkey;
+
+use crate::parse_options::{FailurePolicy, TruncationPolicy, UnknownLinePolicy};
+pub use crate::{parse_options::ParseOptions, types::Pubkey};
 
 lazy_static! {
     static ref LOG: Regex = Regex::new(
@@ -27,12 +31,15 @@ pub enum Error {
     BindEventError,
     #[error("Bad log line: {0}")]
     BadLogLine(String),
-    #[error("Unexpected program result at index {index}. Program {program_id:?} level: {level:?}")]
+    #[error(
+        "Unexpected program result at index {index}. Program {program_id:?} level: {level:?}{trace}"
+    )]
     UnexpectedProgramResult {
         index: usize,
         program_id: Pubkey,
         expected_program: Option<Pubkey>,
         level: Option<Level>,
+        trace: Box<BindTrace>,
     },
     #[error("Error log via binding logs: {program_id:?}, error: {err} at index {index}")]
     ErrorLog {
@@ -42,26 +49,114 @@ pub enum Error {
     },
     #[error("Error to complete log via binding logs: error: {err} at index {index}")]
     ErrorToCompleteLog { err: String, index: usize },
-    #[error("Misplace consumed log: Invoked {expected_program:?}, consumed program id: {consumed_program_id:?} at index {index}")]
+    #[error("Misplace consumed log: Invoked {expected_program:?}, consumed program id: {consumed_program_id:?} at index {index}{trace}")]
     MisplaceConsumed {
         consumed_program_id: Pubkey,
         expected_program: Option<Pubkey>,
         index: usize,
+        trace: Box<BindTrace>,
     },
+    #[error("Logs truncated at index {index}")]
+    LogTruncated { index: usize },
     #[error("Missing invoke log context {index}")]
     EmptyInvokeLogContext { index: usize },
     #[error("Log parser corrupted")]
     ErrorInRegexp,
+    #[error("Multiline log at index {index} joins log of other kind: {line}")]
+    MixedMultilineLog { line: String, index: usize },
+}
+
+impl Error {
+    /// Fill [`BindTrace`] of binding failure with offending line & lines before it from raw
+    /// `logs`, the other errors are returned as is
+    pub fn with_lines(mut self, logs: &[String]) -> Self {
+        if let Self::UnexpectedProgramResult { index, trace, .. }
+        | Self::MisplaceConsumed { index, trace, .. } = &mut self
+        {
+            trace.line = logs.get(*index).cloned();
+            trace.recent_lines = logs
+                .get(index.saturating_sub(BIND_TRACE_RECENT_LINES)..*index)
+                .unwrap_or_default()
+                .to_vec();
+        }
+        self
+    }
 }
 
-#[cfg(feature = "solana")]
-impl From<solana_sdk::pubkey::ParsePubkeyError> for Error {
-    fn from(err: solana_sdk::pubkey::ParsePubkeyError) -> Self {
+/// Lines before offending one in [`BindTrace::recent_lines`]
+pub const BIND_TRACE_RECENT_LINES: usize = 5;
+
+/// Stack-trace of binding failure: invocations open at failure & lines around it, rendered
+/// by [`fmt::Display`] after message of [`Error`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BindTrace {
+    /// Open invocations at failure, from the outermost one
+    pub stack: Vec<ProgramContext>,
+    /// Up to [`BIND_TRACE_RECENT_LINES`] lines before offending one. Lines are filled by
+    /// [`Error::with_lines`], so they are empty for [`bind_events`] of already parsed logs
+    pub recent_lines: Vec<String>,
+    pub line: Option<String>,
+}
+
+impl BindTrace {
+    fn new(stack: Vec<ProgramContext>) -> Box<Self> {
+        Box::new(Self {
+            stack,
+            ..Default::default()
+        })
+    }
+}
+
+impl fmt::Display for BindTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line.as_ref() {
+            write!(f, "\n  at line: {line}")?;
+        }
+        if !self.recent_lines.is_empty() {
+            write!(f, "\n  after lines:")?;
+            for line in &self.recent_lines {
+                write!(f, "\n    {line}")?;
+            }
+        }
+        if !self.stack.is_empty() {
+            write!(f, "\n  invocations stack:")?;
+            for ctx in self.stack.iter().rev() {
+                write!(
+                    f,
+                    "\n    {} invoke [{}], call {}",
+                    ctx.program_id, ctx.invoke_level, ctx.program_call_index
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<crate::types::ParsePubkeyError> for Error {
+    fn from(err: crate::types::ParsePubkeyError) -> Self {
         Self::WrongPubkeySize(err.to_string())
     }
 }
 
-pub type Level = NonZeroU8;
+/// Invoke level (depth) of program, `1` for transaction instructions
+///
+/// Wider than the runtime limit ([`MAX_INVOKE_LEVEL`]), so logs of runtimes with raised
+/// limit or malformed `invoke [<level>]` don't overflow. Serialized as plain integer, same
+/// as former `NonZeroU8`
+pub type Level = NonZeroU32;
+
+/// Max invoke level of current Solana runtime (`MAX_INSTRUCTION_STACK_DEPTH`), deeper levels
+/// are still parsed
+pub const MAX_INVOKE_LEVEL: u32 = 5;
+
+/// Bytes of `level` in hashes & keys (e.g. [`crate::event_parser::EventId`]): single byte
+/// while it fits `u8`, so they are the same as ones computed with `NonZeroU8` levels
+pub fn level_to_bytes(level: Level) -> Vec<u8> {
+    match u8::try_from(level.get()) {
+        Ok(level) => vec![level],
+        Err(_) => level.get().to_le_bytes().to_vec(),
+    }
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Log {
@@ -105,7 +200,8 @@ pub enum Log {
 }
 
 impl Log {
-    fn new(input: &str) -> Result<Self, Error> {
+    /// Parse single log line
+    pub fn new(input: &str) -> Result<Self, Error> {
         #[cfg(not(feature = "unknown_log"))]
         let capture = LOG
             .captures(input)
@@ -260,6 +356,81 @@ pub enum ProgramLog {
     UnknownFormat {
         unknown_log_string: String,
     },
+    /// `Program log:` with JSON object or array payload (pseudo-event printed by `msg!`),
+    /// extracted with [`ParseOptions::json_logs`]
+    #[cfg(feature = "json_log")]
+    Json(#[serde(with = "json_value_serde")] serde_json::Value),
+}
+
+/// Not self-describing formats (e.g. bincode) can't deserialize [`serde_json::Value`], so
+/// in them it's JSON string
+#[cfg(feature = "json_log")]
+mod json_value_serde {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &serde_json::Value,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match serializer.is_human_readable() {
+            true => value.serialize(serializer),
+            false => serializer.serialize_str(&value.to_string()),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<serde_json::Value, D::Error> {
+        match deserializer.is_human_readable() {
+            true => serde_json::Value::deserialize(deserializer),
+            false => {
+                serde_json::from_str(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+            }
+        }
+    }
+}
+
+/// Payload of `Program log:` as [`ProgramLog::Log`], or [`ProgramLog::Json`] with
+/// [`ParseOptions::json_logs`]
+fn program_log(log: String, options: &ParseOptions) -> ProgramLog {
+    #[cfg(feature = "json_log")]
+    if options.json_logs {
+        return extract_json_log(log);
+    }
+    #[cfg(not(feature = "json_log"))]
+    let _ = options;
+    ProgramLog::Log(log)
+}
+
+/// Lines of `Program log:` payload with embedded newlines. Lines joined with their
+/// `Program log: ` prefix are stripped of it, joined lines of other kinds are error
+fn split_multiline_log(log: &str, index: usize) -> Result<Vec<String>, Error> {
+    let mut lines = log.lines();
+    let first = lines.next().unwrap_or_default().to_owned();
+    std::iter::once(Ok(first))
+        .chain(lines.map(|line| match Log::new(line) {
+            Ok(Log::ProgramLog { log }) => Ok(log),
+            #[cfg(feature = "unknown_log")]
+            Ok(Log::UnknownFormat { .. }) => Ok(line.to_owned()),
+            Ok(_) => Err(Error::MixedMultilineLog {
+                line: line.to_owned(),
+                index,
+            }),
+            Err(_) => Ok(line.to_owned()),
+        }))
+        .collect()
+}
+
+/// [`ProgramLog::Json`] if `log` is JSON object or array, [`ProgramLog::Log`] otherwise
+#[cfg(feature = "json_log")]
+pub fn extract_json_log(log: String) -> ProgramLog {
+    if !log.starts_with(['{', '[']) {
+        return ProgramLog::Log(log);
+    }
+    match serde_json::from_str::<serde_json::Value>(&log) {
+        Ok(value) if value.is_object() || value.is_array() => ProgramLog::Json(value),
+        _ => ProgramLog::Log(log),
+    }
 }
 
 #[derive(Clone, Hash, PartialEq, Eq, Debug, PartialOrd, Ord, Serialize, Deserialize)]
@@ -276,11 +447,18 @@ pub struct ProgramContext {
     pub program_call_index: usize,
     /// The depth of this call.
     /// For transaction's instructions - 1
-    pub invoke_level: NonZeroU8,
+    pub invoke_level: Level,
 }
 
 pub fn bind_events(
     input: impl Iterator<Item = Result<Log, Error>>,
+) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error> {
+    bind_events_with_options(input, &ParseOptions::default())
+}
+
+pub fn bind_events_with_options(
+    input: impl Iterator<Item = Result<Log, Error>>,
+    options: &ParseOptions,
 ) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error> {
     let mut programs_stack: Vec<ProgramContext> = vec![];
     let last_at_stack = |stack: &[ProgramContext], index: usize| {
@@ -299,7 +477,14 @@ pub fn bind_events(
 
     let mut result = HashMap::<ProgramContext, Vec<ProgramLog>>::new();
     for (index, log) in input.enumerate() {
-        match log? {
+        let log = match log {
+            Err(Error::BadLogLine(line)) if options.unknown_lines == UnknownLinePolicy::Skip => {
+                tracing::debug!("Skip unknown log \"{}\" at index {}", line, index);
+                continue;
+            }
+            log => log?,
+        };
+        match log {
             Log::DeployedProgram { program_id } => {
                 result
                     .entry(last_at_stack(&programs_stack, index)?)
@@ -314,6 +499,9 @@ pub fn bind_events(
             }
             Log::Truncated => {
                 tracing::debug!("\"Log truncated\" found at index {}", index);
+                if options.truncation == TruncationPolicy::Fail {
+                    return Err(Error::LogTruncated { index });
+                }
                 break;
             }
             Log::ProgramInvoke { program_id, level } => {
@@ -337,14 +525,17 @@ pub fn bind_events(
             Log::ProgramResult {
                 program_id: finished_program_id,
                 err: None,
-            } => match programs_stack.pop() {
-                Some(ctx) if ctx.program_id.eq(&finished_program_id) => {}
+            } => match programs_stack.last().copied() {
+                Some(ctx) if ctx.program_id.eq(&finished_program_id) => {
+                    programs_stack.pop();
+                }
                 Some(ctx) => {
                     return Err(Error::UnexpectedProgramResult {
                         index,
                         program_id: ctx.program_id,
                         level: Some(ctx.invoke_level),
                         expected_program: Some(finished_program_id),
+                        trace: BindTrace::new(programs_stack),
                     });
                 }
                 None => {
@@ -353,6 +544,7 @@ pub fn bind_events(
                         program_id: finished_program_id,
                         level: None,
                         expected_program: None,
+                        trace: BindTrace::new(programs_stack),
                     });
                 }
             },
@@ -360,6 +552,10 @@ pub fn bind_events(
                 program_id,
                 err: Some(err),
             } => {
+                if options.failures == FailurePolicy::Partial {
+                    tracing::debug!("Program {} failed at index {}: {}", program_id, index, err);
+                    break;
+                }
                 return Err(Error::ErrorLog {
                     program_id,
                     err,
@@ -367,13 +563,23 @@ pub fn bind_events(
                 });
             }
             Log::ProgramFailedComplete { err } => {
+                if options.failures == FailurePolicy::Partial {
+                    tracing::debug!("Program failed to complete at index {}: {}", index, err);
+                    break;
+                }
                 return Err(Error::ErrorToCompleteLog { err, index });
             }
             Log::ProgramLog { log } => {
-                result
+                let logs = result
                     .entry(last_at_stack(&programs_stack, index)?)
-                    .or_default()
-                    .push(ProgramLog::Log(log));
+                    .or_default();
+                if options.split_multiline_logs && log.contains('\n') {
+                    for line in split_multiline_log(&log, index)? {
+                        logs.push(program_log(line, options));
+                    }
+                } else {
+                    logs.push(program_log(log, options));
+                }
             }
             Log::ProgramReturn { program_id, data } => {
                 result
@@ -381,10 +587,19 @@ pub fn bind_events(
                     .or_default()
                     .push(ProgramLog::Return(ProgramReturn { program_id, data }));
             }
-            Log::ProgramData { data } => result
-                .entry(last_at_stack(&programs_stack, index)?)
-                .or_default()
-                .push(ProgramLog::Data(data)),
+            Log::ProgramData { data } => {
+                let logs = result
+                    .entry(last_at_stack(&programs_stack, index)?)
+                    .or_default();
+                if options.split_program_data {
+                    logs.extend(
+                        data.split_whitespace()
+                            .map(|payload| ProgramLog::Data(payload.to_owned())),
+                    );
+                } else {
+                    logs.push(ProgramLog::Data(data));
+                }
+            }
             Log::ProgramConsumed {
                 program_id,
                 consumed,
@@ -396,6 +611,7 @@ pub fn bind_events(
                         expected_program: Some(ctx.program_id),
                         consumed_program_id: program_id,
                         index,
+                        trace: BindTrace::new(programs_stack),
                     });
                 }
                 result
@@ -430,7 +646,308 @@ pub fn bind_events(
 }
 
 pub fn parse_events(input: &[String]) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error> {
-    bind_events(input.iter().map(|input_log| Log::new(input_log)))
+    parse_events_with_options(input, &ParseOptions::default())
+}
+
+#[tracing::instrument(
+    level = "debug",
+    skip_all,
+    fields(
+        logs_count = input.len(),
+        contexts_count = tracing::field::Empty,
+        elapsed_us = tracing::field::Empty,
+    )
+)]
+pub fn parse_events_with_options(
+    input: &[String],
+    options: &ParseOptions,
+) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error> {
+    let started_at = std::time::Instant::now();
+    let bound = bind_events_with_options(
+        input.iter().map(|input_log| {
+            if options.normalize_lines {
+                Log::new(input_log.trim_end())
+            } else {
+                Log::new(input_log)
+            }
+        }),
+        options,
+    )
+    .map_err(|err| err.with_lines(input))?;
+
+    let span = tracing::Span::current();
+    span.record("contexts_count", bound.len());
+    span.record("elapsed_us", started_at.elapsed().as_micros() as u64);
+
+    Ok(bound)
+}
+
+/// Parse logs of many transactions (e.g. whole block) on rayon thread pool.
+///
+/// Results are in order of `input`, failure of one transaction doesn't affect others
+#[cfg(feature = "parallel")]
+pub fn parse_events_batch(
+    input: Vec<Vec<String>>,
+) -> Vec<Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error>> {
+    parse_events_batch_with_options(input, &ParseOptions::default())
+}
+
+/// Same as [`parse_events_batch`], with [`ParseOptions`] applied to every transaction
+#[cfg(feature = "parallel")]
+pub fn parse_events_batch_with_options(
+    input: Vec<Vec<String>>,
+    options: &ParseOptions,
+) -> Vec<Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error>> {
+    use rayon::prelude::*;
+
+    input
+        .into_par_iter()
+        .map(|logs| parse_events_with_options(&logs, options))
+        .collect()
+}
+
+/// [`parse_events`] result ordered by context, for output that must not depend on
+/// [`HashMap`] iteration order (e.g. JSON diffed between runs)
+pub fn sorted_events(
+    events: HashMap<ProgramContext, Vec<ProgramLog>>,
+) -> Vec<(ProgramContext, Vec<ProgramLog>)> {
+    let mut events = events.into_iter().collect::<Vec<_>>();
+    events.sort_unstable_by_key(|(ctx, _)| *ctx);
+    events
+}
+
+/// Contexts of top-level (`invoke [1]`) instructions in order of execution.
+///
+/// [`parse_events`] result is unordered, so this order is needed to restore
+/// the transaction tree
+pub fn root_contexts(input: &[String]) -> Result<Vec<ProgramContext>, Error> {
+    root_contexts_with_options(input, &ParseOptions::default())
+}
+
+/// Same as [`root_contexts`], with unknown lines & normalization of [`ParseOptions`]
+pub fn root_contexts_with_options(
+    input: &[String],
+    options: &ParseOptions,
+) -> Result<Vec<ProgramContext>, Error> {
+    let mut call_index_map = HashMap::new();
+    let mut result = vec![];
+    for input_log in input {
+        let input_log = if options.normalize_lines {
+            input_log.trim_end()
+        } else {
+            input_log
+        };
+        let log = match Log::new(input_log) {
+            Err(Error::BadLogLine(_)) if options.unknown_lines == UnknownLinePolicy::Skip => {
+                continue
+            }
+            log => log?,
+        };
+        match log {
+            Log::ProgramInvoke { program_id, level } => {
+                let call_index = call_index_map.entry(program_id).or_insert(0);
+                if level.get() == 1 {
+                    result.push(ProgramContext {
+                        program_id,
+                        program_call_index: *call_index,
+                        invoke_level: level,
+                    });
+                }
+                *call_index += 1;
+            }
+            Log::Truncated => break,
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+/// Is `program_id` invoked at any level, by `Program <program_id> invoke [<level>]` log
+///
+/// Prefix check without regex & parsing, cheap enough to run on every notification
+/// before [`parse_events`]
+pub fn is_program_invoked(program_id: &Pubkey, input: &[String]) -> bool {
+    let prefix = format!(
+        "Program {} invoke [",
+        bs58::encode(program_id).into_string()
+    );
+    input.iter().any(|log| log.starts_with(&prefix))
+}
+
+/// Are logs cut off by `Log truncated`
+pub fn is_truncated(input: &[String]) -> bool {
+    input
+        .iter()
+        .any(|log| matches!(Log::new(log), Ok(Log::Truncated)))
+}
+
+const CLI_TRANSACTION_HEADER: &str = "Transaction executed in slot ";
+const COMPUTE_BUDGET_INVOKE: &str =
+    "Program ComputeBudget111111111111111111111111111111 invoke [1]";
+
+/// Split logs of several transactions concatenated together (e.g. copied from explorer or
+/// validator logs) into logs of every transaction
+///
+/// Logs of transaction are sequence of top-level invocations, so transactions are split only
+/// between them, when all invoked programs returned, at:
+/// - lines which aren't logs, e.g. blank lines, signatures or headers of explorers &
+///   `solana logs`
+/// - top-level invocation after failed one, as failed transaction is stopped
+/// - top-level invocation after `Log truncated`, as the rest of transaction is cut off
+/// - top-level invocation before return of previous one
+/// - invocation of `ComputeBudget` program after invocation of other program, as compute
+///   budget instructions are placed first by wallets & SDKs
+///
+/// Transactions without any of these between them are indistinguishable & kept together.
+/// Lines are trimmed, separators are dropped
+pub fn split_transactions(input: &[String]) -> Vec<Vec<String>> {
+    let mut transactions = vec![];
+    let mut current = Vec::<String>::new();
+    let mut depth = 0;
+    let mut is_boundary = false;
+    let mut has_non_budget_ix = false;
+
+    for line in input {
+        let line = line.trim();
+        let log = match line.is_empty() {
+            true => None,
+            false => Log::new(line).ok(),
+        };
+        match log {
+            #[cfg(feature = "unknown_log")]
+            Some(Log::UnknownFormat { .. }) if depth == 0 => {
+                is_boundary = true;
+                continue;
+            }
+            None if depth == 0 => {
+                is_boundary = true;
+                continue;
+            }
+            Some(Log::ProgramInvoke { level, .. }) => {
+                if level.get() == 1 {
+                    let is_budget_ix = line == COMPUTE_BUDGET_INVOKE;
+                    if is_boundary || depth > 0 || (is_budget_ix && has_non_budget_ix) {
+                        if !current.is_empty() {
+                            transactions.push(std::mem::take(&mut current));
+                        }
+                        is_boundary = false;
+                        has_non_budget_ix = false;
+                    }
+                    has_non_budget_ix |= !is_budget_ix;
+                }
+                depth = level.get();
+            }
+            Some(Log::ProgramResult { err, .. }) => {
+                depth = depth.saturating_sub(1);
+                is_boundary |= depth == 0 && err.is_some();
+            }
+            Some(Log::Truncated) => {
+                depth = 0;
+                is_boundary = true;
+            }
+            _ => {}
+        }
+        current.push(line.to_owned());
+    }
+    if !current.is_empty() {
+        transactions.push(current);
+    }
+    transactions
+}
+
+/// [`parse_events`] of every transaction of [`split_transactions`], failure of one
+/// transaction doesn't affect others
+pub fn parse_transactions(
+    input: &[String],
+) -> Vec<Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error>> {
+    split_transactions(input)
+        .iter()
+        .map(|logs| parse_events(logs))
+        .collect()
+}
+
+/// Transaction of `solana logs` output, see [`split_cli_logs`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliTransactionLogs {
+    pub slot: u64,
+    pub signature: Option<String>,
+    /// `Ok` or error of transaction
+    pub status: Option<String>,
+    /// Log lines without indentation, ready for [`parse_events`]
+    pub logs: Vec<String>,
+}
+
+/// Is `input` output of `solana logs`, with `Transaction executed in slot N:` envelopes
+pub fn is_cli_logs(input: &str) -> bool {
+    input
+        .lines()
+        .any(|line| line.trim_start().starts_with(CLI_TRANSACTION_HEADER))
+}
+
+/// Split output of `solana logs` into transactions
+///
+/// Every transaction starts with `Transaction executed in slot N:` line, followed by
+/// indented `Signature:`, `Status:` and `Log Messages:` headers, log lines are the indented
+/// lines after `Log Messages:`. Lines before the first transaction (e.g. `Streaming
+/// transaction logs...`) are skipped
+///
+/// ```
+/// use solana_events_parser::log_parser::split_cli_logs;
+///
+/// let output = "Streaming transaction logs. Confirmed commitment
+/// Transaction executed in slot 42:
+///   Signature: 5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXFSDwt8GFXM7W5Ncn16wmqokgpiKRLuS83KUxyZyv2sUYv
+///   Status: Ok
+///   Log Messages:
+///     Program 11111111111111111111111111111111 invoke [1]
+///     Program 11111111111111111111111111111111 success
+/// ";
+/// let transactions = split_cli_logs(output);
+/// assert_eq!(transactions.len(), 1);
+/// assert_eq!(transactions[0].slot, 42);
+/// assert_eq!(transactions[0].status.as_deref(), Some("Ok"));
+/// assert_eq!(
+///     transactions[0].logs,
+///     [
+///         "Program 11111111111111111111111111111111 invoke [1]",
+///         "Program 11111111111111111111111111111111 success"
+///     ]
+/// );
+/// ```
+pub fn split_cli_logs(input: &str) -> Vec<CliTransactionLogs> {
+    let mut transactions = Vec::<CliTransactionLogs>::new();
+    let mut in_logs = false;
+    for line in input.lines() {
+        let line = line.trim();
+        if let Some(slot) = line
+            .strip_prefix(CLI_TRANSACTION_HEADER)
+            .and_then(|header| header.strip_suffix(':'))
+            .and_then(|slot| slot.parse().ok())
+        {
+            transactions.push(CliTransactionLogs {
+                slot,
+                ..Default::default()
+            });
+            in_logs = false;
+            continue;
+        }
+        let Some(transaction) = transactions.last_mut() else {
+            continue;
+        };
+        if line.is_empty() {
+            continue;
+        }
+        if in_logs {
+            transaction.logs.push(line.to_owned());
+        } else if let Some(signature) = line.strip_prefix("Signature: ") {
+            transaction.signature = Some(signature.to_owned());
+        } else if let Some(status) = line.strip_prefix("Status: ") {
+            transaction.status = Some(status.to_owned());
+        } else if line == "Log Messages:" {
+            in_logs = true;
+        }
+    }
+    transactions
 }
 
 #[cfg(test)]
@@ -439,6 +956,100 @@ mod log_test {
 
     use super::*;
 
+    #[test]
+    fn test_bind_trace() {
+        const PROGRAM: &str = "M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K";
+        const SYSTEM: &str = "11111111111111111111111111111111";
+        let logs = [
+            format!("Program {PROGRAM} invoke [1]"),
+            "Program log: Instruction: Buy".to_owned(),
+            format!("Program {SYSTEM} invoke [2]"),
+            format!("Program {SYSTEM} success"),
+            format!("Program {SYSTEM} invoke [2]"),
+            "Program log: transfer".to_owned(),
+            // Result of outer program while inner one is open
+            format!("Program {PROGRAM} success"),
+        ];
+
+        let err = parse_events(&logs).unwrap_err();
+        let Error::UnexpectedProgramResult { index, trace, .. } = &err else {
+            panic!("Unexpected error: {err:?}");
+        };
+        assert_eq!(*index, 6);
+        assert_eq!(trace.line.as_ref(), Some(&logs[6]));
+        assert_eq!(trace.recent_lines, logs[1..6]);
+        assert_eq!(
+            trace
+                .stack
+                .iter()
+                .map(|ctx| (ctx.program_id.to_string(), ctx.invoke_level.get()))
+                .collect::<Vec<_>>(),
+            [(PROGRAM.to_owned(), 1), (SYSTEM.to_owned(), 2)]
+        );
+        assert!(err.to_string().ends_with(&format!(
+            "\n  at line: Program {PROGRAM} success\n  after lines:\n    Program log: Instruction: Buy\n    Program {SYSTEM} invoke [2]\n    Program {SYSTEM} success\n    Program {SYSTEM} invoke [2]\n    Program log: transfer\n  invocations stack:\n    {SYSTEM} invoke [2], call 1\n    {PROGRAM} invoke [1], call 0"
+        )));
+
+        let err = parse_events(&[
+            format!("Program {PROGRAM} invoke [1]"),
+            format!("Program {SYSTEM} consumed 150 of 200000 compute units"),
+        ])
+        .unwrap_err();
+        let Error::MisplaceConsumed { trace, .. } = &err else {
+            panic!("Unexpected error: {err:?}");
+        };
+        assert_eq!(trace.recent_lines.len(), 1);
+        assert_eq!(trace.stack.len(), 1);
+    }
+
+    #[test]
+    fn test_split_transactions() {
+        const PROGRAM: &str = "M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K";
+        let instruction = |result: &str| {
+            vec![
+                format!("Program {PROGRAM} invoke [1]"),
+                "Program log: Instruction: Buy".to_owned(),
+                format!("Program {PROGRAM} {result}"),
+            ]
+        };
+        let budget = vec![
+            COMPUTE_BUDGET_INVOKE.to_owned(),
+            "Program ComputeBudget111111111111111111111111111111 success".to_owned(),
+        ];
+
+        let input = [
+            budget.clone(),
+            instruction("success"),
+            instruction("success"),
+            // Separated by compute budget instruction
+            budget.clone(),
+            instruction("failed: custom program error: 0x1"),
+            // Separated by failure
+            instruction("success"),
+            // Separated by not log lines
+            vec![
+                String::new(),
+                "> Signature: 5h6xBEauJ3PK6SWCZ1PGjBvj8vDdWG3KpwATGy1ARAXFSDwt8GFXM7W5Ncn16wmqokgpiKRLuS83KUxyZyv2sUYv".to_owned(),
+            ],
+            instruction("success"),
+        ]
+        .concat();
+
+        let transactions = split_transactions(&input);
+        assert_eq!(
+            transactions.iter().map(Vec::len).collect::<Vec<_>>(),
+            [8, 5, 3, 3]
+        );
+        assert_eq!(transactions[1][0], COMPUTE_BUDGET_INVOKE);
+        assert_eq!(
+            parse_transactions(&input)
+                .iter()
+                .map(Result::is_ok)
+                .collect::<Vec<_>>(),
+            [true, false, true, true]
+        );
+    }
+
     #[cfg(feature = "unknown_log")]
     #[test]
     fn unknown_log_test() {
@@ -494,6 +1105,26 @@ mod log_test {
         );
     }
     #[test]
+    fn test_invoke_level_above_u8() {
+        assert_eq!(
+            Log::new("Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [300]")
+                .expect("Failed to check log"),
+            Log::ProgramInvoke {
+                program_id: Pubkey::from_str("M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K")
+                    .unwrap(),
+                level: Level::new(300).unwrap(),
+            }
+        );
+        assert!(
+            Log::new("Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [0]").is_err()
+        );
+        assert_eq!(level_to_bytes(Level::new(2).unwrap()), vec![2]);
+        assert_eq!(
+            level_to_bytes(Level::new(300).unwrap()),
+            300u32.to_le_bytes()
+        );
+    }
+    #[test]
     fn test_result() {
         assert_eq!(
             Log::new("Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success")
@@ -709,6 +1340,175 @@ Program BRTbgHnC2AWfumCBU6ExthDie912RiDyiS3uXgMPQPQN consumed 200000 of 200000 c
 Program BRTbgHnC2AWfumCBU6ExthDie912RiDyiS3uXgMPQPQN failed: Program failed to complete
 Program return: BRTbgHnC2AWfumCBU6ExthDie912RiDyiS3uXgMPQ123 some return
 Log truncated"#;
+    #[test]
+    fn test_split_program_data() {
+        let logs = [
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+            "Program data: AQI= AwQ=",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+        ]
+        .map(str::to_owned);
+
+        let data = |options: ParseOptions| {
+            super::parse_events_with_options(&logs, &options)
+                .unwrap()
+                .into_values()
+                .flatten()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            data(ParseOptions::default()),
+            vec![ProgramLog::Data("AQI= AwQ=".to_owned())]
+        );
+        assert_eq!(
+            data(ParseOptions {
+                split_program_data: true,
+                ..Default::default()
+            }),
+            vec![
+                ProgramLog::Data("AQI=".to_owned()),
+                ProgramLog::Data("AwQ=".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_multiline_logs() {
+        let logs = [
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+            "Program log: Instruction: Buy\nprice: 1\r\nexpiry: 0",
+            "Program log: single",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+        ]
+        .map(str::to_owned);
+
+        let logs_with = |split_multiline_logs| {
+            super::parse_events_with_options(
+                &logs,
+                &ParseOptions {
+                    split_multiline_logs,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            logs_with(false),
+            vec![
+                ProgramLog::Log("Instruction: Buy\nprice: 1\r\nexpiry: 0".to_owned()),
+                ProgramLog::Log("single".to_owned()),
+            ]
+        );
+        assert_eq!(
+            logs_with(true),
+            ["Instruction: Buy", "price: 1", "expiry: 0", "single"]
+                .map(|log| ProgramLog::Log(log.to_owned()))
+        );
+
+        let options = ParseOptions {
+            split_multiline_logs: true,
+            ..Default::default()
+        };
+        let joined = |log: &str| {
+            super::parse_events_with_options(
+                &[
+                    "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+                    log,
+                    "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+                ]
+                .map(str::to_owned),
+                &options,
+            )
+            .map(|logs| logs.into_values().flatten().collect::<Vec<_>>())
+        };
+        assert_eq!(
+            joined("Program log: a\nProgram log: b").unwrap(),
+            ["a", "b"].map(|log| ProgramLog::Log(log.to_owned()))
+        );
+        assert!(matches!(
+            joined("Program log: a\nProgram data: AQI="),
+            Err(Error::MixedMultilineLog { index: 1, .. })
+        ));
+    }
+
+    #[cfg(feature = "json_log")]
+    #[test]
+    fn test_json_logs() {
+        let logs = [
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+            "Program log: Instruction: Buy",
+            "Program log: {\"price\":17800000000,\"buyer_expiry\":0}",
+            "Program log: {not json",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+        ]
+        .map(str::to_owned);
+
+        let logs_with = |json_logs| {
+            super::parse_events_with_options(
+                &logs,
+                &ParseOptions {
+                    json_logs,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .into_values()
+            .flatten()
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            logs_with(false)[1],
+            ProgramLog::Log("{\"price\":17800000000,\"buyer_expiry\":0}".to_owned())
+        );
+        assert_eq!(
+            logs_with(true),
+            vec![
+                ProgramLog::Log("Instruction: Buy".to_owned()),
+                ProgramLog::Json(serde_json::json!({ "price": 17800000000u64, "buyer_expiry": 0 })),
+                ProgramLog::Log("{not json".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_program_invoked() {
+        let logs = [
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+            "Program log: 11111111111111111111111111111111 invoke [2]",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+        ]
+        .map(str::to_owned);
+
+        let invoked = |program_id: &str| {
+            super::is_program_invoked(&Pubkey::from_str(program_id).unwrap(), &logs)
+        };
+        assert!(invoked("M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K"));
+        assert!(!invoked("11111111111111111111111111111111"));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parse_events_batch() {
+        let ok = [
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K invoke [1]",
+            "Program log: Instruction: Buy",
+            "Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success",
+        ]
+        .map(str::to_owned)
+        .to_vec();
+        let bad = vec!["Program M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K success".to_owned()];
+
+        let result = super::parse_events_batch(vec![ok.clone(), bad.clone(), ok.clone()]);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], super::parse_events(&ok));
+        assert_eq!(result[1], super::parse_events(&bad));
+        assert!(result[1].is_err());
+        assert_eq!(result[2], super::parse_events(&ok));
+    }
+
     #[test]
     fn test_parse() {
         let errors = INPUT
@@ -935,38 +1735,3 @@ Program BPFLoaderUpgradeab1e11111111111111111111111 success"##;
         assert_eq!(expected, program_events);
     }
 }
-
-#[cfg(not(feature = "solana"))]
-mod pubkey {
-    use std::{ops::Deref, str::FromStr};
-
-    use serde::{Deserialize, Serialize};
-
-    #[derive(
-        Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
-    )]
-    pub struct Pubkey([u8; 32]);
-    impl Deref for Pubkey {
-        type Target = [u8; 32];
-        fn deref(&self) -> &Self::Target {
-            &self.0
-        }
-    }
-    impl AsRef<[u8]> for Pubkey {
-        fn as_ref(&self) -> &[u8] {
-            self.deref()
-        }
-    }
-    impl FromStr for Pubkey {
-        type Err = bs58::decode::Error;
-
-        fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let mut pk = Self([0u8; 32]);
-            if bs58::decode(s).into(&mut pk.0)?.eq(&32) {
-                Ok(pk)
-            } else {
-                Err(Self::Err::BufferTooSmall)
-            }
-        }
-    }
-}
@@ -1,6 +1,197 @@
+use std::path::PathBuf;
+
 use anyhow::anyhow;
-use de_solana_client::CommitmentConfig;
+use clap::{Parser, Subcommand, ValueEnum};
 use simple_logger::SimpleLogger;
+use solana_events_parser::program_registry::ProgramRegistry;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Utilities for parsing of solana transactions, logs & anchor events"
+)]
+struct Cli {
+    /// Solana RPC endpoint
+    #[arg(
+        long,
+        global = true,
+        default_value = "https://api.mainnet-beta.solana.com"
+    )]
+    url: String,
+
+    /// JSON object `{"<program_id>": "<name>"}` with program names, in addition to
+    /// well-known ones
+    #[arg(long, global = true)]
+    programs: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Bind instructions & logs of transaction, decode them by IDL if provided
+    ParseTx {
+        signature: String,
+        /// Path to anchor IDL json, used for decode instructions & events by name
+        #[arg(long)]
+        idl: Option<PathBuf>,
+        /// Fetch IDL of `--program-id` from its anchor IDL account instead of file
+        #[arg(long, conflicts_with = "idl", requires = "program_id")]
+        onchain_idl: bool,
+        /// Program id of IDL, needed if IDL metadata has no address
+        #[arg(long)]
+        program_id: Option<String>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+    },
+    /// Discriminator, matching decomposers, accounts & hexdump of data of instruction, to
+    /// debug why it isn't decomposed or decoded
+    InspectIx {
+        signature: String,
+        /// Position of instruction in transaction: `<index>` of top-level one or
+        /// `<index>#<inner_index>` of inner one
+        index: String,
+        /// Path to anchor IDL json, to find instruction with the same discriminator
+        #[arg(long)]
+        idl: Option<PathBuf>,
+        /// Fetch IDL of `--program-id` from its anchor IDL account instead of file
+        #[arg(long, conflicts_with = "idl", requires = "program_id")]
+        onchain_idl: bool,
+        /// Program id of IDL, needed if IDL metadata has no address
+        #[arg(long)]
+        program_id: Option<String>,
+    },
+    /// Bind program logs (e.g. copied from explorer or `solana logs`) without RPC
+    ParseLogs {
+        /// File with log lines or output of `solana logs`, stdin if not provided
+        path: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+    },
+    /// Live tail of program transactions as JSON lines
+    Watch {
+        program_id: String,
+        /// Websocket endpoint, derived from `--url` if not provided
+        #[arg(long)]
+        ws_url: Option<String>,
+        #[arg(long, value_enum, default_value_t = Commitment::Confirmed)]
+        commitment: Commitment,
+        /// Print only events (`Program data:` logs) of the program
+        #[arg(long)]
+        events_only: bool,
+        /// Path to anchor IDL json, used for decode events by name
+        #[arg(long)]
+        idl: Option<PathBuf>,
+        /// Fetch IDL of program from its anchor IDL account instead of file
+        #[arg(long, conflicts_with = "idl")]
+        onchain_idl: bool,
+    },
+    /// Parse program transactions in range and write them as NDJSON
+    ///
+    /// Transactions are walked from newest to oldest, the progress is saved into state file,
+    /// so interrupted backfill continues from the last written transaction
+    Backfill {
+        program_id: String,
+        /// Oldest bound (exclusive signature or inclusive slot), genesis of program if not provided
+        #[arg(long)]
+        from: Option<String>,
+        /// Newest bound (exclusive signature or inclusive slot), latest if not provided
+        #[arg(long)]
+        to: Option<String>,
+        #[arg(long)]
+        out: PathBuf,
+        /// Resume state file, `<out>.state` if not provided
+        #[arg(long)]
+        state: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Commitment::Finalized)]
+        commitment: Commitment,
+    },
+    /// Record transaction into fixture file, consumable offline & by golden tests
+    Capture {
+        signature: String,
+        #[arg(long)]
+        out: PathBuf,
+        /// Edge case covered by fixture
+        #[arg(long, default_value = "")]
+        description: String,
+        #[arg(long, value_enum, default_value_t = Commitment::Finalized)]
+        commitment: Commitment,
+    },
+    /// Per-program, per-invocation compute units breakdown of transaction
+    CuProfile {
+        signature: String,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Tree)]
+        format: OutputFormat,
+    },
+    /// HTTP server with JSON of `dto` layer: `GET /tx/{signature}` and
+    /// `GET /program/{id}/events?limit=N`
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        #[arg(long, value_enum, default_value_t = Commitment::Finalized)]
+        commitment: Commitment,
+    },
+    /// Transactions quarantined by resync of reader with rocksdb storage, the reader must
+    /// be stopped
+    Quarantine {
+        program_id: String,
+        /// Path to rocksdb storage of reader
+        #[arg(long)]
+        db: PathBuf,
+        #[command(subcommand)]
+        action: QuarantineAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum QuarantineAction {
+    /// Print quarantined transactions as JSON lines
+    List,
+    /// Request retry of quarantined transactions by the next resync of reader
+    Retry {
+        /// All quarantined transactions if not provided
+        signatures: Vec<String>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[cfg(feature = "solana")]
+impl From<Commitment> for de_solana_client::CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => Self::processed(),
+            Commitment::Confirmed => Self::confirmed(),
+            Commitment::Finalized => Self::finalized(),
+        }
+    }
+}
+
+/// Source of IDL given by `--idl` or `--onchain-idl`
+#[cfg_attr(not(feature = "idl"), allow(dead_code))]
+enum IdlSource {
+    File(PathBuf),
+    /// Anchor IDL account of program
+    Onchain,
+}
+
+impl IdlSource {
+    fn from_args(idl: Option<PathBuf>, onchain_idl: bool) -> Option<Self> {
+        idl.map(Self::File).or(onchain_idl.then_some(Self::Onchain))
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Tree,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
@@ -9,41 +200,1158 @@ async fn main() -> Result<(), anyhow::Error> {
         .init()
         .map_err(|err| anyhow!("Error while init logger: {}", err))?;
 
-    #[cfg(feature = "solana")]
+    let cli = Cli::parse();
+    let registry = match cli.programs.as_ref() {
+        Some(path) => ProgramRegistry::from_json(&std::fs::read_to_string(path)?)
+            .map_err(|err| anyhow!("Error while parsing programs file: {}", err))?,
+        None => ProgramRegistry::default(),
+    };
+
+    match cli.command {
+        Command::ParseTx {
+            signature,
+            idl,
+            onchain_idl,
+            program_id,
+            format,
+        } => {
+            let idl = IdlSource::from_args(idl, onchain_idl);
+            parse_tx(cli.url, signature, idl, program_id, format, &registry).await
+        }
+        Command::InspectIx {
+            signature,
+            index,
+            idl,
+            onchain_idl,
+            program_id,
+        } => {
+            let idl = IdlSource::from_args(idl, onchain_idl);
+            inspect_ix(cli.url, signature, index, idl, program_id).await
+        }
+        Command::ParseLogs { path, format } => parse_logs(path, format, &registry),
+        Command::Watch {
+            program_id,
+            ws_url,
+            commitment,
+            events_only,
+            idl,
+            onchain_idl,
+        } => {
+            let ws_url = ws_url.unwrap_or_else(|| {
+                cli.url
+                    .replacen("https://", "wss://", 1)
+                    .replacen("http://", "ws://", 1)
+            });
+            let idl = IdlSource::from_args(idl, onchain_idl);
+            watch(cli.url, ws_url, program_id, commitment, events_only, idl).await
+        }
+        Command::Backfill {
+            program_id,
+            from,
+            to,
+            out,
+            state,
+            commitment,
+        } => {
+            let state = state.unwrap_or_else(|| {
+                let mut state = out.clone().into_os_string();
+                state.push(".state");
+                state.into()
+            });
+            backfill(cli.url, program_id, from, to, out, state, commitment).await
+        }
+        Command::Capture {
+            signature,
+            out,
+            description,
+            commitment,
+        } => capture(cli.url, signature, out, description, commitment).await,
+        Command::CuProfile { signature, format } => {
+            cu_profile(cli.url, signature, format, &registry).await
+        }
+        Command::Serve { port, commitment } => serve(cli.url, port, commitment).await,
+        Command::Quarantine {
+            program_id,
+            db,
+            action,
+        } => quarantine(program_id, db, action),
+    }
+}
+
+#[cfg(feature = "solana")]
+async fn capture(
+    url: String,
+    signature: String,
+    out: PathBuf,
+    description: String,
+    commitment: Commitment,
+) -> Result<(), anyhow::Error> {
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_events_parser::{fixtures, transaction_parser::Signature};
+
+    let signature = signature
+        .parse::<Signature>()
+        .map_err(|err| anyhow!("Error while parsing signature: {}", err))?;
+
+    let fixture = fixtures::capture(
+        &RpcClient::new(url),
+        signature,
+        commitment.into(),
+        description,
+    )
+    .await
+    .map_err(|err| anyhow!("Error while capture transaction: {}", err))?;
+    fixture
+        .to_file(&out)
+        .map_err(|err| anyhow!("Error while write fixture: {}", err))?;
+
+    if let Err(err) = fixture.parse() {
+        tracing::warn!("Captured transaction is not parsed: {err}");
+    }
+    println!("Fixture written to {}", out.display());
+
+    Ok(())
+}
+
+#[cfg(not(feature = "solana"))]
+async fn capture(
+    _url: String,
+    _signature: String,
+    _out: PathBuf,
+    _description: String,
+    _commitment: Commitment,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`capture` requires solana feature"))
+}
+
+#[cfg(feature = "solana")]
+async fn cu_profile(
+    url: String,
+    signature: String,
+    format: OutputFormat,
+    registry: &ProgramRegistry,
+) -> Result<(), anyhow::Error> {
+    use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+    use solana_events_parser::{
+        compute_units::{profile, InvocationProfile},
+        transaction_parser::Signature,
+    };
+    use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+
+    let signature = signature
+        .parse::<Signature>()
+        .map_err(|err| anyhow!("Error while parsing signature: {}", err))?;
+
+    let logs = match RpcClient::new(url)
+        .get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base58),
+                max_supported_transaction_version: Some(0),
+                commitment: Some(de_solana_client::CommitmentConfig::finalized()),
+            },
+        )
+        .await?
+        .transaction
+        .meta
+        .map(|meta| meta.log_messages)
     {
-        use std::{env, str::FromStr};
+        Some(OptionSerializer::Some(logs)) => logs,
+        _ => return Err(anyhow!("Transaction {} has no logs", signature)),
+    };
 
-        use solana_client::nonblocking::rpc_client::RpcClient;
-        use solana_events_parser::transaction_parser::*;
+    let mut profile = profile(&logs).map_err(|err| anyhow!("Error while parse logs: {:?}", err))?;
+    profile
+        .iter_mut()
+        .for_each(|invocation| invocation.apply_registry(registry));
 
-        let events = RpcClient::new("https://api.mainnet-beta.solana.com".to_string())
-            .bind_transaction_instructions_logs(
-                Signature::from_str(&env::args().nth(1).ok_or_else(|| {
-                    anyhow!(
-                    "Signatures not provided, Use first argument for provide transaction signature"
-                )
-                })?)
-                .map_err(|err| {
-                    anyhow!(
-                        "Error while parsing argument as transaction signature: {}",
-                        err
-                    )
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&profile)?),
+        OutputFormat::Tree => {
+            fn print_tree(
+                invocation: &InvocationProfile,
+                registry: &ProgramRegistry,
+                depth: usize,
+            ) {
+                let consumed = |consumed: Option<usize>| {
+                    consumed.map_or_else(|| "?".to_owned(), |consumed| consumed.to_string())
+                };
+                println!(
+                    "{}{} [level {}, call #{}]: {} CU (self {}, children {}){}",
+                    "  ".repeat(depth),
+                    registry.label(&invocation.program_ctx.program_id),
+                    invocation.program_ctx.invoke_level,
+                    invocation.program_ctx.program_call_index,
+                    consumed(invocation.consumed),
+                    consumed(invocation.self_consumed),
+                    invocation.children_consumed,
+                    invocation
+                        .err
+                        .as_ref()
+                        .map(|err| format!(", failed: {err}"))
+                        .unwrap_or_default(),
+                );
+                for child in invocation.children.iter() {
+                    print_tree(child, registry, depth + 1);
+                }
+            }
+
+            profile
+                .iter()
+                .for_each(|invocation| print_tree(invocation, registry, 0));
+            println!(
+                "Total: {} CU",
+                profile
+                    .iter()
+                    .filter_map(|invocation| invocation.consumed)
+                    .sum::<usize>()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "solana"))]
+async fn cu_profile(
+    _url: String,
+    _signature: String,
+    _format: OutputFormat,
+    _registry: &ProgramRegistry,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`cu-profile` requires solana feature"))
+}
+
+#[cfg(feature = "solana")]
+async fn backfill(
+    url: String,
+    program_id: String,
+    from: Option<String>,
+    to: Option<String>,
+    out: PathBuf,
+    state: PathBuf,
+    commitment: Commitment,
+) -> Result<(), anyhow::Error> {
+    use std::{fs, io::Write};
+
+    use serde_json::json;
+    use solana_client::{
+        nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    };
+    use solana_events_parser::transaction_parser::*;
+
+    const SIGNATURES_PAGE_SIZE: usize = 1000;
+
+    enum Bound {
+        Signature(Signature),
+        Slot(Slot),
+    }
+    let parse_bound = |bound: Option<String>| -> Result<Option<Bound>, anyhow::Error> {
+        bound
+            .map(|bound| match bound.parse::<Slot>() {
+                Ok(slot) => Ok(Bound::Slot(slot)),
+                Err(_) => bound
+                    .parse::<Signature>()
+                    .map(Bound::Signature)
+                    .map_err(|err| anyhow!("Bound is neither slot nor signature: {}", err)),
+            })
+            .transpose()
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct BackfillState {
+        /// Last (oldest) written transaction
+        before: String,
+    }
+
+    let program_id = program_id
+        .parse::<Pubkey>()
+        .map_err(|err| anyhow!("Error while parsing program id: {}", err))?;
+    let from = parse_bound(from)?;
+    let to = parse_bound(to)?;
+    let commitment = de_solana_client::CommitmentConfig::from(commitment);
+
+    let mut before = match fs::read_to_string(&state) {
+        Ok(state) => {
+            let state: BackfillState = serde_json::from_str(&state)
+                .map_err(|err| anyhow!("Error while parsing state file: {}", err))?;
+            tracing::info!("Resume backfill before {}", state.before);
+            Some(
+                state
+                    .before
+                    .parse::<Signature>()
+                    .map_err(|err| anyhow!("Error while parsing state signature: {}", err))?,
+            )
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => match to {
+            Some(Bound::Signature(signature)) => Some(signature),
+            _ => None,
+        },
+        Err(err) => return Err(err.into()),
+    };
+
+    let client = RpcClient::new(url);
+    let mut output = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&out)?;
+
+    loop {
+        let signatures = client
+            .get_signatures_for_address_with_config(
+                &program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: match from {
+                        Some(Bound::Signature(signature)) => Some(signature),
+                        _ => None,
+                    },
+                    limit: Some(SIGNATURES_PAGE_SIZE),
+                    commitment: Some(commitment),
+                },
+            )
+            .await?;
+        let is_last_page = signatures.len() < SIGNATURES_PAGE_SIZE;
+
+        for status in signatures {
+            let signature = status
+                .signature
+                .parse::<Signature>()
+                .map_err(|err| anyhow!("Error while parsing signature: {}", err))?;
+            before = Some(signature);
+
+            if matches!(to, Some(Bound::Slot(to)) if status.slot > to) {
+                continue;
+            }
+            if matches!(from, Some(Bound::Slot(from)) if status.slot < from) {
+                return Ok(());
+            }
+
+            let document = match status.err {
+                Some(err) => json!({
+                    "signature": status.signature,
+                    "slot": status.slot,
+                    "block_time": status.block_time,
+                    "err": err,
+                }),
+                None => {
+                    let parsed = client
+                        .bind_transaction_instructions_logs(signature, commitment)
+                        .await
+                        .map_err(|err| {
+                            anyhow!("Error while bind transaction {}: {}", signature, err)
+                        })?;
+                    let mut meta = parsed.meta.into_iter().collect::<Vec<_>>();
+                    meta.sort_by_key(|(ctx, _)| *ctx);
+                    json!({
+                        "signature": status.signature,
+                        "slot": parsed.slot,
+                        "block_time": parsed.block_time,
+                        "meta": meta,
+                    })
+                }
+            };
+
+            writeln!(output, "{document}")?;
+            output.flush()?;
+            fs::write(
+                &state,
+                serde_json::to_string(&BackfillState {
+                    before: status.signature,
                 })?,
-                CommitmentConfig::finalized(),
+            )?;
+        }
+
+        if is_last_page {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(not(feature = "solana"))]
+async fn backfill(
+    _url: String,
+    _program_id: String,
+    _from: Option<String>,
+    _to: Option<String>,
+    _out: PathBuf,
+    _state: PathBuf,
+    _commitment: Commitment,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`backfill` requires solana feature"))
+}
+
+#[cfg(feature = "event-reader")]
+async fn watch(
+    url: String,
+    ws_url: String,
+    program_id: String,
+    commitment: Commitment,
+    events_only: bool,
+    idl: Option<IdlSource>,
+) -> Result<(), anyhow::Error> {
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use solana_client::{
+        nonblocking::pubsub_client::PubsubClient,
+        rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+    };
+    use solana_events_parser::{
+        log_parser::{parse_events, sorted_events, ProgramLog},
+        transaction_parser::Pubkey,
+    };
+
+    #[cfg(feature = "idl")]
+    let idl = match idl {
+        Some(idl) => Some(load_idl(&url, idl, Some(program_id.clone())).await?.0),
+        None => None,
+    };
+    #[cfg(not(feature = "idl"))]
+    let _ = url;
+    #[cfg(not(feature = "idl"))]
+    if idl.is_some() {
+        return Err(anyhow!("IDL decoding requires `idl` feature"));
+    }
+    let program_id = program_id
+        .parse::<Pubkey>()
+        .map_err(|err| anyhow!("Error while parsing program id: {}", err))?;
+    let decode_event = |log: &ProgramLog| -> Value {
+        #[cfg(feature = "idl")]
+        if let Some(Ok(Some(decoded))) = idl.as_ref().map(|idl| idl.decode_program_log(log)) {
+            return json!(decoded);
+        }
+        json!(log)
+    };
+
+    let pubsub_client = PubsubClient::new(&ws_url)
+        .await
+        .map_err(|err| anyhow!("Error while connect to {}: {}", ws_url, err))?;
+
+    loop {
+        let (mut stream, _unsubscribe) = pubsub_client
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(commitment.into()),
+                },
             )
             .await
-            .map_err(|err| anyhow!("Error while bind transaction instructions: {}", err))?
-            .meta;
+            .map_err(|err| anyhow!("Error while subscribe to logs: {}", err))?;
+
+        while let Some(response) = stream.next().await {
+            let slot = response.context.slot;
+            let response = response.value;
+
+            let bound = match parse_events(&response.logs) {
+                Ok(bound) => bound,
+                Err(err) => {
+                    if !events_only {
+                        println!(
+                            "{}",
+                            json!({
+                                "signature": response.signature,
+                                "slot": slot,
+                                "err": response.err,
+                                "parse_error": format!("{err:?}"),
+                                "logs": response.logs,
+                            })
+                        );
+                    }
+                    continue;
+                }
+            };
 
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&events.into_iter().collect::<Vec<_>>())
-                .map_err(|err| { anyhow!("Error while serialize result of binding: {}", err) })?
-        );
+            let bound = sorted_events(bound);
+
+            if events_only {
+                for (program_ctx, logs) in
+                    bound.iter().filter(|(ctx, _)| ctx.program_id == program_id)
+                {
+                    for log in logs.iter().filter(|log| matches!(log, ProgramLog::Data(_))) {
+                        println!(
+                            "{}",
+                            json!({
+                                "signature": response.signature,
+                                "slot": slot,
+                                "program_ctx": program_ctx,
+                                "event": decode_event(log),
+                            })
+                        );
+                    }
+                }
+            } else {
+                println!(
+                    "{}",
+                    json!({
+                        "signature": response.signature,
+                        "slot": slot,
+                        "err": response.err,
+                        "logs": bound,
+                    })
+                );
+            }
+        }
+
+        tracing::warn!("Logs stream ended, resubscribe");
+    }
+}
+
+#[cfg(not(feature = "event-reader"))]
+async fn watch(
+    _url: String,
+    _ws_url: String,
+    _program_id: String,
+    _commitment: Commitment,
+    _events_only: bool,
+    _idl: Option<IdlSource>,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`watch` requires event-reader feature"))
+}
+
+fn parse_logs(
+    path: Option<PathBuf>,
+    format: OutputFormat,
+    registry: &ProgramRegistry,
+) -> Result<(), anyhow::Error> {
+    use std::io::{self, IsTerminal, Read};
+
+    use serde_json::json;
+    use solana_events_parser::{
+        log_parser::{
+            is_cli_logs, parse_events, root_contexts, sorted_events, split_cli_logs,
+            split_transactions,
+        },
+        render::Renderer,
+    };
+
+    let mut input = String::new();
+    match path {
+        Some(path) => input = std::fs::read_to_string(path)?,
+        None => {
+            io::stdin().read_to_string(&mut input)?;
+        }
     }
 
-    #[cfg(not(feature = "solana"))]
-    println!("No action when solana feature disable");
+    // Output of `solana logs` with several transactions
+    if is_cli_logs(&input) {
+        let renderer = Renderer::new()
+            .with_ansi(io::stdout().is_terminal())
+            .with_registry(registry);
+        let mut transactions = vec![];
+        for transaction in split_cli_logs(&input) {
+            let bound = parse_events(&transaction.logs);
+            match format {
+                OutputFormat::Json => transactions.push(json!({
+                    "slot": transaction.slot,
+                    "signature": transaction.signature,
+                    "status": transaction.status,
+                    "events": bound.as_ref().ok().map(|bound| sorted_events(bound.clone())),
+                    "error": bound.as_ref().err().map(|err| format!("{err:?}")),
+                })),
+                OutputFormat::Tree => {
+                    println!(
+                        "Transaction {} in slot {}: {}",
+                        transaction.signature.as_deref().unwrap_or("<unknown>"),
+                        transaction.slot,
+                        transaction.status.as_deref().unwrap_or("<unknown status>"),
+                    );
+                    match bound.and_then(|bound| Ok((root_contexts(&transaction.logs)?, bound))) {
+                        Ok((roots, bound)) => print!("{}", renderer.render_logs(&roots, &bound)),
+                        Err(err) => println!("Error while bind logs: {err:?}"),
+                    }
+                }
+            }
+        }
+        if let OutputFormat::Json = format {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&transactions)
+                    .map_err(|err| anyhow!("Error while serialize bound logs: {}", err))?
+            );
+        }
+        return Ok(());
+    }
+    // Explorers prefix log lines with "> ", blank lines are kept to split transactions
+    let lines = input
+        .lines()
+        .map(|line| line.trim().trim_start_matches("> ").to_owned())
+        .collect::<Vec<_>>();
+    let mut transactions = split_transactions(&lines);
 
+    // Logs of several transactions copied together
+    if transactions.len() > 1 {
+        let renderer = Renderer::new()
+            .with_ansi(io::stdout().is_terminal())
+            .with_registry(registry);
+        let mut output = vec![];
+        for (index, logs) in transactions.iter().enumerate() {
+            let bound = parse_events(logs);
+            match format {
+                OutputFormat::Json => output.push(json!({
+                    "events": bound.as_ref().ok().map(|bound| sorted_events(bound.clone())),
+                    "error": bound.as_ref().err().map(|err| format!("{err:?}")),
+                })),
+                OutputFormat::Tree => {
+                    println!("Transaction #{index}:");
+                    match bound.and_then(|bound| Ok((root_contexts(logs)?, bound))) {
+                        Ok((roots, bound)) => print!("{}", renderer.render_logs(&roots, &bound)),
+                        Err(err) => println!("Error while bind logs: {err:?}"),
+                    }
+                }
+            }
+        }
+        if let OutputFormat::Json = format {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&output)
+                    .map_err(|err| anyhow!("Error while serialize bound logs: {}", err))?
+            );
+        }
+        return Ok(());
+    }
+    let lines = transactions.pop().unwrap_or_default();
+
+    let bound = parse_events(&lines).map_err(|err| anyhow!("Error while bind logs: {:?}", err))?;
+
+    match format {
+        OutputFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&sorted_events(bound))
+                    .map_err(|err| anyhow!("Error while serialize bound logs: {}", err))?
+            );
+        }
+        OutputFormat::Tree => {
+            let roots = root_contexts(&lines)
+                .map_err(|err| anyhow!("Error while parse logs: {:?}", err))?;
+            print!(
+                "{}",
+                Renderer::new()
+                    .with_ansi(io::stdout().is_terminal())
+                    .with_registry(registry)
+                    .render_logs(&roots, &bound)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "solana")]
+async fn parse_tx(
+    url: String,
+    signature: String,
+    idl: Option<IdlSource>,
+    program_id: Option<String>,
+    format: OutputFormat,
+    registry: &ProgramRegistry,
+) -> Result<(), anyhow::Error> {
+    use std::{
+        io::{self, IsTerminal},
+        str::FromStr,
+    };
+
+    use de_solana_client::CommitmentConfig;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_events_parser::{render::Renderer, transaction_parser::*};
+
+    let signature = Signature::from_str(&signature).map_err(|err| {
+        anyhow!(
+            "Error while parsing argument as transaction signature: {}",
+            err
+        )
+    })?;
+
+    #[cfg(feature = "idl")]
+    let idl = match idl {
+        Some(idl) => Some(load_idl(&url, idl, program_id).await?),
+        None => None,
+    };
+    #[cfg(not(feature = "idl"))]
+    if idl.is_some() || program_id.is_some() {
+        return Err(anyhow!("IDL decoding requires `idl` feature"));
+    }
+
+    let parsed = RpcClient::new(url)
+        .bind_transaction_instructions_logs(signature, CommitmentConfig::finalized())
+        .await
+        .map_err(|err| anyhow!("Error while bind transaction instructions: {}", err))?;
+
+    if let OutputFormat::Tree = format {
+        #[allow(unused_mut)]
+        let mut renderer = Renderer::new()
+            .with_ansi(io::stdout().is_terminal())
+            .with_registry(registry);
+        #[cfg(feature = "idl")]
+        if let Some((idl, program_id)) = idl.as_ref() {
+            renderer = renderer.with_idl(*program_id, idl);
+        }
+        print!("{}", renderer.render_transaction(&parsed));
+        return Ok(());
+    }
+
+    let mut events = parsed.meta.into_iter().collect::<Vec<_>>();
+    events.sort_by_key(|(ctx, _)| *ctx);
+
+    #[cfg(feature = "idl")]
+    if let Some((idl, program_id)) = idl.as_ref() {
+        println!("{}", decode_by_idl(events, idl, program_id)?);
+        return Ok(());
+    }
+    let output = serde_json::to_string_pretty(&events)
+        .map_err(|err| anyhow!("Error while serialize result of binding: {}", err))?;
+    println!("{output}");
+
+    Ok(())
+}
+
+#[cfg(feature = "solana")]
+async fn inspect_ix(
+    url: String,
+    signature: String,
+    index: String,
+    idl: Option<IdlSource>,
+    program_id: Option<String>,
+) -> Result<(), anyhow::Error> {
+    use de_solana_client::CommitmentConfig;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_events_parser::{
+        debug::InstructionInspection, native_instruction_parser::native_decomposers,
+        transaction_parser::*,
+    };
+
+    let signature = signature
+        .parse::<Signature>()
+        .map_err(|err| anyhow!("Error while parsing transaction signature: {}", err))?;
+    let position = index
+        .parse::<InstructionPosition>()
+        .map_err(|err| anyhow!("Error while parsing instruction index: {}", err))?;
+
+    #[cfg(feature = "idl")]
+    let idl = match idl {
+        Some(idl) => Some(load_idl(&url, idl, program_id).await?),
+        None => None,
+    };
+    #[cfg(not(feature = "idl"))]
+    if idl.is_some() || program_id.is_some() {
+        return Err(anyhow!("IDL lookup requires `idl` feature"));
+    }
+
+    let parsed = RpcClient::new(url)
+        .bind_transaction_instructions_logs(signature, CommitmentConfig::finalized())
+        .await
+        .map_err(|err| anyhow!("Error while bind transaction instructions: {}", err))?;
+
+    let registry = native_decomposers()
+        .into_iter()
+        .collect::<DecomposerRegistry>();
+    #[allow(unused_mut)]
+    let mut inspection = InstructionInspection::of_transaction(&parsed, position)
+        .ok_or_else(|| anyhow!("Transaction has no instruction at {position}"))?
+        .with_registry(&registry);
+    #[cfg(feature = "idl")]
+    if let Some((idl, program_id)) = idl.as_ref() {
+        if inspection.program_ctx.program_id == *program_id {
+            inspection = inspection.with_idl(idl);
+        }
+    }
+
+    print!("{inspection}");
+    Ok(())
+}
+
+#[cfg(not(feature = "solana"))]
+async fn inspect_ix(
+    _url: String,
+    _signature: String,
+    _index: String,
+    _idl: Option<IdlSource>,
+    _program_id: Option<String>,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`inspect-ix` requires solana feature"))
+}
+
+/// Read or fetch IDL and resolve its program id
+#[cfg(all(feature = "solana", feature = "idl"))]
+async fn load_idl(
+    url: &str,
+    idl: IdlSource,
+    program_id: Option<String>,
+) -> Result<
+    (
+        solana_events_parser::idl::Idl,
+        solana_events_parser::transaction_parser::Pubkey,
+    ),
+    anyhow::Error,
+> {
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_events_parser::{
+        idl::{self, Idl},
+        transaction_parser::Pubkey,
+    };
+
+    let program_id = program_id
+        .map(|program_id| {
+            program_id
+                .parse::<Pubkey>()
+                .map_err(|err| anyhow!("Error while parsing program id: {}", err))
+        })
+        .transpose()?;
+    let idl = match (idl, program_id) {
+        (IdlSource::File(path), _) => {
+            Idl::from_file(&path).map_err(|err| anyhow!("Error while read IDL: {}", err))?
+        }
+        (IdlSource::Onchain, Some(program_id)) => {
+            idl::fetch_onchain(&RpcClient::new(url.to_owned()), &program_id)
+                .await
+                .map_err(|err| anyhow!("Error while fetch on-chain IDL: {}", err))?
+        }
+        (IdlSource::Onchain, None) => {
+            return Err(anyhow!("Program id is required to fetch on-chain IDL"))
+        }
+    };
+    let program_id = match program_id {
+        Some(program_id) => program_id,
+        None => idl.program_id().ok_or_else(|| {
+            anyhow!("IDL metadata has no address, use --program-id to provide it")
+        })?,
+    };
+    Ok((idl, program_id))
+}
+
+#[cfg(all(feature = "solana", feature = "idl"))]
+fn decode_by_idl(
+    events: Vec<(
+        solana_events_parser::log_parser::ProgramContext,
+        (
+            solana_sdk::instruction::Instruction,
+            Vec<solana_events_parser::log_parser::ProgramLog>,
+        ),
+    )>,
+    idl: &solana_events_parser::idl::Idl,
+    program_id: &solana_events_parser::transaction_parser::Pubkey,
+) -> Result<String, anyhow::Error> {
+    use solana_events_parser::{
+        idl::Decoded,
+        log_parser::{ProgramContext, ProgramLog},
+    };
+
+    #[derive(serde::Serialize)]
+    struct DecodedInvocation {
+        program_ctx: ProgramContext,
+        instruction: Option<Decoded>,
+        events: Vec<Decoded>,
+        logs: Vec<ProgramLog>,
+    }
+
+    let decoded = events
+        .into_iter()
+        .map(|(program_ctx, (ix, logs))| {
+            if ix.program_id.ne(program_id) {
+                return DecodedInvocation {
+                    program_ctx,
+                    instruction: None,
+                    events: vec![],
+                    logs,
+                };
+            }
+
+            let instruction = idl
+                .decode_instruction(&ix.data)
+                .map_err(|err| {
+                    tracing::warn!("Error while decode instruction {program_ctx:?}: {err}")
+                })
+                .ok()
+                .flatten();
+            let events = logs
+                .iter()
+                .filter_map(|log| {
+                    idl.decode_program_log(log)
+                        .map_err(|err| {
+                            tracing::warn!("Error while decode event {program_ctx:?}: {err}")
+                        })
+                        .ok()
+                        .flatten()
+                })
+                .collect();
+
+            DecodedInvocation {
+                program_ctx,
+                instruction,
+                events,
+                logs,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::to_string_pretty(&decoded)
+        .map_err(|err| anyhow!("Error while serialize decoded transaction: {}", err))
+}
+
+#[cfg(not(feature = "solana"))]
+async fn parse_tx(
+    _url: String,
+    _signature: String,
+    _idl: Option<IdlSource>,
+    _program_id: Option<String>,
+    _format: OutputFormat,
+    _registry: &ProgramRegistry,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`parse-tx` requires solana feature"))
+}
+
+#[cfg(feature = "serve")]
+type HttpResult = Result<serde_json::Value, (hyper::StatusCode, String)>;
+
+#[cfg(feature = "serve")]
+async fn serve(url: String, port: u16, commitment: Commitment) -> Result<(), anyhow::Error> {
+    use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Server,
+    };
+    use solana_client::nonblocking::rpc_client::RpcClient;
+
+    let client = Arc::new(RpcClient::new(url));
+    let make_service = make_service_fn(move |_| {
+        let client = Arc::clone(&client);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let client = Arc::clone(&client);
+                async move { Ok::<_, Infallible>(handle_request(&client, commitment, request).await) }
+            }))
+        }
+    });
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    tracing::info!("Listening on http://{addr}");
+    Server::bind(&addr)
+        .serve(make_service)
+        .await
+        .map_err(|err| anyhow!("Error while serving HTTP: {}", err))
+}
+
+#[cfg(feature = "serve")]
+async fn handle_request(
+    client: &solana_client::nonblocking::rpc_client::RpcClient,
+    commitment: Commitment,
+    request: hyper::Request<hyper::Body>,
+) -> hyper::Response<hyper::Body> {
+    use hyper::{Body, Method, Response, StatusCode};
+    use serde_json::json;
+
+    let path = request
+        .uri()
+        .path()
+        .trim_matches('/')
+        .split('/')
+        .collect::<Vec<_>>();
+    let result = match (request.method(), path.as_slice()) {
+        (&Method::GET, ["tx", signature]) => serve_tx(client, signature, commitment).await,
+        (&Method::GET, ["program", program_id, "events"]) => {
+            serve_program_events(client, program_id, request.uri().query(), commitment).await
+        }
+        _ => Err((StatusCode::NOT_FOUND, "Not found".to_owned())),
+    };
+
+    let (status, body) = match result {
+        Ok(body) => (StatusCode::OK, body),
+        Err((status, err)) => {
+            tracing::warn!("{} {}: {err}", request.method(), request.uri());
+            (status, json!({ "error": err }))
+        }
+    };
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("Response of valid status & header")
+}
+
+/// Parsed transaction as [`solana_events_parser::dto::TransactionParsedMetaDto`]
+#[cfg(feature = "serve")]
+async fn serve_tx(
+    client: &solana_client::nonblocking::rpc_client::RpcClient,
+    signature: &str,
+    commitment: Commitment,
+) -> HttpResult {
+    use hyper::StatusCode;
+    use solana_events_parser::{dto::TransactionParsedMetaDto, transaction_parser::*};
+
+    let signature = signature
+        .parse::<Signature>()
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid signature: {err}")))?;
+    let parsed = client
+        .bind_transaction_instructions_logs(signature, commitment.into())
+        .await
+        .map_err(|err| (StatusCode::BAD_GATEWAY, err.to_string()))?;
+
+    serde_json::to_value(TransactionParsedMetaDto::from(&parsed))
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Events (`Program data:` logs) of last `limit` successful transactions of program,
+/// newest first
+#[cfg(feature = "serve")]
+async fn serve_program_events(
+    client: &solana_client::nonblocking::rpc_client::RpcClient,
+    program_id: &str,
+    query: Option<&str>,
+    commitment: Commitment,
+) -> HttpResult {
+    use std::collections::BTreeMap;
+
+    use hyper::StatusCode;
+    use serde_json::json;
+    use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+    use solana_events_parser::{
+        dto::{program_ctx_key, ProgramLogDto},
+        transaction_parser::*,
+    };
+
+    const DEFAULT_LIMIT: usize = 10;
+    // Every transaction is a separate RPC request
+    const MAX_LIMIT: usize = 100;
+
+    let program_id = program_id.parse::<Pubkey>().map_err(|err| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Invalid program id: {err}"),
+        )
+    })?;
+    let limit = match query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("limit="))
+    {
+        Some(limit) => limit
+            .parse::<usize>()
+            .ok()
+            .filter(|limit| (1..=MAX_LIMIT).contains(limit))
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("`limit` must be in 1..={MAX_LIMIT}"),
+                )
+            })?,
+        None => DEFAULT_LIMIT,
+    };
+    let commitment = de_solana_client::CommitmentConfig::from(commitment);
+
+    let signatures = client
+        .get_signatures_for_address_with_config(
+            &program_id,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(limit),
+                commitment: Some(commitment),
+                ..Default::default()
+            },
+        )
+        .await
+        .map_err(|err| (StatusCode::BAD_GATEWAY, err.to_string()))?;
+
+    let mut transactions = vec![];
+    for status in signatures.into_iter().filter(|status| status.err.is_none()) {
+        let signature = status
+            .signature
+            .parse::<Signature>()
+            .map_err(|err| (StatusCode::BAD_GATEWAY, err.to_string()))?;
+        let parsed = client
+            .bind_transaction_instructions_logs(signature, commitment)
+            .await
+            .map_err(|err| (StatusCode::BAD_GATEWAY, err.to_string()))?;
+
+        let events = parsed
+            .meta
+            .iter()
+            .filter(|(ctx, _)| ctx.program_id == program_id)
+            .map(|(ctx, (_, logs))| {
+                (
+                    program_ctx_key(ctx),
+                    logs.iter()
+                        .filter(|log| matches!(log, ProgramLog::Data(_)))
+                        .map(ProgramLogDto::from)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        transactions.push(json!({
+            "signature": status.signature,
+            "slot": parsed.slot,
+            "block_time": parsed.block_time,
+            "events": events,
+        }));
+    }
+
+    Ok(json!(transactions))
+}
+
+#[cfg(not(feature = "serve"))]
+async fn serve(_url: String, _port: u16, _commitment: Commitment) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`serve` requires serve feature"))
+}
+
+#[cfg(all(feature = "storage", feature = "rocksdb"))]
+fn quarantine(
+    program_id: String,
+    db: PathBuf,
+    action: QuarantineAction,
+) -> Result<(), anyhow::Error> {
+    use serde_json::json;
+    use solana_events_parser::storage::{rocksdb::DB, ResyncedTransactionsPtrStorage};
+
+    let program_id = program_id.parse()?;
+    let db = DB::open_default(db)?;
+    let quarantined = db
+        .get_quarantined_transactions(&program_id)
+        .map_err(|err| anyhow!("Error while get quarantined transactions: {err:?}"))?;
+
+    match action {
+        QuarantineAction::List => {
+            for transaction in quarantined {
+                println!(
+                    "{}",
+                    json!({
+                        "signature": transaction.signature.to_string(),
+                        "reason": transaction.reason,
+                        "attempts": transaction.attempts,
+                        "timestamp_ms": transaction.timestamp_ms,
+                        "retry_requested": transaction.retry_requested,
+                    })
+                );
+            }
+        }
+        QuarantineAction::Retry { signatures } => {
+            let signatures = signatures
+                .iter()
+                .map(|signature| signature.parse())
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(unknown) = signatures
+                .iter()
+                .find(|signature| !quarantined.iter().any(|t| &t.signature == *signature))
+            {
+                return Err(anyhow!("Transaction {unknown} isn't quarantined"));
+            }
+
+            for mut transaction in quarantined {
+                if signatures.is_empty() || signatures.contains(&transaction.signature) {
+                    transaction.retry_requested = true;
+                    db.quarantine_transaction(&program_id, &transaction)
+                        .map_err(|err| anyhow!("Error while request retry: {err:?}"))?;
+                    println!("{}", transaction.signature);
+                }
+            }
+        }
+    }
     Ok(())
 }
+
+#[cfg(not(all(feature = "storage", feature = "rocksdb")))]
+fn quarantine(
+    _program_id: String,
+    _db: PathBuf,
+    _action: QuarantineAction,
+) -> Result<(), anyhow::Error> {
+    Err(anyhow!("`quarantine` requires storage & rocksdb features"))
+}
@@ -0,0 +1,513 @@
+//! Instructions of Metaplex Token Metadata & Bubblegum (compressed NFT) programs and
+//! extraction of NFT mint/transfer/burn events from parsed transactions.
+//!
+//! Token Metadata instructions are tagged by one byte, Bubblegum ones by anchor
+//! discriminator. Compressed NFT leaf is emitted by Bubblegum as `spl-noop` inner instruction,
+//! so [`TransactionParsedMeta::nft_events`] takes it from children of Bubblegum instruction.
+
+use std::{collections::HashMap, io};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    idl::sighash,
+    instruction_parser::{split_discriminator, DISCRIMINATOR_SIZE},
+    native_instruction_parser::{
+        unknown_tag, DataReader, NativeInstruction, NativeInstructionDecomposer,
+    },
+    transaction_parser::{
+        DecomposeInstruction, Instruction, ProgramContext, Pubkey, TransactionParsedMeta,
+    },
+};
+
+pub const TOKEN_METADATA_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+pub const SPL_NOOP_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// Instructions of Token Metadata program
+///
+/// Only arguments needed for NFT events are unpacked, the other instructions are
+/// [`TokenMetadataInstruction::Other`] with name of instruction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenMetadataInstruction {
+    CreateMetadataAccountV3,
+    CreateMasterEditionV3,
+    MintNewEditionFromMasterEditionViaToken,
+    BurnNft,
+    BurnEditionNft,
+    Burn { amount: u64 },
+    Create,
+    Mint { amount: u64 },
+    Transfer { amount: u64 },
+    Print,
+    Other { tag: u8, name: String },
+}
+
+/// Names of Token Metadata instructions by tag
+const TOKEN_METADATA_INSTRUCTIONS: &[&str] = &[
+    "CreateMetadataAccount",
+    "UpdateMetadataAccount",
+    "DeprecatedCreateMasterEdition",
+    "DeprecatedMintNewEditionFromMasterEditionViaPrintingToken",
+    "UpdatePrimarySaleHappenedViaToken",
+    "DeprecatedSetReservationList",
+    "DeprecatedCreateReservationList",
+    "SignMetadata",
+    "DeprecatedMintPrintingTokensViaToken",
+    "DeprecatedMintPrintingTokens",
+    "CreateMasterEdition",
+    "MintNewEditionFromMasterEditionViaToken",
+    "ConvertMasterEditionV1ToV2",
+    "MintNewEditionFromMasterEditionViaVaultProxy",
+    "PuffMetadata",
+    "UpdateMetadataAccountV2",
+    "CreateMetadataAccountV2",
+    "CreateMasterEditionV3",
+    "VerifyCollection",
+    "Utilize",
+    "ApproveUseAuthority",
+    "RevokeUseAuthority",
+    "UnverifyCollection",
+    "ApproveCollectionAuthority",
+    "RevokeCollectionAuthority",
+    "SetAndVerifyCollection",
+    "FreezeDelegatedAccount",
+    "ThawDelegatedAccount",
+    "RemoveCreatorVerification",
+    "BurnNft",
+    "VerifySizedCollectionItem",
+    "UnverifySizedCollectionItem",
+    "SetAndVerifySizedCollectionItem",
+    "CreateMetadataAccountV3",
+    "SetCollectionSize",
+    "SetTokenStandard",
+    "BubblegumSetCollectionSize",
+    "BurnEditionNft",
+    "CreateEscrowAccount",
+    "CloseEscrowAccount",
+    "TransferOutOfEscrow",
+    "Burn",
+    "Create",
+    "Mint",
+    "Delegate",
+    "Revoke",
+    "Lock",
+    "Unlock",
+    "Migrate",
+    "Transfer",
+    "Update",
+    "Use",
+    "Verify",
+    "Unverify",
+    "Collect",
+    "Print",
+    "Resize",
+    "CloseAccounts",
+];
+
+impl NativeInstruction for TokenMetadataInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[TOKEN_METADATA_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        let mut reader = DataReader(data);
+        let tag = reader.read_u8()?;
+        let name = TOKEN_METADATA_INSTRUCTIONS
+            .get(tag as usize)
+            .ok_or_else(|| unknown_tag("TokenMetadata", tag))?;
+
+        // `Burn`, `Mint` & `Transfer` arguments are enums with `V1 { amount: u64, .. }`
+        let mut read_amount = || {
+            reader.read_u8()?;
+            reader.read_u64()
+        };
+        let ix = match *name {
+            "CreateMetadataAccountV3" => Self::CreateMetadataAccountV3,
+            "CreateMasterEditionV3" => Self::CreateMasterEditionV3,
+            "MintNewEditionFromMasterEditionViaToken" => {
+                Self::MintNewEditionFromMasterEditionViaToken
+            }
+            "BurnNft" => Self::BurnNft,
+            "BurnEditionNft" => Self::BurnEditionNft,
+            "Burn" => Self::Burn {
+                amount: read_amount()?,
+            },
+            "Create" => Self::Create,
+            "Mint" => Self::Mint {
+                amount: read_amount()?,
+            },
+            "Transfer" => Self::Transfer {
+                amount: read_amount()?,
+            },
+            "Print" => Self::Print,
+            name => Self::Other {
+                tag,
+                name: name.to_owned(),
+            },
+        };
+        Ok(ix)
+    }
+}
+
+/// Instructions of Bubblegum program
+///
+/// Leaf changing instructions are unpacked with `nonce` (asset id seed) and `index` (leaf
+/// position in tree), the other ones are [`BubblegumInstruction::Other`] with name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BubblegumInstruction {
+    MintV1,
+    MintToCollectionV1,
+    Transfer { nonce: u64, index: u32 },
+    Burn { nonce: u64, index: u32 },
+    Delegate { nonce: u64, index: u32 },
+    Redeem { nonce: u64, index: u32 },
+    DecompressV1,
+    Other { name: String },
+}
+
+/// snake_case names of Bubblegum instructions, anchor discriminators are calculated by them
+const BUBBLEGUM_INSTRUCTIONS: &[&str] = &[
+    "burn",
+    "cancel_redeem",
+    "compress",
+    "create_tree",
+    "decompress_v1",
+    "delegate",
+    "mint_to_collection_v1",
+    "mint_v1",
+    "redeem",
+    "set_and_verify_collection",
+    "set_decompressable_state",
+    "set_decompressible_state",
+    "set_tree_delegate",
+    "transfer",
+    "unverify_collection",
+    "unverify_creator",
+    "update_metadata",
+    "verify_collection",
+    "verify_creator",
+];
+
+lazy_static! {
+    static ref BUBBLEGUM_DISCRIMINATORS: HashMap<[u8; DISCRIMINATOR_SIZE], &'static str> =
+        BUBBLEGUM_INSTRUCTIONS
+            .iter()
+            .map(|name| (sighash("global", name), *name))
+            .collect();
+}
+
+impl NativeInstruction for BubblegumInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[BUBBLEGUM_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        let (discriminator, args) = split_discriminator(data).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "Bubblegum discriminator")
+        })?;
+        let name = <[u8; DISCRIMINATOR_SIZE]>::try_from(discriminator)
+            .ok()
+            .and_then(|discriminator| BUBBLEGUM_DISCRIMINATORS.get(&discriminator))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown Bubblegum discriminator: {discriminator:?}"),
+                )
+            })?;
+
+        // Leaf changing instructions args: `root`, `data_hash`, `creator_hash`, `nonce`, `index`
+        let read_leaf = || {
+            let mut reader = DataReader(args);
+            reader.read_bytes::<96>()?;
+            Ok::<_, io::Error>((reader.read_u64()?, reader.read_u32()?))
+        };
+        let ix = match *name {
+            "mint_v1" => Self::MintV1,
+            "mint_to_collection_v1" => Self::MintToCollectionV1,
+            "transfer" => {
+                let (nonce, index) = read_leaf()?;
+                Self::Transfer { nonce, index }
+            }
+            "burn" => {
+                let (nonce, index) = read_leaf()?;
+                Self::Burn { nonce, index }
+            }
+            "delegate" => {
+                let (nonce, index) = read_leaf()?;
+                Self::Delegate { nonce, index }
+            }
+            "redeem" => {
+                let (nonce, index) = read_leaf()?;
+                Self::Redeem { nonce, index }
+            }
+            "decompress_v1" => Self::DecompressV1,
+            name => Self::Other {
+                name: name.to_owned(),
+            },
+        };
+        Ok(ix)
+    }
+}
+
+/// Compressed NFT leaf, as emitted by Bubblegum into `spl-noop`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafSchema {
+    /// Asset id, see [`asset_id`]
+    pub id: Pubkey,
+    pub owner: Pubkey,
+    pub delegate: Pubkey,
+    pub nonce: u64,
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+}
+
+impl LeafSchema {
+    /// Unpack `spl-noop` instruction data of Bubblegum `LeafSchemaEvent`,
+    /// `None` for other events (e.g. change logs of account compression)
+    pub fn from_noop_data(data: &[u8]) -> Option<Self> {
+        let mut reader = DataReader(data);
+        // `AccountCompressionEvent::ApplicationData(ApplicationDataEvent::V1(..))`
+        if reader.read_u8().ok()? != 1 || reader.read_u8().ok()? != 0 {
+            return None;
+        }
+        let len = reader.read_u32().ok()? as usize;
+        let application_data = reader.rest();
+        let mut reader = DataReader(application_data.get(..len)?);
+
+        // `BubblegumEventType::LeafSchemaEvent`, `Version::V1`, `LeafSchema::V1`
+        if reader.read_bytes::<3>().ok()? != [1, 0, 0] {
+            return None;
+        }
+        Some(Self {
+            id: reader.read_pubkey().ok()?,
+            owner: reader.read_pubkey().ok()?,
+            delegate: reader.read_pubkey().ok()?,
+            nonce: reader.read_u64().ok()?,
+            data_hash: reader.read_bytes().ok()?,
+            creator_hash: reader.read_bytes().ok()?,
+        })
+    }
+}
+
+/// Id of compressed NFT: PDA of Bubblegum by `["asset", tree, nonce]`
+pub fn asset_id(tree: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"asset", tree.as_ref(), &nonce.to_le_bytes()],
+        &BUBBLEGUM_PROGRAM_ID,
+    )
+    .0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NftEventKind {
+    Mint,
+    Transfer,
+    Burn,
+}
+
+/// NFT identity of [`NftEvent`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NftAsset {
+    /// Token Metadata NFT by mint
+    Mint(Pubkey),
+    /// Bubblegum compressed NFT
+    Compressed {
+        tree: Pubkey,
+        asset_id: Pubkey,
+        leaf_index: Option<u32>,
+        nonce: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NftEvent {
+    pub program_ctx: ProgramContext,
+    pub kind: NftEventKind,
+    pub asset: NftAsset,
+    /// Owner after mint & transfer, owner before burn
+    pub owner: Option<Pubkey>,
+    /// Leaf emitted by instruction, for compressed NFT mint & transfer
+    pub leaf: Option<LeafSchema>,
+}
+
+impl TransactionParsedMeta {
+    /// Mint, transfer & burn events of Token Metadata & Bubblegum instructions, ordered by
+    /// program context
+    pub fn nft_events(&self) -> Vec<NftEvent> {
+        let mut events = self
+            .meta
+            .iter()
+            .filter(|(ctx, (ix, _))| ctx.program_id == ix.program_id)
+            .filter_map(|(ctx, (ix, _))| match ix.program_id {
+                TOKEN_METADATA_PROGRAM_ID => token_metadata_event(*ctx, ix),
+                BUBBLEGUM_PROGRAM_ID => self.bubblegum_event(*ctx, ix),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|event| event.program_ctx);
+        events
+    }
+
+    fn bubblegum_event(&self, ctx: ProgramContext, ix: &Instruction) -> Option<NftEvent> {
+        let account = |index: usize| ix.accounts.get(index).map(|meta| meta.pubkey);
+        let leaf = self
+            .parent_ix
+            .iter()
+            .filter(|(child, parent)| **parent == ctx && child.program_id == SPL_NOOP_PROGRAM_ID)
+            .filter_map(|(child, _)| Some((child, self.meta.get(child)?)))
+            .min_by_key(|(child, _)| **child)
+            .and_then(|(_, (noop_ix, _))| LeafSchema::from_noop_data(&noop_ix.data));
+
+        let (kind, tree, leaf_index, nonce, owner) =
+            match BubblegumInstruction::unpack(&ix.data).ok()? {
+                BubblegumInstruction::MintV1 | BubblegumInstruction::MintToCollectionV1 => {
+                    let leaf = leaf.as_ref()?;
+                    (
+                        NftEventKind::Mint,
+                        account(3)?,
+                        None,
+                        leaf.nonce,
+                        leaf.owner,
+                    )
+                }
+                BubblegumInstruction::Transfer { nonce, index } => (
+                    NftEventKind::Transfer,
+                    account(4)?,
+                    Some(index),
+                    nonce,
+                    account(3)?,
+                ),
+                BubblegumInstruction::Burn { nonce, index } => (
+                    NftEventKind::Burn,
+                    account(3)?,
+                    Some(index),
+                    nonce,
+                    account(1)?,
+                ),
+                _ => return None,
+            };
+
+        Some(NftEvent {
+            program_ctx: ctx,
+            kind,
+            asset: NftAsset::Compressed {
+                tree,
+                asset_id: leaf
+                    .as_ref()
+                    .map(|leaf| leaf.id)
+                    .unwrap_or_else(|| asset_id(&tree, nonce)),
+                leaf_index,
+                nonce,
+            },
+            owner: Some(owner),
+            leaf,
+        })
+    }
+}
+
+fn token_metadata_event(ctx: ProgramContext, ix: &Instruction) -> Option<NftEvent> {
+    let account = |index: usize| ix.accounts.get(index).map(|meta| meta.pubkey);
+    let (kind, mint, owner) = match TokenMetadataInstruction::unpack(&ix.data).ok()? {
+        TokenMetadataInstruction::CreateMasterEditionV3 => (NftEventKind::Mint, account(1)?, None),
+        TokenMetadataInstruction::Mint { .. } => (NftEventKind::Mint, account(5)?, account(1)),
+        TokenMetadataInstruction::Transfer { .. } => {
+            (NftEventKind::Transfer, account(4)?, account(3))
+        }
+        TokenMetadataInstruction::BurnNft => (NftEventKind::Burn, account(2)?, account(1)),
+        TokenMetadataInstruction::BurnEditionNft => (NftEventKind::Burn, account(2)?, account(1)),
+        TokenMetadataInstruction::Burn { .. } => (NftEventKind::Burn, account(4)?, account(0)),
+        _ => return None,
+    };
+
+    Some(NftEvent {
+        program_ctx: ctx,
+        kind,
+        asset: NftAsset::Mint(mint),
+        owner,
+        leaf: None,
+    })
+}
+
+/// Decomposers without consumers for Token Metadata & Bubblegum
+pub fn metaplex_decomposers() -> Vec<Box<dyn DecomposeInstruction + Send + Sync>> {
+    vec![
+        NativeInstructionDecomposer::<TokenMetadataInstruction>::new_boxed(),
+        NativeInstructionDecomposer::<BubblegumInstruction>::new_boxed(),
+    ]
+}
+
+#[cfg(test)]
+mod metaplex_test {
+    use super::*;
+
+    #[test]
+    fn test_bubblegum() {
+        let tree = Pubkey::new_unique();
+        let data = [
+            sighash("global", "transfer").as_slice(),
+            &[0; 96],
+            &7u64.to_le_bytes(),
+            &3u32.to_le_bytes(),
+        ]
+        .concat();
+        assert_eq!(
+            BubblegumInstruction::unpack(&data).unwrap(),
+            BubblegumInstruction::Transfer { nonce: 7, index: 3 }
+        );
+        assert!(BubblegumInstruction::unpack(&[0; 8]).is_err());
+
+        let leaf = LeafSchema {
+            id: asset_id(&tree, 7),
+            owner: Pubkey::new_unique(),
+            delegate: Pubkey::new_unique(),
+            nonce: 7,
+            data_hash: [1; 32],
+            creator_hash: [2; 32],
+        };
+        let application_data = [
+            [1, 0, 0].as_slice(),
+            leaf.id.as_ref(),
+            leaf.owner.as_ref(),
+            leaf.delegate.as_ref(),
+            &leaf.nonce.to_le_bytes(),
+            &leaf.data_hash,
+            &leaf.creator_hash,
+            // `leaf_hash`
+            &[3; 32],
+        ]
+        .concat();
+        let noop_data = [
+            [1, 0].as_slice(),
+            &(application_data.len() as u32).to_le_bytes(),
+            &application_data,
+        ]
+        .concat();
+        assert_eq!(LeafSchema::from_noop_data(&noop_data), Some(leaf));
+        assert_eq!(LeafSchema::from_noop_data(&[0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_token_metadata() {
+        assert_eq!(
+            TokenMetadataInstruction::unpack(&[49, 0, 1, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+            TokenMetadataInstruction::Transfer { amount: 1 }
+        );
+        assert_eq!(
+            TokenMetadataInstruction::unpack(&[33]).unwrap(),
+            TokenMetadataInstruction::CreateMetadataAccountV3
+        );
+        assert_eq!(
+            TokenMetadataInstruction::unpack(&[7]).unwrap(),
+            TokenMetadataInstruction::Other {
+                tag: 7,
+                name: "SignMetadata".to_owned()
+            }
+        );
+        assert!(TokenMetadataInstruction::unpack(&[200]).is_err());
+    }
+}
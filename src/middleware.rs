@@ -0,0 +1,255 @@
+//! Composable wrappers around consuming of transaction by
+//! [`EventsReader`](crate::event_reader_service::EventsReader), in the manner of `tower`
+//! layers
+//!
+//! Consuming (consumers of program, then `transaction_consumer`) is [`TransactionConsumer`],
+//! every [`Layer`] of [`EventsReader::layers`](crate::event_reader_service::EventsReader::layers)
+//! wraps it into another one. The first layer is the outermost one:
+//!
+//! ```
+//! use std::{sync::Arc, time::Duration};
+//!
+//! use solana_events_parser::{
+//!     event_reader_service::{RetryParams, TokioClock},
+//!     middleware::{BoxedLayer, LoggingLayer, RetryLayer, TimeoutLayer},
+//! };
+//!
+//! // Every attempt is logged, retried if failed or timed out
+//! let layers: Vec<BoxedLayer> = vec![
+//!     Arc::new(LoggingLayer),
+//!     Arc::new(RetryLayer::new(RetryParams::default(), Arc::new(TokioClock))),
+//!     Arc::new(TimeoutLayer::new(Duration::from_secs(10), Arc::new(TokioClock))),
+//! ];
+//! ```
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use tracing::*;
+
+use crate::{
+    event_reader_service::{Clock, Error, ErrorClass, Result, RetryClassifier, RetryParams},
+    storage,
+    transaction_parser::{Signature as SolanaSignature, TransactionParsedMeta},
+};
+
+/// Consuming of transaction, wrapped by [`Layer`]s
+pub type TransactionConsumer = Arc<
+    dyn Send + Sync + Fn(SolanaSignature, TransactionParsedMeta) -> BoxFuture<'static, Result<()>>,
+>;
+
+/// Wrapper of `S` into another `S` with additional behavior, like `tower::Layer`
+pub trait Layer<S>: Send + Sync {
+    fn layer(&self, inner: S) -> S;
+}
+
+impl<S, F: Send + Sync + Fn(S) -> S> Layer<S> for F {
+    fn layer(&self, inner: S) -> S {
+        self(inner)
+    }
+}
+
+/// Layer of [`EventsReader::layers`](crate::event_reader_service::EventsReader::layers)
+pub type BoxedLayer = Arc<dyn Layer<TransactionConsumer>>;
+
+/// Wrap `inner` by `layers`, the first one is the outermost
+pub fn apply_layers(layers: &[BoxedLayer], inner: TransactionConsumer) -> TransactionConsumer {
+    layers
+        .iter()
+        .rev()
+        .fold(inner, |inner, layer| layer.layer(inner))
+}
+
+/// Logs start, duration & result of consuming
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingLayer;
+
+impl Layer<TransactionConsumer> for LoggingLayer {
+    fn layer(&self, inner: TransactionConsumer) -> TransactionConsumer {
+        Arc::new(move |signature, transaction| {
+            let inner = Arc::clone(&inner);
+            Box::pin(async move {
+                debug!("Start consuming of {signature}");
+                let started_at = Instant::now();
+                let result = inner(signature, transaction).await;
+                match result.as_ref() {
+                    Ok(()) => info!("{signature} consumed in {:?}", started_at.elapsed()),
+                    Err(err) => error!(
+                        "Error while consume {signature} in {:?}: {err:?}",
+                        started_at.elapsed()
+                    ),
+                }
+                result
+            })
+        })
+    }
+}
+
+/// Callback of consumed transaction: signature, duration & result of consuming
+pub type OnConsumedFn = Arc<dyn Send + Sync + Fn(SolanaSignature, Duration, &Result<()>)>;
+
+/// Passes duration & result of every consuming into callback, e.g. to export metrics
+#[derive(Clone)]
+pub struct MetricsLayer {
+    on_consumed: OnConsumedFn,
+}
+
+impl MetricsLayer {
+    pub fn new(
+        on_consumed: impl Send + Sync + Fn(SolanaSignature, Duration, &Result<()>) + 'static,
+    ) -> Self {
+        Self {
+            on_consumed: Arc::new(on_consumed),
+        }
+    }
+}
+
+impl Layer<TransactionConsumer> for MetricsLayer {
+    fn layer(&self, inner: TransactionConsumer) -> TransactionConsumer {
+        let on_consumed = Arc::clone(&self.on_consumed);
+        Arc::new(move |signature, transaction| {
+            let (inner, on_consumed) = (Arc::clone(&inner), Arc::clone(&on_consumed));
+            Box::pin(async move {
+                let started_at = Instant::now();
+                let result = inner(signature, transaction).await;
+                on_consumed(signature, started_at.elapsed(), &result);
+                result
+            })
+        })
+    }
+}
+
+/// Cancels consuming exceeded `timeout` with [`Error::ConsumerTimeout`]
+#[derive(Clone)]
+pub struct TimeoutLayer {
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl TimeoutLayer {
+    pub fn new(timeout: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self { timeout, clock }
+    }
+}
+
+impl Layer<TransactionConsumer> for TimeoutLayer {
+    fn layer(&self, inner: TransactionConsumer) -> TransactionConsumer {
+        let (timeout, clock) = (self.timeout, Arc::clone(&self.clock));
+        Arc::new(move |signature, transaction| {
+            let (inner, clock) = (Arc::clone(&inner), Arc::clone(&clock));
+            Box::pin(async move {
+                tokio::select! {
+                    result = inner(signature, transaction) => result,
+                    _ = clock.sleep(timeout) => {
+                        warn!(%signature, ?timeout, "Consumers of transaction exceeded timeout, cancel");
+                        Err(Error::ConsumerTimeout { signature, timeout })
+                    }
+                }
+            })
+        })
+    }
+}
+
+/// Retries failed consuming by [`RetryParams`], errors classified as
+/// [`ErrorClass::Fatal`] by `classifier` (if any) are returned right away
+#[derive(Clone)]
+pub struct RetryLayer {
+    params: RetryParams,
+    clock: Arc<dyn Clock>,
+    classifier: Option<Arc<dyn RetryClassifier>>,
+}
+
+impl RetryLayer {
+    pub fn new(params: RetryParams, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            params,
+            clock,
+            classifier: None,
+        }
+    }
+
+    pub fn with_classifier(mut self, classifier: Arc<dyn RetryClassifier>) -> Self {
+        self.classifier = Some(classifier);
+        self
+    }
+}
+
+impl Layer<TransactionConsumer> for RetryLayer {
+    fn layer(&self, inner: TransactionConsumer) -> TransactionConsumer {
+        let this = self.clone();
+        Arc::new(move |signature, transaction| {
+            let (inner, this) = (Arc::clone(&inner), this.clone());
+            Box::pin(async move {
+                let mut attempt = 1;
+                loop {
+                    let err = match inner(signature, transaction.clone()).await {
+                        Ok(()) => return Ok(()),
+                        Err(err) => err,
+                    };
+                    let is_fatal = this
+                        .classifier
+                        .as_ref()
+                        .is_some_and(|classifier| classifier.classify(&err) == ErrorClass::Fatal);
+                    if is_fatal || attempt >= this.params.attempts_count().get() {
+                        return Err(err);
+                    }
+                    warn!("Error while consume {signature}, attempt {attempt}: {err:?}, retry");
+                    this.clock.sleep(this.params.delay(attempt)).await;
+                    attempt += 1;
+                }
+            })
+        })
+    }
+}
+
+/// Consumes every transaction once, even if it's consumed by both live & resync paths or
+/// by several replicas sharing `storage`
+///
+/// Key `name + signature` is registered in `storage` before consuming & removed if consuming
+/// failed, so failed transaction will be consumed again. Delivery is at-most-once if process
+/// crashes during consuming, the same as of
+/// [`IdempotentConsumer`](crate::event_reader_service::IdempotentConsumer), which is used
+/// for side effects of single instruction or event
+pub struct IdempotencyLayer<S> {
+    name: String,
+    storage: Arc<S>,
+}
+
+impl<S> IdempotencyLayer<S> {
+    pub fn new(name: impl Into<String>, storage: Arc<S>) -> Self {
+        Self {
+            name: name.into(),
+            storage,
+        }
+    }
+}
+
+impl<S> Layer<TransactionConsumer> for IdempotencyLayer<S>
+where
+    S: storage::RegisterIdempotencyKey + Send + Sync + 'static,
+    Error: From<S::Error>,
+{
+    fn layer(&self, inner: TransactionConsumer) -> TransactionConsumer {
+        let (name, storage) = (self.name.clone(), Arc::clone(&self.storage));
+        Arc::new(move |signature, transaction| {
+            let (inner, storage) = (Arc::clone(&inner), Arc::clone(&storage));
+            let key = [name.as_bytes(), signature.as_ref()].concat();
+            let name = name.clone();
+            Box::pin(async move {
+                if !storage.register_idempotency_key(&key)? {
+                    debug!("{signature} already consumed by {name}, skip");
+                    return Ok(());
+                }
+
+                let result = inner(signature, transaction).await;
+                if result.is_err() {
+                    storage.unregister_idempotency_key(&key)?;
+                }
+                result
+            })
+        })
+    }
+}
@@ -0,0 +1,864 @@
+//! In-memory [`TransactionSource`], [`SignatureSource`], [`LogsSource`] & [`BlocksSource`]
+//! over recorded [`Fixture`]s, to test logic of
+//! [`EventsReader`](crate::event_reader_service::EventsReader) deterministically, without live
+//! endpoints
+//!
+//! [`MockChain::push`] appends transaction to chain & notifies logs and blocks subscribers
+//! (every transaction is a block of its slot), [`MockChain::push_missed`] only appends it, as
+//! transaction missed by websocket. Every transaction is finalized, unless finalized slot is
+//! limited by [`MockChain::set_finalized_slot`], [`MockChain::reinclude`] simulates reorg.
+//! `config` of requests is ignored
+//!
+//! [`MockClock`] is [`Clock`] advanced manually, so schedules of reader are tested step by step
+//!
+//! [`MemoryStorage`] keeps registered transactions, resync pointers, idempotency keys, claims
+//! & events in memory, [`invocation`] synthesizes transaction of program with given logs, and
+//! [`MockChain::events_reader_builder`] wires them all into reader
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::Infallible,
+    result,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::{
+    future::{self, BoxFuture},
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcTransactionConfig,
+        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    rpc_response::{
+        Response, RpcBlockUpdate, RpcConfirmedTransactionStatusWithSignature, RpcLogsResponse,
+        RpcResponseContext,
+    },
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::{hashv, Hash},
+    instruction::Instruction,
+    message::Message,
+    transaction::Transaction,
+};
+use solana_transaction_status::{
+    Encodable, EncodedTransactionWithStatusMeta, TransactionConfirmationStatus, TransactionStatus,
+    TransactionStatusMeta, UiConfirmedBlock, UiTransactionEncoding,
+};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc, watch,
+};
+
+use crate::{
+    event_reader_service::{
+        self, BlocksSource, Clock, Error, EventConsumeResult, EventsReaderBuilder, LogsSource,
+        Result, ResyncOrder, SignatureSource, Slot, SolanaSignature, TransactionSource,
+    },
+    fixtures::{self, Fixture},
+    instruction_parser::GetLoadedAccounts,
+    storage::{
+        ClaimTransaction, EventRegistry, RegisterIdempotencyKey, RegisterTransaction,
+        ResyncedTransactionsPtrStorage,
+    },
+    transaction_parser::{
+        parse_transaction, transaction_logs, EncodedConfirmedTransactionWithStatusMeta, Pubkey,
+        RpcClient, TransactionParsedMeta,
+    },
+};
+
+/// Capacity of logs & blocks notifications channels, lagged subscribers skip oldest
+/// notifications
+const NOTIFICATIONS_CAPACITY: usize = 1024;
+
+struct MockTransaction {
+    signature: SolanaSignature,
+    accounts: Vec<Pubkey>,
+    transaction: EncodedConfirmedTransactionWithStatusMeta,
+}
+
+impl MockTransaction {
+    fn status(&self, finalized_slot: Option<Slot>) -> TransactionStatus {
+        let err = self
+            .transaction
+            .transaction
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.err.clone());
+        TransactionStatus {
+            slot: self.transaction.slot,
+            confirmations: None,
+            status: err.clone().map_or(Ok(()), Err),
+            err,
+            confirmation_status: Some(
+                match finalized_slot.map_or(true, |slot| self.transaction.slot <= slot) {
+                    true => TransactionConfirmationStatus::Finalized,
+                    false => TransactionConfirmationStatus::Confirmed,
+                },
+            ),
+        }
+    }
+}
+
+/// Chain of recorded transactions, oldest first
+pub struct MockChain {
+    transactions: Mutex<Vec<MockTransaction>>,
+    /// Count of replacements of block by slot, see [`MockChain::blockhash`]
+    forks: Mutex<HashMap<Slot, u64>>,
+    finalized_slot: Mutex<Option<Slot>>,
+    notifications: broadcast::Sender<(Vec<Pubkey>, Response<RpcLogsResponse>)>,
+    blocks: broadcast::Sender<(Vec<Pubkey>, Response<RpcBlockUpdate>)>,
+}
+
+impl Default for MockChain {
+    fn default() -> Self {
+        Self {
+            transactions: Mutex::default(),
+            forks: Mutex::default(),
+            finalized_slot: Mutex::default(),
+            notifications: broadcast::channel(NOTIFICATIONS_CAPACITY).0,
+            blocks: broadcast::channel(NOTIFICATIONS_CAPACITY).0,
+        }
+    }
+}
+
+impl MockChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append transaction of `fixture` & notify logs and blocks subscribers
+    pub fn push(&self, fixture: Fixture) -> result::Result<SolanaSignature, fixtures::Error> {
+        self.append(fixture, true)
+    }
+
+    /// Append transaction of `fixture` without notification of logs & blocks subscribers
+    pub fn push_missed(
+        &self,
+        fixture: Fixture,
+    ) -> result::Result<SolanaSignature, fixtures::Error> {
+        self.append(fixture, false)
+    }
+
+    fn append(
+        &self,
+        fixture: Fixture,
+        notify: bool,
+    ) -> result::Result<SolanaSignature, fixtures::Error> {
+        let signature = fixture.signature.parse::<SolanaSignature>()?;
+        let transaction = MockTransaction {
+            signature,
+            accounts: fixture
+                .transaction
+                .transaction
+                .get_loaded_accounts()
+                .transpose()
+                .map_err(crate::transaction_parser::Error::from)?
+                .unwrap_or_default(),
+            transaction: fixture.transaction,
+        };
+
+        if notify {
+            let context = RpcResponseContext {
+                slot: transaction.transaction.slot,
+                api_version: None,
+            };
+            let block = Response {
+                context: context.clone(),
+                value: RpcBlockUpdate {
+                    slot: transaction.transaction.slot,
+                    block: Some(UiConfirmedBlock {
+                        previous_blockhash: String::new(),
+                        blockhash: self.blockhash(transaction.transaction.slot).to_string(),
+                        parent_slot: transaction.transaction.slot.saturating_sub(1),
+                        transactions: Some(vec![transaction.transaction.transaction.clone()]),
+                        signatures: None,
+                        rewards: None,
+                        block_time: transaction.transaction.block_time,
+                        block_height: None,
+                    }),
+                    err: None,
+                },
+            };
+            let notification = Response {
+                context,
+                value: RpcLogsResponse {
+                    signature: fixture.signature,
+                    err: transaction.status(None).err,
+                    logs: transaction_logs(&transaction.transaction.transaction, signature)?
+                        .to_vec(),
+                },
+            };
+            // No receivers is not an error, notification is just lost as in websocket
+            let _ = self
+                .notifications
+                .send((transaction.accounts.clone(), notification));
+            let _ = self.blocks.send((transaction.accounts.clone(), block));
+        }
+
+        self.transactions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(transaction);
+        Ok(signature)
+    }
+
+    /// Simulate reorg: transaction of `signature` is moved into block of `slot` (the same or
+    /// another one), blocks of both slots are replaced. Subscribers are not notified
+    ///
+    /// Returns `false` if transaction isn't found
+    pub fn reinclude(&self, signature: SolanaSignature, slot: Slot) -> bool {
+        let mut transactions = self
+            .transactions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let Some(index) = transactions
+            .iter()
+            .position(|transaction| transaction.signature == signature)
+        else {
+            return false;
+        };
+        let mut transaction = transactions.remove(index);
+
+        let mut forks = self
+            .forks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *forks.entry(transaction.transaction.slot).or_default() += 1;
+        if slot != transaction.transaction.slot {
+            *forks.entry(slot).or_default() += 1;
+        }
+
+        transaction.transaction.slot = slot;
+        let index =
+            transactions.partition_point(|transaction| transaction.transaction.slot <= slot);
+        transactions.insert(index, transaction);
+        true
+    }
+
+    /// Hash of block of `slot`, changed by every [`MockChain::reinclude`] into or from it
+    pub fn blockhash(&self, slot: Slot) -> Hash {
+        let fork = self
+            .forks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&slot)
+            .copied()
+            .unwrap_or_default();
+        hashv(&[&slot.to_le_bytes(), &fork.to_le_bytes()])
+    }
+
+    /// Transactions of slots after `slot` are only confirmed, `None` to finalize all of them
+    pub fn set_finalized_slot(&self, slot: Option<Slot>) {
+        *self
+            .finalized_slot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = slot;
+    }
+
+    fn finalized_slot(&self) -> Option<Slot> {
+        *self
+            .finalized_slot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Count of live logs & blocks subscriptions, e.g. to wait until reader is subscribed
+    pub fn subscribers(&self) -> usize {
+        self.notifications.receiver_count() + self.blocks.receiver_count()
+    }
+
+    /// Wait until somebody, e.g. running reader, is subscribed to logs or blocks
+    pub async fn wait_subscribed(&self) {
+        while self.subscribers() == 0 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    /// [`EventsReaderBuilder`] of `program_id` with `self` as the only source of transactions,
+    /// signatures, logs & blocks, `storage` as all storages and `clock` as time source
+    pub fn events_reader_builder<TransactionConsumerFn, EventRecipient>(
+        self: &Arc<Self>,
+        program_id: Pubkey,
+        transaction_consumer: TransactionConsumerFn,
+        event_recipient: Arc<EventRecipient>,
+        storage: Arc<MemoryStorage>,
+        clock: Arc<MockClock>,
+    ) -> EventsReaderBuilder<TransactionConsumerFn, EventRecipient, Infallible>
+    where
+        EventRecipient: Send + Sync + 'static,
+        TransactionConsumerFn: Send
+            + Sync
+            + Fn(
+                SolanaSignature,
+                TransactionParsedMeta,
+                Arc<RpcClient>,
+                Arc<EventRecipient>,
+            ) -> BoxFuture<'static, event_reader_service::Result<()>>,
+    {
+        EventsReaderBuilder::default()
+            .program_id(program_id)
+            .client(Arc::new(RpcClient::new_mock("succeeds".to_owned())))
+            .pubsub_client(None)
+            .transaction_source(Some(Arc::clone(self) as Arc<dyn TransactionSource>))
+            .signature_source(Some(Arc::clone(self) as Arc<dyn SignatureSource>))
+            .logs_source(Some(Arc::clone(self) as Arc<dyn LogsSource>))
+            .blocks_source(Some(Arc::clone(self) as Arc<dyn BlocksSource>))
+            .clock(clock)
+            .event_recipient(event_recipient)
+            .resync_duration(Duration::from_secs(1))
+            .event_consumer(|_| Ok(EventConsumeResult::TransactionNeeed))
+            .transaction_consumer(transaction_consumer)
+            .local_storage(storage)
+            .resync_signatures_chunk_size(None)
+            .resync_ptr_setter(Arc::new(|_| Box::pin(async { Ok(()) })))
+            .resync_order(ResyncOrder::Historical)
+    }
+
+    fn with_transactions<R>(&self, f: impl FnOnce(&[MockTransaction]) -> R) -> R {
+        f(&self
+            .transactions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+#[async_trait]
+impl TransactionSource for MockChain {
+    async fn get_transaction(
+        &self,
+        signature: SolanaSignature,
+        _config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta> {
+        self.with_transactions(|transactions| {
+            let transaction = transactions
+                .iter()
+                .find(|transaction| transaction.signature == signature)
+                .ok_or(Error::TransactionNotFound(signature))?;
+            Ok(parse_transaction(signature, &transaction.transaction)?)
+        })
+    }
+}
+
+#[async_trait]
+impl SignatureSource for MockChain {
+    async fn get_slot(&self, commitment_config: CommitmentConfig) -> Result<Slot> {
+        let slot = self.with_transactions(|transactions| {
+            transactions
+                .iter()
+                .map(|transaction| transaction.transaction.slot)
+                .max()
+                .unwrap_or_default()
+        });
+        Ok(
+            match (commitment_config.is_finalized(), self.finalized_slot()) {
+                (true, Some(finalized_slot)) => slot.min(finalized_slot),
+                _ => slot,
+            },
+        )
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        let finalized_slot = self.finalized_slot();
+        Ok(self.with_transactions(|transactions| {
+            transactions
+                .iter()
+                .rev()
+                .filter(|transaction| transaction.accounts.contains(address))
+                .skip_while(|transaction| {
+                    config
+                        .before
+                        .is_some_and(|before| transaction.signature != before)
+                })
+                .skip(usize::from(config.before.is_some()))
+                .take_while(|transaction| Some(transaction.signature) != config.until)
+                .take(config.limit.unwrap_or(1000))
+                .map(|transaction| {
+                    let status = transaction.status(finalized_slot);
+                    RpcConfirmedTransactionStatusWithSignature {
+                        signature: transaction.signature.to_string(),
+                        slot: status.slot,
+                        err: status.err,
+                        memo: None,
+                        block_time: transaction.transaction.block_time,
+                        confirmation_status: status.confirmation_status,
+                    }
+                })
+                .collect()
+        }))
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[SolanaSignature],
+    ) -> Result<Vec<Option<TransactionStatus>>> {
+        let finalized_slot = self.finalized_slot();
+        Ok(self.with_transactions(|transactions| {
+            signatures
+                .iter()
+                .map(|signature| {
+                    transactions
+                        .iter()
+                        .find(|transaction| transaction.signature == *signature)
+                        .map(|transaction| transaction.status(finalized_slot))
+                })
+                .collect()
+        }))
+    }
+
+    async fn get_blockhash(
+        &self,
+        slot: Slot,
+        _commitment_config: CommitmentConfig,
+    ) -> Result<Hash> {
+        Ok(self.blockhash(slot))
+    }
+}
+
+/// Stream of notifications of `sender` with accounts of their transactions
+fn subscribe<T: Clone + Send + 'static>(
+    sender: &broadcast::Sender<(Vec<Pubkey>, T)>,
+) -> BoxStream<'static, (Vec<Pubkey>, T)> {
+    stream::unfold(sender.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => return Some((notification, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
+#[async_trait]
+impl LogsSource for MockChain {
+    async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        _config: RpcTransactionLogsConfig,
+    ) -> Result<BoxStream<'_, Response<RpcLogsResponse>>> {
+        Ok(subscribe(&self.notifications)
+            .filter_map(move |(accounts, notification)| {
+                let is_matched = match &filter {
+                    RpcTransactionLogsFilter::All | RpcTransactionLogsFilter::AllWithVotes => true,
+                    RpcTransactionLogsFilter::Mentions(mentions) => accounts
+                        .iter()
+                        .any(|account| mentions.contains(&account.to_string())),
+                };
+                future::ready(is_matched.then_some(notification))
+            })
+            .boxed())
+    }
+}
+
+#[async_trait]
+impl BlocksSource for MockChain {
+    async fn block_subscribe(
+        &self,
+        filter: RpcBlockSubscribeFilter,
+        _config: RpcBlockSubscribeConfig,
+    ) -> Result<BoxStream<'_, Response<RpcBlockUpdate>>> {
+        Ok(subscribe(&self.blocks)
+            .filter_map(move |(accounts, block)| {
+                let is_matched = match &filter {
+                    RpcBlockSubscribeFilter::All => true,
+                    RpcBlockSubscribeFilter::MentionsAccountOrProgram(mention) => accounts
+                        .iter()
+                        .any(|account| account.to_string() == *mention),
+                };
+                future::ready(is_matched.then_some(block))
+            })
+            .boxed())
+    }
+}
+
+/// [`Clock`] which time moves only by [`MockClock::advance`]
+#[derive(Debug)]
+pub struct MockClock {
+    elapsed: watch::Sender<Duration>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self {
+            elapsed: watch::channel(Duration::ZERO).0,
+        }
+    }
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time passed since creation of clock
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.borrow()
+    }
+
+    /// Move time forward, sleeps with passed deadlines are woken up
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+}
+
+#[async_trait]
+impl Clock for MockClock {
+    async fn sleep(&self, duration: Duration) {
+        let deadline = self.elapsed() + duration;
+        let mut elapsed = self.elapsed.subscribe();
+        // Sender is owned by `self`, so it can't be dropped while sleeping
+        let _ = elapsed.wait_for(|elapsed| *elapsed >= deadline).await;
+    }
+}
+
+/// Transaction of `slot` with unique signature, which invokes `program_id` with empty data,
+/// and `logs` of this invocation
+pub fn invocation(program_id: Pubkey, slot: Slot, logs: &[String]) -> Fixture {
+    let payer = Pubkey::new_unique();
+    let mut transaction = Transaction::new_unsigned(Message::new(
+        &[Instruction::new_with_bytes(program_id, &[], vec![])],
+        Some(&payer),
+    ));
+    let signature = SolanaSignature::new_unique();
+    transaction.signatures = vec![signature];
+    let accounts_count = transaction.message.account_keys.len();
+
+    let log_messages = std::iter::once(format!("Program {program_id} invoke [1]"))
+        .chain(logs.iter().cloned())
+        .chain(std::iter::once(format!("Program {program_id} success")))
+        .collect();
+    Fixture {
+        description: format!("Invocation of {program_id}"),
+        signature: signature.to_string(),
+        transaction: EncodedConfirmedTransactionWithStatusMeta {
+            slot,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: transaction.encode(UiTransactionEncoding::Base64),
+                meta: Some(
+                    TransactionStatusMeta {
+                        fee: 5000,
+                        pre_balances: vec![0; accounts_count],
+                        post_balances: vec![0; accounts_count],
+                        inner_instructions: Some(vec![]),
+                        log_messages: Some(log_messages),
+                        ..Default::default()
+                    }
+                    .into(),
+                ),
+                version: None,
+            },
+            block_time: None,
+        },
+        encodings: BTreeMap::new(),
+    }
+}
+
+/// Transaction consumer of [`MockChain::events_reader_builder`], which sends signatures of
+/// consumed transactions to recipient channel
+pub fn send_signature(
+    signature: SolanaSignature,
+    _transaction: TransactionParsedMeta,
+    _client: Arc<RpcClient>,
+    recipient: Arc<mpsc::UnboundedSender<SolanaSignature>>,
+) -> BoxFuture<'static, Result<()>> {
+    // Closed channel is not an error, test just doesn't wait for signatures anymore
+    let _ = recipient.send(signature);
+    Box::pin(future::ready(Ok(())))
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    transactions: HashSet<(Pubkey, SolanaSignature)>,
+    signatures: HashMap<Pubkey, SolanaSignature>,
+    slots: HashMap<Pubkey, Slot>,
+    idempotency_keys: HashSet<Vec<u8>>,
+    /// Expiration of claim, `None` for completed ones
+    claims: HashMap<(Pubkey, SolanaSignature), Option<Instant>>,
+    events: HashSet<Vec<u8>>,
+}
+
+/// Storage of [`EventsReader`](crate::event_reader_service::EventsReader) in memory
+#[derive(Debug, Default)]
+pub struct MemoryStorage(Mutex<MemoryState>);
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut MemoryState) -> R) -> R {
+        f(&mut self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl RegisterTransaction for MemoryStorage {
+    type Error = Infallible;
+
+    fn register_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.transactions.insert((*program_id, *transaction_hash)));
+        Ok(())
+    }
+
+    fn unregister_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.transactions.remove(&(*program_id, *transaction_hash)));
+        Ok(())
+    }
+
+    fn is_transaction_registered(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> result::Result<bool, Self::Error> {
+        Ok(self.with_state(|state| {
+            state
+                .transactions
+                .contains(&(*program_id, *transaction_hash))
+        }))
+    }
+
+    fn filter_unregistered_transactions(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash_set: &[SolanaSignature],
+    ) -> result::Result<Vec<SolanaSignature>, Self::Error> {
+        Ok(self.with_state(|state| {
+            transaction_hash_set
+                .iter()
+                .filter(|signature| !state.transactions.contains(&(*program_id, **signature)))
+                .copied()
+                .collect()
+        }))
+    }
+}
+
+impl ResyncedTransactionsPtrStorage for MemoryStorage {
+    fn initialize_if_needed_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction: &SolanaSignature,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| {
+            state.signatures.entry(*program_id).or_insert(*transaction);
+        });
+        Ok(())
+    }
+
+    fn get_last_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+    ) -> result::Result<Option<SolanaSignature>, Self::Error> {
+        Ok(self.with_state(|state| state.signatures.get(program_id).copied()))
+    }
+
+    fn set_last_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction: &SolanaSignature,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.signatures.insert(*program_id, *transaction));
+        Ok(())
+    }
+
+    fn reset_last_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| {
+            state.signatures.remove(program_id);
+            state.slots.remove(program_id);
+        });
+        Ok(())
+    }
+
+    fn get_last_resynced_slot(
+        &self,
+        program_id: &Pubkey,
+    ) -> result::Result<Option<Slot>, Self::Error> {
+        Ok(self.with_state(|state| state.slots.get(program_id).copied()))
+    }
+
+    fn set_last_resynced_slot(
+        &self,
+        program_id: &Pubkey,
+        slot: Slot,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.slots.insert(*program_id, slot));
+        Ok(())
+    }
+}
+
+impl RegisterIdempotencyKey for MemoryStorage {
+    type Error = Infallible;
+
+    fn register_idempotency_key(&self, key: &[u8]) -> result::Result<bool, Self::Error> {
+        Ok(self.with_state(|state| state.idempotency_keys.insert(key.to_vec())))
+    }
+
+    fn unregister_idempotency_key(&self, key: &[u8]) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.idempotency_keys.remove(key));
+        Ok(())
+    }
+}
+
+impl ClaimTransaction for MemoryStorage {
+    type Error = Infallible;
+
+    fn try_claim(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+        ttl: Duration,
+    ) -> result::Result<bool, Self::Error> {
+        let now = Instant::now();
+        Ok(self.with_state(|state| {
+            let expiration = state
+                .claims
+                .entry((*program_id, *transaction_hash))
+                .or_insert(Some(now));
+            if expiration.map_or(true, |expiration| expiration > now) {
+                return false;
+            }
+            *expiration = Some(now + ttl);
+            true
+        }))
+    }
+
+    fn release_claim(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.claims.remove(&(*program_id, *transaction_hash)));
+        Ok(())
+    }
+
+    fn complete_claim(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> result::Result<(), Self::Error> {
+        self.with_state(|state| state.claims.insert((*program_id, *transaction_hash), None));
+        Ok(())
+    }
+}
+
+impl EventRegistry for MemoryStorage {
+    type Error = Infallible;
+
+    fn register_event(&self, event_id: &[u8]) -> result::Result<bool, Self::Error> {
+        Ok(self.with_state(|state| state.events.insert(event_id.to_vec())))
+    }
+
+    fn is_event_registered(&self, event_id: &[u8]) -> result::Result<bool, Self::Error> {
+        Ok(self.with_state(|state| state.events.contains(event_id)))
+    }
+}
+
+#[cfg(test)]
+mod mock_source_test {
+    use super::*;
+
+    fn fixture(name: &str) -> Fixture {
+        Fixture::load(name).0
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock() {
+        let clock = std::sync::Arc::new(MockClock::new());
+        let sleep = tokio::spawn({
+            let clock = clock.clone();
+            async move { clock.sleep(Duration::from_secs(10)).await }
+        });
+
+        // Let sleep start before time is advanced
+        tokio::task::yield_now().await;
+        clock.advance(Duration::from_secs(5));
+        tokio::task::yield_now().await;
+        assert!(!sleep.is_finished());
+
+        clock.advance(Duration::from_secs(5));
+        sleep.await.unwrap();
+        assert_eq!(clock.elapsed(), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_mock_chain() {
+        let chain = MockChain::new();
+        let program_id = "Fixture111111111111111111111111111111111111"
+            .parse::<Pubkey>()
+            .unwrap();
+
+        let mut logs = chain
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+                RpcTransactionLogsConfig { commitment: None },
+            )
+            .await
+            .unwrap();
+
+        let missed = chain.push_missed(fixture("failed")).unwrap();
+        let signature = chain.push(fixture("cpi_event")).unwrap();
+
+        let notification = logs.next().await.unwrap();
+        assert_eq!(notification.value.signature, signature.to_string());
+
+        let signatures = chain
+            .get_signatures_for_address(&program_id, Default::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            signatures
+                .iter()
+                .map(|status| status.signature.clone())
+                .collect::<Vec<_>>(),
+            vec![signature.to_string(), missed.to_string()]
+        );
+        let page = chain
+            .get_signatures_for_address(
+                &program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before: Some(signature),
+                    until: Some(missed),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(page.is_empty());
+
+        let meta = chain
+            .get_transaction(signature, Default::default())
+            .await
+            .unwrap();
+        assert!(meta.meta.keys().any(|ctx| ctx.program_id == program_id));
+        assert!(matches!(
+            chain
+                .get_transaction(SolanaSignature::default(), Default::default())
+                .await,
+            Err(Error::TransactionNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,689 @@
+//! Decomposers for instructions of native & SPL programs.
+//!
+//! Unlike anchor programs, these instructions are not prefixed with an 8-byte discriminator,
+//! so each program has its own layout: bincode for System & Stake programs,
+//! packed `spl-token` layout for Token & Token-2022, one-byte tags for ATA & ComputeBudget.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    marker::PhantomData,
+};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::program_utils::limited_deserialize;
+pub use solana_sdk::{stake::instruction::StakeInstruction, system_instruction::SystemInstruction};
+
+use crate::transaction_parser::{
+    ConsumeInstruction, Consumer, DecodeError, DecomposeInstruction, DecomposedInstruction,
+    DecomposedInstructionWithConsumer, DispatchKey, Instruction, ParentProgramContext,
+    ProgramContext, ProgramLog, Pubkey, TransactionParsedMeta,
+};
+
+pub const SYSTEM_PROGRAM_ID: Pubkey = solana_sdk::system_program::ID;
+pub const STAKE_PROGRAM_ID: Pubkey = solana_sdk::stake::program::ID;
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = solana_sdk::compute_budget::ID;
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Instruction of a program which is not anchor based
+/// and is unpacked by its own layout instead of discriminator
+pub trait NativeInstruction: Sized {
+    /// Programs, whose instructions are unpacked into this type
+    fn program_ids() -> &'static [Pubkey];
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error>;
+}
+
+impl NativeInstruction for SystemInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[SYSTEM_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        limited_deserialize(data).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+impl NativeInstruction for StakeInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[STAKE_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        limited_deserialize(data).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComputeBudgetInstruction {
+    /// Deprecated variant, unused by runtime
+    RequestUnitsDeprecated {
+        units: u32,
+        additional_fee: u32,
+    },
+    RequestHeapFrame(u32),
+    SetComputeUnitLimit(u32),
+    SetComputeUnitPrice(u64),
+    SetLoadedAccountsDataSizeLimit(u32),
+}
+
+impl NativeInstruction for ComputeBudgetInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[COMPUTE_BUDGET_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        let mut reader = DataReader(data);
+        let ix = match reader.read_u8()? {
+            0 => Self::RequestUnitsDeprecated {
+                units: reader.read_u32()?,
+                additional_fee: reader.read_u32()?,
+            },
+            1 => Self::RequestHeapFrame(reader.read_u32()?),
+            2 => Self::SetComputeUnitLimit(reader.read_u32()?),
+            3 => Self::SetComputeUnitPrice(reader.read_u64()?),
+            4 => Self::SetLoadedAccountsDataSizeLimit(reader.read_u32()?),
+            tag => return Err(unknown_tag("ComputeBudget", tag)),
+        };
+        Ok(ix)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssociatedTokenAccountInstruction {
+    Create,
+    CreateIdempotent,
+    RecoverNested,
+}
+
+impl NativeInstruction for AssociatedTokenAccountInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[ASSOCIATED_TOKEN_ACCOUNT_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        // Empty data is legacy form of `Create`
+        match data.first() {
+            None | Some(0) => Ok(Self::Create),
+            Some(1) => Ok(Self::CreateIdempotent),
+            Some(2) => Ok(Self::RecoverNested),
+            Some(tag) => Err(unknown_tag("AssociatedTokenAccount", *tag)),
+        }
+    }
+}
+
+/// Instructions of SPL Token & Token-2022 programs
+///
+/// Token-2022 extensions instructions, except the transfer fee ones,
+/// are kept as raw [`TokenInstruction::Extension`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenInstruction {
+    InitializeMint {
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    InitializeAccount,
+    InitializeMultisig {
+        m: u8,
+    },
+    Transfer {
+        amount: u64,
+    },
+    Approve {
+        amount: u64,
+    },
+    Revoke,
+    SetAuthority {
+        authority_type: u8,
+        new_authority: Option<Pubkey>,
+    },
+    MintTo {
+        amount: u64,
+    },
+    Burn {
+        amount: u64,
+    },
+    CloseAccount,
+    FreezeAccount,
+    ThawAccount,
+    TransferChecked {
+        amount: u64,
+        decimals: u8,
+    },
+    ApproveChecked {
+        amount: u64,
+        decimals: u8,
+    },
+    MintToChecked {
+        amount: u64,
+        decimals: u8,
+    },
+    BurnChecked {
+        amount: u64,
+        decimals: u8,
+    },
+    InitializeAccount2 {
+        owner: Pubkey,
+    },
+    SyncNative,
+    InitializeAccount3 {
+        owner: Pubkey,
+    },
+    InitializeMultisig2 {
+        m: u8,
+    },
+    InitializeMint2 {
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    GetAccountDataSize,
+    InitializeImmutableOwner,
+    AmountToUiAmount {
+        amount: u64,
+    },
+    UiAmountToAmount {
+        ui_amount: String,
+    },
+    /// Token-2022 only
+    InitializeMintCloseAuthority {
+        close_authority: Option<Pubkey>,
+    },
+    /// Token-2022 only
+    TransferCheckedWithFee {
+        amount: u64,
+        decimals: u8,
+        fee: u64,
+    },
+    /// Token-2022 only
+    SetTransferFee {
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    },
+    /// Token-2022 only
+    Extension {
+        tag: u8,
+        data: Vec<u8>,
+    },
+}
+
+const TOKEN_TRANSFER_FEE_EXTENSION_TAG: u8 = 26;
+
+impl NativeInstruction for TokenInstruction {
+    fn program_ids() -> &'static [Pubkey] {
+        &[TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID]
+    }
+
+    fn unpack(data: &[u8]) -> Result<Self, io::Error> {
+        let mut reader = DataReader(data);
+        let ix = match reader.read_u8()? {
+            0 => Self::InitializeMint {
+                decimals: reader.read_u8()?,
+                mint_authority: reader.read_pubkey()?,
+                freeze_authority: reader.read_coption_pubkey()?,
+            },
+            1 => Self::InitializeAccount,
+            2 => Self::InitializeMultisig {
+                m: reader.read_u8()?,
+            },
+            3 => Self::Transfer {
+                amount: reader.read_u64()?,
+            },
+            4 => Self::Approve {
+                amount: reader.read_u64()?,
+            },
+            5 => Self::Revoke,
+            6 => Self::SetAuthority {
+                authority_type: reader.read_u8()?,
+                new_authority: reader.read_coption_pubkey()?,
+            },
+            7 => Self::MintTo {
+                amount: reader.read_u64()?,
+            },
+            8 => Self::Burn {
+                amount: reader.read_u64()?,
+            },
+            9 => Self::CloseAccount,
+            10 => Self::FreezeAccount,
+            11 => Self::ThawAccount,
+            12 => Self::TransferChecked {
+                amount: reader.read_u64()?,
+                decimals: reader.read_u8()?,
+            },
+            13 => Self::ApproveChecked {
+                amount: reader.read_u64()?,
+                decimals: reader.read_u8()?,
+            },
+            14 => Self::MintToChecked {
+                amount: reader.read_u64()?,
+                decimals: reader.read_u8()?,
+            },
+            15 => Self::BurnChecked {
+                amount: reader.read_u64()?,
+                decimals: reader.read_u8()?,
+            },
+            16 => Self::InitializeAccount2 {
+                owner: reader.read_pubkey()?,
+            },
+            17 => Self::SyncNative,
+            18 => Self::InitializeAccount3 {
+                owner: reader.read_pubkey()?,
+            },
+            19 => Self::InitializeMultisig2 {
+                m: reader.read_u8()?,
+            },
+            20 => Self::InitializeMint2 {
+                decimals: reader.read_u8()?,
+                mint_authority: reader.read_pubkey()?,
+                freeze_authority: reader.read_coption_pubkey()?,
+            },
+            // Token-2022 appends list of extensions types, not needed here
+            21 => Self::GetAccountDataSize,
+            22 => Self::InitializeImmutableOwner,
+            23 => Self::AmountToUiAmount {
+                amount: reader.read_u64()?,
+            },
+            24 => Self::UiAmountToAmount {
+                ui_amount: String::from_utf8(reader.rest().to_vec())
+                    .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?,
+            },
+            25 => Self::InitializeMintCloseAuthority {
+                close_authority: reader.read_coption_pubkey()?,
+            },
+            TOKEN_TRANSFER_FEE_EXTENSION_TAG => match reader.0.first() {
+                Some(1) => {
+                    reader.read_u8()?;
+                    Self::TransferCheckedWithFee {
+                        amount: reader.read_u64()?,
+                        decimals: reader.read_u8()?,
+                        fee: reader.read_u64()?,
+                    }
+                }
+                Some(5) => {
+                    reader.read_u8()?;
+                    Self::SetTransferFee {
+                        transfer_fee_basis_points: reader.read_u16()?,
+                        maximum_fee: reader.read_u64()?,
+                    }
+                }
+                _ => Self::Extension {
+                    tag: TOKEN_TRANSFER_FEE_EXTENSION_TAG,
+                    data: reader.rest().to_vec(),
+                },
+            },
+            tag => Self::Extension {
+                tag,
+                data: reader.rest().to_vec(),
+            },
+        };
+        Ok(ix)
+    }
+}
+
+pub(crate) fn unknown_tag(program: &str, tag: u8) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("Unknown {program} instruction tag: {tag}"),
+    )
+}
+
+/// Little-endian reader over instruction data
+pub(crate) struct DataReader<'data>(pub(crate) &'data [u8]);
+
+impl<'data> DataReader<'data> {
+    pub(crate) fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], io::Error> {
+        if self.0.len() < N {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("Expected {N} more bytes, found {}", self.0.len()),
+            ));
+        }
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(<[u8; N]>::try_from(bytes).expect("Safe, because length checked before"))
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8, io::Error> {
+        self.read_bytes::<1>().map(|[byte]| byte)
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16, io::Error> {
+        self.read_bytes().map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32, io::Error> {
+        self.read_bytes().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64, io::Error> {
+        self.read_bytes().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn read_pubkey(&mut self) -> Result<Pubkey, io::Error> {
+        self.read_bytes().map(Pubkey::new_from_array)
+    }
+
+    /// `COption<Pubkey>` as packed by `spl-token`: one byte tag, then pubkey if tag is 1
+    pub(crate) fn read_coption_pubkey(&mut self) -> Result<Option<Pubkey>, io::Error> {
+        match self.read_u8()? {
+            0 => Ok(None),
+            1 => self.read_pubkey().map(Some),
+            tag => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Wrong COption tag: {tag}"),
+            )),
+        }
+    }
+
+    pub(crate) fn rest(&mut self) -> &'data [u8] {
+        std::mem::take(&mut self.0)
+    }
+}
+
+/// [`DecomposeInstruction`] for [`NativeInstruction`]
+///
+/// All instruction accounts are passed as [`DecomposedInstruction::accounts`],
+/// because the count of accounts in native instructions often depends on variant
+pub struct NativeInstructionDecomposer<IX: NativeInstruction + Send> {
+    ix: PhantomData<IX>,
+    consumer: Option<Consumer<IX, Vec<Pubkey>>>,
+}
+
+impl<IX: 'static + NativeInstruction + Send + Sync> NativeInstructionDecomposer<IX> {
+    pub fn new_boxed() -> Box<dyn DecomposeInstruction + Send + Sync> {
+        Self::default().boxed()
+    }
+
+    pub fn boxed(self) -> Box<dyn DecomposeInstruction + Send + Sync> {
+        Box::new(self)
+    }
+
+    pub fn set_consumer(mut self, consumer: Consumer<IX, Vec<Pubkey>>) -> Self {
+        self.consumer = Some(consumer);
+
+        self
+    }
+}
+
+impl<IX: NativeInstruction + Send> Default for NativeInstructionDecomposer<IX> {
+    fn default() -> Self {
+        Self {
+            ix: Default::default(),
+            consumer: None,
+        }
+    }
+}
+
+impl<IX: 'static + NativeInstruction + Send + Sync> DecomposeInstruction
+    for NativeInstructionDecomposer<IX>
+{
+    fn is_decomposable(&self, program_ctx: &ProgramContext, raw_ix: &Instruction) -> bool {
+        program_ctx.program_id.eq(&raw_ix.program_id)
+            && IX::program_ids().contains(&raw_ix.program_id)
+    }
+
+    fn decompose_instruction(
+        &self,
+        program_ctx: ProgramContext,
+        raw_ix: &Instruction,
+        logs: &[ProgramLog],
+    ) -> Result<Box<dyn ConsumeInstruction + Send + 'static>, DecodeError> {
+        self.decompose_instruction_with_parent(program_ctx, None, raw_ix, logs)
+    }
+
+    fn decompose_instruction_with_parent(
+        &self,
+        program_ctx: ProgramContext,
+        parent_ctx: Option<ParentProgramContext>,
+        raw_ix: &Instruction,
+        logs: &[ProgramLog],
+    ) -> Result<Box<dyn ConsumeInstruction + Send + 'static>, DecodeError> {
+        Ok(Box::new(DecomposedInstructionWithConsumer {
+            consumer: self.consumer.as_ref().cloned(),
+            decomposed_ix: DecomposedInstruction {
+                program_ctx,
+                logs: logs.to_vec(),
+                accounts: raw_ix.accounts.iter().map(|acc| acc.pubkey).collect(),
+                remaining_accounts: vec![],
+                all_account_metas: raw_ix.accounts.clone(),
+                raw_data: raw_ix.data.clone(),
+                parent_ctx,
+                ix: IX::unpack(&raw_ix.data).map_err(|source| DecodeError::Deserialize {
+                    program_id: raw_ix.program_id,
+                    program_ctx: Some(program_ctx),
+                    discriminator: None,
+                    type_name: std::any::type_name::<IX>(),
+                    source,
+                })?,
+            },
+        }))
+    }
+
+    fn dispatch_keys(&self) -> Vec<DispatchKey> {
+        IX::program_ids()
+            .iter()
+            .copied()
+            .map(DispatchKey::Program)
+            .collect()
+    }
+}
+
+/// Transfer of Token or Token-2022 program, see [`TransactionParsedMeta::token_transfers`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTransfer {
+    pub program_ctx: ProgramContext,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub authority: Pubkey,
+    /// `None` for unchecked `Transfer`
+    pub mint: Option<Pubkey>,
+    /// Amount debited from `source`
+    pub amount: u64,
+    /// Token-2022 transfer fee, withheld in `destination`
+    pub fee_amount: u64,
+    /// Amount credited to `destination`, `amount - fee_amount`
+    pub net_amount: u64,
+    /// Program invoked by Token-2022 `TransferHook` extension during transfer
+    pub transfer_hook_program: Option<Pubkey>,
+}
+
+impl TransactionParsedMeta {
+    /// Token flow of transaction: all transfers of Token & Token-2022 programs, including
+    /// inner ones, ordered by program context
+    ///
+    /// Fee is taken from `TransferCheckedWithFee` data. For plain Token-2022 transfers fee
+    /// isn't present in instruction, so it's inferred from balance change of `destination`,
+    /// if `destination` isn't touched by other transfers of transaction
+    pub fn token_transfers(&self) -> Vec<TokenTransfer> {
+        let mut transfers = self
+            .meta
+            .iter()
+            .filter(|(ctx, (ix, _))| {
+                ctx.program_id == ix.program_id
+                    && TokenInstruction::program_ids().contains(&ix.program_id)
+            })
+            .filter_map(|(ctx, (ix, _))| {
+                let account = |index: usize| ix.accounts.get(index).map(|meta| meta.pubkey);
+                let (amount, fee, mint, destination, authority) =
+                    match TokenInstruction::unpack(&ix.data).ok()? {
+                        TokenInstruction::Transfer { amount } => {
+                            (amount, None, None, account(1)?, account(2)?)
+                        }
+                        TokenInstruction::TransferChecked { amount, .. } => {
+                            (amount, None, account(1), account(2)?, account(3)?)
+                        }
+                        TokenInstruction::TransferCheckedWithFee { amount, fee, .. } => {
+                            (amount, Some(fee), account(1), account(2)?, account(3)?)
+                        }
+                        _ => return None,
+                    };
+
+                Some((
+                    fee,
+                    TokenTransfer {
+                        program_ctx: *ctx,
+                        source: account(0)?,
+                        destination,
+                        authority,
+                        mint,
+                        amount,
+                        fee_amount: 0,
+                        net_amount: amount,
+                        transfer_hook_program: self
+                            .parent_ix
+                            .iter()
+                            .filter(|(_, parent)| *parent == ctx)
+                            .map(|(child, _)| child)
+                            .min()
+                            .map(|child| child.program_id),
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let mut touches = HashMap::<Pubkey, usize>::new();
+        for (_, transfer) in transfers.iter() {
+            *touches.entry(transfer.source).or_default() += 1;
+            *touches.entry(transfer.destination).or_default() += 1;
+        }
+
+        for (fee, transfer) in transfers.iter_mut() {
+            let fee = fee.or_else(|| {
+                if transfer.program_ctx.program_id != TOKEN_2022_PROGRAM_ID
+                    || touches.get(&transfer.destination) != Some(&1)
+                {
+                    return None;
+                }
+                let received = self
+                    .token_balances_changes
+                    .iter()
+                    .find(|(wallet, _)| wallet.wallet_address == transfer.destination)
+                    .map(|(_, diff)| *diff)?;
+                u64::try_from(received)
+                    .ok()
+                    .and_then(|received| transfer.amount.checked_sub(received))
+            });
+            transfer.fee_amount = fee.unwrap_or(0);
+            transfer.net_amount = transfer.amount.saturating_sub(transfer.fee_amount);
+        }
+
+        let mut transfers = transfers
+            .into_iter()
+            .map(|(_, transfer)| transfer)
+            .collect::<Vec<_>>();
+        transfers.sort_by_key(|transfer| transfer.program_ctx);
+        transfers
+    }
+}
+
+/// Decomposers without consumers for all supported native programs
+pub fn native_decomposers() -> Vec<Box<dyn DecomposeInstruction + Send + Sync>> {
+    vec![
+        NativeInstructionDecomposer::<SystemInstruction>::new_boxed(),
+        NativeInstructionDecomposer::<TokenInstruction>::new_boxed(),
+        NativeInstructionDecomposer::<AssociatedTokenAccountInstruction>::new_boxed(),
+        NativeInstructionDecomposer::<StakeInstruction>::new_boxed(),
+        NativeInstructionDecomposer::<ComputeBudgetInstruction>::new_boxed(),
+    ]
+}
+
+#[cfg(test)]
+mod native_instruction_test {
+    use super::*;
+
+    #[test]
+    fn test_system_transfer() {
+        let ix = solana_sdk::system_instruction::transfer(
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+            42,
+        );
+        assert_eq!(
+            SystemInstruction::unpack(&ix.data).unwrap(),
+            SystemInstruction::Transfer { lamports: 42 }
+        );
+    }
+
+    #[test]
+    fn test_compute_budget() {
+        assert_eq!(
+            ComputeBudgetInstruction::unpack(&[2, 0x40, 0x0d, 0x03, 0x00]).unwrap(),
+            ComputeBudgetInstruction::SetComputeUnitLimit(200_000)
+        );
+        assert_eq!(
+            ComputeBudgetInstruction::unpack(&[3, 0x10, 0x27, 0, 0, 0, 0, 0, 0]).unwrap(),
+            ComputeBudgetInstruction::SetComputeUnitPrice(10_000)
+        );
+        assert!(ComputeBudgetInstruction::unpack(&[3, 0x10]).is_err());
+    }
+
+    #[test]
+    fn test_token() {
+        assert_eq!(
+            TokenInstruction::unpack(&[12, 1, 0, 0, 0, 0, 0, 0, 0, 6]).unwrap(),
+            TokenInstruction::TransferChecked {
+                amount: 1,
+                decimals: 6
+            }
+        );
+        let mint_authority = Pubkey::new_unique();
+        assert_eq!(
+            TokenInstruction::unpack(
+                &[[20u8, 9].as_slice(), mint_authority.as_ref(), &[0]].concat()
+            )
+            .unwrap(),
+            TokenInstruction::InitializeMint2 {
+                decimals: 9,
+                mint_authority,
+                freeze_authority: None,
+            }
+        );
+        assert_eq!(
+            TokenInstruction::unpack(&[26, 1, 100, 0, 0, 0, 0, 0, 0, 0, 2, 1, 0, 0, 0, 0, 0, 0, 0])
+                .unwrap(),
+            TokenInstruction::TransferCheckedWithFee {
+                amount: 100,
+                decimals: 2,
+                fee: 1,
+            }
+        );
+        assert_eq!(
+            TokenInstruction::unpack(&[36, 0, 1]).unwrap(),
+            TokenInstruction::Extension {
+                tag: 36,
+                data: vec![0, 1]
+            }
+        );
+    }
+
+    #[test]
+    fn test_token_transfers() {
+        let (fixture, _) = crate::fixtures::Fixture::load("token_2022_transfer_checked");
+        let transfers = fixture.parse().unwrap().token_transfers();
+
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].program_ctx.program_id, TOKEN_2022_PROGRAM_ID);
+        assert_eq!(transfers[0].amount, 5_000_000_000);
+        assert_eq!(transfers[0].fee_amount, 0);
+        assert_eq!(transfers[0].net_amount, 5_000_000_000);
+        assert_eq!(transfers[0].transfer_hook_program, None);
+    }
+
+    #[test]
+    fn test_associated_token_account() {
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&[]).unwrap(),
+            AssociatedTokenAccountInstruction::Create
+        );
+        assert_eq!(
+            AssociatedTokenAccountInstruction::unpack(&[1]).unwrap(),
+            AssociatedTokenAccountInstruction::CreateIdempotent
+        );
+    }
+}
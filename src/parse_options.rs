@@ -0,0 +1,120 @@
+//! [`ParseOptions`] shared by entry points of [`crate::log_parser`],
+//! `instruction_parser` & `transaction_parser`, so behavior of all parsers is configured
+//! in one place
+//!
+//! [`ParseOptions::default`] keeps strict behavior of parsers without options
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Version of [`ParseOptions`] layout, increased on incompatible changes
+pub const PARSE_OPTIONS_VERSION: u32 = 1;
+
+/// Handling of log lines not matched by any known format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownLinePolicy {
+    /// Fail with [`crate::log_parser::Error::BadLogLine`]
+    #[default]
+    Fail,
+    /// Skip line, as if it wasn't logged
+    Skip,
+}
+
+/// Handling of failed invocations (`Program <id> failed: <err>`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Fail with [`crate::log_parser::Error::ErrorLog`] or
+    /// [`crate::log_parser::Error::ErrorToCompleteLog`]
+    #[default]
+    Fail,
+    /// Keep everything executed before failure, instructions after failed one are dropped
+    Partial,
+}
+
+/// Handling of logs cut off by `Log truncated`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationPolicy {
+    /// Stop on truncation, contexts of instructions without logs are restored by
+    /// transaction parser
+    #[default]
+    Restore,
+    /// Stop on truncation, instructions without logs are left unmatched
+    Ignore,
+    /// Fail with [`crate::log_parser::Error::LogTruncated`]
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// Options of newer version are rejected on deserialization
+    #[serde(deserialize_with = "deserialize_version")]
+    pub version: u32,
+    pub unknown_lines: UnknownLinePolicy,
+    pub failures: FailurePolicy,
+    pub truncation: TruncationPolicy,
+    /// Split space-separated payloads of single `Program data:` line (`sol_log_data` with
+    /// several slices) into separate [`crate::log_parser::ProgramLog::Data`]
+    pub split_program_data: bool,
+    /// Split `Program log:` payloads with embedded newlines (multi-line `msg!` or several
+    /// lines joined before parsing) into separate [`crate::log_parser::ProgramLog::Log`] of
+    /// the same context. `Program log: ` prefix of joined lines is stripped, joined lines of
+    /// other kinds fail with [`crate::log_parser::Error::MixedMultilineLog`]
+    pub split_multiline_logs: bool,
+    /// Trim trailing whitespace of log lines (e.g. `\r` of CRLF dumps) before parsing
+    pub normalize_lines: bool,
+    /// Keep transaction instructions of precompiles (ed25519, secp256k1), which are executed
+    /// without invocation logs, in parsed transaction with empty logs instead of omitting
+    /// them, so instructions list matches the one seen through Instructions sysvar
+    pub include_precompiles: bool,
+    /// Parse `Program log:` payloads with JSON object or array into
+    /// [`crate::log_parser::ProgramLog::Json`]
+    #[cfg(feature = "json_log")]
+    pub json_logs: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            version: PARSE_OPTIONS_VERSION,
+            unknown_lines: UnknownLinePolicy::default(),
+            failures: FailurePolicy::default(),
+            truncation: TruncationPolicy::default(),
+            split_program_data: false,
+            split_multiline_logs: false,
+            normalize_lines: false,
+            include_precompiles: false,
+            #[cfg(feature = "json_log")]
+            json_logs: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Parse as much as possible: skip unknown lines, keep failed & truncated transactions
+    /// parts, split & normalize data
+    pub fn lenient() -> Self {
+        Self {
+            unknown_lines: UnknownLinePolicy::Skip,
+            failures: FailurePolicy::Partial,
+            truncation: TruncationPolicy::Restore,
+            split_program_data: true,
+            split_multiline_logs: true,
+            normalize_lines: true,
+            include_precompiles: true,
+            ..Self::default()
+        }
+    }
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version > PARSE_OPTIONS_VERSION {
+        return Err(serde::de::Error::custom(format!(
+            "Unsupported parse options version {version}, max supported {PARSE_OPTIONS_VERSION}"
+        )));
+    }
+    Ok(version)
+}
@@ -0,0 +1,239 @@
+//! Human names of program ids: built-in well-known programs, extensible by user
+
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use serde::de::Error as _;
+
+use crate::log_parser::Pubkey;
+
+/// Well-known programs as `(program_id, name)`
+pub const WELL_KNOWN_PROGRAMS: &[(&str, &str)] = &[
+    ("11111111111111111111111111111111", "System Program"),
+    (
+        "Vote111111111111111111111111111111111111111",
+        "Vote Program",
+    ),
+    (
+        "Stake11111111111111111111111111111111111111",
+        "Stake Program",
+    ),
+    (
+        "Config1111111111111111111111111111111111111",
+        "Config Program",
+    ),
+    (
+        "ComputeBudget111111111111111111111111111111",
+        "Compute Budget Program",
+    ),
+    (
+        "AddressLookupTab1e1111111111111111111111111",
+        "Address Lookup Table Program",
+    ),
+    ("BPFLoader2111111111111111111111111111111111", "BPF Loader"),
+    (
+        "BPFLoaderUpgradeab1e11111111111111111111111",
+        "BPF Upgradeable Loader",
+    ),
+    (
+        "Ed25519SigVerify111111111111111111111111111",
+        "Ed25519 SigVerify Precompile",
+    ),
+    (
+        "KeccakSecp256k11111111111111111111111111111",
+        "Secp256k1 SigVerify Precompile",
+    ),
+    (
+        "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+        "Token Program",
+    ),
+    (
+        "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+        "Token-2022 Program",
+    ),
+    (
+        "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+        "Associated Token Account Program",
+    ),
+    (
+        "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo",
+        "Memo Program v1",
+    ),
+    (
+        "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+        "Memo Program",
+    ),
+    (
+        "namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX",
+        "Name Service Program",
+    ),
+    (
+        "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s",
+        "Metaplex Token Metadata",
+    ),
+    (
+        "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY",
+        "Metaplex Bubblegum",
+    ),
+    (
+        "CndyV3LdqHUfDLmE5naZjVN8rBZz4tqhdefbAnjHG3JR",
+        "Metaplex Candy Machine v3",
+    ),
+    (
+        "hausS13jsjafwWwGqZTUQRmWyvyxn9EQpqMwV1PBBmk",
+        "Metaplex Auction House",
+    ),
+    ("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV", "SPL Noop"),
+    (
+        "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK",
+        "SPL Account Compression",
+    ),
+    (
+        "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
+        "Jupiter Aggregator v6",
+    ),
+    (
+        "JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB",
+        "Jupiter Aggregator v4",
+    ),
+    (
+        "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc",
+        "Orca Whirlpool",
+    ),
+    (
+        "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+        "Raydium AMM v4",
+    ),
+    (
+        "srmqPvymJeFKQ4zGQed1GFppgkRHL9kaELCbyksJtPX",
+        "OpenBook DEX",
+    ),
+    (
+        "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+        "Serum DEX v3",
+    ),
+    (
+        "MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD",
+        "Marinade Finance",
+    ),
+    (
+        "FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH",
+        "Pyth Oracle",
+    ),
+];
+
+lazy_static! {
+    static ref WELL_KNOWN: HashMap<Pubkey, &'static str> = WELL_KNOWN_PROGRAMS
+        .iter()
+        .map(|(program_id, name)| {
+            (
+                program_id
+                    .parse::<Pubkey>()
+                    .expect("Well-known program id is valid"),
+                *name,
+            )
+        })
+        .collect();
+}
+
+/// Name of well-known program
+pub fn well_known_name(program_id: &Pubkey) -> Option<&'static str> {
+    WELL_KNOWN.get(program_id).copied()
+}
+
+/// Registry of program names
+///
+/// [`ProgramRegistry::default`] contains [`WELL_KNOWN_PROGRAMS`], user registered names
+/// override them
+#[derive(Debug, Clone, Default)]
+pub struct ProgramRegistry {
+    names: HashMap<Pubkey, String>,
+    without_well_known: bool,
+}
+
+impl ProgramRegistry {
+    /// Registry without well-known programs
+    pub fn empty() -> Self {
+        Self {
+            names: HashMap::new(),
+            without_well_known: true,
+        }
+    }
+
+    /// Parse user registry from JSON object `{"<program_id>": "<name>"}`, on top of
+    /// well-known programs
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let mut registry = Self::default();
+        for (program_id, name) in serde_json::from_str::<HashMap<String, String>>(json)? {
+            let program_id = program_id.parse::<Pubkey>().map_err(|err| {
+                serde_json::Error::custom(format!("invalid program id {program_id}: {err}"))
+            })?;
+            registry.register(program_id, name);
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, program_id: Pubkey, name: impl Into<String>) {
+        self.names.insert(program_id, name.into());
+    }
+
+    pub fn with_program(mut self, program_id: Pubkey, name: impl Into<String>) -> Self {
+        self.register(program_id, name);
+        self
+    }
+
+    pub fn name(&self, program_id: &Pubkey) -> Option<&str> {
+        self.names
+            .get(program_id)
+            .map(String::as_str)
+            .or_else(|| match self.without_well_known {
+                true => None,
+                false => well_known_name(program_id),
+            })
+    }
+
+    /// `"<name> (<program_id>)"` or just base58 of `program_id` if name is unknown
+    pub fn label(&self, program_id: &Pubkey) -> String {
+        let encoded = bs58::encode(program_id).into_string();
+        match self.name(program_id) {
+            Some(name) => format!("{name} ({encoded})"),
+            None => encoded,
+        }
+    }
+}
+
+impl Extend<(Pubkey, String)> for ProgramRegistry {
+    fn extend<T: IntoIterator<Item = (Pubkey, String)>>(&mut self, iter: T) {
+        self.names.extend(iter)
+    }
+}
+
+#[cfg(test)]
+mod program_registry_test {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        assert_eq!(WELL_KNOWN.len(), WELL_KNOWN_PROGRAMS.len());
+
+        let system = "11111111111111111111111111111111"
+            .parse::<Pubkey>()
+            .unwrap();
+        let custom = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"
+            .parse::<Pubkey>()
+            .unwrap();
+
+        let registry = ProgramRegistry::from_json(
+            r#"{"MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr": "My Memo"}"#,
+        )
+        .unwrap();
+        assert_eq!(registry.name(&system), Some("System Program"));
+        assert_eq!(registry.name(&custom), Some("My Memo"));
+        assert_eq!(
+            registry.label(&system),
+            "System Program (11111111111111111111111111111111)"
+        );
+        assert_eq!(ProgramRegistry::empty().name(&system), None);
+        assert!(ProgramRegistry::from_json(r#"{"invalid": "name"}"#).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Detection of upgrades of watched program with reload of its [`Idl`]
+//!
+//! Upgrade is detected by slot of the last deploy, stored in `ProgramData` account of
+//! upgradeable loader. Only header of account is requested, not the program binary.
+//! Programs of non-upgradeable loaders have no `ProgramData` account and are never reported
+
+use std::{sync::Arc, time::Duration};
+
+use arc_swap::ArcSwap;
+use futures::future::BoxFuture;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    client_error::ClientError, nonblocking::rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig,
+};
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    commitment_config::CommitmentConfig,
+};
+use tracing::*;
+
+use crate::{
+    event_reader_service::{Clock, Slot, TokioClock},
+    idl::{self, Idl},
+    transaction_parser::Pubkey,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    ClientError(#[from] ClientError),
+    #[error("Account {0} isn't `ProgramData` of upgradeable loader")]
+    InvalidProgramData(Pubkey),
+    #[error(transparent)]
+    Idl(#[from] idl::Error),
+}
+
+/// IDL shared with decoders, replaced by [`ProgramWatcher`] on upgrade
+pub type SharedIdl = Arc<ArcSwap<Idl>>;
+
+/// Loader of IDL of upgraded program, e.g. from file or on-chain IDL account
+pub type IdlLoaderFn =
+    Arc<dyn Send + Sync + Fn(Pubkey) -> BoxFuture<'static, Result<Idl, idl::Error>>>;
+
+/// Callback of detected upgrade
+pub type OnUpgradeFn = Arc<dyn Send + Sync + Fn(&ProgramUpgrade)>;
+
+#[derive(Debug, Clone)]
+pub struct ProgramUpgrade {
+    pub program_id: Pubkey,
+    /// Deploy slot seen before upgrade
+    pub previous_deploy_slot: Slot,
+    pub deploy_slot: Slot,
+    /// IDL loaded after upgrade, `None` if there is no `idl_loader` or loading failed
+    pub idl: Option<Arc<Idl>>,
+}
+
+/// Polls deploy slot of `program_id` every `interval`. On change, IDL is reloaded by
+/// `idl_loader` into `idl`, then `on_upgrade` is called
+///
+/// Deploy slot on start is only remembered, so upgrades while watcher wasn't running are
+/// not reported
+#[derive(derive_builder::Builder)]
+#[builder(pattern = "owned")]
+pub struct ProgramWatcher {
+    pub program_id: Pubkey,
+    pub client: Arc<RpcClient>,
+    #[builder(default = "CommitmentConfig::finalized()")]
+    pub commitment_config: CommitmentConfig,
+    #[builder(default = "Duration::from_secs(60)")]
+    pub interval: Duration,
+    #[builder(default)]
+    pub idl: Option<SharedIdl>,
+    #[builder(default)]
+    pub idl_loader: Option<IdlLoaderFn>,
+    #[builder(default)]
+    pub on_upgrade: Option<OnUpgradeFn>,
+    /// Time source of polling, [`TokioClock`] by default
+    #[builder(default = "Arc::new(TokioClock)")]
+    pub clock: Arc<dyn Clock>,
+}
+
+impl ProgramWatcher {
+    /// Slot of the last deploy of program, `None` if program isn't upgradeable or closed
+    pub async fn deploy_slot(&self) -> Result<Option<Slot>, Error> {
+        let (program_data, _) = Pubkey::find_program_address(
+            &[self.program_id.as_ref()],
+            &bpf_loader_upgradeable::id(),
+        );
+        let account = self
+            .client
+            .get_account_with_config(
+                &program_data,
+                RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    data_slice: Some(UiDataSliceConfig {
+                        offset: 0,
+                        length: UpgradeableLoaderState::size_of_programdata_metadata(),
+                    }),
+                    commitment: Some(self.commitment_config),
+                    min_context_slot: None,
+                },
+            )
+            .await?
+            .value;
+
+        match account {
+            Some(account) if account.owner == bpf_loader_upgradeable::id() => {
+                program_data_deploy_slot(&account.data)
+                    .map(Some)
+                    .ok_or(Error::InvalidProgramData(program_data))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Load IDL by `idl_loader` & store it into `idl`
+    pub async fn reload_idl(&self) -> Result<Option<Arc<Idl>>, Error> {
+        let Some(idl_loader) = self.idl_loader.as_ref() else {
+            return Ok(None);
+        };
+        let idl = Arc::new(idl_loader(self.program_id).await?);
+        if let Some(shared) = self.idl.as_ref() {
+            shared.store(Arc::clone(&idl));
+        }
+        Ok(Some(idl))
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        let mut known = None;
+        loop {
+            match self.deploy_slot().await {
+                Ok(Some(deploy_slot)) => match known.replace(deploy_slot) {
+                    Some(previous_deploy_slot) if previous_deploy_slot != deploy_slot => {
+                        self.on_deploy(previous_deploy_slot, deploy_slot).await
+                    }
+                    Some(_) => {}
+                    None => debug!(
+                        "Program {} is deployed at {deploy_slot} slot",
+                        self.program_id
+                    ),
+                },
+                Ok(None) => debug!("Program {} isn't upgradeable", self.program_id),
+                Err(err) => error!("Error while get deploy slot: {err:?}"),
+            }
+            self.clock.sleep(self.interval).await;
+        }
+    }
+
+    async fn on_deploy(&self, previous_deploy_slot: Slot, deploy_slot: Slot) {
+        info!(
+            "Program {} upgraded at {deploy_slot} slot, reload IDL",
+            self.program_id
+        );
+        let idl = self.reload_idl().await.unwrap_or_else(|err| {
+            error!("Error while reload IDL of upgraded program: {err:?}");
+            None
+        });
+        if let Some(on_upgrade) = self.on_upgrade.as_ref() {
+            on_upgrade(&ProgramUpgrade {
+                program_id: self.program_id,
+                previous_deploy_slot,
+                deploy_slot,
+                idl,
+            });
+        }
+    }
+}
+
+/// Deploy slot from header of `ProgramData` account, `None` if it's another account
+pub fn program_data_deploy_slot(data: &[u8]) -> Option<Slot> {
+    let header = data.get(..UpgradeableLoaderState::size_of_programdata_metadata())?;
+    match bincode::deserialize(header).ok()? {
+        UpgradeableLoaderState::ProgramData { slot, .. } => Some(slot),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod program_watcher_test {
+    use super::*;
+
+    #[test]
+    fn test_program_data_deploy_slot() {
+        let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: 42,
+            upgrade_authority_address: Some(Pubkey::new_unique()),
+        })
+        .unwrap();
+        data.extend([0; 16]);
+        assert_eq!(program_data_deploy_slot(&data), Some(42));
+
+        let mut program = bincode::serialize(&UpgradeableLoaderState::Program {
+            programdata_address: Pubkey::new_unique(),
+        })
+        .unwrap();
+        program.resize(data.len(), 0);
+        assert_eq!(program_data_deploy_slot(&program), None);
+        assert_eq!(program_data_deploy_slot(&data[..8]), None);
+    }
+}
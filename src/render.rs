@@ -0,0 +1,268 @@
+//! Human-readable indented tree of program invocations with instruction names, compute
+//! units, logs & events. Plain text or with ANSI colors
+
+use std::{borrow::Cow, collections::HashMap, fmt::Write};
+
+#[cfg(feature = "idl")]
+use crate::idl::Idl;
+#[cfg(feature = "solana")]
+use crate::transaction_parser::{Instruction, TransactionParsedMeta};
+use crate::{
+    log_parser::{ProgramContext, ProgramLog},
+    program_registry::ProgramRegistry,
+};
+
+const INDENT: &str = "  ";
+
+mod ansi {
+    pub const RESET: &str = "\x1b[0m";
+    pub const BOLD_CYAN: &str = "\x1b[1;36m";
+    pub const GREEN: &str = "\x1b[32m";
+    pub const YELLOW: &str = "\x1b[33m";
+    pub const RED: &str = "\x1b[31m";
+    pub const DIM: &str = "\x1b[2m";
+}
+
+/// Renderer of transaction tree
+///
+/// ```text
+/// System Program (11111111111111111111111111111111) Transfer [#0] 150 CU
+///   log: ...
+///   Token Program (TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA) TransferChecked [#0] 300 CU
+/// ```
+#[derive(Default)]
+pub struct Renderer<'a> {
+    ansi: bool,
+    hide_logs: bool,
+    registry: Cow<'a, ProgramRegistry>,
+    #[cfg(feature = "idl")]
+    idls: HashMap<crate::transaction_parser::Pubkey, &'a Idl>,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use ANSI colors
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    /// Render only invocations, without logs & events
+    pub fn with_hide_logs(mut self, hide_logs: bool) -> Self {
+        self.hide_logs = hide_logs;
+        self
+    }
+
+    /// Label program ids by `registry`, well-known programs are labeled by default
+    pub fn with_registry(mut self, registry: &'a ProgramRegistry) -> Self {
+        self.registry = Cow::Borrowed(registry);
+        self
+    }
+
+    /// Decode instructions & events of `program_id` by `idl`
+    #[cfg(feature = "idl")]
+    pub fn with_idl(mut self, program_id: crate::transaction_parser::Pubkey, idl: &'a Idl) -> Self {
+        self.idls.insert(program_id, idl);
+        self
+    }
+
+    /// Render tree bound by [`crate::log_parser::parse_events`], `roots` are expected
+    /// in order of [`crate::log_parser::root_contexts`]
+    pub fn render_logs(
+        &self,
+        roots: &[ProgramContext],
+        bound: &HashMap<ProgramContext, Vec<ProgramLog>>,
+    ) -> String {
+        let mut output = String::new();
+        for root in roots {
+            self.render_node(
+                &mut output,
+                root,
+                &|ctx| bound.get(ctx).map(|logs| (None, logs.as_slice())),
+                0,
+            );
+        }
+        output
+    }
+
+    /// Render tree of [`TransactionParsedMeta`] with instruction names
+    #[cfg(feature = "solana")]
+    pub fn render_transaction(&self, meta: &TransactionParsedMeta) -> String {
+        let mut output = String::new();
+        for root in meta.root_ixs.iter() {
+            self.render_node(
+                &mut output,
+                root,
+                &|ctx| {
+                    meta.meta
+                        .get(ctx)
+                        .map(|(ix, logs)| (Some(ix), logs.as_slice()))
+                },
+                0,
+            );
+        }
+        output
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.ansi {
+            format!("{color}{text}{}", ansi::RESET)
+        } else {
+            text.to_owned()
+        }
+    }
+
+    fn render_node<'n>(
+        &self,
+        output: &mut String,
+        ctx: &ProgramContext,
+        lookup: &NodeLookup<'_, 'n>,
+        depth: usize,
+    ) {
+        let indent = INDENT.repeat(depth);
+        let (ix, logs) = lookup(ctx).unwrap_or((None, &[]));
+
+        let consumed = logs.iter().find_map(|log| match log {
+            ProgramLog::Consumed { consumed, .. } => Some(*consumed),
+            _ => None,
+        });
+
+        let _ = write!(
+            output,
+            "{indent}{}",
+            self.paint(ansi::BOLD_CYAN, &self.registry.label(&ctx.program_id))
+        );
+        if let Some(name) = ix.and_then(|ix| self.instruction_name(ix)) {
+            let _ = write!(output, " {}", self.paint(ansi::GREEN, &name));
+        }
+        let _ = write!(output, " [#{}]", ctx.program_call_index);
+        if let Some(consumed) = consumed {
+            let _ = write!(output, " {consumed} CU");
+        }
+        output.push('\n');
+
+        for log in logs {
+            match log {
+                ProgramLog::Invoke(child) => self.render_node(output, child, lookup, depth + 1),
+                _ if self.hide_logs => {}
+                ProgramLog::Log(log) => {
+                    let _ = writeln!(
+                        output,
+                        "{indent}{INDENT}{}",
+                        self.paint(ansi::DIM, &format!("log: {log}"))
+                    );
+                }
+                ProgramLog::Data(data) => {
+                    let _ = writeln!(
+                        output,
+                        "{indent}{INDENT}{}",
+                        self.paint(ansi::YELLOW, &self.event(ctx, log, data))
+                    );
+                }
+                ProgramLog::Return(ret) => {
+                    let _ = writeln!(output, "{indent}{INDENT}return: {}", ret.data);
+                }
+                ProgramLog::DeployedProgram(program_id) => {
+                    let _ = writeln!(
+                        output,
+                        "{indent}{INDENT}deployed: {}",
+                        bs58::encode(program_id).into_string()
+                    );
+                }
+                ProgramLog::UpgradedProgram(program_id) => {
+                    let _ = writeln!(
+                        output,
+                        "{indent}{INDENT}upgraded: {}",
+                        bs58::encode(program_id).into_string()
+                    );
+                }
+                ProgramLog::Consumed { .. } => {}
+                #[cfg(feature = "json_log")]
+                ProgramLog::Json(value) => {
+                    let _ = writeln!(
+                        output,
+                        "{indent}{INDENT}{}",
+                        self.paint(ansi::DIM, &format!("json: {value}"))
+                    );
+                }
+                #[allow(unreachable_patterns)]
+                other => {
+                    let _ = writeln!(
+                        output,
+                        "{indent}{INDENT}{}",
+                        self.paint(ansi::RED, &format!("{other:?}"))
+                    );
+                }
+            }
+        }
+    }
+
+    #[allow(unused_variables)]
+    fn event(&self, ctx: &ProgramContext, log: &ProgramLog, data: &str) -> String {
+        #[cfg(feature = "idl")]
+        if let Some(Ok(Some(decoded))) = self
+            .idls
+            .get(&ctx.program_id)
+            .map(|idl| idl.decode_program_log(log))
+        {
+            return format!("event: {} {}", decoded.name, decoded.data);
+        }
+        format!("data: {data}")
+    }
+
+    #[cfg(feature = "solana")]
+    fn instruction_name(&self, ix: &Ix) -> Option<String> {
+        #[cfg(feature = "idl")]
+        if let Some(Ok(Some(decoded))) = self
+            .idls
+            .get(&ix.program_id)
+            .map(|idl| idl.decode_instruction(&ix.data))
+        {
+            return Some(decoded.name);
+        }
+        native_instruction_name(ix)
+    }
+
+    #[cfg(not(feature = "solana"))]
+    fn instruction_name(&self, _ix: &Ix) -> Option<String> {
+        None
+    }
+}
+
+type NodeLookup<'f, 'n> =
+    dyn Fn(&ProgramContext) -> Option<(Option<&'n Ix>, &'n [ProgramLog])> + 'f;
+
+#[cfg(feature = "solana")]
+type Ix = Instruction;
+/// Without solana feature there are no instructions, only logs
+#[cfg(not(feature = "solana"))]
+type Ix = ();
+
+/// Name of native program instruction by its `Debug` representation
+#[cfg(feature = "solana")]
+fn native_instruction_name(ix: &Instruction) -> Option<String> {
+    use crate::native_instruction_parser::*;
+
+    fn name<IX: NativeInstruction + std::fmt::Debug>(ix: &Instruction) -> Option<String> {
+        if !IX::program_ids().contains(&ix.program_id) {
+            return None;
+        }
+        let debug = format!("{:?}", IX::unpack(&ix.data).ok()?);
+        Some(
+            debug
+                .split(|ch: char| !ch.is_alphanumeric() && ch != '_')
+                .next()
+                .unwrap_or_default()
+                .to_owned(),
+        )
+    }
+
+    name::<SystemInstruction>(ix)
+        .or_else(|| name::<TokenInstruction>(ix))
+        .or_else(|| name::<AssociatedTokenAccountInstruction>(ix))
+        .or_else(|| name::<ComputeBudgetInstruction>(ix))
+        .or_else(|| name::<StakeInstruction>(ix))
+}
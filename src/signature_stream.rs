@@ -0,0 +1,164 @@
+//! Paginated listing of address signatures as [`Stream`], used by resync of
+//! [`EventsReader`](crate::event_reader_service::EventsReader) & backfill tools
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use de_solana_client::SignatureData;
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::event_reader_service::{Error, Pubkey, Result, SignatureSource, Slot, SolanaSignature};
+
+/// Max `limit` of `getSignaturesForAddress`
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Bounds & paging of [`SignatureStream`]
+#[derive(Debug, Clone, Default)]
+pub struct SignatureStreamConfig {
+    /// Start from signature older than this one, from the newest if `None`
+    pub before: Option<SolanaSignature>,
+    /// Stop on this signature, exclusive
+    pub until: Option<SolanaSignature>,
+    /// Stop on the first signature older than this slot, works even if `until` is already
+    /// pruned by RPC provider
+    pub min_slot: Option<Slot>,
+    /// Signatures per request, [`MAX_PAGE_SIZE`] if `None`
+    pub page_size: Option<usize>,
+    pub commitment: Option<CommitmentConfig>,
+}
+
+/// Signatures of address, newest first
+///
+/// Pages are requested on demand, so dropped stream doesn't request the rest of history
+pub struct SignatureStream<'a>(BoxStream<'a, Result<SignatureData>>);
+
+impl<'a> SignatureStream<'a> {
+    pub fn new(
+        source: &'a dyn SignatureSource,
+        address: Pubkey,
+        config: SignatureStreamConfig,
+    ) -> Self {
+        let SignatureStreamConfig {
+            before,
+            until,
+            min_slot,
+            page_size,
+            commitment,
+        } = config;
+        let page_size = page_size.unwrap_or(MAX_PAGE_SIZE);
+
+        let pages = stream::try_unfold(Some(before), move |before| async move {
+            let Some(before) = before else {
+                return Result::Ok(None);
+            };
+
+            let page = source
+                .get_signatures_for_address(
+                    &address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until,
+                        limit: Some(page_size),
+                        commitment,
+                    },
+                )
+                .await?;
+            let is_last_page = page.len() < page_size;
+
+            let page = page
+                .into_iter()
+                .map(|status| {
+                    Ok(SignatureData {
+                        signature: status
+                            .signature
+                            .parse::<SolanaSignature>()
+                            .map_err(|err| Error::SignatureParsingError(err.to_string()))?,
+                        slot: status.slot,
+                        err: status.err,
+                        block_time: status.block_time,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            let next = match page.last() {
+                Some(last) if !is_last_page => Some(Some(last.signature)),
+                _ => None,
+            };
+            Ok(Some((page, next)))
+        });
+
+        Self(
+            pages
+                .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+                .try_flatten()
+                .try_take_while(move |data| {
+                    future::ready(Ok(min_slot.map_or(true, |slot| data.slot >= slot)))
+                })
+                .boxed(),
+        )
+    }
+}
+
+impl Stream for SignatureStream<'_> {
+    type Item = Result<SignatureData>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod signature_stream_test {
+    use super::*;
+    use crate::{fixtures::Fixture, mock_source::MockChain};
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let chain = MockChain::new();
+        let signatures = ["failed", "cpi_event"]
+            .map(|name| chain.push_missed(Fixture::load(name).0).unwrap())
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>();
+        let program_id = "Fixture111111111111111111111111111111111111"
+            .parse::<Pubkey>()
+            .unwrap();
+
+        let listed = SignatureStream::new(
+            &chain,
+            program_id,
+            SignatureStreamConfig {
+                page_size: Some(1),
+                ..Default::default()
+            },
+        )
+        .map_ok(|data| data.signature)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+        assert_eq!(listed, signatures);
+
+        let listed = SignatureStream::new(
+            &chain,
+            program_id,
+            SignatureStreamConfig {
+                until: Some(signatures[1]),
+                page_size: Some(1),
+                ..Default::default()
+            },
+        )
+        .map_ok(|data| data.signature)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+        assert_eq!(listed, signatures[..1]);
+    }
+}
@@ -1,10 +1,17 @@
 //! This module is used to help the [`crate::event_reader_service`] storage management
 //! It allows us to keep track of which transactions have already been processed
 //! (registered) and store a pointer to the transaction - resync boundary
+//! Also it keeps the last seen slot of subscribed accounts to deduplicate account updates
+//! and idempotency keys of side-effectful consumers, leases of transactions shared by
+//! several readers and ids of already emitted events
 
-use std::fmt;
+use std::{fmt, time::Duration};
 
-pub use crate::transaction_parser::{Pubkey, Signature as SolanaSignature};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "solana")]
+use crate::transaction_parser::{RpcTransactionConfig, TransactionParsedMeta};
+pub use crate::types::{Pubkey, Signature as SolanaSignature, Slot};
 
 /// [`RegisterTransaction`] is a trait for managing transactions.
 ///
@@ -13,73 +20,1281 @@ pub use crate::transaction_parser::{Pubkey, Signature as SolanaSignature};
 pub trait RegisterTransaction {
     type Error: fmt::Debug;
 
-    /// Register a transaction with the given `program_id` and `transaction_hash`.
-    fn register_transaction(
-        &self,
-        program_id: &Pubkey,
-        transaction_hash: &SolanaSignature,
-    ) -> Result<(), Self::Error>;
+    /// Register a transaction with the given `program_id` and `transaction_hash`.
+    fn register_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> Result<(), Self::Error>;
+
+    /// Check if a transaction with the given `program_id` and `transaction_hash` is registered
+    /// with [`RegisterTransaction::register_transaction`] before
+    fn is_transaction_registered(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> Result<bool, Self::Error>;
+
+    /// Remove registration of transaction, so it's consumed again by the next resync, e.g.
+    /// after its consuming was reverted
+    fn unregister_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> Result<(), Self::Error>;
+
+    /// Given a `program_id` and a list of transactions (`transaction_hash_set`),
+    /// filter out those that are not registered.
+    ///
+    /// Returns a `Result` containing a `Vec` of unregistered `SolanaSignature`
+    fn filter_unregistered_transactions(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash_set: &[SolanaSignature],
+    ) -> Result<Vec<SolanaSignature>, Self::Error>;
+}
+
+/// Position of resync: last resynced transaction and its slot
+///
+/// Resync stops on `signature` or on the first transaction older than `slot`, so pointer
+/// stays usable after RPC provider pruned `signature`. Empty pointer means resync from
+/// the beginning
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResyncPointer {
+    pub signature: Option<SolanaSignature>,
+    pub slot: Option<Slot>,
+}
+
+/// Why resync pointer was moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResyncPointerMoveReason {
+    /// Resync processed transactions up to new pointer
+    Resync,
+    /// Rollback requested by operator
+    Rollback,
+    /// Transactions after new pointer were dropped by reorg
+    Reorg,
+}
+
+/// Entry of append-only history of resync pointer movements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResyncPointerMove {
+    pub old: ResyncPointer,
+    pub new: ResyncPointer,
+    /// Unix time of movement in milliseconds
+    pub timestamp_ms: u64,
+    pub reason: ResyncPointerMoveReason,
+}
+
+/// Transaction skipped by resync after repeated failures, so it doesn't block resync
+/// pointer. See [`ResyncedTransactionsPtrStorage::quarantine_transaction`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedTransaction {
+    pub signature: SolanaSignature,
+    /// Last error of transaction
+    pub reason: String,
+    /// Count of failed attempts, including ones before quarantine
+    pub attempts: usize,
+    /// Unix time of quarantine in milliseconds
+    pub timestamp_ms: u64,
+    /// Set by operator, so transaction is processed again by the next resync
+    pub retry_requested: bool,
+}
+
+/// This trait extends [`RegisterTransaction`]
+/// and provides methods for managing the last resynced transaction.
+pub trait ResyncedTransactionsPtrStorage: RegisterTransaction {
+    /// Initializes the last resynced transaction if it's not initialized before.
+    fn initialize_if_needed_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction: &SolanaSignature,
+    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+
+    /// Get last resynced transaction, initialized
+    /// by [`ResyncedTransactionsPtrStorage::initialize_if_needed_resynced_transaction`] or
+    /// setted by [`ResyncedTransactionsPtrStorage::set_last_resynced_transaction`]
+    fn get_last_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<Option<SolanaSignature>, <Self as RegisterTransaction>::Error>;
+
+    /// Set last recyned transaction into new one
+    fn set_last_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction: &SolanaSignature,
+    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+
+    fn reset_last_resynced_transaction(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+
+    /// Get slot of last resynced transaction, `None` if it's not stored (e.g. pointer
+    /// was set before slots were stored)
+    fn get_last_resynced_slot(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<Option<Slot>, <Self as RegisterTransaction>::Error>;
+
+    fn set_last_resynced_slot(
+        &self,
+        program_id: &Pubkey,
+        slot: Slot,
+    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+
+    fn get_resync_pointer(
+        &self,
+        program_id: &Pubkey,
+    ) -> Result<ResyncPointer, <Self as RegisterTransaction>::Error> {
+        Ok(ResyncPointer {
+            signature: self.get_last_resynced_transaction(program_id)?,
+            slot: self.get_last_resynced_slot(program_id)?,
+        })
+    }
+
+    fn set_resync_pointer(
+        &self,
+        program_id: &Pubkey,
+        pointer: &ResyncPointer,
+    ) -> Result<(), <Self as RegisterTransaction>::Error> {
+        self.reset_last_resynced_transaction(program_id)?;
+        if let Some(signature) = pointer.signature.as_ref() {
+            self.set_last_resynced_transaction(program_id, signature)?;
+        }
+        if let Some(slot) = pointer.slot {
+            self.set_last_resynced_slot(program_id, slot)?;
+        }
+        Ok(())
+    }
+
+    /// Move pointer to arbitrary `slot`: the next resync will process all transactions
+    /// since `slot`
+    fn reset_resync_pointer_to_slot(
+        &self,
+        program_id: &Pubkey,
+        slot: Slot,
+    ) -> Result<(), <Self as RegisterTransaction>::Error> {
+        self.set_resync_pointer(
+            program_id,
+            &ResyncPointer {
+                signature: None,
+                slot: Some(slot),
+            },
+        )
+    }
+
+    /// Append `movement` to history of pointer of `program_id`, history is optional, so
+    /// it's no-op by default
+    fn record_resync_pointer_move(
+        &self,
+        _program_id: &Pubkey,
+        _movement: &ResyncPointerMove,
+    ) -> Result<(), <Self as RegisterTransaction>::Error> {
+        Ok(())
+    }
+
+    /// Up to `limit` last movements of pointer of `program_id`, newest first. Empty if
+    /// history isn't kept
+    fn get_resync_pointer_history(
+        &self,
+        _program_id: &Pubkey,
+        _limit: usize,
+    ) -> Result<Vec<ResyncPointerMove>, <Self as RegisterTransaction>::Error> {
+        Ok(vec![])
+    }
+
+    /// Put `transaction` into quarantine of `program_id`, replacing existing entry, and
+    /// register it, so resync skips it & moves pointer past it. Quarantine is optional, so
+    /// by default transaction is only registered
+    fn quarantine_transaction(
+        &self,
+        program_id: &Pubkey,
+        transaction: &QuarantinedTransaction,
+    ) -> Result<(), <Self as RegisterTransaction>::Error> {
+        self.register_transaction(program_id, &transaction.signature)
+    }
+
+    /// Quarantined transactions of `program_id`. Empty if quarantine isn't kept
+    fn get_quarantined_transactions(
+        &self,
+        _program_id: &Pubkey,
+    ) -> Result<Vec<QuarantinedTransaction>, <Self as RegisterTransaction>::Error> {
+        Ok(vec![])
+    }
+
+    /// Remove transaction from quarantine, e.g. after successful retry. Transaction stays
+    /// registered
+    ///
+    /// Returns `false` if transaction isn't quarantined
+    fn release_quarantined_transaction(
+        &self,
+        _program_id: &Pubkey,
+        _transaction_hash: &SolanaSignature,
+    ) -> Result<bool, <Self as RegisterTransaction>::Error> {
+        Ok(false)
+    }
+
+    /// Name of storage backend for diagnostics, see
+    /// [`crate::event_reader_service::EventsReader::describe`]
+    fn backend_name(&self) -> String {
+        std::any::type_name::<Self>().to_owned()
+    }
+}
+
+/// Version of account update: slot and hash of account data
+///
+/// Websocket notifications carry no `write_version`, so several updates of account in the same
+/// slot are told apart by data hash. Update is newer if its slot is greater, or slot is the same
+/// and data differs, so write restoring previous data of the slot is delivered again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AccountUpdateVersion {
+    pub slot: Slot,
+    pub data_hash: [u8; 32],
+}
+
+impl AccountUpdateVersion {
+    pub fn is_newer_than(&self, registered: &AccountUpdateVersion) -> bool {
+        self.slot > registered.slot
+            || (self.slot == registered.slot && self.data_hash != registered.data_hash)
+    }
+}
+
+/// [`RegisterAccountUpdate`] is a trait for deduplication of account updates
+///
+/// Updates of the same account can come from several subscriptions or again after resubscribe,
+/// so only updates newer than the registered one must be processed, see
+/// [`AccountUpdateVersion::is_newer_than`]. Update is registered after it's consumed, so
+/// update failed to consume is processed again on redelivery
+pub trait RegisterAccountUpdate {
+    type Error: fmt::Debug;
+
+    /// Register an update of `account` with `version`, check and set must be atomic
+    ///
+    /// Returns `false` if registered update isn't older than `version`, in this case the
+    /// stored version is not changed
+    fn register_account_update(
+        &self,
+        account: &Pubkey,
+        version: AccountUpdateVersion,
+    ) -> Result<bool, Self::Error>;
+
+    /// Get version of last registered update of `account`
+    fn get_last_account_update(
+        &self,
+        account: &Pubkey,
+    ) -> Result<Option<AccountUpdateVersion>, Self::Error>;
+
+    /// Check if update of `account` with `version` is already processed, i.e. registered
+    /// update isn't older than it
+    fn is_account_update_registered(
+        &self,
+        account: &Pubkey,
+        version: &AccountUpdateVersion,
+    ) -> Result<bool, Self::Error> {
+        Ok(self
+            .get_last_account_update(account)?
+            .is_some_and(|registered| !version.is_newer_than(&registered)))
+    }
+}
+
+/// [`RegisterIdempotencyKey`] is a trait for keys of already performed side effects, used by
+/// [`crate::event_reader_service::IdempotentConsumer`]
+pub trait RegisterIdempotencyKey {
+    type Error: fmt::Debug;
+
+    /// Register `key`
+    ///
+    /// Returns `false` if `key` already registered. Check & set must be atomic: of
+    /// concurrent calls with the same `key`, including calls of replicas over shared
+    /// storage, only one returns `true`
+    fn register_idempotency_key(&self, key: &[u8]) -> Result<bool, Self::Error>;
+
+    /// Remove `key`, so side effect can be performed again
+    fn unregister_idempotency_key(&self, key: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// [`ClaimTransaction`] is a trait for leases of transactions, so replicas of reader over
+/// shared storage don't consume the same transaction twice
+pub trait ClaimTransaction {
+    type Error: fmt::Debug;
+
+    /// Claim transaction with the given `program_id` and `transaction_hash` for `ttl`
+    ///
+    /// Returns `false` if transaction is already claimed and claim isn't expired. Must be
+    /// atomic in storages shared by several processes
+    fn try_claim(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+        ttl: Duration,
+    ) -> Result<bool, Self::Error>;
+
+    /// Release claim before `ttl` expiration, so transaction can be claimed again
+    fn release_claim(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> Result<(), Self::Error>;
+
+    /// Keep claim of consumed transaction without expiration, so it can't be claimed again
+    /// by replica which hasn't registered it
+    fn complete_claim(
+        &self,
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> Result<(), Self::Error>;
+}
+
+/// [`EventRegistry`] is a trait for deduplication of events emitted downstream, as the same
+/// transaction can be consumed by both live subscription & resync
+///
+/// Ids are expected to be `EventId` of [`crate::event_parser`]
+pub trait EventRegistry {
+    type Error: fmt::Debug;
+
+    /// Register event with `event_id`
+    ///
+    /// Returns `false` if event already registered, so it must not be emitted again
+    fn register_event(&self, event_id: &[u8]) -> Result<bool, Self::Error>;
+
+    fn is_event_registered(&self, event_id: &[u8]) -> Result<bool, Self::Error>;
+}
+
+/// [`ParsedTxCache`] is a trait for parsed transactions by signature & config of
+/// `getTransaction` (commitment, encoding & max supported version change parsed result), so
+/// transaction processed again (e.g. by resync after restart) skips RPC request & parsing
+///
+/// Transactions are cached with unix time in ms, so expired ones are removed by
+/// [`ParsedTxCache::evict_parsed_transactions`]
+#[cfg(feature = "solana")]
+pub trait ParsedTxCache {
+    type Error: fmt::Debug;
+
+    /// Transaction with unix time in ms of its caching. `None` if transaction isn't cached with
+    /// `config` or cached by incompatible version of crate. Cached by older parser ones are
+    /// returned, see [`TransactionParsedMeta::is_outdated`]
+    fn get_parsed_transaction(
+        &self,
+        signature: &SolanaSignature,
+        config: &RpcTransactionConfig,
+    ) -> Result<Option<(TransactionParsedMeta, u64)>, Self::Error>;
+
+    fn put_parsed_transaction(
+        &self,
+        signature: &SolanaSignature,
+        config: &RpcTransactionConfig,
+        transaction: &TransactionParsedMeta,
+        cached_at: u64,
+    ) -> Result<(), Self::Error>;
+
+    /// Remove transactions cached before `cached_before` unix time in ms, returns count of
+    /// removed ones
+    fn evict_parsed_transactions(&self, cached_before: u64) -> Result<usize, Self::Error>;
+}
+
+/// Decorator of storage recording latency, errors & hit ratio of calls, so storage-side
+/// slowness is visible separately from RPC one
+pub mod instrumented {
+    use std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::{
+        AccountUpdateVersion, ClaimTransaction, EventRegistry, Pubkey, QuarantinedTransaction,
+        RegisterAccountUpdate, RegisterIdempotencyKey, RegisterTransaction, ResyncPointer,
+        ResyncPointerMove, ResyncedTransactionsPtrStorage, Slot, SolanaSignature,
+    };
+
+    /// Instrumented storage call, index of counters in [`StorageMetrics`]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum StorageOperation {
+        RegisterTransaction,
+        UnregisterTransaction,
+        IsTransactionRegistered,
+        FilterUnregisteredTransactions,
+        GetResyncPointer,
+        SetResyncPointer,
+        UpdateResyncPointer,
+        RegisterAccountUpdate,
+        GetLastAccountUpdate,
+        RegisterIdempotencyKey,
+        UnregisterIdempotencyKey,
+        TryClaim,
+        ReleaseClaim,
+        CompleteClaim,
+        RegisterEvent,
+        IsEventRegistered,
+        QuarantineTransaction,
+        GetQuarantinedTransactions,
+        ReleaseQuarantinedTransaction,
+    }
+
+    impl StorageOperation {
+        pub const ALL: [StorageOperation; 19] = [
+            Self::RegisterTransaction,
+            Self::UnregisterTransaction,
+            Self::IsTransactionRegistered,
+            Self::FilterUnregisteredTransactions,
+            Self::GetResyncPointer,
+            Self::SetResyncPointer,
+            Self::UpdateResyncPointer,
+            Self::RegisterAccountUpdate,
+            Self::GetLastAccountUpdate,
+            Self::RegisterIdempotencyKey,
+            Self::UnregisterIdempotencyKey,
+            Self::TryClaim,
+            Self::ReleaseClaim,
+            Self::CompleteClaim,
+            Self::RegisterEvent,
+            Self::IsEventRegistered,
+            Self::QuarantineTransaction,
+            Self::GetQuarantinedTransactions,
+            Self::ReleaseQuarantinedTransaction,
+        ];
+    }
+
+    #[derive(Debug, Default)]
+    struct OperationCounters {
+        calls: AtomicU64,
+        errors: AtomicU64,
+        latency_us: AtomicU64,
+    }
+
+    /// Counters of [`InstrumentedStorage`], shared with the storage
+    #[derive(Debug, Default)]
+    pub struct StorageMetrics {
+        /// Counters by index in [`StorageOperation::ALL`]
+        operations: [OperationCounters; StorageOperation::ALL.len()],
+        /// Already registered transactions checked by
+        /// [`RegisterTransaction::is_transaction_registered`] &
+        /// [`RegisterTransaction::filter_unregistered_transactions`]
+        hits: AtomicU64,
+        /// Unregistered ones
+        misses: AtomicU64,
+    }
+
+    impl StorageMetrics {
+        pub fn calls(&self, operation: StorageOperation) -> u64 {
+            self.operations[operation as usize]
+                .calls
+                .load(Ordering::Relaxed)
+        }
+
+        pub fn errors(&self, operation: StorageOperation) -> u64 {
+            self.operations[operation as usize]
+                .errors
+                .load(Ordering::Relaxed)
+        }
+
+        /// Sum of latencies of all calls of `operation`
+        pub fn total_latency(&self, operation: StorageOperation) -> Duration {
+            Duration::from_micros(
+                self.operations[operation as usize]
+                    .latency_us
+                    .load(Ordering::Relaxed),
+            )
+        }
+
+        /// `None` if `operation` wasn't called
+        pub fn mean_latency(&self, operation: StorageOperation) -> Option<Duration> {
+            let calls = u32::try_from(self.calls(operation)).ok()?;
+            (calls != 0).then(|| self.total_latency(operation) / calls)
+        }
+
+        pub fn hits(&self) -> u64 {
+            self.hits.load(Ordering::Relaxed)
+        }
+
+        pub fn misses(&self) -> u64 {
+            self.misses.load(Ordering::Relaxed)
+        }
+
+        /// Share of already registered transactions among checked ones, `None` if nothing
+        /// was checked
+        pub fn hit_ratio(&self) -> Option<f64> {
+            let (hits, misses) = (self.hits(), self.misses());
+            (hits + misses != 0).then(|| hits as f64 / (hits + misses) as f64)
+        }
+
+        fn record<T, E: std::fmt::Debug>(
+            &self,
+            operation: StorageOperation,
+            call: impl FnOnce() -> Result<T, E>,
+        ) -> Result<T, E> {
+            let started_at = Instant::now();
+            let result = call();
+            let elapsed = started_at.elapsed();
+
+            let counters = &self.operations[operation as usize];
+            counters.calls.fetch_add(1, Ordering::Relaxed);
+            counters
+                .latency_us
+                .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+            if let Err(err) = &result {
+                counters.errors.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Storage {operation:?} failed in {elapsed:?}: {err:?}");
+            } else {
+                tracing::trace!("Storage {operation:?} done in {elapsed:?}");
+            }
+            result
+        }
+    }
+
+    /// Storage `S` with calls recorded into [`StorageMetrics`]
+    pub struct InstrumentedStorage<S> {
+        inner: S,
+        metrics: Arc<StorageMetrics>,
+    }
+
+    impl<S> InstrumentedStorage<S> {
+        pub fn new(inner: S) -> Self {
+            Self {
+                inner,
+                metrics: Arc::default(),
+            }
+        }
+
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+
+        pub fn metrics(&self) -> Arc<StorageMetrics> {
+            Arc::clone(&self.metrics)
+        }
+    }
+
+    impl<S: RegisterTransaction> RegisterTransaction for InstrumentedStorage<S> {
+        type Error = S::Error;
+
+        fn register_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::RegisterTransaction, || {
+                    self.inner
+                        .register_transaction(program_id, transaction_hash)
+                })
+        }
+
+        fn unregister_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UnregisterTransaction, || {
+                    self.inner
+                        .unregister_transaction(program_id, transaction_hash)
+                })
+        }
+
+        fn is_transaction_registered(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<bool, Self::Error> {
+            let is_registered =
+                self.metrics
+                    .record(StorageOperation::IsTransactionRegistered, || {
+                        self.inner
+                            .is_transaction_registered(program_id, transaction_hash)
+                    })?;
+            match is_registered {
+                true => &self.metrics.hits,
+                false => &self.metrics.misses,
+            }
+            .fetch_add(1, Ordering::Relaxed);
+            Ok(is_registered)
+        }
+
+        fn filter_unregistered_transactions(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash_set: &[SolanaSignature],
+        ) -> Result<Vec<SolanaSignature>, Self::Error> {
+            let unregistered =
+                self.metrics
+                    .record(StorageOperation::FilterUnregisteredTransactions, || {
+                        self.inner
+                            .filter_unregistered_transactions(program_id, transaction_hash_set)
+                    })?;
+            self.metrics
+                .misses
+                .fetch_add(unregistered.len() as u64, Ordering::Relaxed);
+            self.metrics.hits.fetch_add(
+                transaction_hash_set
+                    .len()
+                    .saturating_sub(unregistered.len()) as u64,
+                Ordering::Relaxed,
+            );
+            Ok(unregistered)
+        }
+    }
+
+    impl<S: ResyncedTransactionsPtrStorage> ResyncedTransactionsPtrStorage for InstrumentedStorage<S> {
+        fn initialize_if_needed_resynced_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UpdateResyncPointer, || {
+                    self.inner
+                        .initialize_if_needed_resynced_transaction(program_id, transaction)
+                })
+        }
+
+        fn get_last_resynced_transaction(
+            &self,
+            program_id: &Pubkey,
+        ) -> Result<Option<SolanaSignature>, Self::Error> {
+            self.metrics.record(StorageOperation::GetResyncPointer, || {
+                self.inner.get_last_resynced_transaction(program_id)
+            })
+        }
+
+        fn set_last_resynced_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UpdateResyncPointer, || {
+                    self.inner
+                        .set_last_resynced_transaction(program_id, transaction)
+                })
+        }
+
+        fn reset_last_resynced_transaction(&self, program_id: &Pubkey) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UpdateResyncPointer, || {
+                    self.inner.reset_last_resynced_transaction(program_id)
+                })
+        }
+
+        fn get_last_resynced_slot(&self, program_id: &Pubkey) -> Result<Option<Slot>, Self::Error> {
+            self.metrics.record(StorageOperation::GetResyncPointer, || {
+                self.inner.get_last_resynced_slot(program_id)
+            })
+        }
+
+        fn set_last_resynced_slot(
+            &self,
+            program_id: &Pubkey,
+            slot: Slot,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UpdateResyncPointer, || {
+                    self.inner.set_last_resynced_slot(program_id, slot)
+                })
+        }
+
+        fn get_resync_pointer(&self, program_id: &Pubkey) -> Result<ResyncPointer, Self::Error> {
+            self.metrics.record(StorageOperation::GetResyncPointer, || {
+                self.inner.get_resync_pointer(program_id)
+            })
+        }
+
+        fn set_resync_pointer(
+            &self,
+            program_id: &Pubkey,
+            pointer: &ResyncPointer,
+        ) -> Result<(), Self::Error> {
+            self.metrics.record(StorageOperation::SetResyncPointer, || {
+                self.inner.set_resync_pointer(program_id, pointer)
+            })
+        }
+
+        fn reset_resync_pointer_to_slot(
+            &self,
+            program_id: &Pubkey,
+            slot: Slot,
+        ) -> Result<(), Self::Error> {
+            self.metrics.record(StorageOperation::SetResyncPointer, || {
+                self.inner.reset_resync_pointer_to_slot(program_id, slot)
+            })
+        }
+
+        fn record_resync_pointer_move(
+            &self,
+            program_id: &Pubkey,
+            movement: &ResyncPointerMove,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UpdateResyncPointer, || {
+                    self.inner.record_resync_pointer_move(program_id, movement)
+                })
+        }
+
+        fn get_resync_pointer_history(
+            &self,
+            program_id: &Pubkey,
+            limit: usize,
+        ) -> Result<Vec<ResyncPointerMove>, Self::Error> {
+            self.metrics.record(StorageOperation::GetResyncPointer, || {
+                self.inner.get_resync_pointer_history(program_id, limit)
+            })
+        }
+
+        fn quarantine_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &QuarantinedTransaction,
+        ) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::QuarantineTransaction, || {
+                    self.inner.quarantine_transaction(program_id, transaction)
+                })
+        }
+
+        fn get_quarantined_transactions(
+            &self,
+            program_id: &Pubkey,
+        ) -> Result<Vec<QuarantinedTransaction>, Self::Error> {
+            self.metrics
+                .record(StorageOperation::GetQuarantinedTransactions, || {
+                    self.inner.get_quarantined_transactions(program_id)
+                })
+        }
+
+        fn release_quarantined_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<bool, Self::Error> {
+            self.metrics
+                .record(StorageOperation::ReleaseQuarantinedTransaction, || {
+                    self.inner
+                        .release_quarantined_transaction(program_id, transaction_hash)
+                })
+        }
+
+        fn backend_name(&self) -> String {
+            format!("instrumented({})", self.inner.backend_name())
+        }
+    }
+
+    impl<S: RegisterAccountUpdate> RegisterAccountUpdate for InstrumentedStorage<S> {
+        type Error = S::Error;
+
+        fn register_account_update(
+            &self,
+            account: &Pubkey,
+            version: AccountUpdateVersion,
+        ) -> Result<bool, Self::Error> {
+            self.metrics
+                .record(StorageOperation::RegisterAccountUpdate, || {
+                    self.inner.register_account_update(account, version)
+                })
+        }
+
+        fn get_last_account_update(
+            &self,
+            account: &Pubkey,
+        ) -> Result<Option<AccountUpdateVersion>, Self::Error> {
+            self.metrics
+                .record(StorageOperation::GetLastAccountUpdate, || {
+                    self.inner.get_last_account_update(account)
+                })
+        }
+    }
+
+    impl<S: RegisterIdempotencyKey> RegisterIdempotencyKey for InstrumentedStorage<S> {
+        type Error = S::Error;
+
+        fn register_idempotency_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+            self.metrics
+                .record(StorageOperation::RegisterIdempotencyKey, || {
+                    self.inner.register_idempotency_key(key)
+                })
+        }
+
+        fn unregister_idempotency_key(&self, key: &[u8]) -> Result<(), Self::Error> {
+            self.metrics
+                .record(StorageOperation::UnregisterIdempotencyKey, || {
+                    self.inner.unregister_idempotency_key(key)
+                })
+        }
+    }
+
+    impl<S: ClaimTransaction> ClaimTransaction for InstrumentedStorage<S> {
+        type Error = S::Error;
+
+        fn try_claim(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+            ttl: Duration,
+        ) -> Result<bool, Self::Error> {
+            self.metrics.record(StorageOperation::TryClaim, || {
+                self.inner.try_claim(program_id, transaction_hash, ttl)
+            })
+        }
+
+        fn release_claim(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.metrics.record(StorageOperation::ReleaseClaim, || {
+                self.inner.release_claim(program_id, transaction_hash)
+            })
+        }
+
+        fn complete_claim(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.metrics.record(StorageOperation::CompleteClaim, || {
+                self.inner.complete_claim(program_id, transaction_hash)
+            })
+        }
+    }
+
+    impl<S: EventRegistry> EventRegistry for InstrumentedStorage<S> {
+        type Error = S::Error;
+
+        fn register_event(&self, event_id: &[u8]) -> Result<bool, Self::Error> {
+            self.metrics.record(StorageOperation::RegisterEvent, || {
+                self.inner.register_event(event_id)
+            })
+        }
+
+        fn is_event_registered(&self, event_id: &[u8]) -> Result<bool, Self::Error> {
+            self.metrics
+                .record(StorageOperation::IsEventRegistered, || {
+                    self.inner.is_event_registered(event_id)
+                })
+        }
+    }
+
+    #[cfg(test)]
+    mod instrumented_test {
+        use std::{collections::HashSet, sync::Mutex};
+
+        use super::*;
+
+        #[derive(Default)]
+        struct MemoryStorage(Mutex<HashSet<SolanaSignature>>);
+
+        impl RegisterTransaction for MemoryStorage {
+            type Error = ();
+
+            fn register_transaction(
+                &self,
+                _program_id: &Pubkey,
+                transaction_hash: &SolanaSignature,
+            ) -> Result<(), Self::Error> {
+                self.0.lock().unwrap().insert(*transaction_hash);
+                Ok(())
+            }
+
+            fn unregister_transaction(
+                &self,
+                _program_id: &Pubkey,
+                transaction_hash: &SolanaSignature,
+            ) -> Result<(), Self::Error> {
+                self.0.lock().unwrap().remove(transaction_hash);
+                Ok(())
+            }
+
+            fn is_transaction_registered(
+                &self,
+                _program_id: &Pubkey,
+                transaction_hash: &SolanaSignature,
+            ) -> Result<bool, Self::Error> {
+                Ok(self.0.lock().unwrap().contains(transaction_hash))
+            }
+
+            fn filter_unregistered_transactions(
+                &self,
+                _program_id: &Pubkey,
+                _transaction_hash_set: &[SolanaSignature],
+            ) -> Result<Vec<SolanaSignature>, Self::Error> {
+                Err(())
+            }
+        }
+
+        #[test]
+        fn test_instrumented_storage() {
+            let storage = InstrumentedStorage::new(MemoryStorage::default());
+            let metrics = storage.metrics();
+            let (program_id, signature) = (Pubkey::new_unique(), SolanaSignature::default());
+
+            assert!(!storage
+                .is_transaction_registered(&program_id, &signature)
+                .unwrap());
+            storage
+                .register_transaction(&program_id, &signature)
+                .unwrap();
+            assert!(storage
+                .is_transaction_registered(&program_id, &signature)
+                .unwrap());
+            assert!(storage
+                .filter_unregistered_transactions(&program_id, &[signature])
+                .is_err());
+
+            assert_eq!(metrics.calls(StorageOperation::IsTransactionRegistered), 2);
+            assert_eq!(metrics.hit_ratio(), Some(0.5));
+            assert_eq!(
+                metrics.errors(StorageOperation::FilterUnregisteredTransactions),
+                1
+            );
+            assert!(metrics
+                .mean_latency(StorageOperation::RegisterTransaction)
+                .is_some());
+            assert_eq!(metrics.mean_latency(StorageOperation::TryClaim), None);
+        }
+    }
+}
+
+/// Read-through cache of registered transactions over [`RegisterTransaction`], so
+/// `is_transaction_registered` of every websocket message doesn't hit storage
+///
+/// Only registered transactions are cached, as registration is never revoked, while
+/// unregistered ones can be registered by another reader over shared storage. Cache is exact,
+/// as false positive of probabilistic one would skip unregistered transaction
+pub mod cached {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::Mutex,
+    };
+
+    use super::{
+        Pubkey, QuarantinedTransaction, RegisterTransaction, ResyncPointer, ResyncPointerMove,
+        ResyncedTransactionsPtrStorage, Slot, SolanaSignature,
+    };
+
+    type Key = (Pubkey, SolanaSignature);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct CacheConfig {
+        /// Max count of cached transactions
+        pub max_entries: usize,
+    }
+
+    impl Default for CacheConfig {
+        fn default() -> Self {
+            Self {
+                max_entries: 100_000,
+            }
+        }
+    }
+
+    /// Exact cache evicting least recently used entries
+    #[derive(Debug, Default)]
+    struct Lru {
+        ticks: HashMap<Key, u64>,
+        order: BTreeMap<u64, Key>,
+        tick: u64,
+    }
+
+    impl Lru {
+        fn touch(&mut self, key: Key) {
+            self.tick += 1;
+            if let Some(old_tick) = self.ticks.insert(key, self.tick) {
+                self.order.remove(&old_tick);
+            }
+            self.order.insert(self.tick, key);
+        }
+
+        fn contains(&mut self, key: &Key) -> bool {
+            let is_cached = self.ticks.contains_key(key);
+            if is_cached {
+                self.touch(*key);
+            }
+            is_cached
+        }
+
+        fn remove(&mut self, key: &Key) {
+            if let Some(tick) = self.ticks.remove(key) {
+                self.order.remove(&tick);
+            }
+        }
+
+        fn insert(&mut self, key: Key, max_entries: usize) {
+            self.touch(key);
+            while self.ticks.len() > max_entries {
+                let Some((_, evicted)) = self.order.pop_first() else {
+                    break;
+                };
+                self.ticks.remove(&evicted);
+            }
+        }
+    }
+
+    /// Storage `S` with registered transactions cached by [`CacheConfig`]
+    pub struct CachedStorage<S> {
+        inner: S,
+        cache: Mutex<Lru>,
+        max_entries: usize,
+    }
+
+    impl<S> CachedStorage<S> {
+        pub fn new(inner: S, config: CacheConfig) -> Self {
+            Self {
+                inner,
+                cache: Mutex::new(Lru::default()),
+                max_entries: config.max_entries,
+            }
+        }
+
+        pub fn inner(&self) -> &S {
+            &self.inner
+        }
+
+        fn is_cached(&self, key: &Key) -> bool {
+            self.cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .contains(key)
+        }
+
+        fn cache(&self, key: Key) {
+            self.cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(key, self.max_entries)
+        }
+    }
+
+    impl<S: RegisterTransaction> RegisterTransaction for CachedStorage<S> {
+        type Error = S::Error;
+
+        fn register_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.inner
+                .register_transaction(program_id, transaction_hash)?;
+            self.cache((*program_id, *transaction_hash));
+            Ok(())
+        }
+
+        fn unregister_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .remove(&(*program_id, *transaction_hash));
+            self.inner
+                .unregister_transaction(program_id, transaction_hash)
+        }
+
+        fn is_transaction_registered(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<bool, Self::Error> {
+            let key = (*program_id, *transaction_hash);
+            if self.is_cached(&key) {
+                return Ok(true);
+            }
+            let is_registered = self
+                .inner
+                .is_transaction_registered(program_id, transaction_hash)?;
+            if is_registered {
+                self.cache(key);
+            }
+            Ok(is_registered)
+        }
+
+        fn filter_unregistered_transactions(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash_set: &[SolanaSignature],
+        ) -> Result<Vec<SolanaSignature>, Self::Error> {
+            let uncached = transaction_hash_set
+                .iter()
+                .filter(|signature| !self.is_cached(&(*program_id, **signature)))
+                .copied()
+                .collect::<Vec<_>>();
+            if uncached.is_empty() {
+                return Ok(uncached);
+            }
+
+            let unregistered = self
+                .inner
+                .filter_unregistered_transactions(program_id, &uncached)?;
+            for signature in uncached
+                .iter()
+                .filter(|signature| !unregistered.contains(signature))
+            {
+                self.cache((*program_id, *signature));
+            }
+            Ok(unregistered)
+        }
+    }
+
+    impl<S: ResyncedTransactionsPtrStorage> ResyncedTransactionsPtrStorage for CachedStorage<S> {
+        fn initialize_if_needed_resynced_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.inner
+                .initialize_if_needed_resynced_transaction(program_id, transaction)
+        }
+
+        fn get_last_resynced_transaction(
+            &self,
+            program_id: &Pubkey,
+        ) -> Result<Option<SolanaSignature>, Self::Error> {
+            self.inner.get_last_resynced_transaction(program_id)
+        }
+
+        fn set_last_resynced_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.inner
+                .set_last_resynced_transaction(program_id, transaction)
+        }
+
+        fn reset_last_resynced_transaction(&self, program_id: &Pubkey) -> Result<(), Self::Error> {
+            self.inner.reset_last_resynced_transaction(program_id)
+        }
 
-    /// Check if a transaction with the given `program_id` and `transaction_hash` is registered
-    /// with [`RegisterTransaction::register_transaction`] before
-    fn is_transaction_registered(
-        &self,
-        program_id: &Pubkey,
-        transaction_hash: &SolanaSignature,
-    ) -> Result<bool, Self::Error>;
+        fn get_last_resynced_slot(&self, program_id: &Pubkey) -> Result<Option<Slot>, Self::Error> {
+            self.inner.get_last_resynced_slot(program_id)
+        }
 
-    /// Given a `program_id` and a list of transactions (`transaction_hash_set`),
-    /// filter out those that are not registered.
-    ///
-    /// Returns a `Result` containing a `Vec` of unregistered `SolanaSignature`
-    fn filter_unregistered_transactions(
-        &self,
-        program_id: &Pubkey,
-        transaction_hash_set: &[SolanaSignature],
-    ) -> Result<Vec<SolanaSignature>, Self::Error>;
-}
+        fn set_last_resynced_slot(
+            &self,
+            program_id: &Pubkey,
+            slot: Slot,
+        ) -> Result<(), Self::Error> {
+            self.inner.set_last_resynced_slot(program_id, slot)
+        }
 
-/// This trait extends [`RegisterTransaction`]
-/// and provides methods for managing the last resynced transaction.
-pub trait ResyncedTransactionsPtrStorage: RegisterTransaction {
-    /// Initializes the last resynced transaction if it's not initialized before.
-    fn initialize_if_needed_resynced_transaction(
-        &self,
-        program_id: &Pubkey,
-        transaction: &SolanaSignature,
-    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+        fn get_resync_pointer(&self, program_id: &Pubkey) -> Result<ResyncPointer, Self::Error> {
+            self.inner.get_resync_pointer(program_id)
+        }
 
-    /// Get last resynced transaction, initialized
-    /// by [`ResyncedTransactionsPtrStorage::initialize_if_needed_resynced_transaction`] or
-    /// setted by [`ResyncedTransactionsPtrStorage::set_last_resynced_transaction`]
-    fn get_last_resynced_transaction(
-        &self,
-        program_id: &Pubkey,
-    ) -> Result<Option<SolanaSignature>, <Self as RegisterTransaction>::Error>;
+        fn set_resync_pointer(
+            &self,
+            program_id: &Pubkey,
+            pointer: &ResyncPointer,
+        ) -> Result<(), Self::Error> {
+            self.inner.set_resync_pointer(program_id, pointer)
+        }
 
-    /// Set last recyned transaction into new one
-    fn set_last_resynced_transaction(
-        &self,
-        program_id: &Pubkey,
-        transaction: &SolanaSignature,
-    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+        fn reset_resync_pointer_to_slot(
+            &self,
+            program_id: &Pubkey,
+            slot: Slot,
+        ) -> Result<(), Self::Error> {
+            self.inner.reset_resync_pointer_to_slot(program_id, slot)
+        }
 
-    fn reset_last_resynced_transaction(
-        &self,
-        program_id: &Pubkey,
-    ) -> Result<(), <Self as RegisterTransaction>::Error>;
+        fn record_resync_pointer_move(
+            &self,
+            program_id: &Pubkey,
+            movement: &ResyncPointerMove,
+        ) -> Result<(), Self::Error> {
+            self.inner.record_resync_pointer_move(program_id, movement)
+        }
+
+        fn get_resync_pointer_history(
+            &self,
+            program_id: &Pubkey,
+            limit: usize,
+        ) -> Result<Vec<ResyncPointerMove>, Self::Error> {
+            self.inner.get_resync_pointer_history(program_id, limit)
+        }
+
+        fn quarantine_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &QuarantinedTransaction,
+        ) -> Result<(), Self::Error> {
+            self.inner.quarantine_transaction(program_id, transaction)?;
+            self.cache((*program_id, transaction.signature));
+            Ok(())
+        }
+
+        fn get_quarantined_transactions(
+            &self,
+            program_id: &Pubkey,
+        ) -> Result<Vec<QuarantinedTransaction>, Self::Error> {
+            self.inner.get_quarantined_transactions(program_id)
+        }
+
+        fn release_quarantined_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<bool, Self::Error> {
+            self.inner
+                .release_quarantined_transaction(program_id, transaction_hash)
+        }
+
+        fn backend_name(&self) -> String {
+            format!("cached({})", self.inner.backend_name())
+        }
+    }
+
+    #[cfg(test)]
+    mod cached_test {
+        use super::*;
+
+        #[test]
+        fn test_caches() {
+            let program_id = Pubkey::new_unique();
+            let keys = (0..100u8)
+                .map(|i| (program_id, SolanaSignature::from([i; 64])))
+                .collect::<Vec<_>>();
+
+            let mut lru = Lru::default();
+            for key in keys.iter() {
+                lru.insert(*key, 10);
+            }
+            assert!(lru.contains(&keys[99]));
+            assert!(!lru.contains(&keys[0]));
+            lru.remove(&keys[99]);
+            assert!(!lru.contains(&keys[99]));
+        }
+    }
 }
 
 #[cfg(feature = "rocksdb")]
 pub mod rocksdb {
-    use rocksdb::{DBWithThreadMode, MultiThreaded};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use rocksdb::{DBWithThreadMode, MultiThreaded, WriteBatch};
 
-    use super::{Pubkey, RegisterTransaction, ResyncedTransactionsPtrStorage, SolanaSignature};
+    use super::{
+        AccountUpdateVersion, ClaimTransaction, EventRegistry, Pubkey, QuarantinedTransaction,
+        RegisterAccountUpdate, RegisterIdempotencyKey, RegisterTransaction, ResyncPointerMove,
+        ResyncedTransactionsPtrStorage, Slot, SolanaSignature,
+    };
+    #[cfg(feature = "solana")]
+    use crate::transaction_parser::{
+        CompactCodecError, RpcTransactionConfig, TransactionParsedMeta,
+    };
 
     #[derive(Debug)]
     pub enum Error {
         RocksDb(rocksdb::Error),
         Bincode(bincode::Error),
+        #[cfg(feature = "solana")]
+        Codec(CompactCodecError),
+        #[cfg(feature = "solana")]
+        Json(serde_json::Error),
     }
     impl From<rocksdb::Error> for Error {
         fn from(err: rocksdb::Error) -> Self {
@@ -91,6 +1306,18 @@ pub mod rocksdb {
             Error::Bincode(err)
         }
     }
+    #[cfg(feature = "solana")]
+    impl From<CompactCodecError> for Error {
+        fn from(err: CompactCodecError) -> Self {
+            Error::Codec(err)
+        }
+    }
+    #[cfg(feature = "solana")]
+    impl From<serde_json::Error> for Error {
+        fn from(err: serde_json::Error) -> Self {
+            Error::Json(err)
+        }
+    }
     #[cfg(feature = "event-reader")]
     impl From<Error> for crate::event_reader_service::Error {
         fn from(error: Error) -> Self {
@@ -109,8 +1336,41 @@ pub mod rocksdb {
         .concat()
     }
 
+    fn construct_pointer_history_prefix(program_id: &Pubkey) -> Vec<u8> {
+        [POINTER_HISTORY_SUFFIX, &program_id.to_bytes()[..]].concat()
+    }
+
+    fn construct_quarantine_prefix(program_id: &Pubkey) -> Vec<u8> {
+        [QUARANTINE_SUFFIX, &program_id.to_bytes()[..]].concat()
+    }
+
+    fn construct_quarantine_key(
+        program_id: &Pubkey,
+        transaction_hash: &SolanaSignature,
+    ) -> Vec<u8> {
+        [
+            &construct_quarantine_prefix(program_id)[..],
+            transaction_hash.as_ref(),
+        ]
+        .concat()
+    }
+
+    fn construct_account_key(account: &Pubkey) -> Vec<u8> {
+        [ACCOUNT_KEY_SUFFIX, account.to_bytes().as_ref()].concat()
+    }
+
     const LAST_RESYNCED_SUFFIX: &[u8] = b"_last_resynced";
+    const LAST_RESYNCED_SLOT_SUFFIX: &[u8] = b"_last_resynced_slot";
     const KEY_SUFFIX: &[u8] = b"tx";
+    const ACCOUNT_KEY_SUFFIX: &[u8] = b"acc";
+    const IDEMPOTENCY_KEY_SUFFIX: &[u8] = b"idem";
+    const CLAIM_KEY_SUFFIX: &[u8] = b"claim";
+    const POINTER_HISTORY_SUFFIX: &[u8] = b"ptr_history";
+    const POINTER_HISTORY_SEQUENCE_SUFFIX: &[u8] = b"_ptr_history_seq";
+    const EVENT_KEY_SUFFIX: &[u8] = b"event";
+    const QUARANTINE_SUFFIX: &[u8] = b"quarantine";
+    #[cfg(feature = "solana")]
+    const PARSED_TX_KEY_SUFFIX: &[u8] = b"parsed_tx";
 
     impl RegisterTransaction for DB {
         type Error = Error;
@@ -124,6 +1384,15 @@ pub mod rocksdb {
             Ok(())
         }
 
+        fn unregister_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.delete(construct_key(program_id, transaction_hash))?;
+            Ok(())
+        }
+
         fn is_transaction_registered(
             &self,
             program_id: &Pubkey,
@@ -196,8 +1465,358 @@ pub mod rocksdb {
             program_id: &Pubkey,
         ) -> Result<(), <Self as RegisterTransaction>::Error> {
             self.delete([&program_id.to_bytes()[..], LAST_RESYNCED_SUFFIX].concat())?;
+            self.delete([&program_id.to_bytes()[..], LAST_RESYNCED_SLOT_SUFFIX].concat())?;
+
+            Ok(())
+        }
+
+        fn get_last_resynced_slot(&self, program_id: &Pubkey) -> Result<Option<Slot>, Self::Error> {
+            Ok(self
+                .get([&program_id.to_bytes()[..], LAST_RESYNCED_SLOT_SUFFIX].concat())?
+                .map(|raw| bincode::deserialize(&raw))
+                .transpose()?)
+        }
+
+        fn set_last_resynced_slot(
+            &self,
+            program_id: &Pubkey,
+            slot: Slot,
+        ) -> Result<(), Self::Error> {
+            self.put(
+                [&program_id.to_bytes()[..], LAST_RESYNCED_SLOT_SUFFIX].concat(),
+                bincode::serialize(&slot)?,
+            )?;
+
+            Ok(())
+        }
+
+        fn record_resync_pointer_move(
+            &self,
+            program_id: &Pubkey,
+            movement: &ResyncPointerMove,
+        ) -> Result<(), Self::Error> {
+            let sequence_key =
+                [&program_id.to_bytes()[..], POINTER_HISTORY_SEQUENCE_SUFFIX].concat();
+            // FIXME: remove non-atomic increment
+            let sequence = self
+                .get(&sequence_key)?
+                .map(|raw| bincode::deserialize::<u64>(&raw))
+                .transpose()?
+                .unwrap_or_default();
+
+            let mut batch = WriteBatch::default();
+            batch.put(
+                [
+                    &construct_pointer_history_prefix(program_id)[..],
+                    // Big endian, so entries are iterated in order of appending
+                    &sequence.to_be_bytes(),
+                ]
+                .concat(),
+                bincode::serialize(movement)?,
+            );
+            batch.put(sequence_key, bincode::serialize(&(sequence + 1))?);
+            self.write(batch)?;
+            Ok(())
+        }
+
+        fn get_resync_pointer_history(
+            &self,
+            program_id: &Pubkey,
+            limit: usize,
+        ) -> Result<Vec<ResyncPointerMove>, Self::Error> {
+            let prefix = construct_pointer_history_prefix(program_id);
+            let mut history = self
+                .prefix_iterator(&prefix)
+                .take_while(|entry| {
+                    entry
+                        .as_ref()
+                        .map_or(true, |(key, _)| key.starts_with(&prefix))
+                })
+                .map(|entry| Ok(bincode::deserialize(&entry?.1)?))
+                .collect::<Result<Vec<_>, Self::Error>>()?;
+            history.reverse();
+            history.truncate(limit);
+            Ok(history)
+        }
+
+        fn quarantine_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction: &QuarantinedTransaction,
+        ) -> Result<(), Self::Error> {
+            let mut batch = WriteBatch::default();
+            batch.put(
+                construct_quarantine_key(program_id, &transaction.signature),
+                bincode::serialize(transaction)?,
+            );
+            batch.put(construct_key(program_id, &transaction.signature), []);
+            self.write(batch)?;
+            Ok(())
+        }
+
+        fn get_quarantined_transactions(
+            &self,
+            program_id: &Pubkey,
+        ) -> Result<Vec<QuarantinedTransaction>, Self::Error> {
+            let prefix = construct_quarantine_prefix(program_id);
+            self.prefix_iterator(&prefix)
+                .take_while(|entry| {
+                    entry
+                        .as_ref()
+                        .map_or(true, |(key, _)| key.starts_with(&prefix))
+                })
+                .map(|entry| Ok(bincode::deserialize(&entry?.1)?))
+                .collect()
+        }
+
+        fn release_quarantined_transaction(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<bool, Self::Error> {
+            let key = construct_quarantine_key(program_id, transaction_hash);
+            // FIXME: remove non-atomic check
+            if self.get(&key)?.is_none() {
+                return Ok(false);
+            }
+            self.delete(key)?;
+            Ok(true)
+        }
+
+        fn backend_name(&self) -> String {
+            "rocksdb".to_owned()
+        }
+    }
+
+    /// Serializes check & set of account updates of all databases of process
+    static ACCOUNT_UPDATE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    impl RegisterAccountUpdate for DB {
+        type Error = Error;
+
+        fn register_account_update(
+            &self,
+            account: &Pubkey,
+            version: AccountUpdateVersion,
+        ) -> Result<bool, Self::Error> {
+            let _guard = ACCOUNT_UPDATE_LOCK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if self
+                .get_last_account_update(account)?
+                .is_some_and(|registered| !version.is_newer_than(&registered))
+            {
+                return Ok(false);
+            }
+            self.put(
+                construct_account_key(account),
+                bincode::serialize(&version)?,
+            )?;
+            Ok(true)
+        }
+
+        fn get_last_account_update(
+            &self,
+            account: &Pubkey,
+        ) -> Result<Option<AccountUpdateVersion>, Self::Error> {
+            let Some(raw) = self.get(construct_account_key(account))? else {
+                return Ok(None);
+            };
+            // Before data hash only slot was stored
+            if raw.len() == std::mem::size_of::<Slot>() {
+                return Ok(Some(AccountUpdateVersion {
+                    slot: bincode::deserialize(&raw)?,
+                    data_hash: [0; 32],
+                }));
+            }
+            Ok(Some(bincode::deserialize(&raw)?))
+        }
+    }
+
+    /// Serializes check & set of idempotency keys of all databases of process
+    static IDEMPOTENCY_KEY_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    impl RegisterIdempotencyKey for DB {
+        type Error = Error;
+
+        fn register_idempotency_key(&self, key: &[u8]) -> Result<bool, Self::Error> {
+            let key = [IDEMPOTENCY_KEY_SUFFIX, key].concat();
+            // Database is locked by one process, so lock of process is enough
+            let _guard = IDEMPOTENCY_KEY_LOCK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if self.get(&key)?.is_some() {
+                return Ok(false);
+            }
+            self.put(key, [])?;
+            Ok(true)
+        }
 
+        fn unregister_idempotency_key(&self, key: &[u8]) -> Result<(), Self::Error> {
+            self.delete([IDEMPOTENCY_KEY_SUFFIX, key].concat())?;
             Ok(())
         }
     }
+
+    impl ClaimTransaction for DB {
+        type Error = Error;
+
+        fn try_claim(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+            ttl: Duration,
+        ) -> Result<bool, Self::Error> {
+            let key = [
+                CLAIM_KEY_SUFFIX,
+                &construct_key(program_id, transaction_hash),
+            ]
+            .concat();
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+
+            // FIXME: remove non-atomic set
+            if let Some(raw) = self.get(&key)? {
+                if bincode::deserialize::<u128>(&raw)? > now {
+                    return Ok(false);
+                }
+            }
+            self.put(key, bincode::serialize(&(now + ttl.as_millis()))?)?;
+            Ok(true)
+        }
+
+        fn release_claim(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.delete(
+                [
+                    CLAIM_KEY_SUFFIX,
+                    &construct_key(program_id, transaction_hash),
+                ]
+                .concat(),
+            )?;
+            Ok(())
+        }
+
+        fn complete_claim(
+            &self,
+            program_id: &Pubkey,
+            transaction_hash: &SolanaSignature,
+        ) -> Result<(), Self::Error> {
+            self.put(
+                [
+                    CLAIM_KEY_SUFFIX,
+                    &construct_key(program_id, transaction_hash),
+                ]
+                .concat(),
+                bincode::serialize(&u128::MAX)?,
+            )?;
+            Ok(())
+        }
+    }
+
+    impl EventRegistry for DB {
+        type Error = Error;
+
+        fn register_event(&self, event_id: &[u8]) -> Result<bool, Self::Error> {
+            let key = [EVENT_KEY_SUFFIX, event_id].concat();
+            // FIXME: remove non-atomic set
+            if self.get(&key)?.is_some() {
+                return Ok(false);
+            }
+            self.put(key, [])?;
+            Ok(true)
+        }
+
+        fn is_event_registered(&self, event_id: &[u8]) -> Result<bool, Self::Error> {
+            Ok(self.get([EVENT_KEY_SUFFIX, event_id].concat())?.is_some())
+        }
+    }
+
+    /// Config is serialized into JSON, as it's untagged & has flattened fields
+    #[cfg(feature = "solana")]
+    fn construct_parsed_tx_key(
+        signature: &SolanaSignature,
+        config: &RpcTransactionConfig,
+    ) -> Result<Vec<u8>, Error> {
+        Ok([
+            PARSED_TX_KEY_SUFFIX,
+            signature.as_ref(),
+            &serde_json::to_vec(config)?,
+        ]
+        .concat())
+    }
+
+    /// Value is big endian unix time of caching in ms & compact bytes of transaction
+    #[cfg(feature = "solana")]
+    impl super::ParsedTxCache for DB {
+        type Error = Error;
+
+        fn get_parsed_transaction(
+            &self,
+            signature: &SolanaSignature,
+            config: &RpcTransactionConfig,
+        ) -> Result<Option<(TransactionParsedMeta, u64)>, Self::Error> {
+            let Some(raw) = self.get(construct_parsed_tx_key(signature, config)?)? else {
+                return Ok(None);
+            };
+            let Some((cached_at, raw)) = split_cached_at(&raw) else {
+                return Ok(None);
+            };
+            match TransactionParsedMeta::from_compact_bytes(raw) {
+                Ok(transaction) => Ok(Some((transaction, cached_at))),
+                // Cached by other version of crate, will be overwritten by parsed again
+                Err(CompactCodecError::Empty | CompactCodecError::UnsupportedVersion(_)) => {
+                    Ok(None)
+                }
+                Err(err) => Err(err.into()),
+            }
+        }
+
+        fn put_parsed_transaction(
+            &self,
+            signature: &SolanaSignature,
+            config: &RpcTransactionConfig,
+            transaction: &TransactionParsedMeta,
+            cached_at: u64,
+        ) -> Result<(), Self::Error> {
+            self.put(
+                construct_parsed_tx_key(signature, config)?,
+                [
+                    &cached_at.to_be_bytes()[..],
+                    &transaction.to_compact_bytes()?,
+                ]
+                .concat(),
+            )?;
+            Ok(())
+        }
+
+        fn evict_parsed_transactions(&self, cached_before: u64) -> Result<usize, Self::Error> {
+            let mut batch = WriteBatch::default();
+            let mut evicted = 0;
+            for entry in self.prefix_iterator(PARSED_TX_KEY_SUFFIX) {
+                let (key, value) = entry?;
+                if !key.starts_with(PARSED_TX_KEY_SUFFIX) {
+                    break;
+                }
+                if split_cached_at(&value).map_or(true, |(cached_at, _)| cached_at < cached_before)
+                {
+                    batch.delete(key);
+                    evicted += 1;
+                }
+            }
+            self.write(batch)?;
+            Ok(evicted)
+        }
+    }
+
+    #[cfg(feature = "solana")]
+    fn split_cached_at(raw: &[u8]) -> Option<(u64, &[u8])> {
+        let cached_at = raw.get(..8)?.try_into().ok()?;
+        Some((u64::from_be_bytes(cached_at), &raw[8..]))
+    }
 }
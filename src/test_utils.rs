@@ -0,0 +1,416 @@
+//! Harness for end-to-end tests of consumers built on this crate
+//!
+//! [`TestValidator`] runs `solana-test-validator` process with fresh ledger in temporary
+//! directory and funded [`TestValidator::payer`]. Fixture program [`LOG_DATA_PROGRAM_ID`]
+//! (`fixtures/programs/log_data.so`) emitting events is deployed to every validator, own
+//! programs are deployed by [`TestValidatorConfig::programs`]. SPL Memo program, preloaded
+//! by validator, emits plain logs. [`TestValidator::events_reader_builder`] prefills
+//! [`EventsReaderBuilder`] with clients & storage of validator, so [`EventsReader`] can be
+//! run against it
+//!
+//! [`EventsReader`]: crate::event_reader_service::EventsReader
+
+use std::{
+    env, fs, io,
+    net::TcpListener,
+    path::PathBuf,
+    process::{self, Child, Command, ExitStatus, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::{
+        pubsub_client::{PubsubClient, PubsubClientError},
+        rpc_client::RpcClient,
+    },
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    event_reader_service::{
+        self, EventConsumeResult, EventsReaderBuilder, PassEvent, ResyncOrder, RetryParamsBuilder,
+    },
+    storage::rocksdb::{self as storage, DB},
+    transaction_parser::{AccountMeta, Instruction, Pubkey, Signature, TransactionParsedMeta},
+};
+
+/// Fixture program deployed by [`TestValidator`], emits its instruction data as event
+/// (`sol_log_data`, i.e. `Program data:` log) & fails if any account is passed
+pub const LOG_DATA_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("LogData111111111111111111111111111111111111");
+
+/// Built by `fixtures/programs/log_data.py`
+const LOG_DATA_PROGRAM: &[u8] = include_bytes!("../fixtures/programs/log_data.so");
+
+/// SPL Memo v3, preloaded by `solana-test-validator`
+pub const MEMO_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Validator exited before become healthy: {0}")]
+    ValidatorExited(ExitStatus),
+    #[error("Validator isn't healthy after {0:?}")]
+    StartupTimeout(Duration),
+    #[error(transparent)]
+    Rpc(Box<ClientError>),
+    #[error(transparent)]
+    Pubsub(#[from] PubsubClientError),
+    #[error(transparent)]
+    Storage(#[from] rocksdb::Error),
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        Self::Rpc(Box::new(err))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TestValidatorConfig {
+    /// Path to `solana-test-validator`, found in `PATH` by default
+    pub binary: PathBuf,
+    /// `(program_id, path to .so)` deployed at genesis by `--bpf-program`
+    pub programs: Vec<(Pubkey, PathBuf)>,
+    /// Additional arguments of `solana-test-validator`
+    pub extra_args: Vec<String>,
+    pub startup_timeout: Duration,
+}
+
+impl Default for TestValidatorConfig {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from("solana-test-validator"),
+            programs: vec![],
+            extra_args: vec![],
+            startup_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Running `solana-test-validator`, killed with ledger removal on drop
+pub struct TestValidator {
+    process: Child,
+    directory: PathBuf,
+    rpc_url: String,
+    websocket_url: String,
+    payer: Keypair,
+    storage: Arc<DB>,
+}
+
+impl TestValidator {
+    /// Start validator on free local ports & wait until it's healthy
+    pub async fn start(config: TestValidatorConfig) -> Result<Self, Error> {
+        let payer = Keypair::new();
+        let directory = env::temp_dir().join(format!(
+            "solana-events-parser-{}-{}",
+            process::id(),
+            payer.pubkey()
+        ));
+        fs::create_dir_all(&directory)?;
+
+        // Websocket is served by validator on the next port after RPC one
+        let rpc_port = free_ports(3)?;
+        let faucet_port = rpc_port + 2;
+
+        let log_data_program = directory.join("log_data.so");
+        fs::write(&log_data_program, LOG_DATA_PROGRAM)?;
+
+        let mut command = Command::new(&config.binary);
+        command
+            .arg("--ledger")
+            .arg(directory.join("ledger"))
+            .args(["--reset", "--quiet", "--bind-address", "127.0.0.1"])
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--faucet-port", &faucet_port.to_string()])
+            .args(["--mint", &payer.pubkey().to_string()]);
+        let log_data_program = (LOG_DATA_PROGRAM_ID, log_data_program);
+        for (program_id, path) in config.programs.iter().chain([&log_data_program]) {
+            command
+                .arg("--bpf-program")
+                .arg(program_id.to_string())
+                .arg(path);
+        }
+        command
+            .args(config.extra_args.iter())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let mut validator = Self {
+            process: command.spawn()?,
+            storage: Arc::new(DB::open_default(directory.join("storage"))?),
+            directory,
+            rpc_url: format!("http://127.0.0.1:{rpc_port}"),
+            websocket_url: format!("ws://127.0.0.1:{}", rpc_port + 1),
+            payer,
+        };
+        validator.wait_healthy(config.startup_timeout).await?;
+
+        Ok(validator)
+    }
+
+    async fn wait_healthy(&mut self, timeout: Duration) -> Result<(), Error> {
+        let client = self.rpc_client();
+        let started_at = Instant::now();
+        loop {
+            if let Some(status) = self.process.try_wait()? {
+                return Err(Error::ValidatorExited(status));
+            }
+            if client.get_health().await.is_ok() {
+                return Ok(());
+            }
+            if started_at.elapsed() > timeout {
+                return Err(Error::StartupTimeout(timeout));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    pub fn rpc_url(&self) -> &str {
+        &self.rpc_url
+    }
+
+    pub fn websocket_url(&self) -> &str {
+        &self.websocket_url
+    }
+
+    /// Account funded at genesis, pays for all transactions sent by harness
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    /// Storage of readers built by [`TestValidator::events_reader_builder`]
+    pub fn storage(&self) -> Arc<DB> {
+        Arc::clone(&self.storage)
+    }
+
+    /// Client with `confirmed` commitment
+    pub fn rpc_client(&self) -> Arc<RpcClient> {
+        Arc::new(RpcClient::new_with_commitment(
+            self.rpc_url.clone(),
+            CommitmentConfig::confirmed(),
+        ))
+    }
+
+    pub async fn pubsub_client(&self) -> Result<PubsubClient, Error> {
+        Ok(PubsubClient::new(&self.websocket_url).await?)
+    }
+
+    /// Send & confirm transaction with `instructions`, signed by payer & `signers`
+    pub async fn send_instructions(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Signature, Error> {
+        let client = self.rpc_client();
+        let signers = std::iter::once(&self.payer)
+            .chain(signers.iter().copied())
+            .collect::<Vec<_>>();
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &signers,
+            client.get_latest_blockhash().await?,
+        );
+        Ok(client.send_and_confirm_transaction(&transaction).await?)
+    }
+
+    /// Send transaction with [`MEMO_PROGRAM_ID`] instruction, which logs `memo`
+    pub async fn send_memo(&self, memo: &str) -> Result<Signature, Error> {
+        self.send_instructions(
+            &[Instruction {
+                program_id: MEMO_PROGRAM_ID,
+                accounts: vec![AccountMeta::new_readonly(self.payer.pubkey(), true)],
+                data: memo.as_bytes().to_vec(),
+            }],
+            &[],
+        )
+        .await
+    }
+
+    /// Send transaction with [`LOG_DATA_PROGRAM_ID`] instruction, which emits `event`
+    pub async fn send_event(&self, event: &[u8]) -> Result<Signature, Error> {
+        self.send_instructions(
+            &[Instruction {
+                program_id: LOG_DATA_PROGRAM_ID,
+                accounts: vec![],
+                data: event.to_vec(),
+            }],
+            &[],
+        )
+        .await
+    }
+
+    /// [`EventsReaderBuilder`] of `program_id` over this validator with `confirmed`
+    /// commitment, every transaction is requested by RPC & passed into
+    /// `transaction_consumer`
+    pub async fn events_reader_builder<TransactionConsumerFn, EventRecipient>(
+        &self,
+        program_id: Pubkey,
+        transaction_consumer: TransactionConsumerFn,
+        event_recipient: Arc<EventRecipient>,
+    ) -> Result<EventsReaderBuilder<TransactionConsumerFn, EventRecipient, storage::Error>, Error>
+    where
+        EventRecipient: Send + Sync + 'static,
+        TransactionConsumerFn: Send
+            + Sync
+            + Fn(
+                Signature,
+                TransactionParsedMeta,
+                Arc<RpcClient>,
+                Arc<EventRecipient>,
+            ) -> BoxFuture<'static, event_reader_service::Result<()>>,
+    {
+        Ok(EventsReaderBuilder::default()
+            .program_id(program_id)
+            .commitment_config(CommitmentConfig::confirmed())
+            .client(self.rpc_client())
+            .pubsub_client(Some(Arc::new(self.pubsub_client().await?)))
+            .event_recipient(event_recipient)
+            .resync_duration(Duration::from_secs(1))
+            .event_consumer(|_| Ok(EventConsumeResult::TransactionNeeed))
+            .transaction_consumer(transaction_consumer)
+            .local_storage(self.storage())
+            .resync_signatures_chunk_size(None)
+            .resync_ptr_setter(Arc::new(|_| Box::pin(async { Ok(()) })))
+            .resync_order(ResyncOrder::Historical)
+            .live_events_transaction_request_param(
+                RetryParamsBuilder::default()
+                    .attempts_count(10)
+                    .attempt_timeout(Duration::from_millis(500))
+                    .build()
+                    .expect("Positive attempts count"),
+            ))
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// [`PassEvent`] into channel, to assert events passed by reader
+pub struct ChannelRecipient(pub mpsc::UnboundedSender<Vec<u8>>);
+
+#[async_trait]
+impl PassEvent for ChannelRecipient {
+    type Error = mpsc::error::SendError<Vec<u8>>;
+
+    async fn pass_event(&self, event: Vec<u8>) -> Result<(), Self::Error> {
+        self.0.send(event)
+    }
+}
+
+/// First of `count` consecutive free local ports
+///
+/// Ports are released before validator binds them, so another process can still take them
+/// in between
+fn free_ports(count: u16) -> io::Result<u16> {
+    const ATTEMPTS: usize = 100;
+
+    for _ in 0..ATTEMPTS {
+        let first = TcpListener::bind("127.0.0.1:0")?;
+        let port = first.local_addr()?.port();
+        let Some(last) = port.checked_add(count - 1) else {
+            continue;
+        };
+        let rest = (port + 1..=last)
+            .map(|port| TcpListener::bind(("127.0.0.1", port)))
+            .collect::<io::Result<Vec<_>>>();
+        if rest.is_ok() {
+            return Ok(port);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AddrNotAvailable,
+        format!("No {count} consecutive free ports after {ATTEMPTS} attempts"),
+    ))
+}
+
+#[cfg(test)]
+mod test_utils_test {
+    use super::*;
+    use crate::{
+        event_parser::contains_event_discriminator,
+        event_reader_service::{ConsumerRegistry, Event},
+    };
+
+    const DISCRIMINATOR: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn consume_fixture_events(logs: Event) -> event_reader_service::Result<EventConsumeResult> {
+        Ok(if contains_event_discriminator(&logs, &DISCRIMINATOR) {
+            EventConsumeResult::TransactionNeeed
+        } else {
+            EventConsumeResult::ConsumeSuccess
+        })
+    }
+
+    fn skip_transaction(
+        _signature: Signature,
+        _meta: TransactionParsedMeta,
+        _client: Arc<RpcClient>,
+        _recipient: Arc<ChannelRecipient>,
+    ) -> BoxFuture<'static, event_reader_service::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    #[tokio::test]
+    async fn test_events_reader_over_validator() {
+        let validator = TestValidator::start(TestValidatorConfig::default())
+            .await
+            .unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let recipient = Arc::new(ChannelRecipient(sender));
+        let consumers = ConsumerRegistry::default();
+        consumers.add_event_handler(
+            "fixture",
+            Arc::new({
+                let recipient = Arc::clone(&recipient);
+                move |_signature, ctx, event| {
+                    let recipient = Arc::clone(&recipient);
+                    Box::pin(async move {
+                        if ctx.program_id == LOG_DATA_PROGRAM_ID {
+                            let _ = recipient.pass_event(event).await;
+                        }
+                        Ok(())
+                    })
+                }
+            }),
+        );
+
+        let reader = validator
+            .events_reader_builder(LOG_DATA_PROGRAM_ID, skip_transaction, recipient)
+            .await
+            .unwrap()
+            .event_consumer(consume_fixture_events)
+            .consumers(consumers)
+            .build()
+            .unwrap();
+        tokio::spawn(Arc::new(reader).run());
+
+        let event = [DISCRIMINATOR.as_slice(), b"hello"].concat();
+        validator.send_event(&event).await.unwrap();
+
+        let received = tokio::time::timeout(Duration::from_secs(30), receiver.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received, event);
+    }
+}
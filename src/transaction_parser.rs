@@ -2,7 +2,6 @@ use std::{
     collections::HashMap,
     fmt::{self, Debug},
     io,
-    io::ErrorKind,
     marker::PhantomData,
     num::ParseIntError,
     pin::Pin,
@@ -12,17 +11,24 @@ use std::{
 
 use anchor_lang::AnchorDeserialize;
 use async_trait::async_trait;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 pub use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcTransactionConfig;
+pub use solana_client::rpc_config::RpcTransactionConfig;
 pub use solana_sdk::{
     clock::UnixTimestamp,
+    hash::Hash,
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::Signature,
     slot_history::Slot,
+    transaction::TransactionVersion,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, message::VersionedMessage,
+    program_utils::limited_deserialize, pubkey::ParsePubkeyError,
+    system_instruction::SystemInstruction, system_program, transaction::TransactionError,
 };
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::ParsePubkeyError};
 use solana_transaction_status::option_serializer::OptionSerializer;
 pub use solana_transaction_status::{
     EncodedConfirmedTransactionWithStatusMeta, EncodedTransactionWithStatusMeta, UiInstruction,
@@ -31,12 +37,15 @@ pub use solana_transaction_status::{
 
 use crate::{
     event_parser::{Discriminator, Owner},
-    instruction_parser::GetLoadedAccounts,
-    ParseInstruction,
+    instruction_parser::{
+        split_discriminator, GetLoadedAccounts, OuterInstructionProgramId, DISCRIMINATOR_SIZE,
+    },
+    parse_options::TruncationPolicy,
 };
 pub use crate::{
-    instruction_parser::{BindInstructions, InstructionContext},
-    log_parser::{self, ProgramContext, ProgramLog},
+    instruction_parser::{BindInstructions, InstructionContext, InstructionPosition},
+    log_parser::{self, Level, ProgramContext, ProgramLog},
+    parse_options::ParseOptions,
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -53,8 +62,11 @@ pub enum Error {
     InstructionParsingError(#[from] crate::instruction_parser::Error),
     #[error(transparent)]
     ParsePubkeyError(#[from] ParsePubkeyError),
-    #[error("Can't find ix ctx {0:?} in logs")]
-    InstructionLogsConsistencyError(InstructionContext),
+    #[error("Logs & instructions mismatch, contexts without instruction: {contexts:?}, instructions without logs: {instructions:?}")]
+    InstructionLogsConsistencyError {
+        contexts: Vec<ProgramContext>,
+        instructions: Vec<(InstructionContext, InstructionPosition)>,
+    },
     #[error("Provided log and provided ix not match by owner")]
     InstructionLogsOwnerError { ix_owner: Pubkey, log_owner: Pubkey },
     #[error("Failed while transaction decoding with signature: {0}")]
@@ -67,6 +79,105 @@ pub enum Error {
     WrongParserFound,
     #[error("Failed to consume instrucition with error msg: {0}")]
     ErrorWhileConsume(String),
+    #[error(transparent)]
+    DecodeError(#[from] DecodeError),
+    #[error(transparent)]
+    SerdeJsonError(#[from] serde_json::Error),
+    #[error("Invalid accounts count bounds for {accounts_count} accounts: from {min} to {max:?}")]
+    InvalidAccountsCount {
+        accounts_count: usize,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// Error while decoding instruction or event data into a user type
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error(
+        "Data of {program_id} ({program_ctx:?}) is too short for discriminator: {data_len} bytes"
+    )]
+    TruncatedData {
+        program_id: Pubkey,
+        program_ctx: Option<ProgramContext>,
+        data_len: usize,
+    },
+    #[error("Owner mismatch ({program_ctx:?}): expected {expected}, actual {actual}")]
+    OwnerMismatch {
+        expected: Pubkey,
+        actual: Pubkey,
+        program_ctx: Option<ProgramContext>,
+    },
+    #[error("Discriminator mismatch for {program_id} ({program_ctx:?}): expected {expected:?}, actual {actual:?}")]
+    DiscriminatorMismatch {
+        program_id: Pubkey,
+        program_ctx: Option<ProgramContext>,
+        expected: [u8; DISCRIMINATOR_SIZE],
+        actual: Vec<u8>,
+    },
+    #[error("Wrong accounts count of {program_id} ({program_ctx:?}): expected from {min} to {max:?}, found {actual}")]
+    AccountsCount {
+        program_id: Pubkey,
+        program_ctx: Option<ProgramContext>,
+        min: usize,
+        max: Option<usize>,
+        actual: usize,
+    },
+    #[error("Failed to deserialize {type_name} of {program_id} ({program_ctx:?}, discriminator: {discriminator:?}): {source}")]
+    Deserialize {
+        program_id: Pubkey,
+        program_ctx: Option<ProgramContext>,
+        discriminator: Option<[u8; DISCRIMINATOR_SIZE]>,
+        type_name: &'static str,
+        #[source]
+        source: io::Error,
+    },
+    #[cfg(feature = "anchor")]
+    #[error("Invalid base64 in `Program data:` of {program_id} ({program_ctx:?}): {source}")]
+    InvalidBase64 {
+        program_id: Pubkey,
+        program_ctx: Option<ProgramContext>,
+        #[source]
+        source: base64::DecodeError,
+    },
+}
+
+/// Decode anchor instruction `data` of `program_id` into `T`,
+/// checking owner & discriminator before deserialization
+///
+/// Data shorter than [`DISCRIMINATOR_SIZE`] is reported as [`DecodeError::TruncatedData`]
+pub(crate) fn decode_anchor_data<T: Discriminator + Owner + AnchorDeserialize>(
+    program_id: Pubkey,
+    program_ctx: Option<ProgramContext>,
+    data: &[u8],
+) -> Result<T, DecodeError> {
+    if T::owner().ne(&program_id) {
+        return Err(DecodeError::OwnerMismatch {
+            expected: T::owner(),
+            actual: program_id,
+            program_ctx,
+        });
+    }
+    let (discriminator, payload) = split_discriminator(data).ok_or(DecodeError::TruncatedData {
+        program_id,
+        program_ctx,
+        data_len: data.len(),
+    })?;
+    if T::discriminator().ne(discriminator) {
+        return Err(DecodeError::DiscriminatorMismatch {
+            program_id,
+            program_ctx,
+            expected: T::discriminator(),
+            actual: discriminator.to_vec(),
+        });
+    }
+    T::try_from_slice(payload).map_err(|source| DecodeError::Deserialize {
+        program_id,
+        program_ctx,
+        discriminator: Some(T::discriminator()),
+        type_name: std::any::type_name::<T>(),
+        source,
+    })
 }
 
 #[async_trait]
@@ -83,50 +194,550 @@ impl BindTransactionLogs for RpcClient {
         &self,
         signature: Signature,
     ) -> Result<HashMap<ProgramContext, Vec<ProgramLog>>, Error> {
-        Ok(log_parser::parse_events(
-            match self
-                .get_transaction_with_config(
-                    &signature,
-                    RpcTransactionConfig {
-                        encoding: Some(UiTransactionEncoding::Base58),
-                        max_supported_transaction_version: Some(0),
-                        commitment: Some(CommitmentConfig::finalized()),
-                    },
-                )
-                .await?
-                .transaction
-                .meta
-                .ok_or(Error::EmptyMetaInTransaction(signature))?
-                .log_messages
-            {
-                OptionSerializer::Skip | OptionSerializer::None => {
-                    Err(Error::EmptyLogsInTransaction(signature))
-                }
-                OptionSerializer::Some(some) => Ok(some),
-            }?
-            .as_slice(),
-        )?)
+        let transaction = self
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base58),
+                    max_supported_transaction_version: Some(0),
+                    commitment: Some(CommitmentConfig::finalized()),
+                },
+            )
+            .await?;
+        Ok(log_parser::parse_events(transaction_logs(
+            &transaction.transaction,
+            signature,
+        )?)?)
+    }
+}
+
+/// Log messages of transaction
+pub fn transaction_logs(
+    transaction: &EncodedTransactionWithStatusMeta,
+    signature: Signature,
+) -> Result<&[String], Error> {
+    match transaction
+        .meta
+        .as_ref()
+        .ok_or(Error::EmptyMetaInTransaction(signature))?
+        .log_messages
+        .as_ref()
+    {
+        OptionSerializer::None | OptionSerializer::Skip => {
+            Err(Error::EmptyLogsInTransaction(signature))
+        }
+        OptionSerializer::Some(log_messages) => Ok(log_messages.as_slice()),
     }
 }
 
 pub type AmountDiff = i128;
 pub type ChildProgramContext = ProgramContext;
 pub type ParentProgramContext = ProgramContext;
-#[derive(Debug, Serialize, Deserialize)]
+
+/// Version of serialized [`TransactionParsedMeta`], increased on incompatible changes
+pub const TRANSACTION_PARSED_META_SCHEMA_VERSION: u32 = 4;
+
+/// Version of parser, increased on changes of parsing result of the same input (e.g. fixes
+/// of logs binding), unlike [`TRANSACTION_PARSED_META_SCHEMA_VERSION`] of layout. Outputs of
+/// older versions are detected by [`TransactionParsedMeta::is_outdated`]
+pub const PARSER_VERSION: u32 = 1;
+
+/// Serialized as [`StoredTransactionParsedMeta`], so it can be stored in any serde format
+#[derive(Debug, Clone)]
 pub struct TransactionParsedMeta {
-    /// All internal instructions with logs
+    /// All internal instructions with logs, instructions cut off by `Log truncated` are
+    /// restored with empty logs
     pub meta: HashMap<ProgramContext, (Instruction, Vec<ProgramLog>)>,
     pub slot: Slot,
     pub block_time: Option<UnixTimestamp>,
     pub lamports_changes: HashMap<Pubkey, AmountDiff>,
     pub token_balances_changes: HashMap<WalletContext, AmountDiff>,
     pub parent_ix: HashMap<ChildProgramContext, ParentProgramContext>,
+    /// Contexts of top-level instructions in order of execution
+    pub root_ixs: Vec<ProgramContext>,
+    pub compute_budget: ComputeBudget,
+    /// Position in transaction message of every instruction from `meta`
+    pub ix_positions: HashMap<ProgramContext, InstructionPosition>,
+    /// Recent blockhash, durable nonce & version of transaction message
+    pub message: MessageMeta,
+    /// Version of parser & hash of parsed transaction
+    pub stamp: ParseStamp,
+}
+
+impl TransactionParsedMeta {
+    /// Is produced by older [`PARSER_VERSION`] (or deserialized from schema version before
+    /// 4), so must be parsed again, e.g. instead of use from cache
+    pub fn is_outdated(&self) -> bool {
+        self.stamp.parser_version < PARSER_VERSION
+    }
+}
+
+/// [`PARSER_VERSION`] & [`input_hash`] of parsed transaction
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseStamp {
+    /// `0` if unknown, e.g. deserialized from schema version before 4
+    pub parser_version: u32,
+    pub input_hash: Hash,
+}
+
+impl ParseStamp {
+    pub fn new(
+        signature: Signature,
+        confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            parser_version: PARSER_VERSION,
+            input_hash: input_hash(signature, confirmed_transaction)?,
+        })
+    }
+
+    /// Is stamp of `confirmed_transaction`, parsed by current [`PARSER_VERSION`]
+    pub fn is_current_for(
+        &self,
+        signature: Signature,
+        confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> bool {
+        self.parser_version == PARSER_VERSION
+            && input_hash(signature, confirmed_transaction)
+                .is_ok_and(|input_hash| input_hash == self.input_hash)
+    }
+}
+
+/// Content address of transaction: sha256 of its decoded message & meta, which determine
+/// parse result of transaction executed by chain, so the same transaction has the same hash in
+/// any binary encoding (`base58`, `base64`)
+///
+/// Fails like parser for transactions which can't be decoded (e.g. in `json` encoding)
+pub fn input_hash(
+    signature: Signature,
+    confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<Hash, Error> {
+    let transaction = confirmed_transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or(Error::ErrorWhileDecodeTransaction(signature))?;
+    let meta = confirmed_transaction
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or(Error::EmptyMetaInTransaction(signature))?;
+
+    let mut hasher = solana_sdk::hash::Hasher::default();
+    // Length prefixed, so bytes of different fields can't be shifted between them
+    for field in [transaction.message.serialize(), serde_json::to_vec(meta)?] {
+        hasher.hashv(&[&(field.len() as u64).to_le_bytes(), &field]);
+    }
+    Ok(hasher.result())
+}
+
+/// Message-level facts of transaction, e.g. for replay protection
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageMeta {
+    /// Recent blockhash, or the nonce for durable-nonce transactions (see `durable_nonce`)
+    pub recent_blockhash: Option<Hash>,
+    /// Nonce account, if transaction uses durable nonce instead of recent blockhash: its
+    /// first instruction is `AdvanceNonceAccount` of system program
+    pub durable_nonce: Option<Pubkey>,
+    /// `None` if unknown, e.g. deserialized from schema version before 3
+    #[serde(with = "transaction_version_serde")]
+    pub version: Option<TransactionVersion>,
+}
+
+/// [`TransactionVersion`] is untagged enum, which isn't supported by not self-describing
+/// formats (e.g. bincode of [`TransactionParsedMeta::to_compact_bytes`]), so in them it's
+/// `Option<u8>` with `None` for legacy one
+mod transaction_version_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use solana_sdk::transaction::TransactionVersion;
+
+    pub fn serialize<S: Serializer>(
+        version: &Option<TransactionVersion>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return version.serialize(serializer);
+        }
+        version
+            .as_ref()
+            .map(|version| match version {
+                TransactionVersion::Legacy(_) => None,
+                TransactionVersion::Number(number) => Some(*number),
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<TransactionVersion>, D::Error> {
+        if deserializer.is_human_readable() {
+            return Option::<TransactionVersion>::deserialize(deserializer);
+        }
+        Ok(
+            Option::<Option<u8>>::deserialize(deserializer)?.map(|number| match number {
+                Some(number) => TransactionVersion::Number(number),
+                None => TransactionVersion::LEGACY,
+            }),
+        )
+    }
+}
+
+impl MessageMeta {
+    pub fn from_message(message: &VersionedMessage) -> Self {
+        let keys = message.static_account_keys();
+        let durable_nonce = message
+            .instructions()
+            .first()
+            .filter(|ix| keys.get(ix.program_id_index as usize) == Some(&system_program::id()))
+            .filter(|ix| {
+                matches!(
+                    limited_deserialize(&ix.data),
+                    Ok(SystemInstruction::AdvanceNonceAccount)
+                )
+            })
+            .and_then(|ix| keys.get(*ix.accounts.first()? as usize))
+            .copied();
+
+        Self {
+            recent_blockhash: Some(*message.recent_blockhash()),
+            durable_nonce,
+            version: Some(match message {
+                VersionedMessage::Legacy(_) => TransactionVersion::LEGACY,
+                VersionedMessage::V0(_) => TransactionVersion::Number(0),
+            }),
+        }
+    }
+
+    pub fn uses_durable_nonce(&self) -> bool {
+        self.durable_nonce.is_some()
+    }
+}
+
+impl TransactionParsedMeta {
+    /// Contexts of all invocations of `program_id`, in order of call index
+    pub fn contexts_for_program(&self, program_id: &Pubkey) -> Vec<ProgramContext> {
+        self.meta
+            .keys()
+            .filter(|ctx| ctx.program_id.eq(program_id))
+            .copied()
+            .sorted()
+            .collect()
+    }
+
+    /// Top-level instructions with their contexts, in order of execution
+    pub fn top_level_instructions(&self) -> Vec<(&ProgramContext, &Instruction)> {
+        self.root_ixs
+            .iter()
+            .filter_map(|ctx| self.meta.get(ctx).map(|(ix, _)| (ctx, ix)))
+            .collect()
+    }
+
+    /// Direct CPIs made by `ctx`, in order of execution
+    pub fn children_of(&self, ctx: &ProgramContext) -> Vec<ProgramContext> {
+        let children = self
+            .parent_ix
+            .iter()
+            .filter(|(_, parent)| parent.eq(&ctx))
+            .map(|(child, _)| *child)
+            .collect::<Vec<_>>();
+        self.sorted_by_execution(children)
+    }
+
+    /// Contexts of all instructions, in order of execution
+    pub fn contexts_in_execution_order(&self) -> Vec<ProgramContext> {
+        self.sorted_by_execution(self.meta.keys().copied().collect())
+    }
+
+    /// First log (in order of execution) matching `predicate`, with context of its invocation
+    pub fn find_first_log_matching(
+        &self,
+        mut predicate: impl FnMut(&ProgramContext, &ProgramLog) -> bool,
+    ) -> Option<(&ProgramContext, &ProgramLog)> {
+        self.contexts_in_execution_order()
+            .into_iter()
+            .find_map(|ctx| {
+                let (ctx, (_, logs)) = self.meta.get_key_value(&ctx)?;
+                logs.iter()
+                    .find(|log| predicate(ctx, log))
+                    .map(|log| (ctx, log))
+            })
+    }
+
+    /// Sort by [`Self::ix_positions`], contexts without position go last
+    fn sorted_by_execution(&self, contexts: Vec<ProgramContext>) -> Vec<ProgramContext> {
+        contexts
+            .into_iter()
+            .sorted_by_key(|ctx| {
+                let position = self.ix_positions.get(ctx);
+                (position.is_none(), position.copied(), *ctx)
+            })
+            .collect()
+    }
+}
+
+/// Owned mirror of [`Instruction`] with stable serde representation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<StoredAccountMeta>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl From<&Instruction> for StoredInstruction {
+    fn from(ix: &Instruction) -> Self {
+        Self {
+            program_id: ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| StoredAccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: ix.data.clone(),
+        }
+    }
+}
+
+impl From<StoredInstruction> for Instruction {
+    fn from(ix: StoredInstruction) -> Self {
+        Self {
+            program_id: ix.program_id,
+            accounts: ix
+                .accounts
+                .into_iter()
+                .map(|meta| AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: ix.data,
+        }
+    }
+}
+
+/// Serde representation of [`TransactionParsedMeta`]
+///
+/// Maps are stored as lists of pairs, because most of formats (e.g. JSON) don't support
+/// non-string map keys
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredTransactionParsedMeta {
+    /// [`TRANSACTION_PARSED_META_SCHEMA_VERSION`] at the moment of serialization
+    pub schema_version: u32,
+    pub meta: Vec<(ProgramContext, StoredInstruction, Vec<ProgramLog>)>,
+    pub slot: Slot,
+    pub block_time: Option<UnixTimestamp>,
+    pub lamports_changes: Vec<(Pubkey, AmountDiff)>,
+    pub token_balances_changes: Vec<(WalletContext, AmountDiff)>,
+    pub parent_ix: Vec<(ChildProgramContext, ParentProgramContext)>,
+    pub root_ixs: Vec<ProgramContext>,
+    pub compute_budget: ComputeBudget,
+    /// Since schema version 2
+    #[serde(default)]
+    pub ix_positions: Vec<(ProgramContext, InstructionPosition)>,
+    /// Since schema version 3
+    #[serde(default)]
+    pub message: MessageMeta,
+    /// Since schema version 4
+    #[serde(default)]
+    pub stamp: ParseStamp,
+}
+
+impl From<&TransactionParsedMeta> for StoredTransactionParsedMeta {
+    fn from(meta: &TransactionParsedMeta) -> Self {
+        Self {
+            schema_version: TRANSACTION_PARSED_META_SCHEMA_VERSION,
+            meta: meta
+                .meta
+                .iter()
+                .map(|(ctx, (ix, logs))| (*ctx, ix.into(), logs.clone()))
+                .sorted_by_key(|(ctx, _, _)| *ctx)
+                .collect(),
+            slot: meta.slot,
+            block_time: meta.block_time,
+            lamports_changes: meta
+                .lamports_changes
+                .iter()
+                .map(|(account, diff)| (*account, *diff))
+                .sorted()
+                .collect(),
+            token_balances_changes: meta
+                .token_balances_changes
+                .iter()
+                .map(|(wallet, diff)| (*wallet, *diff))
+                .sorted_by_key(|(wallet, _)| {
+                    (
+                        wallet.wallet_address,
+                        wallet.token_mint,
+                        wallet.wallet_owner,
+                    )
+                })
+                .collect(),
+            parent_ix: meta
+                .parent_ix
+                .iter()
+                .map(|(child, parent)| (*child, *parent))
+                .sorted()
+                .collect(),
+            root_ixs: meta.root_ixs.clone(),
+            compute_budget: meta.compute_budget,
+            ix_positions: meta
+                .ix_positions
+                .iter()
+                .map(|(ctx, position)| (*ctx, *position))
+                .sorted_by_key(|(_, position)| *position)
+                .collect(),
+            message: meta.message.clone(),
+            stamp: meta.stamp,
+        }
+    }
+}
+
+impl TryFrom<StoredTransactionParsedMeta> for TransactionParsedMeta {
+    type Error = String;
+
+    /// Fails on schema version newer than [`TRANSACTION_PARSED_META_SCHEMA_VERSION`]
+    fn try_from(stored: StoredTransactionParsedMeta) -> Result<Self, Self::Error> {
+        if stored.schema_version > TRANSACTION_PARSED_META_SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported schema version of TransactionParsedMeta: {}, max supported: {}",
+                stored.schema_version, TRANSACTION_PARSED_META_SCHEMA_VERSION
+            ));
+        }
+
+        Ok(Self {
+            meta: stored
+                .meta
+                .into_iter()
+                .map(|(ctx, ix, logs)| (ctx, (ix.into(), logs)))
+                .collect(),
+            slot: stored.slot,
+            block_time: stored.block_time,
+            lamports_changes: stored.lamports_changes.into_iter().collect(),
+            token_balances_changes: stored.token_balances_changes.into_iter().collect(),
+            parent_ix: stored.parent_ix.into_iter().collect(),
+            root_ixs: stored.root_ixs,
+            compute_budget: stored.compute_budget,
+            ix_positions: stored.ix_positions.into_iter().collect(),
+            message: stored.message,
+            stamp: stored.stamp,
+        })
+    }
+}
+
+impl Serialize for TransactionParsedMeta {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StoredTransactionParsedMeta::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TransactionParsedMeta {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        StoredTransactionParsedMeta::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Version byte of [`TransactionParsedMeta::to_compact_bytes`]. Bincode layout changes with
+/// every schema version, so it's [`TRANSACTION_PARSED_META_SCHEMA_VERSION`] itself
+pub const COMPACT_CODEC_VERSION: u8 = TRANSACTION_PARSED_META_SCHEMA_VERSION as u8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CompactCodecError {
+    #[error("Compact TransactionParsedMeta is empty")]
+    Empty,
+    #[error(
+        "Unsupported version of compact TransactionParsedMeta: {0}, expected {COMPACT_CODEC_VERSION}"
+    )]
+    UnsupportedVersion(u8),
+    #[error(transparent)]
+    Bincode(#[from] bincode::Error),
+}
+
+impl TransactionParsedMeta {
+    /// Compact binary form, e.g. for caching of parsed transactions: [`COMPACT_CODEC_VERSION`]
+    /// byte followed by bincode of [`StoredTransactionParsedMeta`]
+    pub fn to_compact_bytes(&self) -> Result<Vec<u8>, CompactCodecError> {
+        let mut bytes = vec![COMPACT_CODEC_VERSION];
+        bincode::serialize_into(&mut bytes, &StoredTransactionParsedMeta::from(self))?;
+        Ok(bytes)
+    }
+
+    /// Fails with [`CompactCodecError::UnsupportedVersion`] on bytes of other version (e.g.
+    /// written before upgrade of crate), such transactions must be parsed again
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self, CompactCodecError> {
+        let (&version, stored) = bytes.split_first().ok_or(CompactCodecError::Empty)?;
+        if version != COMPACT_CODEC_VERSION {
+            return Err(CompactCodecError::UnsupportedVersion(version));
+        }
+        bincode::deserialize::<StoredTransactionParsedMeta>(stored)?
+            .try_into()
+            .map_err(|_| CompactCodecError::UnsupportedVersion(version))
+    }
+}
+
+/// Compute budget requested by `ComputeBudget` program instructions of transaction,
+/// `None` if the corresponding instruction is absent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComputeBudget {
+    /// `SetComputeUnitLimit`
+    pub unit_limit: Option<u32>,
+    /// `SetComputeUnitPrice`, priority fee per compute unit
+    pub unit_price_micro_lamports: Option<u64>,
+}
+
+impl ComputeBudget {
+    /// Collect from top-level instructions, instructions of other programs or malformed ones
+    /// are ignored
+    pub fn from_instructions<'ix>(
+        instructions: impl IntoIterator<Item = &'ix Instruction>,
+    ) -> Self {
+        use crate::native_instruction_parser::{
+            ComputeBudgetInstruction, NativeInstruction, COMPUTE_BUDGET_PROGRAM_ID,
+        };
+
+        instructions
+            .into_iter()
+            .filter(|ix| ix.program_id == COMPUTE_BUDGET_PROGRAM_ID)
+            .filter_map(|ix| ComputeBudgetInstruction::unpack(&ix.data).ok())
+            .fold(Self::default(), |mut budget, ix| {
+                match ix {
+                    ComputeBudgetInstruction::SetComputeUnitLimit(limit) => {
+                        budget.unit_limit = Some(limit)
+                    }
+                    ComputeBudgetInstruction::SetComputeUnitPrice(price) => {
+                        budget.unit_price_micro_lamports = Some(price)
+                    }
+                    _ => {}
+                }
+                budget
+            })
+    }
 }
 
 pub struct DecomposedInstruction<IX, ACCOUNTS> {
     pub program_ctx: ProgramContext,
     pub ix: IX,
     pub accounts: ACCOUNTS,
+    /// Accounts passed after the ones consumed by `ACCOUNTS`
+    pub remaining_accounts: Vec<Pubkey>,
+    /// All instruction accounts with signer & writable flags
+    pub all_account_metas: Vec<AccountMeta>,
+    /// Raw instruction data, including discriminator
+    pub raw_data: Vec<u8>,
+    /// Context of instruction which invoked this one, `None` for transaction's instructions
+    pub parent_ctx: Option<ParentProgramContext>,
     pub logs: Vec<ProgramLog>,
 }
 
@@ -136,6 +747,10 @@ impl<IX: Debug, ACCOUNTS: Debug> fmt::Debug for DecomposedInstruction<IX, ACCOUN
             .field("program_ctx", &self.program_ctx)
             .field("ix", &self.ix)
             .field("accounts", &self.accounts)
+            .field("remaining_accounts", &self.remaining_accounts)
+            .field("all_account_metas", &self.all_account_metas)
+            .field("raw_data", &self.raw_data)
+            .field("parent_ctx", &self.parent_ctx)
             .field("logs", &self.logs)
             .finish()
     }
@@ -179,7 +794,275 @@ pub trait DecomposeInstruction {
         program_ctx: ProgramContext,
         raw_ix: &Instruction,
         logs: &[ProgramLog],
-    ) -> Result<Box<dyn ConsumeInstruction + Send>, io::Error>;
+    ) -> Result<Box<dyn ConsumeInstruction + Send>, DecodeError>;
+
+    /// Same as [`DecomposeInstruction::decompose_instruction`], but with context of
+    /// instruction which invoked this one, `None` for transaction's instructions.
+    ///
+    /// By default `parent_ctx` is ignored
+    fn decompose_instruction_with_parent(
+        &self,
+        program_ctx: ProgramContext,
+        parent_ctx: Option<ParentProgramContext>,
+        raw_ix: &Instruction,
+        logs: &[ProgramLog],
+    ) -> Result<Box<dyn ConsumeInstruction + Send>, DecodeError> {
+        let _ = parent_ctx;
+        self.decompose_instruction(program_ctx, raw_ix, logs)
+    }
+
+    /// Keys used by [`DecomposerRegistry`] to dispatch instructions to this decomposer.
+    ///
+    /// Empty keys means that decomposer will be checked for any instruction
+    fn dispatch_keys(&self) -> Vec<DispatchKey> {
+        vec![]
+    }
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub enum DispatchKey {
+    /// Any instruction of program
+    Program(Pubkey),
+    /// Instruction of program with specific anchor discriminator
+    Discriminator(Pubkey, [u8; DISCRIMINATOR_SIZE]),
+}
+
+/// Group of decomposers, in which candidate was found by [`DecomposerRegistry::find`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DispatchMatch {
+    /// Registered for program id and discriminator of instruction
+    Discriminator,
+    /// Registered for program id of instruction
+    Program,
+    /// Registered without dispatch keys
+    Fallback,
+}
+
+/// Decomposer checked for instruction, see [`DecomposerRegistry::candidates`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DecomposerCandidate<'r> {
+    /// Name of decomposer registered by [`DecomposerRegistry::register_named`]
+    pub name: Option<&'r str>,
+    pub dispatch: DispatchMatch,
+    /// Result of [`DecomposeInstruction::is_decomposable`]
+    pub is_decomposable: bool,
+}
+
+pub type BoxedDecomposer = Box<dyn DecomposeInstruction + Send + Sync>;
+pub type SharedDecomposer = Arc<dyn DecomposeInstruction + Send + Sync>;
+
+/// Set of decomposers indexed by [`DispatchKey`]
+///
+/// For every instruction candidates are checked with [`DecomposeInstruction::is_decomposable`]
+/// in the following order, and the first suitable one is used:
+/// - decomposers registered for program id and discriminator of instruction
+/// - decomposers registered for program id of instruction
+/// - decomposers without dispatch keys (fallback)
+///
+/// Within each group decomposers are checked in registration order. Decomposers are shared
+/// between clones, so registry can be cheaply copied, changed & swapped at runtime
+#[derive(Default, Clone)]
+pub struct DecomposerRegistry {
+    decomposers: Vec<(Option<String>, SharedDecomposer)>,
+    by_discriminator: HashMap<(Pubkey, [u8; DISCRIMINATOR_SIZE]), Vec<usize>>,
+    by_program: HashMap<Pubkey, Vec<usize>>,
+    fallback: Vec<usize>,
+}
+
+impl DecomposerRegistry {
+    pub fn register(&mut self, decomposer: BoxedDecomposer) -> &mut Self {
+        self.push(None, Arc::from(decomposer));
+        self
+    }
+
+    /// Register decomposer under `name`, replacing previously registered one with the same
+    /// name, so it can be removed later by [`DecomposerRegistry::unregister`]
+    pub fn register_named(
+        &mut self,
+        name: impl Into<String>,
+        decomposer: impl Into<SharedDecomposer>,
+    ) -> &mut Self {
+        let name = name.into();
+        self.unregister(&name);
+        self.push(Some(name), decomposer.into());
+        self
+    }
+
+    /// Remove decomposer registered by [`DecomposerRegistry::register_named`], returns
+    /// `false` if there is no decomposer with this name
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let len = self.decomposers.len();
+        let decomposers = std::mem::take(&mut self.decomposers)
+            .into_iter()
+            .filter(|(decomposer_name, _)| decomposer_name.as_deref() != Some(name))
+            .collect::<Vec<_>>();
+        if decomposers.len() == len {
+            self.decomposers = decomposers;
+            return false;
+        }
+
+        *self = Self::default();
+        for (name, decomposer) in decomposers {
+            self.push(name, decomposer);
+        }
+        true
+    }
+
+    fn push(&mut self, name: Option<String>, decomposer: SharedDecomposer) {
+        let index = self.decomposers.len();
+        let keys = decomposer.dispatch_keys();
+
+        if keys.is_empty() {
+            self.fallback.push(index);
+        }
+        for key in keys {
+            match key {
+                DispatchKey::Program(program_id) => {
+                    self.by_program.entry(program_id).or_default().push(index)
+                }
+                DispatchKey::Discriminator(program_id, discriminator) => self
+                    .by_discriminator
+                    .entry((program_id, discriminator))
+                    .or_default()
+                    .push(index),
+            }
+        }
+        self.decomposers.push((name, decomposer));
+    }
+
+    pub fn with(mut self, decomposer: BoxedDecomposer) -> Self {
+        self.register(decomposer);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.decomposers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decomposers.is_empty()
+    }
+
+    /// Names of decomposers registered by [`DecomposerRegistry::register_named`]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.decomposers
+            .iter()
+            .filter_map(|(name, _)| name.as_deref())
+    }
+
+    /// Find decomposer for instruction
+    pub fn find(
+        &self,
+        program_ctx: &ProgramContext,
+        raw_ix: &Instruction,
+    ) -> Option<&(dyn DecomposeInstruction + Send + Sync)> {
+        let by_discriminator = split_discriminator(&raw_ix.data)
+            .and_then(|(discriminator, _)| <[u8; DISCRIMINATOR_SIZE]>::try_from(discriminator).ok())
+            .and_then(|discriminator| {
+                self.by_discriminator
+                    .get(&(raw_ix.program_id, discriminator))
+            });
+
+        by_discriminator
+            .into_iter()
+            .chain(self.by_program.get(&raw_ix.program_id))
+            .chain(Some(&self.fallback))
+            .flatten()
+            .map(|index| self.decomposers[*index].1.as_ref())
+            .find(|decomposer| decomposer.is_decomposable(program_ctx, raw_ix))
+    }
+
+    /// All decomposers checked for instruction by [`DecomposerRegistry::find`], in the same
+    /// order and with result of check, e.g. to debug why decomposer isn't matched
+    pub fn candidates(
+        &self,
+        program_ctx: &ProgramContext,
+        raw_ix: &Instruction,
+    ) -> Vec<DecomposerCandidate<'_>> {
+        let by_discriminator = split_discriminator(&raw_ix.data)
+            .and_then(|(discriminator, _)| <[u8; DISCRIMINATOR_SIZE]>::try_from(discriminator).ok())
+            .and_then(|discriminator| {
+                self.by_discriminator
+                    .get(&(raw_ix.program_id, discriminator))
+            });
+
+        [
+            (DispatchMatch::Discriminator, by_discriminator),
+            (
+                DispatchMatch::Program,
+                self.by_program.get(&raw_ix.program_id),
+            ),
+            (DispatchMatch::Fallback, Some(&self.fallback)),
+        ]
+        .into_iter()
+        .flat_map(|(dispatch, indexes)| {
+            indexes
+                .into_iter()
+                .flatten()
+                .map(move |index| (dispatch, &self.decomposers[*index]))
+        })
+        .map(|(dispatch, (name, decomposer))| DecomposerCandidate {
+            name: name.as_deref(),
+            dispatch,
+            is_decomposable: decomposer.is_decomposable(program_ctx, raw_ix),
+        })
+        .collect()
+    }
+
+    /// Discriminators of `program_id` with decomposers registered for them, sorted
+    pub fn discriminators(&self, program_id: &Pubkey) -> Vec<[u8; DISCRIMINATOR_SIZE]> {
+        self.by_discriminator
+            .keys()
+            .filter(|(id, _)| id == program_id)
+            .map(|(_, discriminator)| *discriminator)
+            .sorted()
+            .collect()
+    }
+}
+
+impl FromIterator<BoxedDecomposer> for DecomposerRegistry {
+    fn from_iter<I: IntoIterator<Item = BoxedDecomposer>>(iter: I) -> Self {
+        let mut registry = Self::default();
+        for decomposer in iter {
+            registry.register(decomposer);
+        }
+        registry
+    }
+}
+
+/// Splits instruction accounts into the fixed-size head expected by `ACCOUNTS`
+/// and the tail of remaining accounts.
+///
+/// Instructions with fewer than `min_accounts_count` (but at least `ACCOUNTS_COUNT`) accounts
+/// or with more than `max_accounts_count` accounts are rejected
+fn split_accounts<const ACCOUNTS_COUNT: usize>(
+    program_id: Pubkey,
+    program_ctx: Option<ProgramContext>,
+    accounts: &[AccountMeta],
+    min_accounts_count: usize,
+    max_accounts_count: Option<usize>,
+) -> Result<([Pubkey; ACCOUNTS_COUNT], Vec<Pubkey>), DecodeError> {
+    let min_accounts_count = min_accounts_count.max(ACCOUNTS_COUNT);
+    if accounts.len() < min_accounts_count
+        || max_accounts_count.is_some_and(|max_accounts_count| accounts.len() > max_accounts_count)
+    {
+        return Err(DecodeError::AccountsCount {
+            program_id,
+            program_ctx,
+            min: min_accounts_count,
+            max: max_accounts_count,
+            actual: accounts.len(),
+        });
+    }
+
+    Ok((
+        std::array::from_fn(|index| accounts[index].pubkey),
+        accounts
+            .iter()
+            .skip(ACCOUNTS_COUNT)
+            .map(|acc| acc.pubkey)
+            .collect(),
+    ))
 }
 
 pub struct InstructionDecomposer<
@@ -190,6 +1073,10 @@ pub struct InstructionDecomposer<
     ix: PhantomData<IX>,
     accounts: PhantomData<ACCOUNTS>,
     consumer: Option<Consumer<IX, ACCOUNTS>>,
+    /// Minimal number of accounts in instruction, by default - `ACCOUNTS_COUNT`
+    min_accounts_count: usize,
+    /// Maximal number of accounts in instruction, by default - unlimited
+    max_accounts_count: Option<usize>,
 }
 
 impl<
@@ -211,6 +1098,45 @@ impl<
 
         self
     }
+
+    /// Reject instructions with less than `min_accounts_count` accounts.
+    ///
+    /// `min_accounts_count` can't be less than `ACCOUNTS_COUNT` or greater than
+    /// maximal accounts count. Trailing optional accounts should be left out of `ACCOUNTS`
+    /// and read from [`DecomposedInstruction::remaining_accounts`]
+    pub fn set_min_accounts_count(mut self, min_accounts_count: usize) -> Result<Self, Error> {
+        if min_accounts_count < ACCOUNTS_COUNT
+            || self
+                .max_accounts_count
+                .is_some_and(|max_accounts_count| min_accounts_count > max_accounts_count)
+        {
+            return Err(Error::InvalidAccountsCount {
+                accounts_count: ACCOUNTS_COUNT,
+                min: min_accounts_count,
+                max: self.max_accounts_count,
+            });
+        }
+        self.min_accounts_count = min_accounts_count;
+
+        Ok(self)
+    }
+
+    /// Reject instructions with more than `max_accounts_count` accounts.
+    /// Accounts after `ACCOUNTS_COUNT` are available as [`DecomposedInstruction::remaining_accounts`]
+    ///
+    /// `max_accounts_count` can't be less than minimal accounts count
+    pub fn set_max_accounts_count(mut self, max_accounts_count: usize) -> Result<Self, Error> {
+        if max_accounts_count < self.min_accounts_count {
+            return Err(Error::InvalidAccountsCount {
+                accounts_count: ACCOUNTS_COUNT,
+                min: self.min_accounts_count,
+                max: Some(max_accounts_count),
+            });
+        }
+        self.max_accounts_count = Some(max_accounts_count);
+
+        Ok(self)
+    }
 }
 
 impl<
@@ -224,6 +1150,8 @@ impl<
             ix: Default::default(),
             accounts: Default::default(),
             consumer: None,
+            min_accounts_count: ACCOUNTS_COUNT,
+            max_accounts_count: None,
         }
     }
 }
@@ -235,10 +1163,10 @@ impl<
     > DecomposeInstruction for InstructionDecomposer<IX, ACCOUNTS, ACCOUNTS_COUNT>
 {
     fn is_decomposable(&self, program_ctx: &ProgramContext, raw_ix: &Instruction) -> bool {
-        const DISCRIMINATOR_SIZE: usize = 8;
         program_ctx.program_id.eq(&IX::owner())
             && IX::owner().eq(&raw_ix.program_id)
-            && IX::discriminator().eq(raw_ix.data.split_at(DISCRIMINATOR_SIZE).0)
+            && split_discriminator(&raw_ix.data)
+                .is_some_and(|(discriminator, _)| IX::discriminator().eq(discriminator))
     }
 
     fn decompose_instruction(
@@ -246,45 +1174,58 @@ impl<
         program_ctx: ProgramContext,
         raw_ix: &Instruction,
         logs: &[ProgramLog],
-    ) -> Result<Box<dyn ConsumeInstruction + Send + 'static>, io::Error> {
+    ) -> Result<Box<dyn ConsumeInstruction + Send + 'static>, DecodeError> {
+        self.decompose_instruction_with_parent(program_ctx, None, raw_ix, logs)
+    }
+
+    fn decompose_instruction_with_parent(
+        &self,
+        program_ctx: ProgramContext,
+        parent_ctx: Option<ParentProgramContext>,
+        raw_ix: &Instruction,
+        logs: &[ProgramLog],
+    ) -> Result<Box<dyn ConsumeInstruction + Send + 'static>, DecodeError> {
+        let ix = decode_anchor_data::<IX>(raw_ix.program_id, Some(program_ctx), &raw_ix.data)?;
+        let (accounts, remaining_accounts) = split_accounts::<ACCOUNTS_COUNT>(
+            raw_ix.program_id,
+            Some(program_ctx),
+            &raw_ix.accounts,
+            self.min_accounts_count,
+            self.max_accounts_count,
+        )?;
+
         Ok(Box::new(DecomposedInstructionWithConsumer {
             consumer: self.consumer.as_ref().cloned(),
             decomposed_ix: DecomposedInstruction {
                 program_ctx,
                 logs: logs.to_vec(),
-                accounts: ACCOUNTS::from(
-                    <[Pubkey; ACCOUNTS_COUNT]>::try_from(
-                        raw_ix
-                            .accounts
-                            .iter()
-                            .map(|acc| acc.pubkey)
-                            .take(ACCOUNTS_COUNT)
-                            .collect::<Vec<_>>(),
-                    )
-                    .map_err(|err| {
-                        io::Error::new(
-                            ErrorKind::InvalidData,
-                            format!("Instruction accounts parsing error: {err:?}"),
-                        )
-                    })?,
-                ),
-                ix: raw_ix.parse_instruction::<IX>().ok_or_else(|| {
-                    io::Error::new(ErrorKind::InvalidData, Error::WrongParserFound)
-                })??,
+                accounts: ACCOUNTS::from(accounts),
+                remaining_accounts,
+                all_account_metas: raw_ix.accounts.clone(),
+                raw_data: raw_ix.data.clone(),
+                parent_ctx,
+                ix,
             },
         }))
     }
+
+    fn dispatch_keys(&self) -> Vec<DispatchKey> {
+        vec![DispatchKey::Discriminator(IX::owner(), IX::discriminator())]
+    }
 }
 
 #[cfg(feature = "anchor")]
 mod anchor {
-    use std::{io, io::ErrorKind, sync::Arc};
+    use std::sync::Arc;
 
     use anchor_lang::{AnchorDeserialize, Discriminator, Owner};
 
-    use super::{Pubkey, TransactionParsedMeta};
-    use crate::transaction_parser::{
-        ConsumeInstruction, DecomposeInstruction, DecomposedInstruction,
+    use super::{decode_anchor_data, split_accounts, Error, Pubkey, TransactionParsedMeta};
+    use crate::{
+        instruction_parser::split_discriminator,
+        transaction_parser::{
+            ConsumeInstruction, DecomposeInstruction, DecomposedInstruction, DecomposerRegistry,
+        },
     };
 
     impl TransactionParsedMeta {
@@ -294,51 +1235,46 @@ mod anchor {
             ACCOUNTS: From<[Pubkey; ACCOUNTS_COUNT]>,
         >(
             &self,
-        ) -> Result<Vec<DecomposedInstruction<IX, ACCOUNTS>>, io::Error> {
-            use crate::ParseInstruction;
+        ) -> Result<Vec<DecomposedInstruction<IX, ACCOUNTS>>, Error> {
             self.meta
                 .iter()
-                .filter(|(ctx, _meta)| ctx.program_id.eq(&IX::owner()))
-                .filter_map(|(program_ctx, (raw_instruction, logs))| {
-                    Some(
-                        raw_instruction
-                            .parse_instruction::<IX>()?
-                            .map(|instruction| {
-                                Ok(DecomposedInstruction {
-                                    program_ctx: *program_ctx,
-                                    logs: logs.to_vec(),
-                                    accounts: ACCOUNTS::from(
-                                        <[Pubkey; ACCOUNTS_COUNT]>::try_from(
-                                            raw_instruction
-                                                .accounts
-                                                .iter()
-                                                .map(|acc| acc.pubkey)
-                                                .take(ACCOUNTS_COUNT)
-                                                .collect::<Vec<_>>(),
-                                        )
-                                        .map_err(
-                                            |err| {
-                                                io::Error::new(
-                                                    ErrorKind::InvalidData,
-                                                    format!(
-                                                        "Instruction accounts parsing error: {err:?}",
-                                                    ),
-                                                )
-                                            },
-                                        )?,
-                                    ),
-                                    ix: instruction,
-                                })
-                            }),
-                    )
+                .filter(|(ctx, (raw_instruction, _logs))| {
+                    ctx.program_id.eq(&IX::owner())
+                        && raw_instruction.program_id.eq(&IX::owner())
+                        && split_discriminator(&raw_instruction.data)
+                            .is_some_and(|(discriminator, _)| IX::discriminator().eq(discriminator))
                 })
-                .collect::<Result<_, _>>()?
+                .map(|(program_ctx, (raw_instruction, logs))| {
+                    let (accounts, remaining_accounts) = split_accounts::<ACCOUNTS_COUNT>(
+                        raw_instruction.program_id,
+                        Some(*program_ctx),
+                        &raw_instruction.accounts,
+                        ACCOUNTS_COUNT,
+                        None,
+                    )?;
+
+                    Ok(DecomposedInstruction {
+                        program_ctx: *program_ctx,
+                        logs: logs.to_vec(),
+                        accounts: ACCOUNTS::from(accounts),
+                        remaining_accounts,
+                        all_account_metas: raw_instruction.accounts.clone(),
+                        raw_data: raw_instruction.data.clone(),
+                        parent_ctx: self.parent_ix.get(program_ctx).copied(),
+                        ix: decode_anchor_data::<IX>(
+                            raw_instruction.program_id,
+                            Some(*program_ctx),
+                            &raw_instruction.data,
+                        )?,
+                    })
+                })
+                .collect()
         }
 
         pub fn find_and_decompose_ix_with_decomposer(
             &self,
             decomposers: Arc<Vec<Box<dyn DecomposeInstruction + Send + Sync>>>,
-        ) -> Result<Vec<Box<(dyn ConsumeInstruction + Send)>>, io::Error> {
+        ) -> Result<Vec<Box<(dyn ConsumeInstruction + Send)>>, Error> {
             self.meta
                 .iter()
                 .filter_map(|(program_ctx, (raw_instruction, logs))| {
@@ -346,14 +1282,54 @@ mod anchor {
                         .iter()
                         .find(|decomposer| decomposer.is_decomposable(program_ctx, raw_instruction))
                         .map(|decomposer| {
-                            decomposer.decompose_instruction(*program_ctx, raw_instruction, logs)
+                            decomposer.decompose_instruction_with_parent(
+                                *program_ctx,
+                                self.parent_ix.get(program_ctx).copied(),
+                                raw_instruction,
+                                logs,
+                            )
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(Error::from)
+        }
+
+        /// Same as [`TransactionParsedMeta::find_and_decompose_ix_with_decomposer`],
+        /// but dispatch instructions with [`DecomposerRegistry`] instead of linear scan
+        pub fn find_and_decompose_ix_with_registry(
+            &self,
+            registry: &DecomposerRegistry,
+        ) -> Result<Vec<Box<dyn ConsumeInstruction + Send>>, Error> {
+            self.meta
+                .iter()
+                .filter_map(|(program_ctx, (raw_instruction, logs))| {
+                    registry
+                        .find(program_ctx, raw_instruction)
+                        .map(|decomposer| {
+                            decomposer.decompose_instruction_with_parent(
+                                *program_ctx,
+                                self.parent_ix.get(program_ctx).copied(),
+                                raw_instruction,
+                                logs,
+                            )
                         })
                 })
                 .collect::<Result<Vec<_>, _>>()
+                .map_err(Error::from)
         }
     }
 }
 
+/// Config of `getTransaction` request used by
+/// [`BindTransactionInstructionLogs::bind_transaction_instructions_logs`]
+pub fn default_transaction_config(commitment_config: CommitmentConfig) -> RpcTransactionConfig {
+    RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Binary),
+        max_supported_transaction_version: Some(0),
+        commitment: Some(commitment_config),
+    }
+}
+
 #[async_trait]
 pub trait BindTransactionInstructionLogs {
     async fn bind_transaction_instructions_logs(
@@ -361,6 +1337,23 @@ pub trait BindTransactionInstructionLogs {
         signature: Signature,
         commitment_config: CommitmentConfig,
     ) -> Result<TransactionParsedMeta, Error>;
+
+    /// Same as [`BindTransactionInstructionLogs::bind_transaction_instructions_logs`], but
+    /// with custom `getTransaction` config. `encoding` must be binary one (`binary`, `base58`
+    /// or `base64`)
+    async fn bind_transaction_instructions_logs_with_config(
+        &self,
+        signature: Signature,
+        transaction_config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta, Error>;
+
+    /// Same as [`BindTransactionInstructionLogs::bind_transaction_instructions_logs_with_config`],
+    /// but mismatches of logs & instructions are reported, see [`parse_transaction_lenient`]
+    async fn bind_transaction_instructions_logs_lenient(
+        &self,
+        signature: Signature,
+        transaction_config: RpcTransactionConfig,
+    ) -> Result<PartiallyParsedTransaction, Error>;
 }
 
 #[async_trait]
@@ -370,74 +1363,546 @@ impl BindTransactionInstructionLogs for RpcClient {
         signature: Signature,
         commitment_config: CommitmentConfig,
     ) -> Result<TransactionParsedMeta, Error> {
-        let EncodedConfirmedTransactionWithStatusMeta {
-            transaction,
-            slot,
-            block_time,
-        } = self
-            .get_transaction_with_config(
-                &signature,
-                RpcTransactionConfig {
-                    encoding: Some(UiTransactionEncoding::Binary),
-                    max_supported_transaction_version: Some(0),
-                    commitment: Some(commitment_config),
+        self.bind_transaction_instructions_logs_with_config(
+            signature,
+            default_transaction_config(commitment_config),
+        )
+        .await
+    }
+
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(
+            %signature,
+            encoding = ?transaction_config.encoding,
+            commitment = ?transaction_config.commitment.map(|config| config.commitment),
+            rpc_elapsed_us = tracing::field::Empty,
+        )
+    )]
+    async fn bind_transaction_instructions_logs_with_config(
+        &self,
+        signature: Signature,
+        transaction_config: RpcTransactionConfig,
+    ) -> Result<TransactionParsedMeta, Error> {
+        let started_at = std::time::Instant::now();
+        let transaction = self
+            .get_transaction_with_config(&signature, transaction_config)
+            .await?;
+        tracing::Span::current().record("rpc_elapsed_us", started_at.elapsed().as_micros() as u64);
+
+        parse_transaction(signature, &transaction)
+    }
+
+    async fn bind_transaction_instructions_logs_lenient(
+        &self,
+        signature: Signature,
+        transaction_config: RpcTransactionConfig,
+    ) -> Result<PartiallyParsedTransaction, Error> {
+        let transaction = self
+            .get_transaction_with_config(&signature, transaction_config)
+            .await?;
+        parse_transaction_lenient(signature, &transaction)
+    }
+}
+
+/// Build [`TransactionParsedMeta`] from already fetched transaction, with
+/// [`UiTransactionEncoding::Binary`], [`UiTransactionEncoding::Base58`] or
+/// [`UiTransactionEncoding::Base64`] encoding
+///
+/// Logs & instructions are correlated by [`InvocationKey`], so programs invoked without
+/// logs at some depth don't shift the rest of correlation
+///
+/// Fails with [`Error::InstructionLogsConsistencyError`] if logs & instructions disagree, see
+/// [`parse_transaction_lenient`]
+pub fn parse_transaction(
+    signature: Signature,
+    confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<TransactionParsedMeta, Error> {
+    parse_transaction_with_options(signature, confirmed_transaction, &ParseOptions::default())
+}
+
+/// Same as [`parse_transaction`], configured by [`ParseOptions`]
+pub fn parse_transaction_with_options(
+    signature: Signature,
+    confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    options: &ParseOptions,
+) -> Result<TransactionParsedMeta, Error> {
+    bind(signature, confirmed_transaction, false, options).map(|partial| partial.parsed)
+}
+
+/// [`TransactionParsedMeta`] of instructions matched with logs by
+/// [`parse_transaction_lenient`] & unmatched rest
+#[derive(Debug)]
+pub struct PartiallyParsedTransaction {
+    pub parsed: TransactionParsedMeta,
+    /// Contexts from logs without instruction, or with instruction of another level
+    pub unmatched_contexts: Vec<ProgramContext>,
+    /// Instructions without logs, e.g. cut off by `Log truncated`
+    pub unmatched_instructions: Vec<(InstructionContext, Instruction)>,
+}
+
+impl PartiallyParsedTransaction {
+    /// Are all logs & instructions matched
+    pub fn is_complete(&self) -> bool {
+        self.unmatched_contexts.is_empty() && self.unmatched_instructions.is_empty()
+    }
+}
+
+/// Same as [`parse_transaction`], but logs & instructions mismatches are reported instead of
+/// failure, so partially parseable transactions are still usable
+pub fn parse_transaction_lenient(
+    signature: Signature,
+    confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<PartiallyParsedTransaction, Error> {
+    bind(
+        signature,
+        confirmed_transaction,
+        true,
+        &ParseOptions::default(),
+    )
+}
+
+/// `stack_height` of inner instructions by position, `None` for nodes before v1.14
+fn inner_stack_heights(
+    transaction: &EncodedTransactionWithStatusMeta,
+) -> HashMap<InstructionPosition, Option<u32>> {
+    match transaction
+        .meta
+        .as_ref()
+        .map(|meta| &meta.inner_instructions)
+    {
+        Some(OptionSerializer::Some(inner_instructions)) => inner_instructions
+            .iter()
+            .flat_map(|inner| {
+                inner.instructions.iter().enumerate().filter_map(
+                    move |(inner_index, ix)| match ix {
+                        UiInstruction::Compiled(compiled) => Some((
+                            InstructionPosition {
+                                instruction_index: inner.index as usize,
+                                inner_index: Some(inner_index),
+                            },
+                            compiled.stack_height,
+                        )),
+                        UiInstruction::Parsed(_) => None,
+                    },
+                )
+            })
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// Invoke level of instruction at `position`, the same as in `Program <id> invoke [<level>]`
+fn invoke_level(
+    position: &InstructionPosition,
+    stack_heights: &HashMap<InstructionPosition, Option<u32>>,
+) -> u32 {
+    match position.inner_index {
+        None => 1,
+        // Without `stack_height` (before v1.14) only direct CPI is assumed
+        Some(_) => stack_heights
+            .get(position)
+            .copied()
+            .flatten()
+            .filter(|height| *height > 0)
+            .unwrap_or(2),
+    }
+}
+
+/// Key of invocation independent of call index, used to correlate logs & instructions:
+/// `(program_id, invoke level, occurrence of program at this level)`
+pub type InvocationKey = (Pubkey, u32, usize);
+
+/// Key `invocations` of `(item, program_id, call_index, invoke level)`
+fn invocation_keys<T>(
+    invocations: impl Iterator<Item = (T, Pubkey, usize, u32)>,
+) -> Vec<(InvocationKey, T)> {
+    let mut occurrences = HashMap::new();
+    invocations
+        .sorted_by_key(|(_, program_id, call_index, _)| (*program_id, *call_index))
+        .map(|(item, program_id, _, level)| {
+            let occurrence = occurrences.entry((program_id, level)).or_insert(0);
+            let key = (program_id, level, *occurrence);
+            *occurrence += 1;
+            (key, item)
+        })
+        .collect()
+}
+
+/// Disagreement of logs & instructions of the same transaction found by
+/// [`validate_consistency`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Discrepancy {
+    /// Context in logs without instruction
+    MissingInstruction(ProgramContext),
+    /// Instruction without logs, e.g. cut off by `Log truncated`
+    MissingLogs {
+        ix_ctx: InstructionContext,
+        position: InstructionPosition,
+    },
+    /// Matched by [`InvocationKey`], but logs & instructions enumerate calls differently
+    CallIndexShift {
+        ctx: ProgramContext,
+        ix_ctx: InstructionContext,
+    },
+    /// Program of transaction instruction, invoking `ctx` by logs, isn't outer instruction one
+    OuterProgramMismatch {
+        ctx: ProgramContext,
+        log_owner: Pubkey,
+        ix_owner: OuterInstructionProgramId,
+    },
+}
+
+/// Check assumptions of logs & instructions correlation, without RPC & side effects, so it can
+/// be run over recorded transactions as data quality check
+///
+/// `parsed_logs` are from [`log_parser::parse_events`], `bound_instructions` are from
+/// [`BindInstructions::bind_instructions`]. As `stack_height` isn't passed, inner
+/// instructions of any depth are correlated as the same level
+pub fn validate_consistency(
+    parsed_logs: &HashMap<ProgramContext, Vec<ProgramLog>>,
+    bound_instructions: &HashMap<
+        InstructionContext,
+        (Instruction, OuterInstructionProgramId, InstructionPosition),
+    >,
+) -> Vec<Discrepancy> {
+    let parents = parsed_logs
+        .iter()
+        .flat_map(|(parent_ctx, logs)| {
+            logs.iter().filter_map(move |log| match log {
+                ProgramLog::Invoke(children_ctx) => Some((*children_ctx, *parent_ctx)),
+                _ => None,
+            })
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut ix_by_key =
+        invocation_keys(bound_instructions.iter().map(|(ix_ctx, (_, outer_ix, _))| {
+            (
+                *ix_ctx,
+                ix_ctx.program_id,
+                ix_ctx.call_index,
+                if outer_ix.is_none() { 1 } else { 2 },
+            )
+        }))
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let mut discrepancies = vec![];
+    for (key, ctx) in invocation_keys(parsed_logs.keys().map(|ctx| {
+        (
+            *ctx,
+            ctx.program_id,
+            ctx.program_call_index,
+            ctx.invoke_level.get().min(2),
+        )
+    })) {
+        let Some(ix_ctx) = ix_by_key.remove(&key) else {
+            discrepancies.push(Discrepancy::MissingInstruction(ctx));
+            continue;
+        };
+        if ix_ctx.call_index != ctx.program_call_index {
+            discrepancies.push(Discrepancy::CallIndexShift { ctx, ix_ctx });
+        }
+
+        let mut root_ctx = ctx;
+        while let Some(parent_ctx) = parents.get(&root_ctx) {
+            root_ctx = *parent_ctx;
+        }
+        let ix_owner = bound_instructions
+            .get(&ix_ctx)
+            .and_then(|(_, outer_ix, _)| *outer_ix);
+        if root_ctx != ctx && Some(root_ctx.program_id) != ix_owner {
+            discrepancies.push(Discrepancy::OuterProgramMismatch {
+                ctx,
+                log_owner: root_ctx.program_id,
+                ix_owner,
+            });
+        }
+    }
+    discrepancies.extend(ix_by_key.into_values().filter_map(|ix_ctx| {
+        bound_instructions
+            .get(&ix_ctx)
+            .map(|(_, _, position)| Discrepancy::MissingLogs {
+                ix_ctx,
+                position: *position,
+            })
+    }));
+
+    discrepancies.sort();
+    discrepancies
+}
+
+/// Build contexts of executed `instructions` without logs, cut off by `Log truncated`.
+/// Inner instructions in meta are not truncated, so invoke level & parent are restored by their
+/// `stack_height` & order relative to `logged` contexts
+///
+/// Returns `(ctx, ix, position, parent ctx)` in order of execution, restored instructions are
+/// removed from `instructions`
+fn restore_truncated(
+    transaction: &EncodedTransactionWithStatusMeta,
+    instructions: &mut HashMap<
+        InstructionContext,
+        (Instruction, OuterInstructionProgramId, InstructionPosition),
+    >,
+    logged: &HashMap<ProgramContext, InstructionPosition>,
+    stack_heights: &HashMap<InstructionPosition, Option<u32>>,
+) -> Vec<(
+    ProgramContext,
+    Instruction,
+    InstructionPosition,
+    Option<ProgramContext>,
+)> {
+    // Transaction instructions after failed one were not executed
+    let failed_ix_index = transaction.meta.as_ref().and_then(|meta| match &meta.err {
+        Some(TransactionError::InstructionError(index, _)) => Some(*index as usize),
+        _ => None,
+    });
+
+    let restored_ctxs = instructions
+        .iter()
+        .filter(|(_, (_, _, position))| {
+            failed_ix_index.map_or(true, |failed| position.instruction_index <= failed)
+        })
+        .map(|(ix_ctx, (_, _, position))| {
+            let invoke_level = invoke_level(position, stack_heights);
+            (
+                *position,
+                ProgramContext {
+                    program_id: ix_ctx.program_id,
+                    program_call_index: ix_ctx.call_index,
+                    invoke_level: Level::new(invoke_level).unwrap_or(Level::MIN),
                 },
+                true,
             )
-            .await?;
-        let mut instructions = transaction.bind_instructions(signature)?;
+        })
+        .collect::<Vec<_>>();
 
-        let meta = transaction
-            .meta
-            .as_ref()
-            .ok_or(Error::EmptyMetaInTransaction(signature))?;
+    let mut stack = Vec::<ProgramContext>::new();
+    let mut result = vec![];
+    for (position, ctx, is_restored) in logged
+        .iter()
+        .map(|(ctx, position)| (*position, *ctx, false))
+        .chain(restored_ctxs)
+        .sorted_by_key(|(position, _, _)| *position)
+    {
+        while stack
+            .last()
+            .is_some_and(|last| last.invoke_level >= ctx.invoke_level)
+        {
+            stack.pop();
+        }
+        if is_restored {
+            let ix_ctx = InstructionContext {
+                program_id: ctx.program_id,
+                call_index: ctx.program_call_index,
+            };
+            if let Some((ix, _, _)) = instructions.remove(&ix_ctx) {
+                result.push((ctx, ix, position, stack.last().copied()));
+            }
+        }
+        stack.push(ctx);
+    }
+    result
+}
 
-        let meta: HashMap<ProgramContext, (Instruction, Vec<ProgramLog>)> =
-            log_parser::parse_events(match meta.log_messages.as_ref() {
-                OptionSerializer::None | OptionSerializer::Skip => {
-                    Err(Error::EmptyLogsInTransaction(signature))
-                }
-                OptionSerializer::Some(log_messages) => Ok(log_messages.as_slice()),
-            }?)?
+#[tracing::instrument(
+    name = "parse_transaction",
+    level = "debug",
+    skip_all,
+    fields(
+        %signature,
+        slot = confirmed_transaction.slot,
+        instructions_count = tracing::field::Empty,
+        root_instructions_count = tracing::field::Empty,
+        elapsed_us = tracing::field::Empty,
+    )
+)]
+fn bind(
+    signature: Signature,
+    confirmed_transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    lenient: bool,
+    options: &ParseOptions,
+) -> Result<PartiallyParsedTransaction, Error> {
+    let started_at = std::time::Instant::now();
+    let EncodedConfirmedTransactionWithStatusMeta {
+        transaction,
+        slot,
+        block_time,
+    } = confirmed_transaction;
+    let mut instructions = transaction.bind_instructions_with_options(signature, options)?;
+
+    let log_messages = transaction_logs(transaction, signature)?;
+
+    let stack_heights = inner_stack_heights(transaction);
+    // Without `stack_height` only transaction instructions & CPIs are distinguished
+    let is_depth_known = stack_heights.values().all(Option::is_some);
+    let depth = |level: u32| if is_depth_known { level } else { level.min(2) };
+
+    let mut ix_by_key = invocation_keys(instructions.iter().map(|(ix_ctx, (_, _, position))| {
+        (
+            *ix_ctx,
+            ix_ctx.program_id,
+            ix_ctx.call_index,
+            depth(invoke_level(position, &stack_heights)),
+        )
+    }))
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+
+    let mut ix_positions = HashMap::new();
+    let mut meta = HashMap::new();
+    let mut root_ixs = vec![];
+    let mut unmatched_contexts = vec![];
+    let logged = invocation_keys(
+        log_parser::parse_events_with_options(log_messages, options)?
             .into_iter()
             .map(|(ctx, events)| {
-                let ix_ctx = InstructionContext {
-                    program_id: ctx.program_id,
-                    call_index: ctx.program_call_index,
-                };
-                let (ix, outer_ix) = instructions
-                    .remove(&ix_ctx)
-                    .ok_or(Error::InstructionLogsConsistencyError(ix_ctx))?;
-
-                // TODO Add validation of outer ix
-                if (outer_ix.is_none() && ctx.invoke_level.get() == 1)
-                    || (outer_ix.is_some() && ctx.invoke_level.get() != 1)
-                {
-                    Ok((ctx, (ix, events)))
-                } else {
-                    Err(Error::InstructionLogsConsistencyError(ix_ctx))
+                (
+                    (ctx, events),
+                    ctx.program_id,
+                    ctx.program_call_index,
+                    depth(ctx.invoke_level.get()),
+                )
+            }),
+    );
+    for (key, (ctx, events)) in logged {
+        match ix_by_key
+            .remove(&key)
+            .and_then(|ix_ctx| instructions.remove(&ix_ctx))
+        {
+            Some((ix, _, position)) => {
+                if ctx.invoke_level.get() == 1 {
+                    root_ixs.push(ctx);
                 }
+                ix_positions.insert(ctx, position);
+                meta.insert(ctx, (ix, events));
+            }
+            None => unmatched_contexts.push(ctx),
+        }
+    }
+    unmatched_contexts.sort();
+
+    let mut restored_parent_ix = HashMap::new();
+    if options.truncation == TruncationPolicy::Restore && log_parser::is_truncated(log_messages) {
+        let restored = restore_truncated(
+            transaction,
+            &mut instructions,
+            &ix_positions,
+            &stack_heights,
+        );
+        tracing::debug!(
+            "Restored {} contexts cut off by \"Log truncated\"",
+            restored.len()
+        );
+        for (ctx, ix, position, parent_ctx) in restored {
+            if ctx.invoke_level.get() == 1 {
+                root_ixs.push(ctx);
+            }
+            if let Some(parent_ctx) = parent_ctx {
+                restored_parent_ix.insert(ctx, parent_ctx);
+            }
+            ix_positions.insert(ctx, position);
+            meta.insert(ctx, (ix, vec![]));
+        }
+    }
+
+    if options.include_precompiles {
+        let precompiles = instructions
+            .iter()
+            .filter(|(ix_ctx, (_, _, position))| {
+                position.inner_index.is_none() && is_precompile(&ix_ctx.program_id)
             })
-            .collect::<Result<_, Error>>()?;
+            .map(|(ix_ctx, _)| *ix_ctx)
+            .collect::<Vec<_>>();
+        for ix_ctx in precompiles {
+            let Some((ix, _, position)) = instructions.remove(&ix_ctx) else {
+                continue;
+            };
+            let ctx = ProgramContext {
+                program_id: ix_ctx.program_id,
+                program_call_index: ix_ctx.call_index,
+                invoke_level: Level::MIN,
+            };
+            root_ixs.push(ctx);
+            ix_positions.insert(ctx, position);
+            meta.insert(ctx, (ix, vec![]));
+        }
+    }
+    // Parsed logs are unordered, top-level instructions are executed in order of message
+    root_ixs.sort_by_key(|ctx| ix_positions.get(ctx).copied());
 
-        Ok(TransactionParsedMeta {
-            slot,
-            block_time,
-            parent_ix: meta
-                .iter()
-                .flat_map(|(parent_ctx, (_, program_logs))| {
-                    program_logs
-                        .iter()
-                        .filter_map(|program_log| match program_log {
-                            ProgramLog::Invoke(children_ctx) => Some((*children_ctx, *parent_ctx)),
-                            _ => None,
-                        })
-                })
+    let mut unmatched_instructions = instructions
+        .into_iter()
+        .map(|(ix_ctx, (ix, _, position))| (position, ix_ctx, ix))
+        .collect::<Vec<_>>();
+    unmatched_instructions.sort_by_key(|(position, _, _)| *position);
+
+    if !lenient && !unmatched_contexts.is_empty() {
+        let err = Error::InstructionLogsConsistencyError {
+            contexts: unmatched_contexts,
+            instructions: unmatched_instructions
+                .into_iter()
+                .map(|(position, ix_ctx, _)| (ix_ctx, position))
                 .collect(),
-            meta,
-            lamports_changes: transaction.get_lamports_changes(&signature)?,
-            token_balances_changes: transaction.get_assets_changes(&signature)?,
-        })
+        };
+        tracing::warn!("{err}");
+        return Err(err);
     }
+
+    let span = tracing::Span::current();
+    span.record("instructions_count", meta.len());
+    span.record("root_instructions_count", root_ixs.len());
+
+    let parsed = TransactionParsedMeta {
+        compute_budget: ComputeBudget::from_instructions(
+            root_ixs
+                .iter()
+                .filter_map(|ctx| meta.get(ctx).map(|(ix, _)| ix)),
+        ),
+        slot: *slot,
+        block_time: *block_time,
+        parent_ix: meta
+            .iter()
+            .flat_map(|(parent_ctx, (_, program_logs))| {
+                program_logs
+                    .iter()
+                    .filter_map(|program_log| match program_log {
+                        ProgramLog::Invoke(children_ctx) => Some((*children_ctx, *parent_ctx)),
+                        _ => None,
+                    })
+            })
+            .chain(restored_parent_ix)
+            .collect(),
+        meta,
+        root_ixs,
+        ix_positions,
+        lamports_changes: transaction.get_lamports_changes(&signature)?,
+        token_balances_changes: transaction.get_assets_changes(&signature)?,
+        message: transaction
+            .transaction
+            .decode()
+            .map(|tx| MessageMeta::from_message(&tx.message))
+            .unwrap_or_default(),
+        stamp: ParseStamp::new(signature, confirmed_transaction)?,
+    };
+    span.record("elapsed_us", started_at.elapsed().as_micros() as u64);
+
+    Ok(PartiallyParsedTransaction {
+        parsed,
+        unmatched_contexts,
+        unmatched_instructions: unmatched_instructions
+            .into_iter()
+            .map(|(_, ix_ctx, ix)| (ix_ctx, ix))
+            .collect(),
+    })
+}
+
+/// Is program precompiled (ed25519, secp256k1): executed natively without invocation logs,
+/// so it's present only in instructions of transaction
+pub fn is_precompile(program_id: &Pubkey) -> bool {
+    solana_sdk::precompiles::is_precompile(program_id, |_| true)
 }
 
 pub trait GetLamportsChanges {
@@ -473,7 +1938,7 @@ impl GetLamportsChanges for EncodedTransactionWithStatusMeta {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct WalletContext {
     pub wallet_address: Pubkey,
     pub wallet_owner: Option<Pubkey>,
@@ -551,3 +2016,710 @@ impl GetAssetsChanges for EncodedTransactionWithStatusMeta {
             .unwrap_or_else(|| Ok(HashMap::default()))
     }
 }
+
+#[cfg(test)]
+mod transaction_parser_test {
+
+    use solana_transaction_status::{EncodedTransaction, TransactionBinaryEncoding};
+
+    use super::*;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let program_id = Pubkey::new_unique();
+        let ctx = ProgramContext {
+            program_id,
+            program_call_index: 0,
+            invoke_level: Level::new(1).unwrap(),
+        };
+        let meta = TransactionParsedMeta {
+            meta: HashMap::from([(
+                ctx,
+                (
+                    Instruction {
+                        program_id,
+                        accounts: vec![AccountMeta::new(Pubkey::new_unique(), true)],
+                        data: vec![1, 2, 3],
+                    },
+                    vec![ProgramLog::Log("hello".to_owned())],
+                ),
+            )]),
+            slot: 1,
+            block_time: Some(2),
+            lamports_changes: HashMap::from([(program_id, -5000)]),
+            token_balances_changes: HashMap::new(),
+            parent_ix: HashMap::new(),
+            root_ixs: vec![ctx],
+            compute_budget: ComputeBudget::default(),
+            ix_positions: HashMap::from([(
+                ctx,
+                InstructionPosition {
+                    instruction_index: 0,
+                    inner_index: None,
+                },
+            )]),
+            message: MessageMeta {
+                recent_blockhash: Some(Hash::new_unique()),
+                durable_nonce: None,
+                version: Some(TransactionVersion::LEGACY),
+            },
+            stamp: ParseStamp {
+                parser_version: PARSER_VERSION,
+                input_hash: Hash::new_unique(),
+            },
+        };
+
+        let json = serde_json::to_value(&meta).unwrap();
+        assert_eq!(
+            json["schema_version"],
+            TRANSACTION_PARSED_META_SCHEMA_VERSION
+        );
+
+        let restored = serde_json::from_value::<TransactionParsedMeta>(json.clone()).unwrap();
+        assert_eq!(restored.meta, meta.meta);
+        assert_eq!(restored.lamports_changes, meta.lamports_changes);
+        assert_eq!(restored.root_ixs, meta.root_ixs);
+        assert_eq!(restored.ix_positions, meta.ix_positions);
+        assert_eq!(restored.message, meta.message);
+        assert_eq!(restored.stamp, meta.stamp);
+        assert!(!restored.is_outdated());
+
+        // Stored before parser stamping
+        let mut older = json.clone();
+        older["schema_version"] = 3.into();
+        older.as_object_mut().unwrap().remove("stamp");
+        let older = serde_json::from_value::<TransactionParsedMeta>(older).unwrap();
+        assert!(older.is_outdated());
+
+        let mut newer = json;
+        newer["schema_version"] = (TRANSACTION_PARSED_META_SCHEMA_VERSION + 1).into();
+        assert!(serde_json::from_value::<TransactionParsedMeta>(newer).is_err());
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let meta = crate::fixtures::Fixture::load("v0_address_lookup_table")
+            .0
+            .parse()
+            .unwrap();
+
+        let bytes = meta.to_compact_bytes().unwrap();
+        assert_eq!(bytes[0], COMPACT_CODEC_VERSION);
+        assert!(bytes.len() < serde_json::to_vec(&meta).unwrap().len());
+
+        let restored = TransactionParsedMeta::from_compact_bytes(&bytes).unwrap();
+        assert_eq!(restored.meta, meta.meta);
+        assert_eq!(restored.lamports_changes, meta.lamports_changes);
+        assert_eq!(restored.token_balances_changes, meta.token_balances_changes);
+        assert_eq!(restored.ix_positions, meta.ix_positions);
+        assert_eq!(restored.message, meta.message);
+        assert_eq!(restored.stamp, meta.stamp);
+        assert_eq!(
+            restored.message.version,
+            Some(TransactionVersion::Number(0))
+        );
+
+        let mut stale = bytes;
+        stale[0] = COMPACT_CODEC_VERSION - 1;
+        assert!(matches!(
+            TransactionParsedMeta::from_compact_bytes(&stale),
+            Err(CompactCodecError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            TransactionParsedMeta::from_compact_bytes(&[]),
+            Err(CompactCodecError::Empty)
+        ));
+    }
+
+    #[test]
+    fn test_message_meta() {
+        use solana_sdk::{message::Message, system_instruction};
+
+        let (payer, nonce_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let nonce = Hash::new_unique();
+        let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+        let message = Message::new_with_blockhash(
+            &[
+                system_instruction::advance_nonce_account(&nonce_account, &payer),
+                transfer.clone(),
+            ],
+            Some(&payer),
+            &nonce,
+        );
+        let meta = MessageMeta::from_message(&VersionedMessage::Legacy(message));
+        assert_eq!(meta.recent_blockhash, Some(nonce));
+        assert_eq!(meta.durable_nonce, Some(nonce_account));
+        assert_eq!(meta.version, Some(TransactionVersion::LEGACY));
+
+        let message = Message::new_with_blockhash(&[transfer], Some(&payer), &nonce);
+        let meta = MessageMeta::from_message(&VersionedMessage::Legacy(message));
+        assert!(!meta.uses_durable_nonce());
+    }
+    #[test]
+    fn test_invocation_keys() {
+        let program_id = Pubkey::new_unique();
+        // CPI into `program_id` at level 2 has no logs, so call indexes of logs are shifted
+        let logged =
+            invocation_keys([("a", program_id, 0, 1), ("b", program_id, 1, 1)].into_iter());
+        let instructions = invocation_keys(
+            [
+                ("a", program_id, 0, 1),
+                ("cpi", program_id, 1, 2),
+                ("b", program_id, 2, 1),
+            ]
+            .into_iter(),
+        )
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        for (key, item) in logged {
+            assert_eq!(instructions.get(&key), Some(&item));
+        }
+        assert_eq!(instructions.get(&(program_id, 2, 0)), Some(&"cpi"));
+    }
+
+    #[test]
+    fn test_validate_consistency() {
+        for (name, expected) in [("cpi_event", 0), ("truncated_logs", 1)] {
+            let (fixture, signature) = crate::fixtures::Fixture::load(name);
+            let transaction = &fixture.transaction.transaction;
+
+            let discrepancies = validate_consistency(
+                &log_parser::parse_events(transaction_logs(transaction, signature).unwrap())
+                    .unwrap(),
+                &transaction.bind_instructions(signature).unwrap(),
+            );
+            assert_eq!(discrepancies.len(), expected, "{name}: {discrepancies:?}");
+            assert!(discrepancies
+                .iter()
+                .all(|discrepancy| matches!(discrepancy, Discrepancy::MissingLogs { .. })));
+        }
+    }
+
+    #[test]
+    fn test_parse_stamp() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+        let mut parsed = parse_transaction(signature, &fixture.transaction).unwrap();
+        assert!(parsed.stamp.is_current_for(signature, &fixture.transaction));
+        assert!(!parsed.is_outdated());
+
+        let mut changed = crate::fixtures::Fixture::load("cpi_event").0.transaction;
+        if let Some(meta) = changed.transaction.meta.as_mut() {
+            if let OptionSerializer::Some(logs) = &mut meta.log_messages {
+                logs.push("Program log: injected".to_owned());
+            }
+        }
+        assert_ne!(
+            input_hash(signature, &changed).unwrap(),
+            parsed.stamp.input_hash
+        );
+        assert!(!parsed.stamp.is_current_for(signature, &changed));
+
+        // The same transaction requested in another binary encoding has the same hash
+        let mut base58 = crate::fixtures::Fixture::load("cpi_event").0.transaction;
+        let transaction = base58.transaction.transaction.decode().unwrap();
+        base58.transaction.transaction = EncodedTransaction::Binary(
+            bs58::encode(bincode::serialize(&transaction).unwrap()).into_string(),
+            TransactionBinaryEncoding::Base58,
+        );
+        assert_eq!(
+            input_hash(signature, &base58).unwrap(),
+            parsed.stamp.input_hash
+        );
+        assert!(parsed.stamp.is_current_for(signature, &base58));
+
+        parsed.stamp.parser_version = PARSER_VERSION - 1;
+        assert!(parsed.is_outdated());
+        assert!(!parsed.stamp.is_current_for(signature, &fixture.transaction));
+    }
+
+    #[test]
+    fn test_stable_serialization() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+
+        // Every parse fills maps with own random hasher state
+        let serialized = (0..8)
+            .map(|_| {
+                serde_json::to_string(&parse_transaction(signature, &fixture.transaction).unwrap())
+                    .unwrap()
+            })
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(serialized.len(), 1);
+    }
+
+    #[test]
+    fn test_queries() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("cpi_event");
+        let parsed = parse_transaction(signature, &fixture.transaction).unwrap();
+
+        let top_level = parsed.top_level_instructions();
+        assert_eq!(
+            top_level.iter().map(|(ctx, _)| **ctx).collect::<Vec<_>>(),
+            parsed.root_ixs
+        );
+
+        let children = parsed
+            .root_ixs
+            .iter()
+            .flat_map(|root| parsed.children_of(root))
+            .collect::<Vec<_>>();
+        assert!(!children.is_empty());
+        assert!(children
+            .iter()
+            .all(|child| parsed.root_ixs.contains(&parsed.parent_ix[child])));
+
+        let (root, ix) = top_level[0];
+        let contexts = parsed.contexts_for_program(&ix.program_id);
+        assert!(contexts.contains(root));
+        assert!(contexts.iter().all(|ctx| ctx.program_id == ix.program_id));
+
+        let (ctx, log) = parsed
+            .find_first_log_matching(|_, log| matches!(log, ProgramLog::Log(_)))
+            .unwrap();
+        assert_eq!(log, &ProgramLog::Log("Instruction: Deposit".to_owned()));
+        // `ComputeBudget` instruction goes first, but logs nothing
+        assert_eq!(ctx, &parsed.root_ixs[1]);
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("failed");
+
+        assert!(parse_transaction(signature, &fixture.transaction).is_err());
+        let parsed = parse_transaction_with_options(
+            signature,
+            &fixture.transaction,
+            &ParseOptions::lenient(),
+        )
+        .unwrap();
+        assert!(!parsed.root_ixs.is_empty());
+
+        let newer = serde_json::json!({
+            "version": crate::parse_options::PARSE_OPTIONS_VERSION + 1
+        });
+        assert!(serde_json::from_value::<ParseOptions>(newer).is_err());
+    }
+
+    #[test]
+    fn test_truncated_logs() {
+        let (fixture, signature) = crate::fixtures::Fixture::load("truncated_logs");
+
+        let strict = parse_transaction(signature, &fixture.transaction).unwrap();
+        let partial = parse_transaction_lenient(signature, &fixture.transaction).unwrap();
+        assert!(partial.is_complete());
+        assert_eq!(partial.parsed.meta, strict.meta);
+        assert_eq!(partial.parsed.root_ixs, strict.root_ixs);
+
+        // Transfer after `Log truncated` is restored from instructions without logs
+        let (ctx, (_ix, logs)) = strict
+            .meta
+            .iter()
+            .find(|(ctx, _)| {
+                ctx.program_id == solana_sdk::system_program::ID && ctx.program_call_index == 2
+            })
+            .unwrap();
+        assert!(logs.is_empty());
+        assert_eq!(ctx.invoke_level.get(), 2);
+        assert_eq!(strict.parent_ix.get(ctx), strict.root_ixs.first());
+    }
+
+    struct Noop;
+
+    #[async_trait]
+    impl ConsumeInstruction for Noop {
+        async fn consume_ix(self: Box<Self>) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Implements only the required method, as decomposers written before `parent_ctx`
+    struct Legacy;
+
+    impl DecomposeInstruction for Legacy {
+        fn is_decomposable(&self, _: &ProgramContext, _: &Instruction) -> bool {
+            true
+        }
+
+        fn decompose_instruction(
+            &self,
+            _: ProgramContext,
+            _: &Instruction,
+            _: &[ProgramLog],
+        ) -> Result<Box<dyn ConsumeInstruction + Send>, DecodeError> {
+            Ok(Box::new(Noop))
+        }
+    }
+
+    type Recorded = Vec<(ProgramContext, Option<ParentProgramContext>)>;
+
+    #[derive(Default)]
+    struct ParentRecorder(Arc<std::sync::Mutex<Recorded>>);
+
+    impl DecomposeInstruction for ParentRecorder {
+        fn is_decomposable(&self, _: &ProgramContext, _: &Instruction) -> bool {
+            true
+        }
+
+        fn decompose_instruction(
+            &self,
+            program_ctx: ProgramContext,
+            raw_ix: &Instruction,
+            logs: &[ProgramLog],
+        ) -> Result<Box<dyn ConsumeInstruction + Send>, DecodeError> {
+            self.decompose_instruction_with_parent(program_ctx, None, raw_ix, logs)
+        }
+
+        fn decompose_instruction_with_parent(
+            &self,
+            program_ctx: ProgramContext,
+            parent_ctx: Option<ParentProgramContext>,
+            _: &Instruction,
+            _: &[ProgramLog],
+        ) -> Result<Box<dyn ConsumeInstruction + Send>, DecodeError> {
+            self.0.lock().unwrap().push((program_ctx, parent_ctx));
+            Ok(Box::new(Noop))
+        }
+    }
+
+    #[test]
+    fn test_decompose_with_parent() {
+        let program_id = Pubkey::new_unique();
+        let (root, child) = (
+            ProgramContext {
+                program_id,
+                program_call_index: 0,
+                invoke_level: Level::new(1).unwrap(),
+            },
+            ProgramContext {
+                program_id,
+                program_call_index: 1,
+                invoke_level: Level::new(2).unwrap(),
+            },
+        );
+        let ix = Instruction {
+            program_id,
+            accounts: vec![],
+            data: vec![],
+        };
+        let meta = TransactionParsedMeta {
+            meta: HashMap::from([(root, (ix.clone(), vec![])), (child, (ix, vec![]))]),
+            slot: 1,
+            block_time: None,
+            lamports_changes: HashMap::new(),
+            token_balances_changes: HashMap::new(),
+            parent_ix: HashMap::from([(child, root)]),
+            root_ixs: vec![root],
+            compute_budget: ComputeBudget::default(),
+            ix_positions: HashMap::new(),
+            message: MessageMeta::default(),
+            stamp: ParseStamp {
+                parser_version: PARSER_VERSION,
+                input_hash: Hash::default(),
+            },
+        };
+
+        let recorder = ParentRecorder::default();
+        let recorded = recorder.0.clone();
+        let decomposers: Vec<BoxedDecomposer> = vec![Box::new(recorder)];
+        assert_eq!(
+            meta.find_and_decompose_ix_with_decomposer(Arc::new(decomposers))
+                .unwrap()
+                .len(),
+            2
+        );
+        let mut recorded = recorded.lock().unwrap().clone();
+        recorded.sort();
+        assert_eq!(recorded, [(root, None), (child, Some(root))]);
+
+        let mut registry = DecomposerRegistry::default();
+        registry.register(Box::new(Legacy));
+        assert_eq!(
+            meta.find_and_decompose_ix_with_registry(&registry)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    const DECODE_PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+    #[derive(Debug, PartialEq, AnchorDeserialize)]
+    struct Deposit {
+        amount: u64,
+    }
+    impl Owner for Deposit {
+        fn owner() -> Pubkey {
+            DECODE_PROGRAM_ID
+        }
+    }
+    impl Discriminator for Deposit {
+        const DISCRIMINATOR: [u8; 8] = [1; 8];
+    }
+
+    type Checks = Arc<std::sync::Mutex<Vec<&'static str>>>;
+
+    /// Decomposer which records checks of [`DecomposeInstruction::is_decomposable`]
+    struct Keyed {
+        name: &'static str,
+        keys: Vec<DispatchKey>,
+        decomposable: bool,
+        checks: Checks,
+    }
+
+    impl DecomposeInstruction for Keyed {
+        fn is_decomposable(&self, _: &ProgramContext, _: &Instruction) -> bool {
+            self.checks.lock().unwrap().push(self.name);
+            self.decomposable
+        }
+
+        fn decompose_instruction(
+            &self,
+            _: ProgramContext,
+            _: &Instruction,
+            _: &[ProgramLog],
+        ) -> Result<Box<dyn ConsumeInstruction + Send>, DecodeError> {
+            Ok(Box::new(Noop))
+        }
+
+        fn dispatch_keys(&self) -> Vec<DispatchKey> {
+            self.keys.clone()
+        }
+    }
+
+    #[test]
+    fn test_decomposer_registry() {
+        let program_id = Pubkey::new_unique();
+        let checks = Checks::default();
+        let keyed = |name, keys, decomposable| -> SharedDecomposer {
+            Arc::new(Keyed {
+                name,
+                keys,
+                decomposable,
+                checks: checks.clone(),
+            })
+        };
+        let ctx = ProgramContext {
+            program_id,
+            program_call_index: 0,
+            invoke_level: Level::new(1).unwrap(),
+        };
+        let ix = |program_id, data: &[u8]| Instruction {
+            program_id,
+            accounts: vec![],
+            data: data.to_vec(),
+        };
+        let find = |registry: &DecomposerRegistry, ix: &Instruction| {
+            checks.lock().unwrap().clear();
+            registry
+                .find(&ctx, ix)
+                .map(|_| checks.lock().unwrap().clone())
+        };
+
+        let mut registry = DecomposerRegistry::default();
+        registry
+            .register_named("fallback", keyed("fallback", vec![], true))
+            .register_named(
+                "program",
+                keyed("program", vec![DispatchKey::Program(program_id)], true),
+            )
+            .register_named(
+                "skipped",
+                keyed(
+                    "skipped",
+                    vec![DispatchKey::Discriminator(program_id, [1; 8])],
+                    false,
+                ),
+            )
+            .register_named(
+                "discriminator",
+                keyed(
+                    "discriminator",
+                    vec![DispatchKey::Discriminator(program_id, [1; 8])],
+                    true,
+                ),
+            );
+        assert_eq!(registry.len(), 4);
+        assert_eq!(registry.discriminators(&program_id), [[1; 8]]);
+
+        // Discriminator key first, in registration order, then program, then fallback
+        let with_discriminator = ix(program_id, &[1; 10]);
+        assert_eq!(
+            find(&registry, &with_discriminator).unwrap(),
+            ["skipped", "discriminator"]
+        );
+        assert_eq!(
+            registry
+                .candidates(&ctx, &with_discriminator)
+                .into_iter()
+                .map(|candidate| (
+                    candidate.name,
+                    candidate.dispatch,
+                    candidate.is_decomposable
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (Some("skipped"), DispatchMatch::Discriminator, false),
+                (Some("discriminator"), DispatchMatch::Discriminator, true),
+                (Some("program"), DispatchMatch::Program, true),
+                (Some("fallback"), DispatchMatch::Fallback, true),
+            ]
+        );
+
+        // Other discriminator & data shorter than discriminator are dispatched by program
+        assert_eq!(
+            find(&registry, &ix(program_id, &[2; 8])).unwrap(),
+            ["program"]
+        );
+        assert_eq!(
+            find(&registry, &ix(program_id, &[1; 3])).unwrap(),
+            ["program"]
+        );
+        // Other program is dispatched to fallback only
+        assert_eq!(
+            find(&registry, &ix(Pubkey::new_unique(), &[1; 8])).unwrap(),
+            ["fallback"]
+        );
+
+        // Unregister rebuilds indexes
+        assert!(registry.unregister("discriminator"));
+        assert!(!registry.unregister("discriminator"));
+        assert_eq!(registry.len(), 3);
+        assert_eq!(
+            find(&registry, &with_discriminator).unwrap(),
+            ["skipped", "program"]
+        );
+        assert!(registry.unregister("program"));
+        assert_eq!(
+            find(&registry, &with_discriminator).unwrap(),
+            ["skipped", "fallback"]
+        );
+        assert!(registry.unregister("fallback"));
+        assert_eq!(find(&registry, &with_discriminator), None);
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["skipped"]);
+
+        // Registration with the same name replaces decomposer
+        registry.register_named("skipped", keyed("replaced", vec![], true));
+        assert_eq!(registry.len(), 1);
+        assert!(registry.discriminators(&program_id).is_empty());
+        assert_eq!(find(&registry, &with_discriminator).unwrap(), ["replaced"]);
+    }
+
+    #[test]
+    fn test_accounts_count() {
+        type Decomposer = InstructionDecomposer<Deposit, [Pubkey; 2], 2>;
+
+        assert!(matches!(
+            Decomposer::default().set_min_accounts_count(1),
+            Err(Error::InvalidAccountsCount { min: 1, .. })
+        ));
+        assert!(matches!(
+            Decomposer::default().set_max_accounts_count(1),
+            Err(Error::InvalidAccountsCount { max: Some(1), .. })
+        ));
+        assert!(matches!(
+            Decomposer::default()
+                .set_max_accounts_count(3)
+                .unwrap()
+                .set_min_accounts_count(4),
+            Err(Error::InvalidAccountsCount {
+                min: 4,
+                max: Some(3),
+                ..
+            })
+        ));
+        let decomposer = Decomposer::default()
+            .set_min_accounts_count(3)
+            .unwrap()
+            .set_max_accounts_count(4)
+            .unwrap();
+        assert_eq!(
+            (decomposer.min_accounts_count, decomposer.max_accounts_count),
+            (3, Some(4))
+        );
+
+        let accounts = (0..5)
+            .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+            .collect::<Vec<_>>();
+        let pubkeys = accounts.iter().map(|acc| acc.pubkey).collect::<Vec<_>>();
+        let split = |count: usize, min, max| {
+            split_accounts::<2>(DECODE_PROGRAM_ID, None, &accounts[..count], min, max)
+        };
+
+        // Short: no padding of absent accounts, even if minimum is lower
+        assert!(matches!(
+            split(1, 0, None),
+            Err(DecodeError::AccountsCount {
+                min: 2,
+                actual: 1,
+                ..
+            })
+        ));
+        assert_eq!(
+            split(2, 2, None).unwrap(),
+            ([pubkeys[0], pubkeys[1]], vec![])
+        );
+
+        // Min
+        assert!(matches!(
+            split(2, 3, Some(4)),
+            Err(DecodeError::AccountsCount {
+                min: 3,
+                actual: 2,
+                ..
+            })
+        ));
+        assert_eq!(
+            split(3, 3, Some(4)).unwrap(),
+            ([pubkeys[0], pubkeys[1]], vec![pubkeys[2]])
+        );
+
+        // Max
+        assert_eq!(
+            split(4, 3, Some(4)).unwrap(),
+            ([pubkeys[0], pubkeys[1]], pubkeys[2..4].to_vec())
+        );
+        assert!(matches!(
+            split(5, 3, Some(4)),
+            Err(DecodeError::AccountsCount {
+                max: Some(4),
+                actual: 5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_decode_anchor_data() {
+        let data = [[1; DISCRIMINATOR_SIZE].as_slice(), &5u64.to_le_bytes()].concat();
+        assert_eq!(
+            decode_anchor_data::<Deposit>(DECODE_PROGRAM_ID, None, &data).unwrap(),
+            Deposit { amount: 5 }
+        );
+
+        assert!(matches!(
+            decode_anchor_data::<Deposit>(DECODE_PROGRAM_ID, None, &data[..DISCRIMINATOR_SIZE - 1]),
+            Err(DecodeError::TruncatedData { data_len, .. }) if data_len == DISCRIMINATOR_SIZE - 1
+        ));
+        assert!(matches!(
+            decode_anchor_data::<Deposit>(DECODE_PROGRAM_ID, None, &[]),
+            Err(DecodeError::TruncatedData { data_len: 0, .. })
+        ));
+
+        let wrong = [[2; DISCRIMINATOR_SIZE].as_slice(), &5u64.to_le_bytes()].concat();
+        assert!(matches!(
+            decode_anchor_data::<Deposit>(DECODE_PROGRAM_ID, None, &wrong),
+            Err(DecodeError::DiscriminatorMismatch { expected, actual, .. })
+                if expected == [1; DISCRIMINATOR_SIZE] && actual == [2; DISCRIMINATOR_SIZE]
+        ));
+
+        assert!(matches!(
+            decode_anchor_data::<Deposit>(Pubkey::new_unique(), None, &data),
+            Err(DecodeError::OwnerMismatch { .. })
+        ));
+        assert!(matches!(
+            decode_anchor_data::<Deposit>(DECODE_PROGRAM_ID, None, &data[..DISCRIMINATOR_SIZE + 4]),
+            Err(DecodeError::Deserialize { .. })
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+//! [`Pubkey`], [`Signature`] & [`Slot`] used across crate
+//!
+//! With `solana` feature these are re-exports of `solana_sdk`. Without it - lightweight
+//! types with the same layout, base58 text form & serde representation, so logs, storage
+//! records & serialized outputs are interchangeable between light services and full ones
+
+#[cfg(feature = "solana")]
+pub use solana_sdk::{
+    clock::Slot,
+    pubkey::{ParsePubkeyError, Pubkey},
+    signature::{ParseSignatureError, Signature},
+};
+
+#[cfg(not(feature = "solana"))]
+pub use light::{ParsePubkeyError, ParseSignatureError, Pubkey, Signature, Slot};
+
+#[cfg(not(feature = "solana"))]
+mod light {
+    use std::{
+        fmt,
+        str::FromStr,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        ser::SerializeTuple,
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+
+    pub type Slot = u64;
+
+    const PUBKEY_BYTES: usize = 32;
+    const MAX_BASE58_PUBKEY_LEN: usize = 44;
+    const SIGNATURE_BYTES: usize = 64;
+    const MAX_BASE58_SIGNATURE_LEN: usize = 88;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+    pub enum ParsePubkeyError {
+        #[error("String is the wrong size")]
+        WrongSize,
+        #[error("Invalid Base58 string")]
+        Invalid,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+    pub enum ParseSignatureError {
+        #[error("string decoded to wrong size for signature")]
+        WrongSize,
+        #[error("failed to decode string to signature")]
+        Invalid,
+    }
+
+    /// Decode base58 `s` of exactly `N` bytes, `None` if it's not base58
+    fn decode_base58<const N: usize>(s: &str) -> Option<Result<[u8; N], ()>> {
+        let bytes = bs58::decode(s).into_vec().ok()?;
+        Some(bytes.try_into().map_err(|_| ()))
+    }
+
+    #[derive(
+        Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+    )]
+    pub struct Pubkey([u8; PUBKEY_BYTES]);
+
+    impl Pubkey {
+        pub const fn new_from_array(bytes: [u8; PUBKEY_BYTES]) -> Self {
+            Self(bytes)
+        }
+
+        pub const fn to_bytes(self) -> [u8; PUBKEY_BYTES] {
+            self.0
+        }
+
+        /// Unique pubkey for tests, the same way as `solana_sdk` does: counter in the first
+        /// bytes
+        pub fn new_unique() -> Self {
+            static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+            let mut bytes = [0; PUBKEY_BYTES];
+            bytes[..8].copy_from_slice(&COUNTER.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+            Self(bytes)
+        }
+    }
+
+    impl From<[u8; PUBKEY_BYTES]> for Pubkey {
+        fn from(bytes: [u8; PUBKEY_BYTES]) -> Self {
+            Self(bytes)
+        }
+    }
+
+    impl TryFrom<&[u8]> for Pubkey {
+        type Error = std::array::TryFromSliceError;
+
+        fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+            bytes.try_into().map(Self)
+        }
+    }
+
+    impl AsRef<[u8]> for Pubkey {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl FromStr for Pubkey {
+        type Err = ParsePubkeyError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            if s.len() > MAX_BASE58_PUBKEY_LEN {
+                return Err(ParsePubkeyError::WrongSize);
+            }
+            match decode_base58(s) {
+                Some(Ok(bytes)) => Ok(Self(bytes)),
+                Some(Err(())) => Err(ParsePubkeyError::WrongSize),
+                None => Err(ParsePubkeyError::Invalid),
+            }
+        }
+    }
+
+    impl fmt::Display for Pubkey {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&bs58::encode(self.0).into_string())
+        }
+    }
+
+    impl fmt::Debug for Pubkey {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(self, f)
+        }
+    }
+
+    /// Serialized as newtype over tuple of 64 bytes, the same as `solana_sdk` one
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Signature([u8; SIGNATURE_BYTES]);
+
+    impl Default for Signature {
+        fn default() -> Self {
+            Self([0; SIGNATURE_BYTES])
+        }
+    }
+
+    impl From<[u8; SIGNATURE_BYTES]> for Signature {
+        fn from(bytes: [u8; SIGNATURE_BYTES]) -> Self {
+            Self(bytes)
+        }
+    }
+
+    impl From<Signature> for [u8; SIGNATURE_BYTES] {
+        fn from(signature: Signature) -> Self {
+            signature.0
+        }
+    }
+
+    impl TryFrom<&[u8]> for Signature {
+        type Error = std::array::TryFromSliceError;
+
+        fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+            bytes.try_into().map(Self)
+        }
+    }
+
+    impl AsRef<[u8]> for Signature {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl FromStr for Signature {
+        type Err = ParseSignatureError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            if s.len() > MAX_BASE58_SIGNATURE_LEN {
+                return Err(ParseSignatureError::WrongSize);
+            }
+            match decode_base58(s) {
+                Some(Ok(bytes)) => Ok(Self(bytes)),
+                Some(Err(())) => Err(ParseSignatureError::WrongSize),
+                None => Err(ParseSignatureError::Invalid),
+            }
+        }
+    }
+
+    impl fmt::Display for Signature {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&bs58::encode(self.0).into_string())
+        }
+    }
+
+    impl fmt::Debug for Signature {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            fmt::Display::fmt(self, f)
+        }
+    }
+
+    struct SignatureBytes<'s>(&'s [u8; SIGNATURE_BYTES]);
+
+    impl Serialize for SignatureBytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut tuple = serializer.serialize_tuple(SIGNATURE_BYTES)?;
+            for byte in self.0 {
+                tuple.serialize_element(byte)?;
+            }
+            tuple.end()
+        }
+    }
+
+    impl Serialize for Signature {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_newtype_struct("Signature", &SignatureBytes(&self.0))
+        }
+    }
+
+    struct SignatureVisitor;
+
+    impl<'de> Visitor<'de> for SignatureVisitor {
+        type Value = Signature;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{SIGNATURE_BYTES} bytes of signature")
+        }
+
+        fn visit_newtype_struct<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_tuple(SIGNATURE_BYTES, self)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = [0; SIGNATURE_BYTES];
+            for (index, byte) in bytes.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(index, &self))?;
+            }
+            Ok(Signature(bytes))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Signature {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_newtype_struct("Signature", SignatureVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod types_test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_text_and_serde_forms() {
+        let pubkey = Pubkey::from_str("9H9dXYU1NBMqsZZpUnztavyeiD2Sr7Xw3YLQCCSgvbwX").unwrap();
+        assert_eq!(
+            pubkey.to_string(),
+            "9H9dXYU1NBMqsZZpUnztavyeiD2Sr7Xw3YLQCCSgvbwX"
+        );
+        assert_eq!(Pubkey::from_str("1"), Err(ParsePubkeyError::WrongSize));
+        assert_eq!(Pubkey::from_str("0OIl"), Err(ParsePubkeyError::Invalid));
+
+        let signature = Signature::from([7; 64]);
+        assert_eq!(
+            Signature::from_str(&signature.to_string()).unwrap(),
+            signature
+        );
+
+        // Both serialized as plain sequences of bytes, the same as `solana_sdk` ones
+        let encoded = serde_json::to_value((pubkey, signature)).unwrap();
+        assert_eq!(encoded[0].as_array().unwrap().len(), 32);
+        assert_eq!(encoded[1].as_array().unwrap().len(), 64);
+        assert_eq!(
+            serde_json::from_value::<(Pubkey, Signature)>(encoded).unwrap(),
+            (pubkey, signature)
+        );
+    }
+}
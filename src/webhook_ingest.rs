@@ -0,0 +1,241 @@
+//! Ingestion of transactions delivered by webhooks of managed RPC providers (e.g. Helius raw
+//! webhooks, Triton), instead of websocket subscription
+//!
+//! Webhook body is a transaction with meta as returned by `getTransaction` (or array of
+//! them), in `json` or binary encoding. [`WebhookIngest::handle`] is called by HTTP server
+//! of user with body of POST request, transactions are pushed into
+//! [`EventsReader`](crate::event_reader_service::EventsReader) by [`TransactionIngestor`]
+//! and consumed by the same consumers as live ones. Use
+//! [`LiveSource::Ingest`](crate::event_reader_service::LiveSource::Ingest) to disable
+//! websocket subscription at all
+
+use serde::Deserialize;
+use solana_sdk::{
+    hash::{Hash, ParseHashError},
+    instruction::CompiledInstruction,
+    message::{
+        v0::{self, MessageAddressTableLookup},
+        Message, VersionedMessage,
+    },
+    pubkey::{ParsePubkeyError, Pubkey},
+    signature::{ParseSignatureError, Signature},
+    transaction::{TransactionVersion, VersionedTransaction},
+};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, TransactionBinaryEncoding,
+    UiMessage, UiRawMessage, UiTransaction,
+};
+use tracing::*;
+
+use crate::event_reader_service::TransactionIngestor;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Authorization header of webhook request doesn't match")]
+    Unauthorized,
+    #[error(transparent)]
+    Payload(#[from] serde_json::Error),
+    #[error("Transaction {0} is in unsupported encoding, expected `json` or binary one")]
+    UnsupportedEncoding(usize),
+    #[error(transparent)]
+    ParseSignature(#[from] ParseSignatureError),
+    #[error(transparent)]
+    ParsePubkey(#[from] ParsePubkeyError),
+    #[error(transparent)]
+    ParseHash(#[from] ParseHashError),
+    #[error(transparent)]
+    Base58(#[from] bs58::decode::Error),
+    #[error(transparent)]
+    Bincode(#[from] bincode::Error),
+    #[error("Events reader is dropped, transactions can't be ingested")]
+    ReaderDropped,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Payload {
+    Batch(Vec<EncodedConfirmedTransactionWithStatusMeta>),
+    Single(Box<EncodedConfirmedTransactionWithStatusMeta>),
+}
+
+/// Handler of webhook requests, pushing transactions into reader
+#[derive(Debug, Clone)]
+pub struct WebhookIngest {
+    ingestor: TransactionIngestor,
+    auth_header: Option<String>,
+}
+
+impl WebhookIngest {
+    pub fn new(ingestor: TransactionIngestor) -> Self {
+        Self {
+            ingestor,
+            auth_header: None,
+        }
+    }
+
+    /// Expected value of `Authorization` header, as configured for webhook in provider
+    pub fn with_auth_header(mut self, auth_header: impl Into<String>) -> Self {
+        self.auth_header = Some(auth_header.into());
+        self
+    }
+
+    /// Handle POST request with `authorization` header & `body`, returns count of ingested
+    /// transactions. Nothing is ingested if any transaction of body is malformed
+    pub async fn handle(&self, authorization: Option<&str>, body: &[u8]) -> Result<usize, Error> {
+        if let Some(expected) = self.auth_header.as_ref() {
+            if !authorization.is_some_and(|actual| constant_time_eq(actual, expected)) {
+                warn!("Unauthorized webhook request");
+                return Err(Error::Unauthorized);
+            }
+        }
+
+        let transactions = parse_payload(body)?;
+        let count = transactions.len();
+        for transaction in transactions {
+            self.ingestor
+                .ingest(transaction)
+                .await
+                .map_err(|_| Error::ReaderDropped)?;
+        }
+        debug!("{count} transactions ingested by webhook");
+        Ok(count)
+    }
+}
+
+/// Transactions of webhook `body` (one or array), converted into binary encoding
+pub fn parse_payload(body: &[u8]) -> Result<Vec<EncodedConfirmedTransactionWithStatusMeta>, Error> {
+    let transactions = match serde_json::from_slice(body)? {
+        Payload::Batch(transactions) => transactions,
+        Payload::Single(transaction) => vec![*transaction],
+    };
+    transactions
+        .into_iter()
+        .enumerate()
+        .map(|(index, transaction)| into_binary_encoding(index, transaction))
+        .collect()
+}
+
+/// Binary encoding of `json` encoded transaction, required by parser. Binary encoded
+/// transactions are returned as is
+fn into_binary_encoding(
+    index: usize,
+    mut confirmed_transaction: EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, Error> {
+    let transaction = &mut confirmed_transaction.transaction;
+    let UiTransaction {
+        signatures,
+        message,
+    } = match &transaction.transaction {
+        EncodedTransaction::LegacyBinary(_) | EncodedTransaction::Binary(..) => {
+            return Ok(confirmed_transaction)
+        }
+        EncodedTransaction::Json(ui_transaction) => ui_transaction,
+        EncodedTransaction::Accounts(_) => return Err(Error::UnsupportedEncoding(index)),
+    };
+    let UiMessage::Raw(UiRawMessage {
+        header,
+        account_keys,
+        recent_blockhash,
+        instructions,
+        address_table_lookups,
+    }) = message
+    else {
+        return Err(Error::UnsupportedEncoding(index));
+    };
+
+    let account_keys = account_keys
+        .iter()
+        .map(|key| key.parse())
+        .collect::<Result<Vec<Pubkey>, _>>()?;
+    let recent_blockhash = recent_blockhash.parse::<Hash>()?;
+    let instructions = instructions
+        .iter()
+        .map(|ix| {
+            Ok(CompiledInstruction {
+                program_id_index: ix.program_id_index,
+                accounts: ix.accounts.clone(),
+                data: bs58::decode(&ix.data).into_vec()?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let message = match transaction.version {
+        Some(TransactionVersion::Number(_)) => VersionedMessage::V0(v0::Message {
+            header: *header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups: address_table_lookups
+                .iter()
+                .flatten()
+                .map(|lookup| {
+                    Ok(MessageAddressTableLookup {
+                        account_key: lookup.account_key.parse()?,
+                        writable_indexes: lookup.writable_indexes.clone(),
+                        readonly_indexes: lookup.readonly_indexes.clone(),
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+        }),
+        Some(TransactionVersion::Legacy(_)) | None => VersionedMessage::Legacy(Message {
+            header: *header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+        }),
+    };
+    let versioned_transaction = VersionedTransaction {
+        signatures: signatures
+            .iter()
+            .map(|signature| signature.parse())
+            .collect::<Result<Vec<Signature>, _>>()?,
+        message,
+    };
+
+    transaction.transaction = EncodedTransaction::Binary(
+        base64::encode(bincode::serialize(&versioned_transaction)?),
+        TransactionBinaryEncoding::Base64,
+    );
+    Ok(confirmed_transaction)
+}
+
+fn constant_time_eq(actual: &str, expected: &str) -> bool {
+    actual.len() == expected.len()
+        && actual
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod webhook_ingest_test {
+    use solana_transaction_status::EncodableWithMeta;
+
+    use super::*;
+    use crate::{fixtures::Fixture, transaction_parser::parse_transaction};
+
+    #[test]
+    fn test_parse_json_payload() {
+        let (fixture, signature) = Fixture::load("v0_address_lookup_table");
+
+        let mut json_transaction = Fixture::load("v0_address_lookup_table").0.transaction;
+        json_transaction.transaction.transaction = json_transaction
+            .transaction
+            .transaction
+            .decode()
+            .unwrap()
+            .json_encode();
+        let body = serde_json::to_vec(&[json_transaction]).unwrap();
+
+        let transactions = parse_payload(&body).unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(
+            parse_transaction(signature, &transactions[0]).unwrap().meta,
+            fixture.parse().unwrap().meta
+        );
+
+        let single = serde_json::to_vec(&fixture.transaction).unwrap();
+        assert_eq!(parse_payload(&single).unwrap().len(), 1);
+        assert!(parse_payload(b"{}").is_err());
+    }
+}